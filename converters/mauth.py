@@ -1,31 +1,20 @@
 import sqlite3
 import sys
 import json
-import base64
-
-TOTP_TYPE = 0
-MICROSOFT_TYPE = 1
 
 
 def get_accounts(filename):
     conn = sqlite3.connect(filename)
     c = conn.cursor()
     accounts = []
+    # Secrets are decoded by cotp, depending on the account type
     for row in c.execute("SELECT account_type,name,username,oath_secret_key FROM accounts"):
-        account_type = row[0]
-        if account_type == TOTP_TYPE:
-            secret = row[3].replace("-", "").replace(" ", "").upper()
-        else:
-            secret = base64.b32encode(base64.b64decode(row[3]))
         accounts.append(
             {
-                'label': row[2],
-                'secret': secret,
-                'issuer': row[1],
-                'digits': 6,
-                'type': 'TOTP',
-                'counter': 0,
-                'algorithm': "SHA1",
+                'account_type': row[0],
+                'name': row[1],
+                'username': row[2],
+                'oath_secret_key': row[3],
             }
         )
     c.close()
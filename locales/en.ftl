@@ -0,0 +1,206 @@
+# English messages of cotp, the fallback of the other catalogs.
+# Translations are added as <language>.ftl next to this file and listed in src/i18n.rs.
+
+## Actions of the dashboard, listed in the help
+
+action-add = Add a new code
+action-edit = Edit selected code
+action-delete = Delete the selected or the marked codes
+action-increment-counter = Increment the HOTP counter
+action-decrement-counter = Decrement the HOTP counter
+action-qrcode = Show QRCode of the selected element
+action-save-qrcode = Save the QR code shown as a PNG image
+action-copy-uri = Copy the otpauth URI of the QR code shown
+action-nearby-codes = Show the previous and next codes of the selected element
+action-presentation = Show the code of the selected element in full screen
+action-recovery-codes = Show the recovery codes of the selected element
+action-pin = Pin the selected code to the top, or unpin it
+action-hide-codes = Hide or show all the codes
+action-reveal = Show the selected code while codes are hidden, or the secret below the QR code
+action-detail-pane = Show or hide the details of the selected code
+action-columns = Choose the columns of the table
+action-mark = Mark the selected code for a batch operation, or unmark it
+action-tag = Tag the selected or the marked codes, or untag them if they all have the tag
+action-export = Export the selected or the marked codes to a cotp backup
+action-copy = Copy the OTP Code to the clipboard
+action-search = Search codes
+action-tag-filter = Filter codes by tag
+action-sort = Change the sorting mode
+action-move-up = Move the selected code up in manual sorting mode
+action-move-down = Move the selected code down in manual sorting mode
+action-previous = Select the previous code
+action-next = Select the next code
+action-undo = Undo the last change
+action-redo = Redo the last undone change
+action-help = Show this help
+action-quit = Exit the application
+
+## Sections of the help and the keys which cannot be configured
+
+context-navigation = Navigation
+context-codes = Codes
+context-selection = Marked codes
+context-views = Views
+context-qrcode-page = QR code page
+context-search-bar = Search bar
+context-application = Application
+help-security = Security
+memory-locked = The memory is locked, so the secrets are never written to the swap
+memory-limited = The memory is not locked, the RLIMIT_MEMLOCK limit must be unlimited to lock it
+memory-lock-failed = The memory is not locked, the system refused to lock it
+memory-lock-unsupported = The memory is not locked, cotp can lock it only on Linux
+help-first-last = Select the first or the last code
+help-search-match = Select the next or the previous search match
+help-mark-range = Mark the codes from the last marked one to the selected one
+help-confirm-search = Confirm the search
+help-copy-selected = Copy the selected code
+help-leave-search = Leave the search bar
+help-move-cursor = Move the cursor
+help-move-cursor-words = Move the cursor by words
+help-delete-text = Delete the previous word, the text before or after the cursor
+help-scroll = Scroll this help
+
+## Dashboard
+
+popup-help = Help
+popup-alert = Alert
+status-marked = { $count } marked
+status-clipboard-clear = Clipboard cleared in { $seconds }s
+status-help = Press { $keys } for help
+no-element-selected = No element is selected
+detail-expires = Expires in { $seconds }s
+code-trashed = Code moved to the trash
+codes-trashed = { $count } codes moved to the trash
+code-copied = Copied { $name } code
+code-remote-copied = Remote copied { $name } code
+column-id = Id
+column-issuer = Issuer
+column-label = Label
+column-otp = OTP
+column-time-left = Expires
+column-type = Type
+column-tags = Tags
+popup-nope = Nope
+popup-password = Password
+popup-code-info = Code info
+popup-details = Details
+popup-recovery-codes = Recovery codes
+lock-title-one = Locked after 1 minute without input
+lock-title = Locked after { $minutes } minutes without input
+lock-hint = Enter to unlock, Ctrl-C to quit
+lock-quit-unsaved = Type the password to save your changes before quitting, Ctrl-C again to discard them
+lock-unsaved = Your changes have not been saved: { $error }
+lock-failed = Cannot lock the dashboard: { $error }
+password-field = Password: { $password }_
+password-wrong = Wrong password
+protected-continue = Type the database password to continue
+protected-delete = Type the database password to delete the code
+protected-edit = Type the database password to edit the code
+protected-qrcode = Type the database password to show the QR code
+protected-recovery-codes = Type the database password to show the recovery codes
+protected-export = Type the database password to export the codes
+terminal-too-small = The terminal is too small, make it larger
+search-title = Press { $keys } to search a code...
+qrcode-show-secret = Press { $keys } to show the secret
+qrcode-hide-secret = Press { $keys } to hide the secret
+qrcode-first = Press { $keys } to show the QR code first
+qrcode-path = Path of the QR code image
+qrcode-saved = QR code saved to { $path }
+qrcode-save-failed = Cannot save the QR code to { $path }: { $error }
+uri-copied = URI copied!
+nearby-current = Current
+nearby-previous = Previous
+nearby-next = Next
+nearby-valid-in = valid in { $seconds }s
+nearby-expired = expired { $seconds }s ago
+nearby-left = { $seconds }s left
+nearby-counter = counter { $counter }
+no-recovery-codes = No recovery codes, add them with cotp recovery add
+presentation-counter = Counter { $counter }
+form-add = Add code
+form-edit = Edit code
+form-hint-fields = Tab, Down: next field - Shift+Tab, Up: previous field - Left, Right: change option
+form-hint-save = Enter: save - Esc: cancel
+form-label-required = Label is required
+form-invalid-digits = Digits must be a number between 1 and { $max }
+form-invalid-period = Period must be a positive number
+form-invalid-counter = Counter must be a number
+form-pin-required = Pin is required
+form-invalid-domain = Invalid domain "{ $domain }"
+form-invalid-secret = Invalid secret
+field-secret = Secret
+field-algorithm = Algorithm
+field-digits = Digits
+field-period = Period
+field-counter = Counter
+field-pin = Pin
+field-suite = Suite
+field-domains = Domains
+field-note = Note
+field-last-used = Last used
+field-times-used = Times used
+field-added = Added
+field-period-from = { $period }s from { $t0 }
+field-never = Never
+not-available = N/A
+code-added = Code added
+code-edited = Code edited
+undone = Undone
+redone = Redone
+nothing-to-undo = Nothing to undo
+nothing-to-redo = Nothing to redo
+no-code-selected = No code selected
+no-code-marked = No code marked
+index-out-of-bounds = Index out of bounds
+element-not-found = Cannot fetch element from index: { $index }
+otp-column-missing = Cannot get OTP Code column
+copy-failed = Cannot copy
+manual-sorting-needed = Press { $keys } to switch to manual sorting
+tag-prompt-one = Tag of the code, removed if it has it
+tag-prompt = Tag of the { $count } codes, removed if they all have it
+tag-empty = The tag cannot be empty
+tag-added-one = Added { $tag } to 1 code
+tag-added = Added { $tag } to { $count } codes
+tag-removed-one = Removed { $tag } from 1 code
+tag-removed = Removed { $tag } from { $count } codes
+export-path = Path of the cotp backup
+export-failed = Cannot export to { $path }: { $error }
+export-overwrite = { $path } already exists, overwrite it? [Y/N]
+code-exported = Exported 1 code to { $path }
+codes-exported = Exported { $count } codes to { $path }
+trash-confirm = Do you want to move { $issuer } - { $label } to the trash? [Y/N]
+trash-marked-confirm-one = Do you want to move the marked code to the trash? [Y/N]
+trash-marked-confirm = Do you want to move the { $count } marked codes to the trash? [Y/N]
+columns-menu = Press a number to show or hide a column
+columns-menu-close = Enter to close
+save-confirm = Save changes? [Y/N]
+
+## Command line
+
+plain-counter = counter { $counter }
+plain-second-left = 1 second left
+plain-seconds-left = { $seconds } seconds left
+plain-error = error { $message }
+cli-copied = Copied to clipboard
+column-problem = Problem
+column-date = Date
+column-host = Host
+column-change = Change
+audit-clean = No problems found
+audit-invalid-secret = The secret is not valid
+audit-short-secret = The secret is only { $bits } bits long, at least { $min } are recommended
+audit-few-digits = Codes of { $digits } digits are easy to guess, at least { $min } are recommended
+audit-test-secret = The secret is a well known test secret
+audit-low-entropy = The secret repeats a few characters, check it
+audit-duplicate-secret = Same secret of the codes { $indexes }
+history-empty = No changes recorded
+remote-not-configured = No server configured, use cotp remote configure
+remote-configured = Upload the database with cotp remote push, or replace it with the remote one with cotp remote pull
+remote-pushed = Database pushed to { $url }
+remote-unchanged = The database at { $url } did not change since the last push or pull
+remote-pulled = Database pulled from { $url }, the previous one can be restored with cotp backup
+remote-no-versions = No versions found
+remote-restored = Version of { $date } restored, push it to replace the remote database
+remote-not-database = The file at { $url } is not a cotp database
+remote-password = Password on the server:
+remote-secret-key = Secret access key:
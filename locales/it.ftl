@@ -0,0 +1,205 @@
+# Messaggi italiani di cotp
+
+## Azioni della dashboard, elencate nell'aiuto
+
+action-add = Aggiungi un nuovo codice
+action-edit = Modifica il codice selezionato
+action-delete = Elimina i codici selezionati o marcati
+action-increment-counter = Incrementa il contatore HOTP
+action-decrement-counter = Decrementa il contatore HOTP
+action-qrcode = Mostra il codice QR dell'elemento selezionato
+action-save-qrcode = Salva il codice QR mostrato come immagine PNG
+action-copy-uri = Copia l'URI otpauth del codice QR mostrato
+action-nearby-codes = Mostra i codici precedenti e successivi dell'elemento selezionato
+action-presentation = Mostra il codice dell'elemento selezionato a schermo intero
+action-recovery-codes = Mostra i codici di recupero dell'elemento selezionato
+action-pin = Fissa in cima il codice selezionato, o rimuovilo dalla cima
+action-hide-codes = Nascondi o mostra tutti i codici
+action-reveal = Mostra il codice selezionato mentre i codici sono nascosti, o il segreto sotto il codice QR
+action-detail-pane = Mostra o nascondi i dettagli del codice selezionato
+action-columns = Scegli le colonne della tabella
+action-mark = Marca il codice selezionato per un'operazione multipla, o smarcalo
+action-tag = Aggiungi un tag ai codici selezionati o marcati, o rimuovilo se lo hanno tutti
+action-export = Esporta i codici selezionati o marcati in un backup di cotp
+action-copy = Copia il codice OTP negli appunti
+action-search = Cerca i codici
+action-tag-filter = Filtra i codici per tag
+action-sort = Cambia l'ordinamento
+action-move-up = Sposta in su il codice selezionato nell'ordinamento manuale
+action-move-down = Sposta in giù il codice selezionato nell'ordinamento manuale
+action-previous = Seleziona il codice precedente
+action-next = Seleziona il codice successivo
+action-undo = Annulla l'ultima modifica
+action-redo = Ripeti l'ultima modifica annullata
+action-help = Mostra questo aiuto
+action-quit = Esci dall'applicazione
+
+## Sezioni dell'aiuto e tasti non configurabili
+
+context-navigation = Navigazione
+context-codes = Codici
+context-selection = Codici marcati
+context-views = Viste
+context-qrcode-page = Pagina del codice QR
+context-search-bar = Barra di ricerca
+context-application = Applicazione
+help-security = Sicurezza
+memory-locked = La memoria è bloccata, quindi i segreti non vengono mai scritti nello swap
+memory-limited = La memoria non è bloccata, il limite RLIMIT_MEMLOCK deve essere illimitato per bloccarla
+memory-lock-failed = La memoria non è bloccata, il sistema si è rifiutato di bloccarla
+memory-lock-unsupported = La memoria non è bloccata, cotp può bloccarla solo su Linux
+help-first-last = Seleziona il primo o l'ultimo codice
+help-search-match = Seleziona il risultato successivo o precedente della ricerca
+help-mark-range = Marca i codici dall'ultimo marcato a quello selezionato
+help-confirm-search = Conferma la ricerca
+help-copy-selected = Copia il codice selezionato
+help-leave-search = Esci dalla barra di ricerca
+help-move-cursor = Sposta il cursore
+help-move-cursor-words = Sposta il cursore di una parola
+help-delete-text = Elimina la parola precedente, il testo prima o dopo il cursore
+help-scroll = Scorri questo aiuto
+
+## Dashboard
+
+popup-help = Aiuto
+popup-alert = Avviso
+status-marked = { $count } marcati
+status-clipboard-clear = Appunti svuotati tra { $seconds }s
+status-help = Premi { $keys } per l'aiuto
+no-element-selected = Nessun elemento selezionato
+detail-expires = Scade tra { $seconds }s
+code-trashed = Codice spostato nel cestino
+codes-trashed = { $count } codici spostati nel cestino
+code-copied = Codice di { $name } copiato
+code-remote-copied = Codice di { $name } copiato da remoto
+column-id = Id
+column-issuer = Emittente
+column-label = Etichetta
+column-otp = OTP
+column-time-left = Scadenza
+column-type = Tipo
+column-tags = Tag
+popup-nope = Niente
+popup-password = Password
+popup-code-info = Informazioni
+popup-details = Dettagli
+popup-recovery-codes = Codici di recupero
+lock-title-one = Bloccato dopo 1 minuto di inattività
+lock-title = Bloccato dopo { $minutes } minuti di inattività
+lock-hint = Invio per sbloccare, Ctrl-C per uscire
+lock-quit-unsaved = Digita la password per salvare le modifiche prima di uscire, di nuovo Ctrl-C per scartarle
+lock-unsaved = Le modifiche non sono state salvate: { $error }
+lock-failed = Impossibile bloccare la dashboard: { $error }
+password-field = Password: { $password }_
+password-wrong = Password errata
+protected-continue = Digita la password del database per continuare
+protected-delete = Digita la password del database per eliminare il codice
+protected-edit = Digita la password del database per modificare il codice
+protected-qrcode = Digita la password del database per mostrare il codice QR
+protected-recovery-codes = Digita la password del database per mostrare i codici di recupero
+protected-export = Digita la password del database per esportare i codici
+terminal-too-small = Il terminale è troppo piccolo, ingrandiscilo
+search-title = Premi { $keys } per cercare un codice...
+qrcode-show-secret = Premi { $keys } per mostrare il segreto
+qrcode-hide-secret = Premi { $keys } per nascondere il segreto
+qrcode-first = Premi { $keys } per mostrare prima il codice QR
+qrcode-path = Percorso dell'immagine del codice QR
+qrcode-saved = Codice QR salvato in { $path }
+qrcode-save-failed = Impossibile salvare il codice QR in { $path }: { $error }
+uri-copied = URI copiato!
+nearby-current = Attuale
+nearby-previous = Precedente
+nearby-next = Successivo
+nearby-valid-in = valido tra { $seconds }s
+nearby-expired = scaduto da { $seconds }s
+nearby-left = { $seconds }s rimasti
+nearby-counter = contatore { $counter }
+no-recovery-codes = Nessun codice di recupero, aggiungili con cotp recovery add
+presentation-counter = Contatore { $counter }
+form-add = Aggiungi codice
+form-edit = Modifica codice
+form-hint-fields = Tab, Giù: campo successivo - Shift+Tab, Su: campo precedente - Sinistra, Destra: cambia opzione
+form-hint-save = Invio: salva - Esc: annulla
+form-label-required = L'etichetta è obbligatoria
+form-invalid-digits = Le cifre devono essere un numero tra 1 e { $max }
+form-invalid-period = Il periodo deve essere un numero positivo
+form-invalid-counter = Il contatore deve essere un numero
+form-pin-required = Il pin è obbligatorio
+form-invalid-domain = Dominio non valido "{ $domain }"
+form-invalid-secret = Segreto non valido
+field-secret = Segreto
+field-algorithm = Algoritmo
+field-digits = Cifre
+field-period = Periodo
+field-counter = Contatore
+field-pin = Pin
+field-suite = Suite
+field-domains = Domini
+field-note = Nota
+field-last-used = Ultimo uso
+field-times-used = Utilizzi
+field-added = Aggiunto
+field-period-from = { $period }s da { $t0 }
+field-never = Mai
+not-available = N/D
+code-added = Codice aggiunto
+code-edited = Codice modificato
+undone = Annullato
+redone = Ripristinato
+nothing-to-undo = Niente da annullare
+nothing-to-redo = Niente da ripristinare
+no-code-selected = Nessun codice selezionato
+no-code-marked = Nessun codice marcato
+index-out-of-bounds = Indice fuori dai limiti
+element-not-found = Impossibile trovare l'elemento all'indice: { $index }
+otp-column-missing = Impossibile leggere la colonna del codice OTP
+copy-failed = Impossibile copiare
+manual-sorting-needed = Premi { $keys } per passare all'ordinamento manuale
+tag-prompt-one = Tag del codice, rimosso se lo ha già
+tag-prompt = Tag dei { $count } codici, rimosso se lo hanno tutti
+tag-empty = Il tag non può essere vuoto
+tag-added-one = Aggiunto { $tag } a 1 codice
+tag-added = Aggiunto { $tag } a { $count } codici
+tag-removed-one = Rimosso { $tag } da 1 codice
+tag-removed = Rimosso { $tag } da { $count } codici
+export-path = Percorso del backup di cotp
+export-failed = Impossibile esportare in { $path }: { $error }
+export-overwrite = { $path } esiste già, sovrascriverlo? [Y/N]
+code-exported = Esportato 1 codice in { $path }
+codes-exported = Esportati { $count } codici in { $path }
+trash-confirm = Vuoi spostare { $issuer } - { $label } nel cestino? [Y/N]
+trash-marked-confirm-one = Vuoi spostare il codice marcato nel cestino? [Y/N]
+trash-marked-confirm = Vuoi spostare i { $count } codici marcati nel cestino? [Y/N]
+columns-menu = Premi un numero per mostrare o nascondere una colonna
+columns-menu-close = Invio per chiudere
+save-confirm = Salvare le modifiche? [Y/N]
+
+## Riga di comando
+
+plain-counter = contatore { $counter }
+plain-second-left = 1 secondo rimasto
+plain-seconds-left = { $seconds } secondi rimasti
+plain-error = errore { $message }
+cli-copied = Copiato negli appunti
+column-problem = Problema
+column-date = Data
+column-host = Host
+column-change = Modifica
+audit-clean = Nessun problema trovato
+audit-invalid-secret = Il segreto non è valido
+audit-short-secret = Il segreto è lungo solo { $bits } bit, ne sono consigliati almeno { $min }
+audit-few-digits = I codici di { $digits } cifre sono facili da indovinare, ne sono consigliate almeno { $min }
+audit-test-secret = Il segreto è un noto segreto di prova
+audit-low-entropy = Il segreto ripete pochi caratteri, controllalo
+audit-duplicate-secret = Stesso segreto dei codici { $indexes }
+history-empty = Nessuna modifica registrata
+remote-not-configured = Nessun server configurato, usa cotp remote configure
+remote-configured = Carica il database con cotp remote push, o sostituiscilo con quello remoto con cotp remote pull
+remote-pushed = Database caricato su { $url }
+remote-unchanged = Il database su { $url } non è cambiato dall'ultimo push o pull
+remote-pulled = Database scaricato da { $url }, il precedente può essere ripristinato con cotp backup
+remote-no-versions = Nessuna versione trovata
+remote-restored = Versione del { $date } ripristinata, caricala con push per sostituire il database remoto
+remote-not-database = Il file su { $url } non è un database di cotp
+remote-password = Password sul server:
+remote-secret-key = Chiave di accesso segreta:
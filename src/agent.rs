@@ -0,0 +1,381 @@
+//! Agent keeping the database unlocked, answering the requests of cotp client on a Unix socket,
+//! like ssh-agent. The socket can be forwarded to remote machines and containers.
+//!
+//! Every message is JSON preceded by its length as a big endian 32 bits integer.
+
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::output::CodeOutput;
+
+/// Requests are small, except for the password of the unlock one
+const MAX_MESSAGE_SIZE: usize = 1024 * 1024;
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum Request {
+    List,
+    GetCode {
+        query: String,
+    },
+    /// Wipes the decrypted codes from the memory of the agent until the next unlock
+    Lock,
+    Unlock {
+        password: String,
+    },
+}
+
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct Response {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub codes: Option<Vec<CodeOutput>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl Response {
+    fn error(error: impl ToString) -> Self {
+        Response {
+            codes: None,
+            error: Some(error.to_string()),
+        }
+    }
+}
+
+/// Socket in the runtime directory of the user, or in a directory of the temporary one created for the user,
+/// which only the user can access
+pub fn default_socket_path() -> PathBuf {
+    match dirs::runtime_dir() {
+        Some(dir) => dir.join("cotp-agent.sock"),
+        None => user_temp_dir().join("agent.sock"),
+    }
+}
+
+#[cfg(unix)]
+fn user_temp_dir() -> PathBuf {
+    std::env::temp_dir().join(format!("cotp-{}", nix::unistd::getuid()))
+}
+
+#[cfg(not(unix))]
+fn user_temp_dir() -> PathBuf {
+    std::env::temp_dir()
+}
+
+#[cfg(unix)]
+mod unix {
+    use std::fs::{self, DirBuilder};
+    use std::io;
+    use std::os::unix::fs::{DirBuilderExt, FileTypeExt, MetadataExt, PermissionsExt};
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::path::Path;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::thread;
+    use std::time::Duration;
+
+    use color_eyre::eyre::eyre;
+    use cotp::crypto::cryptography::DatabaseKey;
+    use cotp::otp::otp_element::OTPDatabase;
+    use nix::unistd::getuid;
+    use zeroize::{Zeroize, Zeroizing};
+
+    use super::{read_message, write_message, Request, Response};
+    use crate::argument_functions::find_single_match;
+    use crate::output::CodeOutput;
+    use crate::signals::TerminationSignals;
+
+    /// Clients taking longer to send a request are disconnected, to not block the others
+    const CLIENT_TIMEOUT: Duration = Duration::from_secs(10);
+
+    struct Agent<'a> {
+        database: OTPDatabase,
+        key: &'a mut DatabaseKey,
+        salt: &'a [u8],
+    }
+
+    impl Agent<'_> {
+        fn handle(&mut self, request: Request) -> Response {
+            if self.database.is_locked() && !matches!(request, Request::Unlock { .. }) {
+                return Response::error("The agent is locked, use cotp client unlock");
+            }
+            match request {
+                Request::List => Response {
+                    codes: Some(
+                        self.database
+                            .elements_ref()
+                            .iter()
+                            .enumerate()
+                            .map(|(i, e)| CodeOutput::new(i, e))
+                            .collect(),
+                    ),
+                    error: None,
+                },
+                Request::GetCode { query } => match find_single_match(&self.database, &query) {
+                    Ok(index) => {
+                        self.database.record_usage(index);
+                        // Safe to unwrap because the index comes from the database
+                        let element = self.database.get_element(index).unwrap();
+                        Response {
+                            codes: Some(vec![CodeOutput::new(index, element)]),
+                            error: None,
+                        }
+                    }
+                    Err(e) => Response::error(e),
+                },
+                // Same as the automatic lock of the dashboard, the key is not needed until the next unlock
+                Request::Lock => match self.database.lock(self.key, self.salt) {
+                    Ok(()) => {
+                        self.key.zeroize();
+                        Response::default()
+                    }
+                    Err(e) => Response::error(e),
+                },
+                Request::Unlock { mut password } => {
+                    let response = if !self.database.is_locked() {
+                        Response::default()
+                    } else {
+                        match self.database.unlock(&password) {
+                            Ok(key) => {
+                                *self.key = key;
+                                Response::default()
+                            }
+                            Err(e) => Response::error(e),
+                        }
+                    };
+                    password.zeroize();
+                    response
+                }
+            }
+        }
+
+        fn serve_client(&mut self, stream: &mut UnixStream) -> color_eyre::Result<()> {
+            stream.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+            // The unlock requests contain the password
+            while let Some(message) = read_message(stream)?.map(Zeroizing::new) {
+                let response = match serde_json::from_slice::<Request>(&message) {
+                    Ok(request) => self.handle(request),
+                    Err(e) => Response::error(format!("Invalid request: {e}")),
+                };
+                write_message(stream, &serde_json::to_vec(&response)?)?;
+            }
+            Ok(())
+        }
+    }
+
+    /// Answers the requests until the process receives SIGINT or SIGTERM, then gives back the database
+    /// to save the usage of the codes
+    pub fn serve(
+        database: OTPDatabase,
+        key: &mut DatabaseKey,
+        salt: &[u8],
+        socket: &Path,
+    ) -> color_eyre::Result<OTPDatabase> {
+        let listener = bind(socket)?;
+        // Blocked before starting the thread answering the clients, so it does not receive the signals
+        let signals = TerminationSignals::block()?;
+        let stop = AtomicBool::new(false);
+        let mut agent = Agent {
+            database,
+            key,
+            salt,
+        };
+        println!("Listening on {}, press Ctrl+C to stop", socket.display());
+
+        thread::scope(|scope| {
+            scope.spawn(|| {
+                for stream in listener.incoming() {
+                    if stop.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    if let Ok(mut stream) = stream {
+                        // Like ssh-agent, the socket permissions are not trusted alone
+                        if !is_same_user(&stream) {
+                            eprintln!("Refused the connection of another user");
+                            continue;
+                        }
+                        if let Err(e) = agent.serve_client(&mut stream) {
+                            eprintln!("Client disconnected: {e}");
+                        }
+                    }
+                }
+            });
+            let result = signals.wait();
+            stop.store(true, Ordering::Relaxed);
+            // Wakes up the thread waiting for a connection
+            let _ = UnixStream::connect(socket);
+            result
+        })?;
+        let _ = fs::remove_file(socket);
+        Ok(agent.database)
+    }
+
+    /// Listens on the socket, which only the user can access
+    fn bind(socket: &Path) -> color_eyre::Result<UnixListener> {
+        if let Some(dir) = socket.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            check_private_dir(dir)?;
+        }
+        match fs::symlink_metadata(socket) {
+            Ok(metadata) => {
+                if !metadata.file_type().is_socket() {
+                    return Err(eyre!("{} exists and is not a socket", socket.display()));
+                }
+                if UnixStream::connect(socket).is_ok() {
+                    return Err(eyre!(
+                        "An agent is already listening on {}",
+                        socket.display()
+                    ));
+                }
+                // Left by an agent which did not stop cleanly
+                fs::remove_file(socket)?;
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+        let listener = UnixListener::bind(socket)?;
+        fs::set_permissions(socket, fs::Permissions::from_mode(0o600))?;
+        Ok(listener)
+    }
+
+    /// Creates the directory of the socket only for the user, or checks that other users cannot replace
+    /// files in it
+    fn check_private_dir(dir: &Path) -> color_eyre::Result<()> {
+        if !dir.exists() {
+            DirBuilder::new().recursive(true).mode(0o700).create(dir)?;
+        }
+        let metadata = fs::metadata(dir)?;
+        if metadata.uid() != getuid().as_raw() || metadata.mode() & 0o022 != 0 {
+            return Err(eyre!(
+                "Other users can write in {}, choose a socket in a directory of yours",
+                dir.display()
+            ));
+        }
+        Ok(())
+    }
+
+    /// Tells if the process at the other end of the socket runs as the same user
+    fn is_same_user(stream: &UnixStream) -> bool {
+        peer_uid(stream).is_ok_and(|uid| uid == getuid().as_raw())
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    fn peer_uid(stream: &UnixStream) -> nix::Result<u32> {
+        use nix::sys::socket::{getsockopt, sockopt::PeerCredentials};
+        getsockopt(stream, PeerCredentials).map(|credentials| credentials.uid())
+    }
+
+    #[cfg(any(
+        target_os = "macos",
+        target_os = "ios",
+        target_os = "freebsd",
+        target_os = "openbsd",
+        target_os = "netbsd",
+        target_os = "dragonfly"
+    ))]
+    fn peer_uid(stream: &UnixStream) -> nix::Result<u32> {
+        nix::unistd::getpeereid(stream).map(|(uid, _)| uid.as_raw())
+    }
+
+    #[cfg(not(any(
+        target_os = "linux",
+        target_os = "android",
+        target_os = "macos",
+        target_os = "ios",
+        target_os = "freebsd",
+        target_os = "openbsd",
+        target_os = "netbsd",
+        target_os = "dragonfly"
+    )))]
+    fn peer_uid(_stream: &UnixStream) -> nix::Result<u32> {
+        Err(nix::errno::Errno::ENOSYS)
+    }
+
+    /// Sends a request to the agent listening on the socket
+    pub fn request(socket: &Path, request: &Request) -> color_eyre::Result<Response> {
+        let mut stream = UnixStream::connect(socket).map_err(|e| {
+            eyre!(
+                "Cannot connect to the agent on {}, is cotp agent running? {e}",
+                socket.display()
+            )
+        })?;
+        // The unlock request sends the password, so it must not reach an agent of another user
+        if !is_same_user(&stream) {
+            return Err(eyre!(
+                "The agent on {} runs as another user",
+                socket.display()
+            ));
+        }
+        write_message(&mut stream, &Zeroizing::new(serde_json::to_vec(request)?))?;
+        let response =
+            read_message(&mut stream)?.ok_or(eyre!("The agent closed the connection"))?;
+        Ok(serde_json::from_slice(&response)?)
+    }
+}
+
+#[cfg(unix)]
+pub use unix::{request, serve};
+
+#[cfg(not(unix))]
+pub fn serve(
+    _database: cotp::otp::otp_element::OTPDatabase,
+    _key: &mut cotp::crypto::cryptography::DatabaseKey,
+    _salt: &[u8],
+    _socket: &std::path::Path,
+) -> color_eyre::Result<cotp::otp::otp_element::OTPDatabase> {
+    Err(color_eyre::eyre::eyre!(
+        "The agent is only available on Unix"
+    ))
+}
+
+#[cfg(not(unix))]
+pub fn request(_socket: &std::path::Path, _request: &Request) -> color_eyre::Result<Response> {
+    Err(color_eyre::eyre::eyre!(
+        "The agent is only available on Unix"
+    ))
+}
+
+/// Reads a message, returning None when the connection is closed
+fn read_message(reader: &mut impl Read) -> io::Result<Option<Vec<u8>>> {
+    let mut length = [0u8; 4];
+    match reader.read_exact(&mut length) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    }
+    let length = u32::from_be_bytes(length) as usize;
+    if length > MAX_MESSAGE_SIZE {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Agent message too long",
+        ));
+    }
+    let mut message = vec![0u8; length];
+    reader.read_exact(&mut message)?;
+    Ok(Some(message))
+}
+
+fn write_message(writer: &mut impl Write, message: &[u8]) -> io::Result<()> {
+    writer.write_all(&(message.len() as u32).to_be_bytes())?;
+    writer.write_all(message)?;
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_message, write_message, Request};
+
+    #[test]
+    fn test_message_round_trip() {
+        let mut buffer = Vec::new();
+        let request = Request::GetCode {
+            query: String::from("github"),
+        };
+        write_message(&mut buffer, &serde_json::to_vec(&request).unwrap()).unwrap();
+        assert_eq!([0, 0, 0, 38], buffer[..4]);
+
+        let mut reader = buffer.as_slice();
+        let message = read_message(&mut reader).unwrap().unwrap();
+        assert_eq!(request, serde_json::from_slice(&message).unwrap());
+        assert!(read_message(&mut reader).unwrap().is_none());
+    }
+}
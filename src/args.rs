@@ -1,11 +1,20 @@
 use std::path::PathBuf;
 
 use clap::{Args, Parser, Subcommand};
+use clap_complete::Shell;
 use color_eyre::eyre::eyre;
 
+use cotp::crypto::cryptography::DatabaseKey;
+use cotp::otp::{
+    otp_algorithm::OTPAlgorithm,
+    otp_element::{DuplicatePolicy, OTPDatabase, MAX_NEARBY_WINDOW},
+    otp_type::OTPType,
+};
+use cotp::search::{CodeFilter, Regex};
+use cotp::utils;
+
 use crate::{
-    argument_functions, dashboard,
-    otp::{otp_algorithm::OTPAlgorithm, otp_element::OTPDatabase, otp_type::OTPType},
+    argument_functions, completions, dashboard, output::OutputFormat, picker::Menu, wizard,
 };
 
 #[derive(Parser)]
@@ -16,10 +25,92 @@ pub struct CotpArgs {
     /// Fetch the password from standard input
     #[arg(long = "password-stdin", default_value_t = false)]
     pub password_from_stdin: bool,
+    /// Fetch the password from the first line of this file, or of a file descriptor like /dev/fd/3.
+    /// The COTP_PASSWORD environment variable is read too, but other processes of the same user may see it
+    #[arg(
+        long = "password-file",
+        value_name = "PATH",
+        conflicts_with = "password_from_stdin"
+    )]
+    pub password_file: Option<PathBuf>,
+    /// Unlock the database with the password stored in the OS keyring, see the keyring subcommand
+    #[arg(long = "use-keyring", default_value_t = false)]
+    pub use_keyring: bool,
+    /// Ask for a Windows Hello, Touch ID or polkit confirmation before reading the password stored in the OS keyring.
+    /// It only confirms the presence of the user, the password stays readable with --use-keyring
+    #[arg(long, default_value_t = false)]
+    pub biometric: bool,
+    /// Seconds after which a code copied from the dashboard is cleared from the clipboard, 0 to keep it.
+    /// Overrides the configuration file
+    #[arg(long = "clipboard-timeout", value_name = "SECONDS")]
+    pub clipboard_timeout: Option<u64>,
+    /// Minutes without input after which the dashboard is locked until the password is typed again, 0 to never lock it.
+    /// Overrides the configuration file
+    #[arg(long = "auto-lock", value_name = "MINUTES")]
+    pub auto_lock: Option<u64>,
+    /// Dashboard colors: dark, light or high-contrast, overriding the configuration file
+    #[arg(long, value_name = "NAME")]
+    pub theme: Option<String>,
+    /// Keyfile needed to unlock the database, if one was set with cotp passwd --keyfile
+    #[arg(long, value_name = "PATH", env = "COTP_KEYFILE")]
+    pub keyfile: Option<PathBuf>,
+    /// Unlock the database with this age identity file, like an age key or an SSH private key, see the age subcommand
+    #[arg(
+        long = "age-identity",
+        value_name = "PATH",
+        env = "COTP_AGE_IDENTITY",
+        conflicts_with = "password_from_stdin"
+    )]
+    pub age_identity: Option<PathBuf>,
+    /// Do not offer to encrypt again a database using weaker key derivation parameters than the configured ones
+    #[arg(long = "no-upgrade", default_value_t = false)]
+    pub no_upgrade: bool,
+    /// Use the database of the profile, see the profile subcommand
+    #[arg(long, value_name = "NAME", env = "COTP_PROFILE")]
+    pub profile: Option<String>,
+    /// Use the database at this path, or the db.cotp file inside this directory
+    #[arg(
+        long = "database-path",
+        value_name = "PATH",
+        env = "COTP_DB_PATH",
+        conflicts_with = "profile"
+    )]
+    pub database_path: Option<PathBuf>,
+}
+
+impl CotpArgs {
+    /// Profile commands are run before unlocking any database
+    pub fn profile_command(&self) -> Option<&ProfileArgs> {
+        match &self.command {
+            Some(CotpSubcommands::Profile(args)) => Some(args),
+            _ => None,
+        }
+    }
+
+    /// Runs the commands which do not unlock the database, returning None for the other ones
+    pub fn run_without_database(&self) -> Option<color_eyre::Result<()>> {
+        match &self.command {
+            // The client asks the codes to the agent
+            Some(CotpSubcommands::Client(args)) => Some(argument_functions::client(args)),
+            Some(CotpSubcommands::Completions(args)) => {
+                completions::print_completions(args.shell);
+                Some(Ok(()))
+            }
+            Some(CotpSubcommands::CompleteNames(args)) => {
+                completions::print_names(args.indexes);
+                Some(Ok(()))
+            }
+            Some(CotpSubcommands::Init(_)) => Some(wizard::init(self.use_keyring)),
+            Some(CotpSubcommands::Config(args)) => Some(argument_functions::config(args)),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Subcommand)]
 enum CotpSubcommands {
+    /// Create the database step by step, importing the codes of another app, like on the first run
+    Init(InitArgs),
     /// Add new OTP code
     Add(AddArgs),
     /// Edit an existing OTP Code
@@ -30,8 +121,65 @@ enum CotpSubcommands {
     Export(ExportArgs),
     /// Copies the selected code into the clipboard
     Extract(ExtractArgs),
+    /// List every code with its current value
+    List(ListArgs),
+    /// Copies the code matching the issuer or label into the clipboard, without opening the dashboard
+    Copy(CopyArgs),
+    /// Show how many times each code has been copied, most used first
+    Stats(StatsArgs),
+    /// Check the codes for invalid, short, public or shared secrets and too few digits
+    Audit(AuditArgs),
+    /// Merge the codes sharing the same secret, keeping the tags and notes of all of them
+    Dedupe(DedupeArgs),
+    /// Add the codes of another cotp database, like the one of a different device
+    Merge(MergeArgs),
     /// Change database password
-    Passwd,
+    Passwd(PasswdArgs),
+    /// Manage the database password stored in the OS keyring
+    Keyring(KeyringArgs),
+    /// Manage the age recipients able to unlock the database without the password
+    Age(AgeArgs),
+    /// List or restore the automatic database backups
+    Backup(BackupArgs),
+    /// Manage the counter of HOTP codes
+    Hotp(HotpArgs),
+    /// Compute the response of an OCRA code to a challenge
+    Ocra(OcraArgs),
+    /// Show the previous, current and next codes, with the time they are valid
+    Show(ShowArgs),
+    /// Store the recovery codes of an account and mark them as used
+    Recovery(RecoveryArgs),
+    /// List, restore or permanently delete the deleted codes
+    Trash(TrashArgs),
+    /// Show when and where the database was changed, oldest changes first
+    History(HistoryArgs),
+    /// Synchronize the codes with a folder shared by git, Syncthing or a cloud drive, merging the changes of every machine
+    Sync(SyncArgs),
+    /// Push the encrypted database to a WebDAV or S3 server, or pull it from there
+    Remote(RemoteArgs),
+    /// Choose a code with rofi, dmenu, wofi or fuzzel and copy it
+    Picker(PickerArgs),
+    /// Answer the requests of a browser extension, started by the browser with the native messaging protocol
+    NativeHost(NativeHostArgs),
+    /// Print the current code on a single line, for the status bars like waybar, polybar and i3status
+    Status(StatusArgs),
+    /// List the codes used on a website, best matches first
+    Match(MatchArgs),
+    /// Keep the database unlocked and answer the requests of cotp client on a Unix socket, like ssh-agent
+    Agent(AgentArgs),
+    /// Ask the codes to a running cotp agent, without unlocking the database
+    Client(ClientArgs),
+    /// Print the completion script of a shell, see the README to complete the code names too
+    Completions(CompletionsArgs),
+    /// Print the cached code names, used by the completion scripts
+    #[command(hide = true)]
+    CompleteNames(CompleteNamesArgs),
+    /// Serve the codes on the D-Bus session bus to the other programs of the desktop, Linux only
+    Daemon(DaemonArgs),
+    /// List, create or delete the profiles, each one with its own database
+    Profile(ProfileArgs),
+    /// Read or change the settings of the configuration file
+    Config(ConfigArgs),
 }
 
 #[derive(Args)]
@@ -45,45 +193,88 @@ pub struct AddArgs {
     pub issuer: String,
 
     /// Code label
-    #[arg(short, long, required = true)]
+    #[arg(short, long, required_unless_present = "scan_screen")]
     pub label: Option<String>,
 
     /// OTP Algorithm
     #[arg(short, long, value_enum, default_value_t = OTPAlgorithm::Sha1)]
     pub algorithm: OTPAlgorithm,
 
-    /// Code digits
+    /// Code digits, 6 unless changed in the configuration file
     #[arg(
         short,
         long,
-        default_value_t = 6,
-        default_value_if("type", "STEAM", "5")
+        default_value_if("otp_type", "steam", "5"),
+        default_value_if("otp_type", "yandex", "8")
     )]
-    pub digits: u64,
+    pub digits: Option<u64>,
 
-    /// Code period
-    #[arg(short = 'e', long, default_value_t = 30)]
-    pub period: u64,
+    /// Code period, 30 unless changed in the configuration file
+    #[arg(short = 'e', long, default_value_if("otp_type", "motp", "10"))]
+    pub period: Option<u64>,
+
+    /// Unix time from which the TOTP periods are counted, to shift them from the default of 0
+    #[arg(long, default_value_t = 0, allow_hyphen_values = true)]
+    pub t0: i64,
 
     /// HOTP counter
-    #[arg(short, long, required_if_eq("otp_type", "HOTP"))]
+    #[arg(short, long, required_if_eq("otp_type", "hotp"))]
     pub counter: Option<u64>,
 
     /// Yandex / MOTP pin
     #[arg(
         short,
         long,
-        required_if_eq("otp_type", "YANDEX"),
-        required_if_eq("otp_type", "MOTP")
+        required_if_eq("otp_type", "yandex"),
+        required_if_eq("otp_type", "motp")
     )]
     pub pin: Option<String>,
+
+    /// OCRA suite, like OCRA-1:HOTP-SHA1-6:QN08, setting the algorithm and digits of the code
+    #[arg(long = "suite", required_if_eq("otp_type", "ocra"))]
+    pub ocra_suite: Option<String>,
+
+    /// Comma separated list of tags
+    #[arg(long, value_delimiter = ',')]
+    pub tags: Vec<String>,
+
+    /// Comma separated list of the websites where the code is used, like github.com
+    #[arg(long, value_delimiter = ',', value_parser = parse_domain)]
+    pub domains: Vec<String>,
+
+    /// Free text about the account, like where its recovery codes are kept
+    #[arg(long, default_value = "")]
+    pub note: String,
+
+    /// Scan the screen looking for QR codes to add
+    #[arg(long = "scan-screen", default_value_t = false)]
+    pub scan_screen: bool,
+
+    /// Add the code even if its secret is invalid, to fix it later with cotp edit
+    #[arg(long)]
+    pub force: bool,
 }
 
 #[derive(Args)]
 pub struct EditArgs {
     /// Code Index
-    #[arg(short, long)]
-    pub index: usize,
+    #[arg(
+        short,
+        long,
+        required_unless_present = "filter",
+        conflicts_with = "filter"
+    )]
+    pub index: Option<usize>,
+
+    /// Edit every code whose field has this value ignoring case, like issuer=Google. Can be repeated,
+    /// the codes must match all of them. Fields are issuer, label, type, algorithm, digits, period, tag and domain
+    #[arg(long, value_parser = parse_assignment)]
+    pub filter: Vec<(String, String)>,
+
+    /// Change a field, like digits=6. Can be repeated. Fields are issuer, label, algorithm, digits, period, t0,
+    /// counter, tags, domains, note and digit_group
+    #[arg(long, value_parser = parse_assignment)]
+    pub set: Vec<(String, String)>,
 
     /// Code issuer
     #[arg(short = 's', long)]
@@ -105,6 +296,10 @@ pub struct EditArgs {
     #[arg(short = 'e', long)]
     pub period: Option<u64>,
 
+    /// Unix time from which the TOTP periods are counted
+    #[arg(long, allow_hyphen_values = true)]
+    pub t0: Option<i64>,
+
     /// HOTP counter
     #[arg(short, long)]
     pub counter: Option<u64>,
@@ -113,8 +308,28 @@ pub struct EditArgs {
     #[arg(short, long)]
     pub pin: Option<String>,
 
+    /// Replace code tags with this comma separated list, pass an empty string to remove them
+    #[arg(short, long, value_delimiter = ',')]
+    pub tags: Option<Vec<String>>,
+
+    /// Replace code websites with this comma separated list, pass an empty string to remove them
+    #[arg(long, value_delimiter = ',', value_parser = parse_domain)]
+    pub domains: Option<Vec<String>>,
+
+    /// Replace the code note, pass an empty string to remove it
+    #[arg(long)]
+    pub note: Option<String>,
+
+    /// Letters or glyph shown in the badge before the issuer, pass an empty string to use its first letter
+    #[arg(long)]
+    pub icon: Option<String>,
+
+    /// Badge color, a name like blue or a hex value like #fb8c00, pass an empty string to use the issuer one
+    #[arg(long)]
+    pub icon_color: Option<String>,
+
     /// Change code secret
-    #[arg(short = 'k', long = "change-secret")]
+    #[arg(short = 'k', long = "change-secret", conflicts_with = "filter")]
     pub change_secret: bool,
 }
 
@@ -126,6 +341,35 @@ pub struct ImportArgs {
     /// Backup file path
     #[arg(short, long)]
     pub path: PathBuf,
+
+    /// Key file used to open the KeePass database, together with its password
+    #[arg(long, requires = "kdbx")]
+    pub keyfile: Option<PathBuf>,
+
+    /// CSV columns of the OTP fields, starting from 1, like issuer=1,label=2,secret=3.
+    /// Valid fields are issuer, label, secret, digits, period, algorithm, type and counter
+    #[arg(long, requires = "csv")]
+    pub map: Option<String>,
+
+    /// The first CSV row contains the column names, used as fields when --map is missing
+    #[arg(long, requires = "csv")]
+    pub header: bool,
+
+    /// CSV values delimiter
+    #[arg(long, requires = "csv", default_value_t = ',')]
+    pub delimiter: char,
+
+    /// What to do with the imported codes having the same secret and issuer of an existing one
+    #[arg(long, value_enum, default_value_t = DuplicatePolicy::Skip)]
+    pub duplicates: DuplicatePolicy,
+
+    /// Print what would be imported without changing the database
+    #[arg(long = "dry-run")]
+    pub dry_run: bool,
+
+    /// Import the codes with an invalid secret too, to fix them later with cotp edit
+    #[arg(long)]
+    pub force: bool,
 }
 
 #[derive(Args)]
@@ -145,6 +389,507 @@ pub struct ExtractArgs {
     /// Copy the code to the clipboard
     #[arg(short, long = "copy-clipboard", default_value_t = false)]
     pub copy_to_clipboard: bool,
+
+    /// Output format
+    #[arg(short, long, value_enum, default_value_t = OutputFormat::Text)]
+    pub format: OutputFormat,
+}
+
+#[derive(Args)]
+pub struct ListArgs {
+    /// Output format
+    #[arg(short, long, value_enum, default_value_t = OutputFormat::Text)]
+    pub format: OutputFormat,
+
+    /// Only the codes whose issuer or label matches this regular expression, ignoring case
+    #[arg(long, value_parser = parse_regex)]
+    pub search: Option<Regex>,
+
+    /// Only the codes whose issuer matches this glob, like git*, ignoring case
+    #[arg(long, value_parser = parse_glob)]
+    pub issuer: Option<Regex>,
+
+    /// Only the codes whose label matches this glob, like *@example.com, ignoring case
+    #[arg(long, value_parser = parse_glob)]
+    pub label: Option<Regex>,
+
+    /// Only the codes of this type
+    #[arg(short = 't', long = "type", value_enum)]
+    pub otp_type: Option<OTPType>,
+
+    /// One sentence per code, like "1. GitHub alice: 123456, 20 seconds left", for screen readers and scripts
+    #[arg(long, default_value_t = false, conflicts_with = "format")]
+    pub plain: bool,
+
+    /// Keep running, printing again the codes changing when their period ends
+    #[arg(long, default_value_t = false, requires = "plain")]
+    pub follow: bool,
+}
+
+impl ListArgs {
+    pub fn code_filter(&self) -> CodeFilter {
+        CodeFilter {
+            search: self.search.clone(),
+            issuer: self.issuer.clone(),
+            label: self.label.clone(),
+            type_: self.otp_type,
+        }
+    }
+}
+
+#[derive(Args)]
+pub struct StatusArgs {
+    /// Issuer, label or "issuer:label" of the code, partial matches are allowed if unambiguous
+    #[arg(short, long)]
+    pub entry: String,
+
+    /// Keep printing a new line every second, when the remaining time or the code changes
+    #[arg(short, long, default_value_t = false)]
+    pub watch: bool,
+
+    /// Output format, json follows the format of the waybar custom modules
+    #[arg(short, long, value_enum, default_value_t = OutputFormat::Text)]
+    pub format: OutputFormat,
+}
+
+#[derive(Args)]
+pub struct MatchArgs {
+    /// Address or domain of the website, like https://github.com/login
+    pub url: String,
+
+    /// Output format
+    #[arg(short, long, value_enum, default_value_t = OutputFormat::Text)]
+    pub format: OutputFormat,
+}
+
+#[derive(Args)]
+pub struct StatsArgs {
+    /// Output format
+    #[arg(short, long, value_enum, default_value_t = OutputFormat::Text)]
+    pub format: OutputFormat,
+}
+
+#[derive(Args)]
+pub struct AuditArgs {
+    /// Output format
+    #[arg(short, long, value_enum, default_value_t = OutputFormat::Text)]
+    pub format: OutputFormat,
+}
+
+#[derive(Args)]
+pub struct DedupeArgs {
+    /// Merge every group into its first code without asking
+    #[arg(short, long, default_value_t = false)]
+    pub yes: bool,
+}
+
+#[derive(Args)]
+pub struct HistoryArgs {
+    /// Only show the changes mentioning this text, like the issuer of a code, case insensitively
+    pub query: Option<String>,
+
+    /// Output format
+    #[arg(short, long, value_enum, default_value_t = OutputFormat::Text)]
+    pub format: OutputFormat,
+}
+
+#[derive(Args)]
+pub struct SyncArgs {
+    /// Folder to synchronize with, created if missing. Needed only the first time
+    pub folder: Option<PathBuf>,
+
+    /// Stop synchronizing, leaving the folder untouched
+    #[arg(long, conflicts_with = "folder")]
+    pub forget: bool,
+}
+
+#[derive(Args)]
+pub struct RemoteArgs {
+    #[command(subcommand)]
+    pub action: RemoteAction,
+}
+
+#[derive(Subcommand)]
+pub enum RemoteAction {
+    /// Store the address of the server and its credentials in the database, the secret will be asked
+    Configure {
+        #[command(subcommand)]
+        server: RemoteServer,
+    },
+    /// Upload the database, unless another machine pushed a newer one since the last push or pull
+    Push {
+        /// Replace the remote database even if another machine changed it
+        #[arg(long)]
+        force: bool,
+    },
+    /// Replace the database with the remote one, backing it up first
+    Pull,
+    /// List the versions kept by the previous pushes, S3 servers only
+    Versions,
+    /// Replace the database with a version kept by a previous push, backing it up first
+    Restore {
+        /// Timestamp of the version, as shown by the versions command
+        #[arg(long)]
+        version: u64,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum RemoteServer {
+    /// WebDAV server like Nextcloud
+    Webdav {
+        /// Address of the database file on the server
+        #[arg(long)]
+        url: String,
+
+        /// Username on the server, none if empty
+        #[arg(long, default_value = "")]
+        username: String,
+    },
+    /// S3 compatible object storage like AWS, MinIO or Backblaze B2
+    S3 {
+        /// Address of the server, like https://s3.eu-west-1.amazonaws.com
+        #[arg(long)]
+        endpoint: String,
+
+        /// Region of the bucket, us-east-1 if missing
+        #[arg(long)]
+        region: Option<String>,
+
+        #[arg(long)]
+        bucket: String,
+
+        /// Key of the database object in the bucket
+        #[arg(long, default_value = "cotp/db.cotp")]
+        key: String,
+
+        /// Access key id, the secret key will be asked
+        #[arg(long)]
+        access_key: String,
+    },
+}
+
+#[derive(Args)]
+pub struct MergeArgs {
+    /// Path of the other cotp database, its password will be asked
+    pub path: PathBuf,
+
+    /// What to do with the codes having the same secret and issuer of an existing one
+    #[arg(long, value_enum, default_value_t = DuplicatePolicy::Skip)]
+    pub duplicates: DuplicatePolicy,
+
+    /// Print what would be merged without changing the database
+    #[arg(long = "dry-run")]
+    pub dry_run: bool,
+}
+
+#[derive(Args)]
+pub struct CopyArgs {
+    /// Issuer, label or "issuer:label" of the code, partial matches are allowed if unambiguous
+    pub query: String,
+
+    /// Print the code on the standard output instead of copying it
+    #[arg(long, default_value_t = false)]
+    pub stdout: bool,
+
+    /// Type the code into the focused window instead of copying it
+    #[arg(long = "type", default_value_t = false, conflicts_with = "stdout")]
+    pub type_code: bool,
+}
+
+#[derive(Args)]
+pub struct PasswdArgs {
+    /// Combine the password with the HMAC-SHA1 challenge-response of a YubiKey, the password can be left empty
+    #[arg(long, default_value_t = false)]
+    pub yubikey: bool,
+
+    /// YubiKey slot configured for challenge-response
+    #[arg(long, requires = "yubikey", default_value_t = 2, value_parser = clap::value_parser!(u8).range(1..=2))]
+    pub slot: u8,
+
+    /// Stop requiring the YubiKey to unlock the database
+    #[arg(long, conflicts_with = "yubikey", default_value_t = false)]
+    pub remove_yubikey: bool,
+
+    /// Combine the password with a keyfile, which is created with random content if missing
+    #[arg(long, value_name = "PATH")]
+    pub keyfile: Option<PathBuf>,
+
+    /// Stop requiring the keyfile to unlock the database
+    #[arg(long, conflicts_with = "keyfile", default_value_t = false)]
+    pub remove_keyfile: bool,
+
+    /// Benchmark this machine to choose Argon2 parameters taking about half a second, instead of the configured ones
+    #[arg(long, default_value_t = false)]
+    pub calibrate: bool,
+}
+
+#[derive(Args)]
+pub struct BackupArgs {
+    #[command(subcommand)]
+    pub action: BackupAction,
+}
+
+#[derive(Subcommand)]
+pub enum BackupAction {
+    /// List the available backups, newest first
+    List,
+    /// Replace the database with a backup, the current database is backed up first
+    Restore {
+        /// Backup id, its timestamp as shown by the list command
+        id: String,
+    },
+}
+
+#[derive(Args)]
+pub struct ConfigArgs {
+    #[command(subcommand)]
+    pub action: ConfigAction,
+}
+
+#[derive(Subcommand)]
+pub enum ConfigAction {
+    /// Print a setting, a section or the whole configuration, defaults included
+    Get {
+        /// Setting name, like dashboard.auto_lock
+        key: Option<String>,
+    },
+    /// Change a setting of the configuration file
+    Set {
+        /// Setting name, like dashboard.auto_lock
+        key: String,
+        /// New value, like 5, true, light or '["c", "y"]'
+        value: String,
+    },
+}
+
+#[derive(Args)]
+pub struct ProfileArgs {
+    #[command(subcommand)]
+    pub action: ProfileAction,
+}
+
+#[derive(Subcommand)]
+pub enum ProfileAction {
+    /// List the profiles, marking the selected one
+    List,
+    /// Create a new profile with an empty database
+    Create {
+        /// Profile name, made of letters, digits, - and _
+        name: String,
+    },
+    /// Delete a profile and its database
+    Delete {
+        /// Profile name
+        name: String,
+    },
+}
+
+#[derive(Args)]
+pub struct AgeArgs {
+    #[command(subcommand)]
+    pub action: AgeAction,
+}
+
+#[derive(Args)]
+pub struct RecoveryArgs {
+    #[command(subcommand)]
+    pub action: RecoveryAction,
+}
+
+#[derive(Subcommand)]
+pub enum RecoveryAction {
+    /// Add recovery codes, read from the standard input one per line
+    Add {
+        /// Issuer, label or "issuer:label" of the code, partial matches are allowed if unambiguous
+        query: String,
+    },
+    /// List the recovery codes, with the time the used ones were used
+    List {
+        /// Issuer, label or "issuer:label" of the code, partial matches are allowed if unambiguous
+        query: String,
+    },
+    /// Print the first unused recovery code and mark it as used
+    Use {
+        /// Issuer, label or "issuer:label" of the code, partial matches are allowed if unambiguous
+        query: String,
+        /// Number of the recovery code to mark as used, as shown by the list command
+        number: Option<usize>,
+    },
+}
+
+#[derive(Args)]
+pub struct TrashArgs {
+    #[command(subcommand)]
+    pub action: TrashAction,
+}
+
+#[derive(Subcommand)]
+pub enum TrashAction {
+    /// List the deleted codes, with the time they were deleted
+    List,
+    /// Move a deleted code back to the end of the codes
+    Restore {
+        /// Number of the deleted code, as shown by the list command
+        number: usize,
+    },
+    /// Permanently delete a deleted code, or all of them
+    Purge {
+        /// Number of the deleted code, as shown by the list command
+        number: Option<usize>,
+    },
+}
+
+#[derive(Args)]
+pub struct ShowArgs {
+    /// Issuer, label or "issuer:label" of the code, partial matches are allowed if unambiguous
+    pub query: String,
+
+    /// How many codes to show before and after the current one, at most 100
+    #[arg(short, long, default_value_t = 1, value_parser = clap::value_parser!(u64).range(0..=MAX_NEARBY_WINDOW))]
+    pub window: u64,
+}
+
+#[derive(Args)]
+pub struct PickerArgs {
+    /// Program showing the list of codes
+    #[arg(short, long, value_enum, default_value_t = Menu::Rofi)]
+    pub menu: Menu,
+
+    /// Print the code on the standard output instead of copying it
+    #[arg(long, default_value_t = false)]
+    pub stdout: bool,
+
+    /// Type the code into the focused window instead of copying it
+    #[arg(long = "type", default_value_t = false, conflicts_with = "stdout")]
+    pub type_code: bool,
+}
+
+#[derive(Args)]
+pub struct DaemonArgs {}
+
+#[derive(Args)]
+pub struct InitArgs {}
+
+#[derive(Args)]
+pub struct CompletionsArgs {
+    pub shell: Shell,
+}
+
+#[derive(Args)]
+pub struct CompleteNamesArgs {
+    /// Print the index of the codes before a tab
+    #[arg(long, default_value_t = false)]
+    pub indexes: bool,
+}
+
+#[derive(Args)]
+pub struct AgentArgs {
+    /// Path of the socket, in the runtime directory by default
+    #[arg(long, env = "COTP_AGENT_SOCK")]
+    pub socket: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct ClientArgs {
+    #[command(subcommand)]
+    pub action: ClientAction,
+
+    /// Path of the socket of the agent, in the runtime directory by default
+    #[arg(long, env = "COTP_AGENT_SOCK", global = true)]
+    pub socket: Option<PathBuf>,
+
+    /// Output format
+    #[arg(short, long, value_enum, default_value_t = OutputFormat::Text, global = true)]
+    pub format: OutputFormat,
+}
+
+#[derive(Subcommand)]
+pub enum ClientAction {
+    /// List the codes with their current value
+    List,
+    /// Print the current code
+    GetCode {
+        /// Issuer, label or "issuer:label" of the code, partial matches are allowed if unambiguous
+        query: String,
+    },
+    /// Wipe the decrypted codes from the memory of the agent
+    Lock,
+    /// Unlock the agent with the database password
+    Unlock,
+}
+
+#[derive(Args)]
+pub struct NativeHostArgs {
+    /// Origin of the extension and other arguments added by the browser, not used
+    #[arg(hide = true, trailing_var_arg = true, allow_hyphen_values = true)]
+    pub browser_args: Vec<String>,
+}
+
+#[derive(Args)]
+pub struct OcraArgs {
+    /// Issuer, label or "issuer:label" of the code, partial matches are allowed if unambiguous
+    pub query: String,
+
+    /// Challenge shown by the service, in the format of the suite
+    #[arg(short, long)]
+    pub challenge: String,
+
+    /// Hexadecimal session information, for suites using it
+    #[arg(short, long)]
+    pub session: Option<String>,
+}
+
+#[derive(Args)]
+pub struct HotpArgs {
+    #[command(subcommand)]
+    pub action: HotpAction,
+}
+
+#[derive(Subcommand)]
+pub enum HotpAction {
+    /// Realigns the stored counter with the server, using two consecutive codes it accepted or asked for
+    Resync {
+        /// Issuer, label or "issuer:label" of the code, partial matches are allowed if unambiguous
+        query: String,
+        /// First code
+        first_code: String,
+        /// The code following the first one
+        second_code: String,
+        /// How many counter values after the stored one are searched
+        #[arg(long, default_value_t = 100)]
+        window: u64,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum AgeAction {
+    /// List the recipients
+    List,
+    /// Let the owner of an age or SSH key unlock the database, run cotp with --age-identity to use it
+    Add {
+        /// age recipient, like age1..., or SSH public key, like "ssh-ed25519 AAAA..."
+        recipient: String,
+    },
+    /// Stop a recipient from unlocking the database
+    Remove {
+        /// Recipient, as shown by the list command
+        recipient: String,
+    },
+}
+
+#[derive(Args)]
+pub struct KeyringArgs {
+    #[command(subcommand)]
+    pub action: KeyringAction,
+}
+
+#[derive(Subcommand)]
+pub enum KeyringAction {
+    /// Store the database password, used when running with --use-keyring
+    Set,
+    /// Remove the stored database password
+    Clear,
 }
 
 #[derive(Args)]
@@ -156,6 +901,18 @@ pub struct ExportArgs {
     /// Export format
     #[command(flatten)]
     pub format: Option<ExportFormat>,
+
+    /// Leave the secrets column empty, to export an inventory of the codes
+    #[arg(long = "redact-secrets", requires = "csv")]
+    pub redact_secrets: bool,
+
+    /// Encrypt the export with GnuPG to this OpenPGP key ID, fingerprint or email, can be repeated
+    #[arg(
+        long = "gpg-recipient",
+        value_name = "KEYID",
+        conflicts_with = "qr_png"
+    )]
+    pub gpg_recipients: Vec<String>,
 }
 
 #[derive(Args)]
@@ -169,6 +926,10 @@ pub struct BackupType {
     #[arg(short = 'e', long)]
     pub andotp: bool,
 
+    /// Import from andOTP encrypted backup
+    #[arg(long = "andotp-encrypted")]
+    pub andotp_encrypted: bool,
+
     /// Import from Aegis backup
     #[arg(short, long)]
     pub aegis: bool,
@@ -177,15 +938,31 @@ pub struct BackupType {
     #[arg(short = 'k', long = "aegis-encrypted")]
     pub aegis_encrypted: bool,
 
+    /// Import the TOTP secrets of an unencrypted Bitwarden JSON export
+    #[arg(long)]
+    pub bitwarden: bool,
+
+    /// Import from a CSV file, see the --map option
+    #[arg(long)]
+    pub csv: bool,
+
+    /// Import the third party accounts of Duo Mobile
+    #[arg(long)]
+    pub duo: bool,
+
+    /// Import from Ente Auth export, plain text or password encrypted
+    #[arg(long)]
+    pub ente: bool,
+
     /// Import from FreeOTP+ backup
     #[arg(short, long = "freeotp-plus")]
     pub freeotp_plus: bool,
 
-    /// Import from FreeOTP backup
+    /// Import from FreeOTP tokens.xml file or JSON tokens list
     #[arg(short = 'r', long)]
     pub freeotp: bool,
 
-    /// Import from Google Authenticator backup
+    /// Import from Google Authenticator backup or from a list of otpauth-migration:// URIs
     #[arg(short, long = "google-authenticator")]
     pub google_authenticator: bool,
 
@@ -194,9 +971,18 @@ pub struct BackupType {
     pub authy: bool,
 
     /// Import from Authy Database exported following this guide https://gist.github.com/gboudreau/94bb0c11a6209c82418d01a59d958c93
+    /// or from the decrypted tokens JSON returned by the Authy API
     #[arg(short = 'u', long = "authy-exported")]
     pub authy_exported: bool,
 
+    /// Import the OTP secrets stored in a KeePass or KeePassXC database
+    #[arg(long)]
+    pub kdbx: bool,
+
+    /// Import from 2FAS Authenticator backup, plain or password encrypted
+    #[arg(long = "two-fas")]
+    pub two_fas: bool,
+
     /// Import from Microsoft Authenticator
     #[arg(short = 'm', long = "microsoft-authenticator")]
     pub microsoft_authenticator: bool,
@@ -204,6 +990,41 @@ pub struct BackupType {
     /// Import from OTP Uri batch
     #[arg(short, long = "otp-uri")]
     pub otp_uri: bool,
+
+    /// Import from a PNG/JPEG image containing QR codes
+    #[arg(short, long = "qr-image")]
+    pub qr_image: bool,
+}
+
+impl BackupType {
+    /// Name of the chosen format, like its flag
+    pub fn name(&self) -> &'static str {
+        [
+            (self.cotp, "cotp"),
+            (self.andotp, "andOTP"),
+            (self.andotp_encrypted, "andOTP encrypted"),
+            (self.aegis, "Aegis"),
+            (self.aegis_encrypted, "Aegis encrypted"),
+            (self.bitwarden, "Bitwarden"),
+            (self.csv, "CSV"),
+            (self.duo, "Duo"),
+            (self.ente, "Ente Auth"),
+            (self.freeotp_plus, "FreeOTP+"),
+            (self.freeotp, "FreeOTP"),
+            (self.google_authenticator, "Google Authenticator"),
+            (self.authy, "Authy"),
+            (self.authy_exported, "Authy exported"),
+            (self.kdbx, "KeePass"),
+            (self.two_fas, "2FAS"),
+            (self.microsoft_authenticator, "Microsoft Authenticator"),
+            (self.otp_uri, "OTP URI"),
+            (self.qr_image, "QR image"),
+        ]
+        .into_iter()
+        .find(|(chosen, _)| *chosen)
+        .map(|(_, name)| name)
+        .unwrap_or_default()
+    }
 }
 
 #[derive(Args)]
@@ -224,6 +1045,26 @@ pub struct ExportFormat {
     /// Export into the FreeOTP+ database format
     #[arg(short, long = "freeotp-plus")]
     pub freeotp_plus: bool,
+
+    /// Export into a plain text file containing an OTP URI per line
+    #[arg(short = 't', long = "otp-uri-text")]
+    pub otp_uri_text: bool,
+
+    /// Export each code as a QR code PNG image inside the directory given by --path
+    #[arg(short, long = "qr-png")]
+    pub qr_png: bool,
+
+    /// Export into the Ente Auth plain text format
+    #[arg(long)]
+    pub ente: bool,
+
+    /// Export into the Ente Auth encrypted format, protected by a new password
+    #[arg(long = "ente-encrypted")]
+    pub ente_encrypted: bool,
+
+    /// Export into a CSV file, with the issuer, label, secret, algorithm, digits, period, type and counter columns
+    #[arg(long)]
+    pub csv: bool,
 }
 
 impl Default for ExportFormat {
@@ -233,20 +1074,99 @@ impl Default for ExportFormat {
             andotp: false,
             otp_uri: false,
             freeotp_plus: false,
+            otp_uri_text: false,
+            qr_png: false,
+            ente: false,
+            ente_encrypted: false,
+            csv: false,
         }
     }
 }
 
-pub fn args_parser(matches: CotpArgs, read_result: OTPDatabase) -> color_eyre::Result<OTPDatabase> {
+/// Domain of a website, accepting full addresses too. Empty values are used to remove the domains.
+fn parse_domain(value: &str) -> Result<String, String> {
+    if value.trim().is_empty() {
+        return Ok(String::new());
+    }
+    utils::url_host(value).ok_or(format!("Invalid domain \"{value}\""))
+}
+
+fn parse_regex(value: &str) -> Result<Regex, String> {
+    Regex::new(value).map_err(|e| e.to_string())
+}
+
+fn parse_glob(value: &str) -> Result<Regex, String> {
+    Regex::glob(value).map_err(|e| e.to_string())
+}
+
+/// Field and value of a --filter or --set argument, like issuer=Google
+fn parse_assignment(value: &str) -> Result<(String, String), String> {
+    value
+        .split_once('=')
+        .map(|(field, value)| (field.trim().to_lowercase(), value.to_owned()))
+        .ok_or(format!("Expected field=value, found \"{value}\""))
+}
+
+pub fn args_parser(
+    matches: CotpArgs,
+    read_result: OTPDatabase,
+    key: &mut DatabaseKey,
+    salt: &[u8],
+) -> color_eyre::Result<OTPDatabase> {
     match matches.command {
         Some(CotpSubcommands::Add(args)) => argument_functions::add(args, read_result),
         Some(CotpSubcommands::Edit(args)) => argument_functions::edit(args, read_result),
         Some(CotpSubcommands::Import(args)) => argument_functions::import(args, read_result),
         Some(CotpSubcommands::Export(args)) => argument_functions::export(args, read_result),
         Some(CotpSubcommands::Extract(args)) => argument_functions::extract(args, read_result),
-        Some(CotpSubcommands::Passwd) => argument_functions::change_password(read_result),
+        Some(CotpSubcommands::List(args)) => argument_functions::list(args, read_result),
+        Some(CotpSubcommands::Copy(args)) => argument_functions::copy(args, read_result),
+        Some(CotpSubcommands::Stats(args)) => argument_functions::stats(args, read_result),
+        Some(CotpSubcommands::Audit(args)) => argument_functions::audit(args, read_result),
+        Some(CotpSubcommands::Dedupe(args)) => argument_functions::dedupe(args, read_result),
+        Some(CotpSubcommands::Merge(args)) => argument_functions::merge(args, read_result),
+        Some(CotpSubcommands::Passwd(args)) => {
+            argument_functions::change_password(args, read_result)
+        }
+        Some(CotpSubcommands::Keyring(args)) => argument_functions::keyring(args, read_result),
+        Some(CotpSubcommands::Age(args)) => argument_functions::age(args, read_result),
+        Some(CotpSubcommands::Backup(args)) => argument_functions::backup(args, read_result),
+        Some(CotpSubcommands::Hotp(args)) => argument_functions::hotp(args, read_result),
+        Some(CotpSubcommands::Ocra(args)) => argument_functions::ocra(args, read_result),
+        Some(CotpSubcommands::Show(args)) => argument_functions::show(args, read_result),
+        Some(CotpSubcommands::Recovery(args)) => argument_functions::recovery(args, read_result),
+        Some(CotpSubcommands::Trash(args)) => argument_functions::trash(args, read_result),
+        Some(CotpSubcommands::History(args)) => argument_functions::history(args, read_result),
+        Some(CotpSubcommands::Sync(args)) => argument_functions::sync(args, read_result),
+        Some(CotpSubcommands::Remote(args)) => argument_functions::remote(args, read_result),
+        Some(CotpSubcommands::Picker(args)) => argument_functions::picker(args, read_result),
+        Some(CotpSubcommands::NativeHost(args)) => {
+            argument_functions::native_host(args, read_result)
+        }
+        Some(CotpSubcommands::Status(args)) => argument_functions::status(args, read_result),
+        Some(CotpSubcommands::Match(args)) => argument_functions::match_url(args, read_result),
+        Some(CotpSubcommands::Agent(args)) => {
+            argument_functions::agent(args, read_result, key, salt)
+        }
+        // Already handled before unlocking the database
+        Some(CotpSubcommands::Client(_))
+        | Some(CotpSubcommands::Completions(_))
+        | Some(CotpSubcommands::CompleteNames(_))
+        | Some(CotpSubcommands::Init(_))
+        | Some(CotpSubcommands::Config(_)) => Ok(read_result),
+        Some(CotpSubcommands::Daemon(args)) => argument_functions::daemon(args, read_result),
+        // Already handled before unlocking the database
+        Some(CotpSubcommands::Profile(_)) => Ok(read_result),
         // no args, show dashboard
-        None => dashboard(read_result).map_err(|e| eyre!("An error occurred: {e}")),
+        None => dashboard(
+            read_result,
+            key,
+            salt,
+            matches.clipboard_timeout,
+            matches.theme.as_deref(),
+            matches.auto_lock,
+        )
+        .map_err(|e| eyre!("An error occurred: {e}")),
     }
 }
 
@@ -255,3 +1175,35 @@ fn verify_cli() {
     use clap::CommandFactory;
     CotpArgs::command().debug_assert()
 }
+
+#[test]
+fn verify_type_dependent_arguments() {
+    let parse = |args: &[&str]| CotpArgs::try_parse_from([&["cotp", "add"], args].concat());
+
+    let steam = parse(&["-t", "steam", "-l", "label"]).unwrap();
+    match steam.command {
+        Some(CotpSubcommands::Add(args)) => assert_eq!(Some(5), args.digits),
+        _ => panic!("Expected add subcommand"),
+    }
+
+    assert!(parse(&["-t", "hotp", "-l", "label"]).is_err());
+    assert!(parse(&["-t", "yandex", "-l", "label"]).is_err());
+    assert!(parse(&["-t", "motp", "-l", "label"]).is_err());
+}
+
+#[test]
+fn verify_domains() {
+    let parse =
+        |args: &[&str]| CotpArgs::try_parse_from([&["cotp", "add", "-l", "label"], args].concat());
+
+    match parse(&["--domains", "GitHub.com,https://gist.github.com/new"])
+        .unwrap()
+        .command
+    {
+        Some(CotpSubcommands::Add(args)) => {
+            assert_eq!(vec!["github.com", "gist.github.com"], args.domains)
+        }
+        _ => panic!("Expected add subcommand"),
+    }
+    assert!(parse(&["--domains", "not a domain"]).is_err());
+}
@@ -1,18 +1,116 @@
-use crate::args::{AddArgs, EditArgs, ExportArgs, ExtractArgs, ImportArgs};
-use crate::exporters::do_export;
-use crate::exporters::otp_uri::OtpUriList;
-use crate::importers::aegis::AegisJson;
-use crate::importers::aegis_encrypted::AegisEncryptedDatabase;
-use crate::importers::authy_remote_debug::AuthyExportedList;
-use crate::importers::converted::ConvertedJsonList;
-use crate::importers::freeotp_plus::FreeOTPPlusJson;
-use crate::importers::importer::import_from_path;
-use crate::otp::otp_element::{OTPDatabase, OTPElement};
-use crate::{clipboard, utils};
+use crate::args::{
+    AddArgs, AgeAction, AgeArgs, AgentArgs, AuditArgs, BackupAction, BackupArgs, ClientAction,
+    ClientArgs, ConfigAction, ConfigArgs, CopyArgs, DaemonArgs, DedupeArgs, EditArgs, ExportArgs,
+    ExportFormat, ExtractArgs, HistoryArgs, HotpAction, HotpArgs, ImportArgs, KeyringAction,
+    KeyringArgs, ListArgs, MatchArgs, MergeArgs, NativeHostArgs, OcraArgs, PasswdArgs, PickerArgs,
+    ProfileAction, ProfileArgs, RecoveryAction, RecoveryArgs, RemoteAction, RemoteArgs,
+    RemoteServer, ShowArgs, StatsArgs, StatusArgs, SyncArgs, TrashAction, TrashArgs,
+};
+use crate::i18n::{tr, tr_args};
+use crate::output::{
+    print_audit, print_code, print_codes, print_history, print_plain, print_stats, print_status,
+    AuditOutput, CodeOutput, OutputFormat, StatsOutput, StatusOutput,
+};
+use crate::{
+    agent, autotype, clipboard, dbus_service, native_host, os_keyring, picker, screenshot,
+};
 use color_eyre::eyre::{eyre, ErrReport};
+use cotp::backup::{list_backups, replace_database, restore_backup};
+use cotp::clock;
+use cotp::config::{set_config_value, Config, DefaultsConfig};
+use cotp::crypto::age::is_valid_recipient;
+use cotp::crypto::cryptography::{calibrate_kdf_params, DatabaseKey, KdfParams};
+use cotp::crypto::encrypted_database::EncryptedDatabase;
+use cotp::crypto::keyfile::{generate_keyfile, hash_keyfile};
+use cotp::exporters::csv::to_csv;
+use cotp::exporters::ente::{encrypt_ente_export, to_ente_text};
+use cotp::exporters::gpg::{do_export_gpg, gpg_file_name};
+use cotp::exporters::otp_uri::{is_plain_text_list, OtpUriList};
+use cotp::exporters::qr_png::export_qr_images;
+use cotp::exporters::{do_export_text, to_export_json};
+use cotp::importers::aegis::AegisJson;
+use cotp::importers::aegis_encrypted::AegisEncryptedDatabase;
+use cotp::importers::andotp::AndOtpJson;
+use cotp::importers::andotp_encrypted::import_from_andotp_encrypted;
+use cotp::importers::authy_remote_debug::AuthyExportedList;
+use cotp::importers::bitwarden::BitwardenExport;
+use cotp::importers::converted::ConvertedJsonList;
+use cotp::importers::csv::{import_from_csv, CsvOptions};
+use cotp::importers::duo::DuoAccounts;
+use cotp::importers::ente::import_from_ente;
+use cotp::importers::freeotp::import_from_freeotp;
+use cotp::importers::freeotp_plus::FreeOTPPlusJson;
+use cotp::importers::google_authenticator::{is_migration_export, GoogleAuthenticatorMigration};
+use cotp::importers::importer::{import_from_path, import_from_text_path};
+use cotp::importers::kdbx::import_from_kdbx;
+use cotp::importers::microsoft_authenticator::MicrosoftAuthenticatorExport;
+use cotp::importers::qr_image::{decode_qr_codes, import_from_qr_image};
+use cotp::importers::two_fas::TwoFasBackup;
+use cotp::otp::algorithms::ocra_maker::OcraSuite;
+use cotp::otp::audit::{audit_element, audit_elements, mergeable_groups, Finding};
+use cotp::otp::otp_element::{
+    DuplicatePolicy, HistoryEntry, Icon, MergeOutcome, OTPDatabase, OTPElement, RecoveryCode,
+};
+use cotp::otp::otp_type::OTPType;
+use cotp::path::{
+    get_db_path, get_profile_path, has_custom_db_path, list_profiles, set_profile, DEFAULT_PROFILE,
+};
+use cotp::reading::{read_from_file, read_from_path};
+use cotp::remote::{self, s3::S3Config, webdav::WebDavConfig, Precondition, RemoteConfig};
+use cotp::sync::{self, SyncReport};
+use cotp::utils;
+use std::cmp::Reverse;
+use std::io::{self, ErrorKind, IsTerminal, Write};
+use std::path::Path;
+use std::str::FromStr;
+use std::time::Duration;
 use zeroize::Zeroize;
 
-pub fn import(matches: ImportArgs, mut database: OTPDatabase) -> color_eyre::Result<OTPDatabase> {
+/// Key derivation time targeted by passwd --calibrate
+const CALIBRATION_TARGET_MILLIS: u64 = 500;
+
+pub fn import(matches: ImportArgs, database: OTPDatabase) -> color_eyre::Result<OTPDatabase> {
+    let (duplicates, dry_run, force) = (matches.duplicates, matches.dry_run, matches.force);
+    let source = backup_source(&matches);
+    let elements = check_secrets(read_backup(matches)?, force);
+    Ok(merge_elements(
+        database, elements, duplicates, dry_run, &source,
+    ))
+}
+
+/// Warns about the weak secrets of the added or imported codes, leaving out the invalid ones unless forced
+pub(crate) fn check_secrets(elements: Vec<OTPElement>, force: bool) -> Vec<OTPElement> {
+    elements
+        .into_iter()
+        .filter(|element| {
+            let findings = audit_element(element);
+            for finding in &findings {
+                eprintln!("{}: {finding}", element.name());
+            }
+            let invalid = findings.contains(&Finding::InvalidSecret);
+            if invalid && !force {
+                eprintln!("Skipping {}, add it anyway with --force", element.name());
+            }
+            !invalid || force
+        })
+        .collect()
+}
+
+/// Format and file of the imported backup, like "Aegis backup aegis.json"
+pub(crate) fn backup_source(matches: &ImportArgs) -> String {
+    format!(
+        "{} backup {}",
+        matches.backup_type.name(),
+        matches
+            .path
+            .file_name()
+            .unwrap_or(matches.path.as_os_str())
+            .to_string_lossy()
+    )
+}
+
+/// Reads the codes of the backup, in the format chosen by the arguments
+pub(crate) fn read_backup(matches: ImportArgs) -> color_eyre::Result<Vec<OTPElement>> {
     let path = matches.path;
 
     let backup_type = matches.backup_type;
@@ -20,60 +118,227 @@ pub fn import(matches: ImportArgs, mut database: OTPDatabase) -> color_eyre::Res
     let result = if backup_type.cotp {
         import_from_path::<OTPDatabase>(path)
     } else if backup_type.andotp {
-        import_from_path::<Vec<OTPElement>>(path)
+        import_from_path::<AndOtpJson>(path)
+    } else if backup_type.andotp_encrypted {
+        import_from_andotp_encrypted(path)
     } else if backup_type.aegis {
         import_from_path::<AegisJson>(path)
     } else if backup_type.aegis_encrypted {
         import_from_path::<AegisEncryptedDatabase>(path)
+    } else if backup_type.bitwarden {
+        import_from_path::<BitwardenExport>(path)
+    } else if backup_type.kdbx {
+        import_from_kdbx(path, matches.keyfile)
+    } else if backup_type.two_fas {
+        import_from_path::<TwoFasBackup>(path)
+    } else if backup_type.microsoft_authenticator {
+        import_from_path::<MicrosoftAuthenticatorExport>(path)
+    } else if backup_type.csv {
+        let options = CsvOptions {
+            mapping: matches.map,
+            header: matches.header,
+            delimiter: matches.delimiter,
+        };
+        import_from_csv(path, &options)
+    } else if backup_type.duo {
+        import_from_path::<DuoAccounts>(path)
+    } else if backup_type.ente {
+        import_from_ente(path)
+    } else if backup_type.freeotp {
+        import_from_freeotp(path)
     } else if backup_type.freeotp_plus {
         import_from_path::<FreeOTPPlusJson>(path)
     } else if backup_type.authy_exported {
         import_from_path::<AuthyExportedList>(path)
     } else if backup_type.google_authenticator
-        || backup_type.authy
-        || backup_type.microsoft_authenticator
-        || backup_type.freeotp
+        && std::fs::read_to_string(&path).is_ok_and(|c| is_migration_export(&c))
     {
+        import_from_text_path::<GoogleAuthenticatorMigration>(path)
+    } else if backup_type.google_authenticator || backup_type.authy {
         import_from_path::<ConvertedJsonList>(path)
+    } else if backup_type.otp_uri
+        && std::fs::read_to_string(&path).is_ok_and(|c| is_plain_text_list(&c))
+    {
+        import_from_text_path::<OtpUriList>(path)
     } else if backup_type.otp_uri {
         import_from_path::<OtpUriList>(path)
+    } else if backup_type.qr_image {
+        import_from_qr_image(path)
     } else {
         return Err(eyre!("Invalid arguments provided"));
     };
 
-    let elements = result.map_err(|e| eyre!("{e}"))?;
+    result.map_err(|e| eyre!("{e}"))
+}
 
-    database.add_all(elements);
+pub fn merge(args: MergeArgs, database: OTPDatabase) -> color_eyre::Result<OTPDatabase> {
+    let mut password = utils::password("Password of the database to merge: ", 0)?;
+    let result = read_from_path(&args.path, &password);
+    password.zeroize();
+    let (other, _, _) = result?;
+    let mut database = merge_elements(
+        database,
+        other.elements_ref().to_vec(),
+        args.duplicates,
+        args.dry_run,
+        &format!("database {}", args.path.display()),
+    );
+    if !args.dry_run {
+        database.merge_history(other.history_ref());
+    }
     Ok(database)
 }
 
+/// Adds the elements handling the duplicates, printing what happened or what would happen in a dry run
+pub(crate) fn merge_elements(
+    mut database: OTPDatabase,
+    elements: Vec<OTPElement>,
+    policy: DuplicatePolicy,
+    dry_run: bool,
+    source: &str,
+) -> OTPDatabase {
+    let names: Vec<String> = elements
+        .iter()
+        .map(|e| {
+            if e.issuer.is_empty() {
+                e.label.to_owned()
+            } else {
+                format!("{} ({})", e.issuer, e.label)
+            }
+        })
+        .collect();
+    let outcomes = if dry_run {
+        let mut preview: OTPDatabase = database.elements_ref().to_vec().into();
+        preview.merge(elements, policy)
+    } else {
+        database.merge(elements, policy)
+    };
+
+    let (mut added, mut skipped, mut overwritten) = (0, 0, 0);
+    for (name, outcome) in names.iter().zip(outcomes) {
+        let action = match outcome {
+            MergeOutcome::Added => {
+                added += 1;
+                "Add"
+            }
+            MergeOutcome::Skipped => {
+                skipped += 1;
+                "Skip duplicate"
+            }
+            MergeOutcome::Overwritten(_) => {
+                overwritten += 1;
+                "Overwrite"
+            }
+        };
+        if dry_run {
+            println!("{action}: {name}");
+        }
+    }
+    println!(
+        "{}{added} added, {skipped} duplicates skipped, {overwritten} overwritten",
+        if dry_run {
+            "Dry run, nothing saved: "
+        } else {
+            ""
+        }
+    );
+    if !dry_run && added + overwritten > 0 {
+        database.record_change(format!(
+            "Imported {added} codes from {source}, {overwritten} overwritten"
+        ));
+    }
+    database
+}
+
 pub fn add(matches: AddArgs, mut database: OTPDatabase) -> color_eyre::Result<OTPDatabase> {
-    let otp_element = get_from_args(matches)?;
+    if matches.scan_screen {
+        return add_from_screen(matches, database);
+    }
 
-    if !otp_element.valid_secret() {
-        return Err(ErrReport::msg("Invalid secret."));
+    let force = matches.force;
+    // Checked before asking the secret
+    let suite = matches
+        .ocra_suite
+        .as_deref()
+        .filter(|_| matches.otp_type == OTPType::Ocra)
+        .map(str::parse::<OcraSuite>)
+        .transpose()?;
+
+    let mut otp_element = get_from_args(matches)?;
+
+    if let Some(suite) = suite {
+        otp_element.algorithm = suite.algorithm;
+        otp_element.digits = suite.digits;
+        otp_element.counter = suite.uses_counter.then(|| otp_element.counter.unwrap_or(0));
     }
 
+    // The weak secrets are only warned about, the invalid ones would not generate any code
+    let Some(otp_element) = check_secrets(vec![otp_element], force).pop() else {
+        return Err(ErrReport::msg("Invalid secret."));
+    };
+
+    database.record_change(format!("Added {}", otp_element.name()));
     database.add_element(otp_element);
     Ok(database)
 }
 
+fn add_from_screen(matches: AddArgs, mut database: OTPDatabase) -> color_eyre::Result<OTPDatabase> {
+    let screenshot = screenshot::capture_screen()?;
+    let elements: Vec<OTPElement> = decode_qr_codes(&screenshot).try_into()?;
+    for mut element in check_secrets(elements, matches.force) {
+        let message = format!(
+            "Found {} - {}, add it? [Y/n] ",
+            element.issuer, element.label
+        );
+        if utils::confirm(&message, true) {
+            element.tags = matches.tags.clone();
+            database.record_change(format!("Added {} from the screen", element.name()));
+            database.add_element(element);
+        }
+    }
+    Ok(database)
+}
+
 fn get_from_args(matches: AddArgs) -> color_eyre::Result<OTPElement> {
+    let defaults = Config::load()?.defaults;
     let secret = rpassword::prompt_password("Insert the secret: ").map_err(ErrReport::from)?;
-    Ok(map_args_to_code(secret, matches))
+    Ok(map_args_to_code(secret, matches, defaults))
+}
+
+fn map_args_to_code(secret: String, matches: AddArgs, defaults: DefaultsConfig) -> OTPElement {
+    let mut element = OTPElement::default();
+    element.secret = secret;
+    element.issuer = matches.issuer;
+    element.label = matches.label.unwrap();
+    element.digits = matches.digits.unwrap_or(defaults.digits);
+    element.type_ = matches.otp_type;
+    element.algorithm = matches.algorithm;
+    element.period = matches.period.unwrap_or(defaults.period);
+    element.counter = matches.counter;
+    element.pin = matches.pin;
+    element.tags = matches.tags;
+    element.t0 = matches.t0;
+    element.ocra_suite = matches.ocra_suite;
+    element.domains = matches
+        .domains
+        .into_iter()
+        .filter(|d| !d.is_empty())
+        .collect();
+    element.note = matches.note;
+    element
 }
 
-fn map_args_to_code(secret: String, matches: AddArgs) -> OTPElement {
-    OTPElement {
-        secret,
-        issuer: matches.issuer,
-        label: matches.label.unwrap(),
-        digits: matches.digits,
-        type_: matches.otp_type,
-        algorithm: matches.algorithm,
-        period: matches.period,
-        counter: matches.counter,
-        pin: matches.pin,
+/// Replaces the badge text and color, an empty value goes back to the one of the issuer
+fn edit_icon(element: &mut OTPElement, text: Option<String>, color: Option<String>) {
+    let icon = element.icon.get_or_insert_with(Icon::default);
+    if let Some(text) = text {
+        icon.text = Some(text).filter(|t| !t.is_empty());
+    }
+    if let Some(color) = color {
+        icon.color = Some(color).filter(|c| !c.is_empty());
+    }
+    if *icon == Icon::default() {
+        element.icon = None;
     }
 }
 
@@ -82,71 +347,138 @@ pub fn edit(matches: EditArgs, mut database: OTPDatabase) -> color_eyre::Result<
         .change_secret
         .then(|| rpassword::prompt_password("Insert the secret: ").unwrap());
 
-    // User provides row number from dashboard which is equal to the array index plus one
-    let index = matches.index;
+    if let Some(color) = matches.icon_color.as_deref().filter(|c| !c.is_empty()) {
+        ratatui::style::Color::from_str(color)
+            .map_err(|_| eyre!("{color} is not a valid color"))?;
+    }
 
-    if let Some(real_index) = index.checked_sub(1) {
-        if real_index >= database.elements_ref().len() {
-            return Err(eyre!("{index} is an invalid index"));
+    let indexes = match matches.index {
+        // User provides row number from dashboard which is equal to the array index plus one
+        Some(index) => {
+            let real_index = index
+                .checked_sub(1)
+                .filter(|i| *i < database.elements_ref().len())
+                .ok_or(eyre!("{index} is an invalid index"))?;
+            vec![real_index]
         }
+        None => filtered_indexes(&database, &matches.filter)?,
+    };
 
-        match database.mut_element(real_index) {
-            Some(element) => {
-                if let Some(v) = matches.issuer {
-                    element.issuer = v;
-                }
-                if let Some(v) = matches.label {
-                    element.label = v;
-                }
-                if let Some(v) = matches.digits {
-                    element.digits = v;
-                }
-                if let Some(v) = matches.period {
-                    element.period = v;
-                }
-                if let Some(v) = matches.algorithm {
-                    element.algorithm = v;
-                }
-                if matches.counter.is_some() {
-                    element.counter = matches.counter;
-                }
-                if matches.pin.is_some() {
-                    element.pin = matches.pin;
-                }
-                if let Some(s) = secret {
-                    element.secret = s;
-                }
-                database.mark_modified();
-            }
-            None => return Err(eyre!("No element found at index {index}")),
+    for &index in &indexes {
+        // Safe to unwrap because the index comes from the database
+        let element = database.mut_element(index).unwrap();
+        let before = element.clone();
+        edit_element(element, &matches, secret.as_deref())?;
+        let description = before.edit_description(element);
+        database.record_change(description);
+    }
+    if matches.index.is_none() {
+        println!("Edited {} codes", indexes.len());
+    }
+    Ok(database)
+}
+
+/// Indexes of the codes matching all the filters of cotp edit
+fn filtered_indexes(
+    database: &OTPDatabase,
+    filters: &[(String, String)],
+) -> color_eyre::Result<Vec<usize>> {
+    let mut indexes = vec![];
+    for (index, element) in database.elements_ref().iter().enumerate() {
+        let mut matches = true;
+        for (field, value) in filters {
+            matches &= element.field_matches(field, value)?;
+        }
+        if matches {
+            indexes.push(index);
         }
-        Ok(database)
-    } else {
-        Err(eyre!("{index} is an invalid index"))
     }
+    if indexes.is_empty() {
+        return Err(eyre!("No code matches the filters"));
+    }
+    Ok(indexes)
+}
+
+fn edit_element(
+    element: &mut OTPElement,
+    matches: &EditArgs,
+    secret: Option<&str>,
+) -> color_eyre::Result<()> {
+    if let Some(v) = &matches.issuer {
+        element.issuer = v.to_owned();
+    }
+    if let Some(v) = &matches.label {
+        element.label = v.to_owned();
+    }
+    if let Some(v) = matches.digits {
+        element.digits = v;
+    }
+    if let Some(v) = matches.period {
+        element.period = v;
+    }
+    if let Some(v) = matches.t0 {
+        element.t0 = v;
+    }
+    if let Some(v) = matches.algorithm {
+        element.algorithm = v;
+    }
+    if matches.counter.is_some() {
+        element.counter = matches.counter;
+    }
+    if matches.pin.is_some() {
+        element.pin = matches.pin.clone();
+    }
+    if let Some(v) = &matches.tags {
+        element.tags = v.iter().filter(|t| !t.is_empty()).cloned().collect();
+    }
+    if let Some(v) = &matches.domains {
+        element.domains = v.iter().filter(|d| !d.is_empty()).cloned().collect();
+    }
+    if let Some(v) = &matches.note {
+        element.note = v.to_owned();
+    }
+    if matches.icon.is_some() || matches.icon_color.is_some() {
+        edit_icon(element, matches.icon.clone(), matches.icon_color.clone());
+    }
+    for (field, value) in &matches.set {
+        element.set_field(field, value)?;
+    }
+    if let Some(s) = secret {
+        element.secret = s.to_owned();
+    }
+    Ok(())
 }
 
 pub fn export(matches: ExportArgs, database: OTPDatabase) -> color_eyre::Result<OTPDatabase> {
     let export_format = matches.format.unwrap_or_default();
-    let exported_path = if matches.path.is_dir() {
-        matches.path.join("exported.cotp")
+    let exported_path = if matches.path.is_dir() && !export_format.qr_png {
+        let file_name = if export_format.otp_uri_text || export_format.ente {
+            "exported.txt"
+        } else if export_format.ente_encrypted {
+            "exported.json"
+        } else if export_format.csv {
+            "exported.csv"
+        } else {
+            "exported.cotp"
+        };
+        if matches.gpg_recipients.is_empty() {
+            matches.path.join(file_name)
+        } else {
+            matches.path.join(gpg_file_name(file_name))
+        }
     } else {
         matches.path
     };
 
-    if export_format.cotp {
-        do_export(&database, exported_path)
-    } else if export_format.andotp {
-        let andotp: &Vec<OTPElement> = (&database).into();
-        do_export(&andotp, exported_path)
-    } else if export_format.otp_uri {
-        let otp_uri_list: OtpUriList = (&database).into();
-        do_export(&otp_uri_list, exported_path)
-    } else if export_format.freeotp_plus {
-        let freeotp_plus: FreeOTPPlusJson = (&database).try_into()?;
-        do_export(&freeotp_plus, exported_path)
+    if export_format.qr_png {
+        export_qr_images(&database, exported_path)
     } else {
-        unreachable!("Unreachable code");
+        let contents = export_contents(&export_format, matches.redact_secrets, &database)?;
+        if matches.gpg_recipients.is_empty() {
+            do_export_text(contents, exported_path)
+        } else {
+            do_export_gpg(contents, &matches.gpg_recipients, exported_path)
+        }
     }
     .map(|path| {
         println!(
@@ -160,18 +492,20 @@ pub fn export(matches: ExportArgs, database: OTPDatabase) -> color_eyre::Result<
 
 pub fn extract(args: ExtractArgs, database: OTPDatabase) -> color_eyre::Result<OTPDatabase> {
     let first_with_filters = database
-        .elements
+        .elements_ref()
         .iter()
         .enumerate()
-        .find(|(index, code)| filter_extract(&args, index, code))
-        .map(|(_, code)| code);
+        .find(|(index, code)| filter_extract(&args, index, code));
 
-    if let Some(otp) = first_with_filters {
+    if let Some((index, otp)) = first_with_filters {
         let code = otp.get_otp_code()?;
-        println!("{}", code);
+        print_code(&CodeOutput::new(index, otp), args.format)?;
         if args.copy_to_clipboard {
             let _ = clipboard::copy_string_to_clipboard(code.as_str())?;
-            println!("Copied to clipboard");
+            // Keep the JSON output parsable
+            if args.format == OutputFormat::Text {
+                println!("{}", tr("cli-copied"));
+            }
         }
         Ok(database)
     } else {
@@ -179,25 +513,962 @@ pub fn extract(args: ExtractArgs, database: OTPDatabase) -> color_eyre::Result<O
     }
 }
 
-pub fn change_password(mut database: OTPDatabase) -> color_eyre::Result<OTPDatabase> {
-    let mut new_password = utils::verified_password("New password: ", 8);
-    database
-        .save_with_pw(&new_password)
-        .map_err(ErrReport::from)?;
+pub fn list(args: ListArgs, database: OTPDatabase) -> color_eyre::Result<OTPDatabase> {
+    let filter = args.code_filter();
+    let codes = || -> Vec<CodeOutput> {
+        database
+            .sorted_by_issuer()
+            .into_iter()
+            .filter(|(_, element)| filter.matches(element))
+            .map(|(index, element)| CodeOutput::new(index, element))
+            .collect()
+    };
+    if !args.plain {
+        print_codes(&codes(), args.format)?;
+        return Ok(database);
+    }
+    match print_plain_codes(&mut std::io::stdout().lock(), codes, args.follow) {
+        // The reader has gone away, like `head` once it has its lines
+        Err(e) if e.kind() == ErrorKind::BrokenPipe => Ok(database),
+        result => result.map(|()| database).map_err(ErrReport::from),
+    }
+}
+
+/// Prints the codes, then each code that changes if following them
+fn print_plain_codes(
+    out: &mut impl Write,
+    codes: impl Fn() -> Vec<CodeOutput>,
+    follow: bool,
+) -> std::io::Result<()> {
+    let mut outputs = codes();
+    print_plain(out, &outputs)?;
+    if !follow {
+        return Ok(());
+    }
+    loop {
+        // Checking every second catches the end of any period
+        let millis = 1000 - clock::now_ms() % 1000;
+        std::thread::sleep(Duration::from_millis(millis));
+        let current = codes();
+        for (now, before) in current.iter().zip(&outputs) {
+            if now.code != before.code || now.error != before.error {
+                writeln!(out, "{}", now.plain_line())?;
+            }
+        }
+        out.flush()?;
+        outputs = current;
+    }
+}
+
+pub fn status(args: StatusArgs, database: OTPDatabase) -> color_eyre::Result<OTPDatabase> {
+    let index = find_single_match(&database, &args.entry)?;
+    // Safe to unwrap because the index comes from the database
+    let element = database.get_element(index).unwrap();
+    let mut stdout = std::io::stdout().lock();
+    if !args.watch {
+        print_status(&mut stdout, &StatusOutput::new(element), args.format)?;
+        return Ok(database);
+    }
+    match watch_status(&mut stdout, element, args.format) {
+        // The status bar has been closed or restarted
+        Err(e) if e.kind() == ErrorKind::BrokenPipe => Ok(database),
+        result => result.map(|()| database).map_err(ErrReport::from),
+    }
+}
+
+/// Prints the status of the code every second until the reader goes away
+fn watch_status(
+    out: &mut impl Write,
+    element: &OTPElement,
+    format: OutputFormat,
+) -> std::io::Result<()> {
+    loop {
+        print_status(out, &StatusOutput::new(element), format)?;
+        // Waking up on the next second also refreshes the code exactly when its period ends
+        let millis = 1000 - clock::now_ms() % 1000;
+        std::thread::sleep(Duration::from_millis(millis));
+    }
+}
+
+pub fn match_url(args: MatchArgs, database: OTPDatabase) -> color_eyre::Result<OTPDatabase> {
+    let outputs: Vec<CodeOutput> = database
+        .find_by_url(&args.url)
+        .into_iter()
+        .filter_map(|index| {
+            database
+                .get_element(index)
+                .map(|e| CodeOutput::new(index, e))
+        })
+        .collect();
+    print_codes(&outputs, args.format)?;
+    Ok(database)
+}
+
+pub fn stats(args: StatsArgs, database: OTPDatabase) -> color_eyre::Result<OTPDatabase> {
+    let mut outputs: Vec<StatsOutput> = database
+        .elements_ref()
+        .iter()
+        .enumerate()
+        .map(|(index, element)| StatsOutput::new(index, element))
+        .collect();
+    outputs.sort_by_key(|o| (Reverse(o.usage_count), Reverse(o.last_used)));
+    print_stats(&outputs, args.format)?;
+    Ok(database)
+}
+
+pub fn audit(args: AuditArgs, database: OTPDatabase) -> color_eyre::Result<OTPDatabase> {
+    let outputs: Vec<AuditOutput> = audit_elements(database.elements_ref())
+        .into_iter()
+        .zip(database.elements_ref())
+        .enumerate()
+        .filter(|(_, (findings, _))| !findings.is_empty())
+        .map(|(index, (findings, element))| AuditOutput::new(index, element, findings))
+        .collect();
+    print_audit(&outputs, args.format)?;
+    Ok(database)
+}
+
+pub fn dedupe(args: DedupeArgs, mut database: OTPDatabase) -> color_eyre::Result<OTPDatabase> {
+    // The codes sharing the secret but not the settings generate different codes, so they are kept
+    let groups = mergeable_groups(database.elements_ref());
+    if groups.is_empty() {
+        println!("No codes share the same secret and settings");
+        return Ok(database);
+    }
+    let mut merges = vec![];
+    let mut changes = vec![];
+    for group in groups {
+        println!("These codes share the same secret:");
+        for (n, &index) in group.iter().enumerate() {
+            let element = &database.elements_ref()[index];
+            let tags = if element.tags.is_empty() {
+                String::new()
+            } else {
+                format!("  [{}]", element.tags.join(", "))
+            };
+            println!("{:>3}  {}{tags}", n + 1, element.name());
+        }
+        let kept = if args.yes {
+            Some(0)
+        } else {
+            loop {
+                let answer = utils::ask(&format!(
+                    "Keep which code, merging the others into it? [1-{}, Enter to skip] ",
+                    group.len()
+                ));
+                if answer.is_empty() {
+                    break None;
+                }
+                match answer.parse::<usize>() {
+                    Ok(n) if (1..=group.len()).contains(&n) => break Some(n - 1),
+                    _ => println!("Please type a number between 1 and {}", group.len()),
+                }
+            }
+        };
+        if let Some(kept) = kept {
+            let kept = group[kept];
+            let others: Vec<usize> = group.into_iter().filter(|i| *i != kept).collect();
+            let elements = database.elements_ref();
+            let names: Vec<String> = others.iter().map(|i| elements[*i].name()).collect();
+            changes.push(format!(
+                "Merged {} into {}",
+                names.join(", "),
+                elements[kept].name()
+            ));
+            merges.push((kept, others));
+        }
+    }
+    database.merge_duplicates(&merges);
+    for change in changes {
+        println!("{change}");
+        database.record_change(change);
+    }
+    Ok(database)
+}
+
+pub fn history(args: HistoryArgs, database: OTPDatabase) -> color_eyre::Result<OTPDatabase> {
+    let query = args.query.unwrap_or_default().to_lowercase();
+    let entries: Vec<&HistoryEntry> = database
+        .history_ref()
+        .iter()
+        .filter(|entry| entry.message.to_lowercase().contains(&query))
+        .collect();
+    print_history(&entries, args.format)?;
+    Ok(database)
+}
+
+pub fn sync(args: SyncArgs, mut database: OTPDatabase) -> color_eyre::Result<OTPDatabase> {
+    if args.forget {
+        let state = database
+            .sync_state()
+            .ok_or(eyre!("The database is not synchronized"))?;
+        let folder = state.folder.display().to_string();
+        database.set_sync_state(None);
+        database.record_change(format!("Stopped synchronizing with {folder}"));
+        println!("Stopped synchronizing with {folder}");
+        return Ok(database);
+    }
+    if let Some(folder) = args.folder {
+        let mut pw = if sync::is_sync_folder(&folder) {
+            utils::password("Password of the sync folder: ", 0)?
+        } else {
+            utils::verified_password("Choose a password for the sync folder: ", 8)?
+        };
+        let state = if sync::is_sync_folder(&folder) {
+            sync::open_folder(&folder, &pw)
+        } else {
+            sync::create_folder(&folder, &pw, Config::load()?.kdf)
+        };
+        pw.zeroize();
+        database.set_sync_state(Some(state?));
+    }
+    let folder = database
+        .sync_state()
+        .ok_or(eyre!(
+            "The database is not synchronized, choose the folder with cotp sync <FOLDER>"
+        ))?
+        .folder
+        .display()
+        .to_string();
+
+    let report = database.synchronize()?;
+    for name in &report.conflicts {
+        println!("{name} was changed on both sides, the changes made here win");
+    }
+    if report.merged_copies > 0 {
+        println!(
+            "Merged and deleted {} conflicting copies of the codes made by the sync tool",
+            report.merged_copies
+        );
+    }
+    let summary = format!(
+        "{} added, {} updated, {} deleted, {} sent",
+        report.added, report.updated, report.deleted, report.sent
+    );
+    if report != SyncReport::default() {
+        database.record_change(format!("Synchronized with {folder}: {summary}"));
+    }
+    println!("Synchronized with {folder}: {summary}");
+    Ok(database)
+}
+
+pub fn remote(args: RemoteArgs, mut database: OTPDatabase) -> color_eyre::Result<OTPDatabase> {
+    let configured = |database: &OTPDatabase| {
+        database
+            .remote_config()
+            .cloned()
+            .ok_or(eyre!(tr("remote-not-configured")))
+    };
+    match args.action {
+        RemoteAction::Configure { server } => {
+            let config = remote_config(server)?;
+            let url = config.url();
+            database.set_remote_config(Some(config));
+            database.record_change(format!("Configured the remote database at {url}"));
+            println!("{}", tr("remote-configured"));
+        }
+        RemoteAction::Push { force } => {
+            let remote = configured(&database)?;
+            let url = remote.url();
+            let etag = remote::last_etag(&url);
+            let precondition = match &etag {
+                _ if force => Precondition::Any,
+                Some(etag) => Precondition::Version(etag),
+                None => Precondition::Missing,
+            };
+            // The database as last saved, the changes made by this command are not pushed
+            let etag = remote.upload(&get_db_path(), precondition)?;
+            remote::set_last_etag(&url, etag)?;
+            println!("{}", tr_args("remote-pushed", &[("url", &url)]));
+        }
+        RemoteAction::Pull => {
+            let remote = configured(&database)?;
+            let url = remote.url();
+            let Some(download) = remote.download(remote::last_etag(&url).as_deref())? else {
+                println!("{}", tr_args("remote-unchanged", &[("url", &url)]));
+                return Ok(database);
+            };
+            replace_with_remote(&mut database, &download.content, &url)?;
+            remote::set_last_etag(&url, download.etag)?;
+            println!("{}", tr_args("remote-pulled", &[("url", &url)]));
+        }
+        RemoteAction::Versions => {
+            let versions = configured(&database)?.versions()?;
+            if versions.is_empty() {
+                println!("{}", tr("remote-no-versions"));
+            }
+            for version in versions {
+                let date = utils::format_timestamp(version / 1000);
+                println!("{version}  {date}");
+            }
+        }
+        RemoteAction::Restore { version } => {
+            let remote = configured(&database)?;
+            let content = remote.download_version(version)?;
+            replace_with_remote(&mut database, &content, &remote.url())?;
+            let date = utils::format_timestamp(version / 1000);
+            println!("{}", tr_args("remote-restored", &[("date", &date)]));
+        }
+    }
+    Ok(database)
+}
+
+/// Asks the secret of the server
+fn remote_config(server: RemoteServer) -> color_eyre::Result<RemoteConfig> {
+    match server {
+        RemoteServer::Webdav { url, username } => {
+            let mut pw = if username.is_empty() {
+                String::new()
+            } else {
+                utils::password(&format!("{} ", tr("remote-password")), 0)?
+            };
+            let config = WebDavConfig::new(&url, &username, &pw).map(RemoteConfig::WebDav);
+            pw.zeroize();
+            config
+        }
+        RemoteServer::S3 {
+            endpoint,
+            region,
+            bucket,
+            key,
+            access_key,
+        } => {
+            let mut secret_key = utils::password(&format!("{} ", tr("remote-secret-key")), 1)?;
+            let config = S3Config::new(
+                &endpoint,
+                region.as_deref(),
+                &bucket,
+                &key,
+                &access_key,
+                &secret_key,
+            )
+            .map(RemoteConfig::S3);
+            secret_key.zeroize();
+            config
+        }
+    }
+}
+
+/// Replaces the database with the downloaded one, after checking it is a cotp database
+fn replace_with_remote(
+    database: &mut OTPDatabase,
+    content: &[u8],
+    url: &str,
+) -> color_eyre::Result<()> {
+    serde_json::from_slice::<EncryptedDatabase>(content)
+        .map_err(|_| eyre!(tr_args("remote-not-database", &[("url", url)])))?;
+    replace_database(content, &Config::load()?.backup)?;
+    // The downloaded file must not be overwritten by the database loaded in memory
+    database.discard_modifications();
+    Ok(())
+}
+
+pub fn copy(args: CopyArgs, mut database: OTPDatabase) -> color_eyre::Result<OTPDatabase> {
+    let index = find_single_match(&database, &args.query)?;
+    copy_code(&mut database, index, args.stdout, args.type_code)?;
+    Ok(database)
+}
+
+/// Copies the code of the element at the given index, or prints or types it
+fn copy_code(
+    database: &mut OTPDatabase,
+    index: usize,
+    stdout: bool,
+    type_code: bool,
+) -> color_eyre::Result<()> {
+    // Safe to unwrap because the index comes from the database
+    let code = database.get_element(index).unwrap().get_otp_code()?;
+    if stdout {
+        println!("{}", code);
+    } else if type_code {
+        autotype::type_string(&code)?;
+    } else {
+        let _ = clipboard::copy_string_to_clipboard(code.as_str())?;
+        println!("{}", tr("cli-copied"));
+    }
+    database.record_usage(index);
+    Ok(())
+}
+
+/// Returns the index of the only element matching the query
+pub(crate) fn find_single_match(database: &OTPDatabase, query: &str) -> color_eyre::Result<usize> {
+    match database.find_matching(query)[..] {
+        [index] => Ok(index),
+        [] => Err(eyre!("No code found matching \"{query}\"")),
+        ref indexes => {
+            let candidates = indexes
+                .iter()
+                .filter_map(|i| database.get_element(*i))
+                .map(|e| format!("{}:{}", e.issuer, e.label))
+                .collect::<Vec<String>>()
+                .join(", ");
+            Err(eyre!(
+                "More than one code matches \"{query}\": {candidates}"
+            ))
+        }
+    }
+}
+
+pub fn hotp(args: HotpArgs, mut database: OTPDatabase) -> color_eyre::Result<OTPDatabase> {
+    match args.action {
+        HotpAction::Resync {
+            query,
+            first_code,
+            second_code,
+            window,
+        } => {
+            let index = find_single_match(&database, &query)?;
+            // Safe to unwrap because the index comes from the database
+            let mut element = database.get_element(index).unwrap().clone();
+            if element.type_ != OTPType::Hotp {
+                return Err(eyre!(
+                    "{}:{} is not a HOTP code",
+                    element.issuer,
+                    element.label
+                ));
+            }
+            let counter = element
+                .find_hotp_resync_counter(first_code.trim(), second_code.trim(), window)?
+                .ok_or(eyre!(
+                    "The codes were not found within {window} counter values, check them or use a larger --window"
+                ))?;
+            element.counter = Some(counter);
+            database.record_change(format!(
+                "Resynchronized the counter of {} to {counter}",
+                element.name()
+            ));
+            database.replace_element(index, element);
+            println!("Counter set to {counter}");
+        }
+    }
+    Ok(database)
+}
+
+pub fn recovery(args: RecoveryArgs, mut database: OTPDatabase) -> color_eyre::Result<OTPDatabase> {
+    match args.action {
+        RecoveryAction::Add { query } => {
+            let index = find_single_match(&database, &query)?;
+            let codes = read_recovery_codes()?;
+            // Safe to unwrap because the index comes from the database
+            let element = database.mut_element(index).unwrap();
+            let previous = element.recovery_codes.len();
+            for code in codes {
+                if !element.recovery_codes.iter().any(|c| c.code == code.code) {
+                    element.recovery_codes.push(code);
+                }
+            }
+            let added = element.recovery_codes.len() - previous;
+            let name = element.name();
+            println!("{added} recovery codes added");
+            database.record_change(format!("Added {added} recovery codes to {name}"));
+        }
+        RecoveryAction::List { query } => {
+            let index = find_single_match(&database, &query)?;
+            let codes = &database.get_element(index).unwrap().recovery_codes;
+            if codes.is_empty() {
+                println!("No recovery codes, add them with cotp recovery add");
+            }
+            for (i, code) in codes.iter().enumerate() {
+                if code.is_used() {
+                    println!(
+                        "{:>3}  {}  used on {}",
+                        i + 1,
+                        code.code,
+                        utils::format_timestamp(code.used_at)
+                    );
+                } else {
+                    println!("{:>3}  {}", i + 1, code.code);
+                }
+            }
+        }
+        RecoveryAction::Use { query, number } => {
+            let index = find_single_match(&database, &query)?;
+            let element = database.mut_element(index).unwrap();
+            let name = element.name();
+            let codes = &mut element.recovery_codes;
+            let position = match number {
+                Some(number) => number
+                    .checked_sub(1)
+                    .filter(|i| *i < codes.len())
+                    .ok_or(eyre!("No recovery code with number {number}"))?,
+                None => codes
+                    .iter()
+                    .position(|c| !c.is_used())
+                    .ok_or(eyre!("No unused recovery codes left"))?,
+            };
+            if codes[position].is_used() {
+                return Err(eyre!("The recovery code has already been used"));
+            }
+            codes[position].used_at = utils::get_current_timestamp();
+            println!("{}", codes[position].code);
+            // Not on the standard output, which only has the code for the scripts
+            eprintln!(
+                "{} unused recovery codes left",
+                codes.iter().filter(|c| !c.is_used()).count()
+            );
+            database.record_change(format!("Used the recovery code {} of {name}", position + 1));
+        }
+    }
+    Ok(database)
+}
+
+pub fn trash(args: TrashArgs, mut database: OTPDatabase) -> color_eyre::Result<OTPDatabase> {
+    // Numbers start from 1, like the ones of the list command
+    let trash_len = database.trash_ref().len();
+    let index = |number: usize| {
+        number
+            .checked_sub(1)
+            .filter(|i| *i < trash_len)
+            .ok_or(eyre!("No deleted code with number {number}"))
+    };
+    match args.action {
+        TrashAction::List => {
+            if database.trash_ref().is_empty() {
+                println!("The trash is empty");
+            }
+            for (i, trashed) in database.trash_ref().iter().enumerate() {
+                let element = &trashed.element;
+                println!(
+                    "{:>3}  {}  {}  deleted on {}",
+                    i + 1,
+                    element.issuer,
+                    element.label,
+                    utils::format_timestamp(trashed.deleted_at)
+                );
+            }
+        }
+        TrashAction::Restore { number } => {
+            let index = index(number)?;
+            let name = database.trash_ref()[index].element.name();
+            database.restore_trashed(index)?;
+            database.record_change(format!("Restored {name} from the trash"));
+            println!("Code restored with index {}", database.elements_ref().len());
+        }
+        TrashAction::Purge { number } => {
+            let index = number.map(index).transpose()?;
+            let purged = match index {
+                Some(i) => database.trash_ref()[i].element.name(),
+                None if trash_len == 0 => {
+                    println!("The trash is empty");
+                    return Ok(database);
+                }
+                None => format!("the {trash_len} deleted codes"),
+            };
+            if utils::confirm(&format!("Permanently delete {purged}? [y/N] "), false) {
+                database.purge_trash(index)?;
+                database.record_change(format!("Permanently deleted {purged}"));
+                println!("Permanently deleted {purged}");
+            }
+        }
+    }
+    Ok(database)
+}
+
+/// Reads the recovery codes one per line, until an empty line when typed in the terminal
+fn read_recovery_codes() -> color_eyre::Result<Vec<RecoveryCode>> {
+    let terminal = io::stdin().is_terminal();
+    if terminal {
+        println!("Type or paste the recovery codes one per line, then an empty line:");
+    }
+    let mut codes = vec![];
+    for line in io::stdin().lines() {
+        let mut line = line?;
+        if !line.trim().is_empty() {
+            codes.push(RecoveryCode::new(&line));
+        } else if terminal {
+            break;
+        }
+        line.zeroize();
+    }
+    if codes.is_empty() {
+        return Err(eyre!("No recovery codes given"));
+    }
+    Ok(codes)
+}
+
+pub fn show(args: ShowArgs, database: OTPDatabase) -> color_eyre::Result<OTPDatabase> {
+    let index = find_single_match(&database, &args.query)?;
+    // Safe to unwrap because the index comes from the database
+    let element = database.get_element(index).unwrap();
+    for nearby in element.get_nearby_codes(args.window)? {
+        let when = match (nearby.validity, nearby.counter) {
+            (Some((from, until)), _) => format!(
+                "{} - {}",
+                utils::format_timestamp(from),
+                utils::format_timestamp(until)
+            ),
+            (None, Some(counter)) => format!("counter {counter}"),
+            (None, None) => String::new(),
+        };
+        let (offset, current) = match nearby.offset {
+            0 => (String::from("0"), "  (current)"),
+            o => (format!("{o:+}"), ""),
+        };
+        println!("{offset:>4}  {}  {when}{current}", nearby.code);
+    }
+    Ok(database)
+}
+
+pub fn picker(args: PickerArgs, mut database: OTPDatabase) -> color_eyre::Result<OTPDatabase> {
+    let Some(index) = picker::pick(args.menu, database.elements_ref())? else {
+        return Ok(database);
+    };
+    copy_code(&mut database, index, args.stdout, args.type_code)?;
+    Ok(database)
+}
+
+pub fn native_host(
+    _args: NativeHostArgs,
+    database: OTPDatabase,
+) -> color_eyre::Result<OTPDatabase> {
+    native_host::serve(database)
+}
+
+pub fn agent(
+    args: AgentArgs,
+    database: OTPDatabase,
+    key: &mut DatabaseKey,
+    salt: &[u8],
+) -> color_eyre::Result<OTPDatabase> {
+    let socket = args.socket.unwrap_or_else(agent::default_socket_path);
+    agent::serve(database, key, salt, &socket)
+}
+
+pub fn config(args: &ConfigArgs) -> color_eyre::Result<()> {
+    match &args.action {
+        ConfigAction::Get { key: None } => print!("{}", toml::to_string(&Config::load()?)?),
+        ConfigAction::Get { key: Some(key) } => match Config::load()?.get(key)? {
+            toml::Value::String(value) => println!("{value}"),
+            toml::Value::Table(section) => print!("{}", toml::to_string(&section)?),
+            value => println!("{value}"),
+        },
+        ConfigAction::Set { key, value } => {
+            let path = set_config_value(key, value)?;
+            println!("{key} saved into {}", path.display());
+        }
+    }
+    Ok(())
+}
+
+pub fn client(args: &ClientArgs) -> color_eyre::Result<()> {
+    let request = match &args.action {
+        ClientAction::List => agent::Request::List,
+        ClientAction::GetCode { query } => agent::Request::GetCode {
+            query: query.to_owned(),
+        },
+        ClientAction::Lock => agent::Request::Lock,
+        ClientAction::Unlock => agent::Request::Unlock {
+            password: rpassword::prompt_password("Password: ")?,
+        },
+    };
+    let socket = args
+        .socket
+        .clone()
+        .unwrap_or_else(agent::default_socket_path);
+    let response = agent::request(&socket, &request)?;
+    if let Some(error) = response.error {
+        return Err(eyre!(error));
+    }
+    let codes = response.codes.unwrap_or_default();
+    match args.action {
+        ClientAction::List => print_codes(&codes, args.format)?,
+        ClientAction::GetCode { .. } => {
+            let code = codes.first().ok_or(eyre!("The agent sent no code"))?;
+            print_code(code, args.format)?;
+        }
+        ClientAction::Lock => println!("Agent locked"),
+        ClientAction::Unlock => println!("Agent unlocked"),
+    }
+    Ok(())
+}
+
+pub fn daemon(_args: DaemonArgs, database: OTPDatabase) -> color_eyre::Result<OTPDatabase> {
+    dbus_service::serve(database)
+}
+
+pub fn ocra(args: OcraArgs, mut database: OTPDatabase) -> color_eyre::Result<OTPDatabase> {
+    let index = find_single_match(&database, &args.query)?;
+    // Safe to unwrap because the index comes from the database
+    let element = database.get_element(index).unwrap();
+    if element.type_ != OTPType::Ocra {
+        return Err(eyre!(
+            "{}:{} is not an OCRA code",
+            element.issuer,
+            element.label
+        ));
+    }
+    let response = element.get_ocra_response(args.challenge.trim(), args.session.as_deref())?;
+    // Like HOTP codes, every response moves the counter forward
+    if element.parsed_ocra_suite()?.uses_counter {
+        let mut element = element.clone();
+        let counter = element.counter.unwrap_or(0).saturating_add(1);
+        element.counter = Some(counter);
+        database.record_change(format!(
+            "Moved the counter of {} to {counter}",
+            element.name()
+        ));
+        database.replace_element(index, element);
+    }
+    println!("{response}");
+    Ok(database)
+}
+
+pub fn change_password(
+    args: PasswdArgs,
+    mut database: OTPDatabase,
+) -> color_eyre::Result<OTPDatabase> {
+    if args.yubikey {
+        database.set_yubikey_slot(Some(args.slot));
+    } else if args.remove_yubikey {
+        database.set_yubikey_slot(None);
+    }
+    if let Some(keyfile) = &args.keyfile {
+        database.set_keyfile_hash(Some(enroll_keyfile(keyfile)?));
+    } else if args.remove_keyfile {
+        database.set_keyfile_hash(None);
+    }
+    let kdf_params = if args.calibrate {
+        calibrated_kdf_params()?
+    } else {
+        Config::load()?.kdf
+    };
+    database.set_kdf_params(kdf_params);
+    let minimum_length = if database.yubikey_slot().is_some() {
+        0
+    } else {
+        8
+    };
+    let mut new_password = utils::verified_password("New password: ", minimum_length)?;
+    database.record_change("Changed the password");
+    let save_result = database.save_with_pw(&new_password);
+    // Keep the stored password in sync, otherwise the next unlock would fail
+    let keyring_result = match save_result {
+        Ok(_) if os_keyring::get_password().is_some() => os_keyring::set_password(&new_password),
+        _ => Ok(()),
+    };
     new_password.zeroize();
+    save_result?;
+    keyring_result?;
+    Ok(database)
+}
+
+/// Hash of the keyfile, which is created with random content if missing
+pub(crate) fn enroll_keyfile(keyfile: &Path) -> color_eyre::Result<[u8; 32]> {
+    if !keyfile.exists() {
+        generate_keyfile(keyfile)?;
+        println!(
+            "Keyfile created at {}, keep a copy of it since the database cannot be unlocked without it",
+            keyfile.display()
+        );
+    }
+    hash_keyfile(keyfile)
+}
+
+/// Argon2 parameters taking about half a second on this machine
+pub(crate) fn calibrated_kdf_params() -> color_eyre::Result<KdfParams> {
+    println!("Measuring the key derivation speed...");
+    let params = calibrate_kdf_params(Duration::from_millis(CALIBRATION_TARGET_MILLIS))?;
+    println!(
+        "Using memory = {}, iterations = {}, parallelism = {}, add them to the [kdf] section of the configuration file to keep them for new databases",
+        params.memory, params.iterations, params.parallelism
+    );
+    Ok(params)
+}
+
+/// Formats the database following the export format, QR code images excluded
+fn export_contents(
+    export_format: &ExportFormat,
+    redact_secrets: bool,
+    database: &OTPDatabase,
+) -> color_eyre::Result<String> {
+    let json = if export_format.cotp {
+        to_export_json(database)
+    } else if export_format.andotp {
+        let andotp: Vec<OTPElement> = database.into();
+        to_export_json(&andotp)
+    } else if export_format.otp_uri {
+        let otp_uri_list: OtpUriList = database.into();
+        to_export_json(&otp_uri_list)
+    } else if export_format.freeotp_plus {
+        let freeotp_plus: FreeOTPPlusJson = database.try_into()?;
+        to_export_json(&freeotp_plus)
+    } else if export_format.otp_uri_text {
+        let otp_uri_list: OtpUriList = database.into();
+        return Ok(otp_uri_list.to_text());
+    } else if export_format.csv {
+        return Ok(to_csv(database, redact_secrets));
+    } else if export_format.ente {
+        return to_ente_text(database);
+    } else if export_format.ente_encrypted {
+        let mut text = to_ente_text(database)?;
+        let mut password =
+            match utils::verified_password("Choose a password for the Ente export: ", 8) {
+                Ok(password) => password,
+                Err(e) => {
+                    text.zeroize();
+                    return Err(e.into());
+                }
+            };
+        let encrypted = encrypt_ente_export(&text, &password);
+        text.zeroize();
+        password.zeroize();
+        to_export_json(&encrypted?)
+    } else {
+        unreachable!("Unreachable code");
+    };
+    json.map_err(|e| eyre!("An error occurred while exporting database: {e}"))
+}
+
+pub fn keyring(args: KeyringArgs, database: OTPDatabase) -> color_eyre::Result<OTPDatabase> {
+    match args.action {
+        KeyringAction::Set => {
+            let mut password = utils::password("Password to store: ", 8)?;
+            // Do not store a password which cannot unlock the database
+            let result = read_from_file(&password)
+                .map_err(|_| eyre!("Wrong password"))
+                .and_then(|_| os_keyring::set_password(&password));
+            password.zeroize();
+            result?;
+            println!("Password stored in the keyring, run cotp with --use-keyring to use it");
+        }
+        KeyringAction::Clear => {
+            os_keyring::delete_password()?;
+            println!("Password removed from the keyring");
+        }
+    }
+    Ok(database)
+}
+
+pub fn age(args: AgeArgs, mut database: OTPDatabase) -> color_eyre::Result<OTPDatabase> {
+    match args.action {
+        AgeAction::List => {
+            if database.age_recipients().is_empty() {
+                println!("No age recipients, only the password can unlock the database");
+            }
+            for recipient in database.age_recipients() {
+                println!("{recipient}");
+            }
+        }
+        AgeAction::Add { recipient } => {
+            let recipient = recipient.trim();
+            if !is_valid_recipient(recipient) {
+                return Err(eyre!(
+                    "Invalid recipient, use an age recipient like age1... or an SSH public key"
+                ));
+            }
+            if !database.add_age_recipient(recipient) {
+                return Err(eyre!("The recipient can already unlock the database"));
+            }
+            database.record_change(format!("Added the age recipient {recipient}"));
+            println!(
+                "Recipient added, unlock the database with cotp --age-identity <identity file>"
+            );
+        }
+        AgeAction::Remove { recipient } => {
+            if !database.remove_age_recipient(recipient.trim()) {
+                return Err(eyre!("No such recipient"));
+            }
+            database.record_change(format!("Removed the age recipient {}", recipient.trim()));
+            println!("Recipient removed");
+        }
+    }
+    Ok(database)
+}
+
+pub fn backup(args: BackupArgs, mut database: OTPDatabase) -> color_eyre::Result<OTPDatabase> {
+    match args.action {
+        BackupAction::List => {
+            let backups = list_backups()?;
+            if backups.is_empty() {
+                println!("No backups found");
+            }
+            for backup in backups {
+                println!(
+                    "{}  {}",
+                    backup.id(),
+                    utils::format_timestamp(backup.timestamp)
+                );
+            }
+        }
+        BackupAction::Restore { id } => {
+            let timestamp = restore_backup(&id, &Config::load()?.backup)?;
+            // The restored file must not be overwritten by the database loaded in memory
+            database.discard_modifications();
+            println!(
+                "Backup restored, unlock it with the password in use at {}",
+                utils::format_timestamp(timestamp)
+            );
+        }
+    }
     Ok(database)
 }
 
+/// Runs without an unlocked database, selected is the profile chosen with --profile
+pub fn profile(args: &ProfileArgs, selected: Option<&str>) -> color_eyre::Result<()> {
+    if has_custom_db_path() && !matches!(args.action, ProfileAction::List) {
+        return Err(eyre!(
+            "Profiles cannot be managed together with --database-path or COTP_DB_PATH"
+        ));
+    }
+    match &args.action {
+        ProfileAction::List => {
+            let selected = selected.unwrap_or(DEFAULT_PROFILE);
+            let profiles = list_profiles()?;
+            if profiles.is_empty() {
+                println!("No profiles found");
+            }
+            for profile in profiles {
+                let marker = if profile == selected { "*" } else { " " };
+                println!("{marker} {profile}");
+            }
+        }
+        ProfileAction::Create { name } => {
+            set_profile(name)?;
+            if !utils::init_app()? {
+                return Err(eyre!("The {name} profile already exists"));
+            }
+            let mut pw = utils::verified_password("Choose a password: ", 8)?;
+            let mut database = OTPDatabase::default();
+            database.set_kdf_params(Config::load()?.kdf);
+            let result = database.save_with_pw(&pw);
+            pw.zeroize();
+            result?;
+            println!("Profile created, use it with --profile {name}");
+        }
+        ProfileAction::Delete { name } => {
+            set_profile(name)?;
+            if name == DEFAULT_PROFILE {
+                return Err(eyre!("The default profile cannot be deleted"));
+            }
+            let path = get_profile_path(name);
+            if !path.exists() {
+                return Err(eyre!("The {name} profile does not exist"));
+            }
+            let message = format!("Delete the {name} profile and all its codes? [y/N] ");
+            if !utils::confirm(&message, false) {
+                return Ok(());
+            }
+            std::fs::remove_file(path)?;
+            // The profile password may have never been stored, or no keyring may be available
+            let _ = os_keyring::delete_password();
+            println!("Profile deleted, its backups are kept");
+        }
+    }
+    Ok(())
+}
+
 fn filter_extract(args: &ExtractArgs, index: &usize, code: &OTPElement) -> bool {
-    let match_by_index = args.index.map_or(true, |i| i == *index);
+    let match_by_index = args.index.is_none_or(|i| i == *index);
 
-    let match_by_issuer = args.issuer.as_ref().map_or(true, |issuer| {
-        code.issuer.to_lowercase() == issuer.to_lowercase()
-    });
+    let match_by_issuer = args
+        .issuer
+        .as_ref()
+        .is_none_or(|issuer| code.issuer.to_lowercase() == issuer.to_lowercase());
 
-    let match_by_label = args.label.as_ref().map_or(true, |label| {
-        code.label.to_lowercase() == label.to_lowercase()
-    });
+    let match_by_label = args
+        .label
+        .as_ref()
+        .is_none_or(|label| code.label.to_lowercase() == label.to_lowercase());
 
     match_by_index && match_by_issuer && match_by_label
 }
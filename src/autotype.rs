@@ -0,0 +1,62 @@
+//! Types the codes into the focused window, so they never go through the clipboard and its history.
+//!
+//! The keystrokes are sent with xdotool on X11, wtype on Wayland and System Events on macOS.
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::Duration;
+
+use color_eyre::eyre::eyre;
+
+/// Time given to the window manager to focus back the previous window, like after closing a menu
+const FOCUS_DELAY: Duration = Duration::from_millis(300);
+
+pub fn type_string(text: &str) -> color_eyre::Result<()> {
+    thread::sleep(FOCUS_DELAY);
+    let (mut command, stdin) = type_command(text)?;
+    let program = command.get_program().to_string_lossy().to_string();
+    let mut child = command
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(|e| eyre!("Cannot start {program}: {e}"))?;
+    // The text is written to the standard input, where other processes cannot see it
+    if let Some(mut pipe) = child.stdin.take() {
+        pipe.write_all(stdin.as_bytes())?;
+    }
+    if child.wait()?.success() {
+        Ok(())
+    } else {
+        Err(eyre!("{program} failed to type the code"))
+    }
+}
+
+/// Command typing the text and what to write on its standard input
+#[cfg(all(unix, not(target_os = "macos")))]
+fn type_command(text: &str) -> color_eyre::Result<(Command, String)> {
+    if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+        let mut command = Command::new("wtype");
+        command.arg("-");
+        Ok((command, text.to_owned()))
+    } else if std::env::var_os("DISPLAY").is_some() {
+        let mut command = Command::new("xdotool");
+        command.args(["type", "--clearmodifiers", "--file", "-"]);
+        Ok((command, text.to_owned()))
+    } else {
+        Err(eyre!("Typing the code needs a graphical session"))
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn type_command(text: &str) -> color_eyre::Result<(Command, String)> {
+    let mut command = Command::new("osascript");
+    command.arg("-");
+    // The codes only contain letters and digits, which do not need to be escaped
+    let script = format!("tell application \"System Events\" to keystroke \"{text}\"");
+    Ok((command, script))
+}
+
+#[cfg(not(unix))]
+fn type_command(_text: &str) -> color_eyre::Result<(Command, String)> {
+    Err(eyre!("Typing the code is not supported on this platform"))
+}
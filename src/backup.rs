@@ -0,0 +1,171 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use color_eyre::eyre::eyre;
+
+use crate::{
+    config::{BackupConfig, Config},
+    lock::DatabaseLock,
+    otp::otp_element::OTPDatabase,
+    path::{get_db_path, get_profile},
+    utils::{get_current_timestamp, write_atomically},
+};
+
+const BACKUP_DIR: &str = "backups";
+const BACKUP_PREFIX: &str = "db-";
+const BACKUP_EXTENSION: &str = ".cotp";
+
+/// Encrypted copy of the database, identified by the unix timestamp of its creation
+pub struct Backup {
+    pub timestamp: u64,
+    /// Tells apart the backups created in the same second, 0 for the first one
+    pub sequence: u32,
+    pub path: PathBuf,
+}
+
+impl Backup {
+    /// Name used to restore the backup, like 1729000000 or 1729000000-1
+    pub fn id(&self) -> String {
+        backup_id(self.timestamp, self.sequence)
+    }
+}
+
+fn backup_id(timestamp: u64, sequence: u32) -> String {
+    match sequence {
+        0 => timestamp.to_string(),
+        _ => format!("{timestamp}-{sequence}"),
+    }
+}
+
+/// Backups are stored next to the database file, in a separate directory for each profile
+fn get_backup_dir() -> PathBuf {
+    let backup_dir = get_db_path()
+        .parent()
+        .unwrap_or(Path::new("."))
+        .join(BACKUP_DIR);
+    match get_profile() {
+        Some(profile) => backup_dir.join(profile),
+        None => backup_dir,
+    }
+}
+
+fn parse_backup_name(name: &str) -> Option<(u64, u32)> {
+    let id = name
+        .strip_prefix(BACKUP_PREFIX)?
+        .strip_suffix(BACKUP_EXTENSION)?;
+    match id.split_once('-') {
+        Some((timestamp, sequence)) => Some((timestamp.parse().ok()?, sequence.parse().ok()?)),
+        None => Some((id.parse().ok()?, 0)),
+    }
+}
+
+/// Backs up the database file and purges the expired trash, before saving the changes.
+/// The backup is not worth losing the changes, so its errors are returned as warnings.
+pub fn prepare_save(database: &mut OTPDatabase) -> Vec<String> {
+    let mut warnings = vec![];
+    let config = Config::load().unwrap_or_else(|e| {
+        warnings.push(format!(
+            "Cannot read the configuration, using the default one: {e}"
+        ));
+        Config::default()
+    });
+    // Usage statistics changes are not worth a backup
+    if database.is_modified() {
+        if let Err(e) = DatabaseLock::shared().and_then(|_lock| create_backup(&config.backup)) {
+            warnings.push(e.to_string());
+        }
+    }
+    if !database.is_locked() {
+        database.purge_expired_trash(config.trash.retention_days);
+    }
+    warnings
+}
+
+/// Copies the current encrypted database into the backup directory, then deletes the oldest backups
+pub fn create_backup(config: &BackupConfig) -> color_eyre::Result<()> {
+    let db_path = get_db_path();
+    if !config.enabled || config.rotations == 0 || !db_path.exists() {
+        return Ok(());
+    }
+    let backup_dir = get_backup_dir();
+    fs::create_dir_all(&backup_dir)?;
+    let timestamp = get_current_timestamp();
+    let backup_path = (0..)
+        .map(|sequence| {
+            backup_dir.join(format!(
+                "{BACKUP_PREFIX}{}{BACKUP_EXTENSION}",
+                backup_id(timestamp, sequence)
+            ))
+        })
+        .find(|path| !path.exists())
+        .unwrap();
+    fs::copy(db_path, backup_path).map_err(|e| eyre!("Cannot create the database backup: {e}"))?;
+
+    list_backups()?
+        .into_iter()
+        .skip(config.rotations)
+        .try_for_each(|backup| fs::remove_file(backup.path))
+        .map_err(|e| eyre!("Cannot delete old backups: {e}"))
+}
+
+/// Returns the available backups, newest first
+pub fn list_backups() -> color_eyre::Result<Vec<Backup>> {
+    let backup_dir = get_backup_dir();
+    if !backup_dir.exists() {
+        return Ok(vec![]);
+    }
+    let mut backups: Vec<Backup> = fs::read_dir(backup_dir)?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let (timestamp, sequence) = parse_backup_name(entry.file_name().to_str()?)?;
+            Some(Backup {
+                timestamp,
+                sequence,
+                path: entry.path(),
+            })
+        })
+        .collect();
+    backups.sort_unstable_by_key(|b| std::cmp::Reverse((b.timestamp, b.sequence)));
+    Ok(backups)
+}
+
+/// Replaces the database with the given backup, backing up the current database first.
+/// Returns the timestamp of the restored backup.
+pub fn restore_backup(id: &str, config: &BackupConfig) -> color_eyre::Result<u64> {
+    let backup = list_backups()?
+        .into_iter()
+        .find(|b| b.id() == id)
+        .ok_or(eyre!("No backup found with id {id}"))?;
+    // Read it before creating the new backup, which may rotate it away
+    let content = fs::read(backup.path)?;
+    replace_database(&content, config)?;
+    Ok(backup.timestamp)
+}
+
+/// Replaces the database with the given encrypted content, backing up the current database first
+pub fn replace_database(content: &[u8], config: &BackupConfig) -> color_eyre::Result<()> {
+    let _lock = DatabaseLock::exclusive()?;
+    create_backup(config)?;
+    write_atomically(&get_db_path(), content).map_err(|e| eyre!("Cannot replace the database: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_backup_name;
+
+    #[test]
+    fn test_parse_backup_name() {
+        assert_eq!(
+            Some((1729000000, 0)),
+            parse_backup_name("db-1729000000.cotp")
+        );
+        assert_eq!(
+            Some((1729000000, 2)),
+            parse_backup_name("db-1729000000-2.cotp")
+        );
+        assert_eq!(None, parse_backup_name("db-latest.cotp"));
+        assert_eq!(None, parse_backup_name("notes.txt"));
+    }
+}
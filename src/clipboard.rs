@@ -6,13 +6,131 @@ use copypasta_ext::wayland_bin::WaylandBinClipboardContext;
 use copypasta_ext::x11_bin::ClipboardContext as BinClipboardContext;
 use copypasta_ext::x11_fork::ClipboardContext as ForkClipboardContext;
 use crossterm::style::Print;
-use std::{env, io};
+use std::{
+    env, io,
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
 
+#[derive(Clone, Copy)]
 pub enum CopyType {
     Native,
     OSC52,
 }
 
+/// Clipboard contents to put back once the copied code expires
+struct ClipboardRestore {
+    copied: String,
+    previous: Option<String>,
+    copy_type: CopyType,
+}
+
+/// Clears the copied code from the clipboard in a background thread after a timeout
+pub struct ScheduledClear {
+    deadline: Instant,
+    restore: Arc<Mutex<Option<ClipboardRestore>>>,
+}
+
+impl ScheduledClear {
+    /// Schedules the clipboard clearing, restoring the previous content if available
+    pub fn new(
+        copied: &str,
+        previous: Option<String>,
+        copy_type: CopyType,
+        timeout: Duration,
+    ) -> Self {
+        let restore = Arc::new(Mutex::new(Some(ClipboardRestore {
+            copied: copied.to_owned(),
+            previous,
+            copy_type,
+        })));
+        let thread_restore = Arc::clone(&restore);
+        thread::spawn(move || {
+            thread::sleep(timeout);
+            if let Some(restore) = take_restore(&thread_restore) {
+                restore.run();
+            }
+        });
+        ScheduledClear {
+            deadline: Instant::now() + timeout,
+            restore,
+        }
+    }
+
+    /// Seconds left before the clipboard gets cleared
+    pub fn remaining_seconds(&self) -> u64 {
+        let remaining = self.deadline.saturating_duration_since(Instant::now());
+        // Round up so the countdown reaches 0 only when the clipboard is cleared
+        remaining.as_secs() + u64::from(remaining.subsec_nanos() > 0)
+    }
+
+    pub fn is_pending(&self) -> bool {
+        self.restore.lock().map(|r| r.is_some()).unwrap_or(false)
+    }
+
+    /// Stops the background clearing, returning the clipboard content to restore later
+    pub fn cancel(self) -> Option<String> {
+        take_restore(&self.restore).and_then(|r| r.previous)
+    }
+
+    /// Clears the clipboard without waiting for the timeout, used before exiting
+    pub fn clear_now(self) {
+        if let Some(restore) = take_restore(&self.restore) {
+            restore.run();
+        }
+    }
+}
+
+impl ClipboardRestore {
+    fn run(self) {
+        let content = self.previous.unwrap_or_default();
+        match self.copy_type {
+            CopyType::OSC52 => {
+                ssh_clipboard(&content);
+            }
+            CopyType::Native => {
+                // Do not override something the user copied in the meantime
+                if read_clipboard().is_some_and(|current| current.trim() != self.copied) {
+                    return;
+                }
+                let _ = wayland_clipboard(&content) || other_platform_clipboard(&content);
+            }
+        }
+    }
+}
+
+fn take_restore(restore: &Mutex<Option<ClipboardRestore>>) -> Option<ClipboardRestore> {
+    restore.lock().ok().and_then(|mut r| r.take())
+}
+
+/// Reads the current clipboard content, not available through OSC52
+pub fn read_clipboard() -> Option<String> {
+    if env_var_set("SSH_CONNECTION") {
+        return None;
+    }
+    wayland_read_clipboard().or_else(|| {
+        BinClipboardContext::new()
+            .and_then(|mut ctx| ctx.get_contents())
+            .ok()
+    })
+}
+
+#[cfg(target_os = "linux")]
+fn wayland_read_clipboard() -> Option<String> {
+    if !env_var_set("WAYLAND_DISPLAY") {
+        return None;
+    }
+    WaylandBinClipboardContext::new()
+        .and_then(|mut ctx| ctx.get_contents())
+        .ok()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn wayland_read_clipboard() -> Option<String> {
+    None
+}
+
 pub fn copy_string_to_clipboard(content: &str) -> color_eyre::Result<CopyType> {
     if ssh_clipboard(content) {
         Ok(CopyType::OSC52)
@@ -63,3 +181,24 @@ fn env_var_set(env_var: &str) -> bool {
         .map(|v| !v.trim().is_empty())
         .unwrap_or(false)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{CopyType, ScheduledClear};
+
+    #[test]
+    fn test_cancel_scheduled_clear() {
+        let scheduled_clear = ScheduledClear::new(
+            "123456",
+            Some(String::from("previous")),
+            CopyType::Native,
+            Duration::from_secs(60),
+        );
+
+        assert!(scheduled_clear.is_pending());
+        assert_eq!(60, scheduled_clear.remaining_seconds());
+        assert_eq!(Some(String::from("previous")), scheduled_clear.cancel());
+    }
+}
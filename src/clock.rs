@@ -0,0 +1,193 @@
+//! Current time used to generate the codes, which can be corrected when the local clock is wrong.
+//!
+//! The correction is a fixed offset from the configuration or the one measured with an NTP server.
+
+use std::io;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, ToSocketAddrs, UdpSocket};
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const NTP_PORT: u16 = 123;
+const NTP_PACKET_SIZE: usize = 48;
+/// Seconds between the NTP epoch (1900) and the Unix one
+const NTP_UNIX_EPOCH_DELTA: u64 = 2_208_988_800;
+const NTP_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Milliseconds added to the local clock
+static OFFSET_MS: AtomicI64 = AtomicI64::new(0);
+
+pub fn set_offset_ms(offset: i64) {
+    OFFSET_MS.store(offset, Ordering::Relaxed);
+}
+
+pub fn offset_ms() -> i64 {
+    OFFSET_MS.load(Ordering::Relaxed)
+}
+
+/// Milliseconds since the Unix epoch, with the offset applied
+pub fn now_ms() -> u64 {
+    local_ms().saturating_add(offset_ms()).max(0) as u64
+}
+
+/// Asks the time to an NTP server, returning how many milliseconds the local clock is behind it,
+/// negative if it is ahead
+pub fn query_ntp_offset_ms(server: &str) -> io::Result<i64> {
+    let address = ntp_address(server)?;
+    let local: SocketAddr = match address {
+        SocketAddr::V4(_) => (Ipv4Addr::UNSPECIFIED, 0).into(),
+        SocketAddr::V6(_) => (Ipv6Addr::UNSPECIFIED, 0).into(),
+    };
+    let socket = UdpSocket::bind(local)?;
+    socket.set_read_timeout(Some(NTP_TIMEOUT))?;
+    socket.set_write_timeout(Some(NTP_TIMEOUT))?;
+    socket.connect(address)?;
+
+    let mut request = [0u8; NTP_PACKET_SIZE];
+    // No leap indicator, version 4, client mode
+    request[0] = 0b00_100_011;
+    // The server copies the transmit timestamp into the origin one of its response: a random value
+    // tells the answers to this request from the spoofed ones, without revealing the local time
+    let mut origin = [0u8; 8];
+    getrandom::getrandom(&mut origin).map_err(io::Error::other)?;
+    request[40..48].copy_from_slice(&origin);
+    let sent = local_ms();
+    socket.send(&request)?;
+    let mut response = [0u8; NTP_PACKET_SIZE];
+    let length = socket.recv(&mut response)?;
+    let received = local_ms();
+    if length < NTP_PACKET_SIZE {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Short NTP response",
+        ));
+    }
+    ntp_offset_ms(&response, &origin, sent, received)
+}
+
+/// Address of the server, with the default port unless it has one like `time.example.com:1123` or `[::1]:1123`
+fn ntp_address(server: &str) -> io::Result<SocketAddr> {
+    let mut addresses = match server.to_socket_addrs() {
+        Ok(addresses) => addresses,
+        Err(_) => {
+            let host = (server
+                .strip_prefix('[')
+                .and_then(|host| host.strip_suffix(']')))
+            .unwrap_or(server);
+            (host, NTP_PORT).to_socket_addrs()?
+        }
+    };
+    addresses.next().ok_or(io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("No address found for {server}"),
+    ))
+}
+
+fn local_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as i64
+}
+
+/// Clock offset of the SNTP exchange (RFC 4330), from the server receive and transmit timestamps
+fn ntp_offset_ms(
+    response: &[u8; NTP_PACKET_SIZE],
+    origin: &[u8; 8],
+    sent: i64,
+    received: i64,
+) -> io::Result<i64> {
+    if response[24..32] != origin[..] {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "The NTP response does not answer the request",
+        ));
+    }
+    let mode = response[0] & 0b111;
+    let stratum = response[1];
+    // Server mode, stratum 0 is a "kiss-o'-death" refusal
+    if mode != 4 || stratum == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "The NTP server refused the request",
+        ));
+    }
+    let server_received = ntp_timestamp_ms(&response[32..40]);
+    let server_sent = ntp_timestamp_ms(&response[40..48]);
+    Ok(((server_received - sent) + (server_sent - received)) / 2)
+}
+
+/// Unix milliseconds of an NTP timestamp, made of 32 bits of seconds and 32 bits of fraction
+fn ntp_timestamp_ms(bytes: &[u8]) -> i64 {
+    let mut seconds = u32::from_be_bytes(bytes[0..4].try_into().unwrap()) as i64;
+    // Without the most significant bit the timestamp is after 2036, in the next NTP era
+    if seconds & 0x8000_0000 == 0 {
+        seconds += 1 << 32;
+    }
+    let fraction = u32::from_be_bytes(bytes[4..8].try_into().unwrap()) as i64;
+    (seconds - NTP_UNIX_EPOCH_DELTA as i64) * 1000 + ((fraction * 1000) >> 32)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use super::{ntp_address, ntp_offset_ms, NTP_PACKET_SIZE, NTP_UNIX_EPOCH_DELTA};
+
+    const ORIGIN: [u8; 8] = [1, 2, 3, 4, 5, 6, 7, 8];
+
+    fn ntp_timestamp(unix_ms: i64) -> [u8; 8] {
+        let seconds = (unix_ms / 1000 + NTP_UNIX_EPOCH_DELTA as i64) as u32;
+        let fraction = (((unix_ms % 1000) << 32) / 1000) as u32;
+        let mut bytes = [0u8; 8];
+        bytes[..4].copy_from_slice(&seconds.to_be_bytes());
+        bytes[4..].copy_from_slice(&fraction.to_be_bytes());
+        bytes
+    }
+
+    fn response(server_received: i64, server_sent: i64) -> [u8; NTP_PACKET_SIZE] {
+        let mut response = [0u8; NTP_PACKET_SIZE];
+        response[0] = 0b00_100_100;
+        response[1] = 2;
+        response[24..32].copy_from_slice(&ORIGIN);
+        response[32..40].copy_from_slice(&ntp_timestamp(server_received));
+        response[40..48].copy_from_slice(&ntp_timestamp(server_sent));
+        response
+    }
+
+    #[test]
+    fn test_ntp_offset() {
+        let local = 1_700_000_000_000;
+        // 100 ms of network delay each way, with the server 42.5 seconds ahead
+        let server = local + 42_500;
+        let reply = response(server + 100, server + 110);
+        let offset = ntp_offset_ms(&reply, &ORIGIN, local, local + 210).unwrap();
+        assert!((42_499..=42_501).contains(&offset), "{offset}");
+
+        let reply = response(local - 5000, local - 5000);
+        let offset = ntp_offset_ms(&reply, &ORIGIN, local, local).unwrap();
+        assert!((-5001..=-4999).contains(&offset), "{offset}");
+
+        let mut refused = response(local, local);
+        refused[1] = 0;
+        assert!(ntp_offset_ms(&refused, &ORIGIN, local, local).is_err());
+        // A response to another request, or forged without seeing the request
+        assert!(ntp_offset_ms(&response(local, local), &[0; 8], local, local).is_err());
+    }
+
+    #[test]
+    fn test_ntp_address() {
+        let address = |server| ntp_address(server).unwrap();
+        assert_eq!(
+            SocketAddr::from(([127, 0, 0, 1], 123)),
+            address("127.0.0.1")
+        );
+        assert_eq!(
+            SocketAddr::from(([127, 0, 0, 1], 1123)),
+            address("127.0.0.1:1123")
+        );
+        let localhost = std::net::Ipv6Addr::LOCALHOST;
+        assert_eq!(SocketAddr::from((localhost, 123)), address("::1"));
+        assert_eq!(SocketAddr::from((localhost, 123)), address("[::1]"));
+        assert_eq!(SocketAddr::from((localhost, 1123)), address("[::1]:1123"));
+    }
+}
@@ -0,0 +1,161 @@
+//! Shell completion scripts, completing the codes by name with a plain text cache of the issuers and labels,
+//! since the shells cannot unlock the database at every tab press.
+
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use clap::CommandFactory;
+use clap_complete::Shell;
+use cotp::otp::otp_element::OTPElement;
+use cotp::path::get_db_path;
+use cotp::utils::write_private;
+
+use crate::args::CotpArgs;
+
+/// Completes the code names after the commands taking one, the code indexes after edit --index
+const BASH_NAMES: &str = r#"
+_cotp_names() {
+    local IFS=$'\n'
+    compopt -o filenames
+    COMPREPLY=($(compgen -W "$(cotp complete-names "$@" 2>/dev/null | cut -f1)" -- "$cur"))
+    # Bash replaces only the part of issuer:label after the colon
+    if declare -F __ltrim_colon_completions >/dev/null; then
+        __ltrim_colon_completions "$cur"
+    elif [[ "$cur" == *:* ]]; then
+        local prefix="${cur%"${cur##*:}"}"
+        COMPREPLY=("${COMPREPLY[@]#"$prefix"}")
+    fi
+}
+_cotp_with_names() {
+    # The words of the line, since COMP_WORDS splits issuer:label at the colon
+    local line="${COMP_LINE:0:COMP_POINT}" words cur prev
+    read -ra words <<< "$line"
+    if [[ "$line" == *[[:space:]] ]]; then
+        cur="" prev="${words[-1]}"
+    else
+        cur="${words[-1]}" prev="${words[-2]}"
+    fi
+    case "$prev" in
+        copy|show|ocra|resync|get-code)
+            _cotp_names; return ;;
+        -e|--entry)
+            [[ " ${COMP_WORDS[*]} " == *" status "* ]] && { _cotp_names; return; } ;;
+        -i|--index)
+            [[ " ${COMP_WORDS[*]} " == *" edit "* ]] && { _cotp_names --indexes; return; } ;;
+    esac
+    _cotp "$@"
+}
+complete -F _cotp_with_names -o nosort -o bashdefault -o default cotp
+"#;
+
+const ZSH_NAMES: &str = r#"
+_cotp_with_names() {
+    local -a names
+    case "$words[CURRENT-1]" in
+        copy|show|ocra|resync|get-code)
+            names=("${(@f)$(cotp complete-names 2>/dev/null)}") ;;
+        -e|--entry)
+            (( ${words[(I)status]} )) && names=("${(@f)$(cotp complete-names 2>/dev/null)}") ;;
+        -i|--index)
+            if (( ${words[(I)edit]} )); then
+                local -a indexes=("${(@f)$(cotp complete-names --indexes 2>/dev/null)}")
+                indexes=("${(@)indexes//$'\t'/:}")
+                _describe 'code' indexes
+                return
+            fi ;;
+    esac
+    if (( ${#names} )); then
+        compadd -a names
+    else
+        _cotp "$@"
+    fi
+}
+compdef _cotp_with_names cotp
+"#;
+
+const FISH_NAMES: &str = r#"
+complete -c cotp -n "__fish_seen_subcommand_from copy show ocra resync get-code" -f -a "(cotp complete-names)"
+complete -c cotp -n "__fish_seen_subcommand_from status" -s e -l entry -x -a "(cotp complete-names)"
+complete -c cotp -n "__fish_seen_subcommand_from edit" -s i -l index -x -a "(cotp complete-names --indexes)"
+"#;
+
+/// Prints the completion script of the shell, completing the code names in bash, zsh and fish
+pub fn print_completions(shell: Shell) {
+    clap_complete::generate(shell, &mut CotpArgs::command(), "cotp", &mut io::stdout());
+    match shell {
+        Shell::Bash => print!("{BASH_NAMES}"),
+        Shell::Zsh => print!("{ZSH_NAMES}"),
+        Shell::Fish => print!("{FISH_NAMES}"),
+        _ => {}
+    }
+}
+
+/// Next to the database, so every profile has its own
+fn get_names_cache_path() -> PathBuf {
+    let mut path = get_db_path().into_os_string();
+    path.push(".names");
+    PathBuf::from(path)
+}
+
+/// "issuer:label" of every code, accepted by the commands taking a code name
+fn code_names(elements: &[OTPElement]) -> String {
+    elements
+        .iter()
+        .map(|e| {
+            if e.issuer.is_empty() {
+                format!("{}\n", e.label)
+            } else {
+                format!("{}:{}\n", e.issuer, e.label)
+            }
+        })
+        .collect()
+}
+
+/// Writes the names of the codes when they change, or deletes them if the cache is disabled
+pub fn update_names_cache(enabled: bool, elements: &[OTPElement]) -> io::Result<()> {
+    let path = get_names_cache_path();
+    if !enabled {
+        return match fs::remove_file(path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        };
+    }
+    let names = code_names(elements);
+    if fs::read_to_string(&path).ok().as_deref() != Some(names.as_str()) {
+        // The names tell which accounts the user has
+        write_private(&path, names.as_bytes())?;
+    }
+    Ok(())
+}
+
+/// Prints the cached names once each, or every name with the index of its code before a tab
+pub fn print_names(indexes: bool) {
+    let names = fs::read_to_string(get_names_cache_path()).unwrap_or_default();
+    let mut printed = HashSet::new();
+    for (index, name) in names.lines().enumerate() {
+        if indexes {
+            println!("{}\t{name}", index + 1);
+        } else if printed.insert(name) {
+            println!("{name}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::code_names;
+    use crate::test_element;
+
+    #[test]
+    fn test_code_names() {
+        assert_eq!(
+            "GitHub Enterprise:alice\nbob\n",
+            code_names(&[
+                test_element("GitHub Enterprise", "alice"),
+                test_element("", "bob")
+            ])
+        );
+    }
+}
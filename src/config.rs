@@ -0,0 +1,340 @@
+use std::{
+    collections::BTreeMap,
+    env,
+    fs::{self, read_to_string},
+    path::PathBuf,
+};
+
+use color_eyre::eyre::eyre;
+use dirs::config_dir;
+use serde::{Deserialize, Serialize};
+use toml_edit::DocumentMut;
+
+use crate::crypto::cryptography::KdfParams;
+
+const CONFIG_PATH: &str = "cotp/config.toml";
+
+/// User settings, read from a TOML file. Missing values fall back to the defaults.
+#[derive(Serialize, Deserialize, Default, PartialEq, Eq, Debug)]
+#[serde(default)]
+pub struct Config {
+    pub database: DatabaseConfig,
+    pub backup: BackupConfig,
+    pub dashboard: DashboardConfig,
+    pub theme: ThemeConfig,
+    pub clock: ClockConfig,
+    pub completion: CompletionConfig,
+    pub defaults: DefaultsConfig,
+    pub trash: TrashConfig,
+    /// Argon2 parameters used when the database password is set
+    pub kdf: KdfParams,
+    /// Dashboard keybindings, mapping an action name to the keys which trigger it
+    pub keys: BTreeMap<String, Vec<String>>,
+}
+
+#[derive(Serialize, Deserialize, Default, PartialEq, Eq, Debug)]
+#[serde(default)]
+pub struct DatabaseConfig {
+    /// Database used when neither --database-path, COTP_DB_PATH nor a profile choose another one
+    pub path: Option<PathBuf>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug)]
+#[serde(default)]
+pub struct BackupConfig {
+    /// Save an encrypted copy of the database before every modification
+    pub enabled: bool,
+    /// How many backups to keep, the oldest ones are deleted
+    pub rotations: usize,
+}
+
+impl Default for BackupConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            rotations: 10,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug)]
+#[serde(default)]
+pub struct DashboardConfig {
+    /// Navigate with j, k, gg, G and repeat searches with n, N
+    pub vim_mode: bool,
+    /// Show the codes only after they are revealed or copied
+    pub hide_codes: bool,
+    /// Minutes without input after which the dashboard is locked, 0 to never lock it
+    pub auto_lock: u64,
+    /// Ask the database password again before deleting codes or showing their secrets
+    pub password_prompt: bool,
+    /// Seconds during which the password is not asked again after typing it, 0 to ask it every time
+    pub password_grace_period: u64,
+    /// Seconds after which a copied code is cleared from the clipboard, 0 to keep it
+    pub clipboard_timeout: u64,
+    /// Seconds after which the QR code page is left, 0 to keep it
+    pub qr_timeout: u64,
+    /// Show the QR code only while its key is held down
+    pub qr_hold: bool,
+    /// Milliseconds without a repetition of the held key after which the QR code is hidden,
+    /// longer than the delay before the keyboard starts repeating it
+    pub qr_hold_delay: u64,
+    /// How the QR codes are drawn: auto, kitty, iterm2, sixel or unicode
+    pub qr_graphics: String,
+    /// Show the detail pane next to the table at startup
+    pub detail_pane: bool,
+    /// Digits per group of the shown codes, like 3 for 123 456, 0 to not group them. Codes can override it
+    pub digit_group: u64,
+    /// Initial order of the codes: issuer, label, creation-time, usage-count, last-used or manual
+    pub sort: String,
+    /// Columns of the table in their order: id, issuer, label, otp, time_left, type and tags
+    pub columns: Vec<String>,
+    /// Relative widths of the columns, like issuer = 30
+    pub column_widths: BTreeMap<String, u16>,
+}
+
+impl Default for DashboardConfig {
+    fn default() -> Self {
+        Self {
+            vim_mode: false,
+            hide_codes: false,
+            auto_lock: 0,
+            password_prompt: true,
+            password_grace_period: 300,
+            clipboard_timeout: 30,
+            qr_timeout: 0,
+            qr_hold: false,
+            qr_hold_delay: 1000,
+            qr_graphics: String::from("auto"),
+            detail_pane: false,
+            digit_group: 0,
+            sort: String::from("issuer"),
+            columns: ["id", "issuer", "label", "otp", "time_left"]
+                .map(String::from)
+                .to_vec(),
+            column_widths: BTreeMap::new(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug)]
+#[serde(default)]
+pub struct ClockConfig {
+    /// NTP server asked for the time at startup, like pool.ntp.org, to detect a wrong local clock
+    pub ntp_server: Option<String>,
+    /// Seconds of difference with the NTP server after which a warning is shown
+    pub warn_threshold: u64,
+    /// Generate the codes using the time of the NTP server instead of the local one
+    pub compensate: bool,
+    /// Fixed seconds added to the local clock, used when the NTP server is not compensating
+    pub offset: i64,
+}
+
+impl Default for ClockConfig {
+    fn default() -> Self {
+        Self {
+            ntp_server: None,
+            warn_threshold: 5,
+            compensate: false,
+            offset: 0,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, PartialEq, Eq, Debug)]
+#[serde(default)]
+pub struct CompletionConfig {
+    /// Save the issuers and labels of the codes in plain text next to the database, to complete them in the shell
+    pub cache_names: bool,
+}
+
+/// Values of the new codes when they are not given, the Steam, Yandex and mOTP codes keep their own
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(default)]
+pub struct DefaultsConfig {
+    pub digits: u64,
+    pub period: u64,
+}
+
+impl Default for DefaultsConfig {
+    fn default() -> Self {
+        Self {
+            digits: 6,
+            period: 30,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug)]
+#[serde(default)]
+pub struct TrashConfig {
+    /// Days after which the deleted codes are purged from the trash, 0 to keep them until purged
+    pub retention_days: u64,
+}
+
+impl Default for TrashConfig {
+    fn default() -> Self {
+        Self { retention_days: 30 }
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, PartialEq, Eq, Debug)]
+#[serde(default)]
+pub struct ThemeConfig {
+    /// Built-in theme to start from: dark, light or high-contrast
+    pub name: Option<String>,
+    /// Colors replacing the ones of the built-in theme, like `accent = "#ff8800"`
+    #[serde(flatten)]
+    pub colors: BTreeMap<String, String>,
+}
+
+/// The configuration file path can be overridden with the COTP_CONFIG_PATH environment variable
+pub fn get_config_path() -> Option<PathBuf> {
+    env::var("COTP_CONFIG_PATH")
+        .map(PathBuf::from)
+        .ok()
+        .or_else(|| config_dir().map(|p| p.join(CONFIG_PATH)))
+}
+
+impl Config {
+    /// Reads the configuration file, using the default settings if it does not exist
+    pub fn load() -> color_eyre::Result<Config> {
+        match get_config_path().filter(|p| p.exists()) {
+            Some(path) => read_to_string(&path)
+                .map_err(|e| eyre!("Cannot read the configuration file: {e}"))
+                .and_then(|content| {
+                    toml::from_str(&content)
+                        .map_err(|e| eyre!("Invalid configuration file {}: {e}", path.display()))
+                }),
+            None => Ok(Config::default()),
+        }
+    }
+
+    /// Value of a setting, like dashboard.auto_lock, or of a whole section
+    pub fn get(&self, key: &str) -> color_eyre::Result<toml::Value> {
+        lookup(toml::Value::try_from(self)?, key)
+            .ok_or(eyre!("The setting {key} does not exist or is not set"))
+    }
+}
+
+/// Changes a setting of the configuration file, keeping the rest of its content and comments.
+/// The value is written as TOML, like 5, true or ["a", "b"], other words are taken as strings.
+/// Returns the path of the configuration file
+pub fn set_config_value(key: &str, value: &str) -> color_eyre::Result<PathBuf> {
+    let path = get_config_path().ok_or(eyre!("Cannot find the configuration directory"))?;
+    let content = match path.exists() {
+        true => {
+            read_to_string(&path).map_err(|e| eyre!("Cannot read the configuration file: {e}"))?
+        }
+        false => String::new(),
+    };
+    let content = with_value(&content, key, value)?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(&path, content).map_err(|e| {
+        eyre!(
+            "Cannot write the configuration file {}: {e}",
+            path.display()
+        )
+    })?;
+    Ok(path)
+}
+
+/// Sets the value inside the TOML content, checking that it is a valid setting
+fn with_value(content: &str, key: &str, value: &str) -> color_eyre::Result<String> {
+    let mut document: DocumentMut = content
+        .parse()
+        .map_err(|e| eyre!("Invalid configuration file: {e}"))?;
+    let value = value
+        .parse::<toml_edit::Value>()
+        .unwrap_or_else(|_| value.into());
+    let (name, sections) = key
+        .rsplit_once('.')
+        .map_or((key, None), |(s, n)| (n, Some(s)));
+    let mut table = document.as_table_mut();
+    for section in sections.into_iter().flat_map(|s| s.split('.')) {
+        table = table
+            .entry(section)
+            .or_insert(toml_edit::table())
+            .as_table_mut()
+            .ok_or(eyre!("{section} is not a section"))?;
+    }
+    table.insert(name, toml_edit::value(value));
+
+    let content = document.to_string();
+    let config: Config =
+        toml::from_str(&content).map_err(|e| eyre!("Invalid value for {key}: {e}"))?;
+    // Unknown settings are ignored when reading the file, so they are missing from the parsed configuration
+    let written = lookup(toml::from_str(&content)?, key);
+    if written.is_none() || config.get(key).ok() != written {
+        return Err(eyre!("Unknown setting {key}"));
+    }
+    Ok(content)
+}
+
+fn lookup(value: toml::Value, key: &str) -> Option<toml::Value> {
+    key.split('.')
+        .try_fold(value, |value, part| value.get(part).cloned())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::{with_value, BackupConfig, Config};
+
+    #[test]
+    fn test_partial_config() {
+        let config: Config = toml::from_str("[backup]\nrotations = 3\n").unwrap();
+
+        assert_eq!(
+            BackupConfig {
+                enabled: true,
+                rotations: 3
+            },
+            config.backup
+        );
+        assert!(config.keys.is_empty());
+
+        let config: Config =
+            toml::from_str("[theme]\nname = \"light\"\naccent = \"blue\"\n").unwrap();
+        assert_eq!(Some("light"), config.theme.name.as_deref());
+        assert_eq!(
+            Some("blue"),
+            config.theme.colors.get("accent").map(String::as_str)
+        );
+        let config: Config = toml::from_str("[database]\npath = \"/media/usb\"\n").unwrap();
+        assert_eq!(Some(PathBuf::from("/media/usb")), config.database.path);
+        let config: Config = toml::from_str("[kdf]\nmemory = 65536\n").unwrap();
+        assert_eq!(65536, config.kdf.memory);
+        assert_eq!(4, config.kdf.iterations);
+        let config: Config = toml::from_str("[clock]\nntp_server = \"pool.ntp.org\"\n").unwrap();
+        assert_eq!(Some("pool.ntp.org"), config.clock.ntp_server.as_deref());
+        assert_eq!(5, config.clock.warn_threshold);
+        assert!(!config.clock.compensate);
+        assert_eq!(Config::default(), toml::from_str("").unwrap());
+    }
+
+    #[test]
+    fn test_with_value() {
+        let content = "# Lock quickly\n[dashboard]\nauto_lock = 5\n";
+        let content = with_value(content, "dashboard.auto_lock", "10").unwrap();
+        assert_eq!("# Lock quickly\n[dashboard]\nauto_lock = 10\n", content);
+
+        let content = with_value(&content, "theme.name", "light").unwrap();
+        let content = with_value(&content, "keys.copy", r#"["c", "y"]"#).unwrap();
+        let config: Config = toml::from_str(&content).unwrap();
+        assert_eq!(10, config.dashboard.auto_lock);
+        assert_eq!(Some("light"), config.theme.name.as_deref());
+        assert_eq!(vec!["c", "y"], config.keys["copy"]);
+        assert_eq!(
+            toml::Value::Integer(10),
+            config.get("dashboard.auto_lock").unwrap()
+        );
+
+        assert!(with_value(&content, "dashboard.auto_lock", "never").is_err());
+        assert!(with_value(&content, "dashboard.autolock", "10").is_err());
+        assert!(with_value(&content, "dashboard", "10").is_err());
+    }
+}
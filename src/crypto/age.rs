@@ -0,0 +1,80 @@
+use std::{
+    io::{ErrorKind, Write},
+    path::Path,
+    process::{Command, Stdio},
+};
+
+use color_eyre::eyre::eyre;
+
+/// The age (https://age-encryption.org) and rage command line tools are supported, together with their plugins
+const AGE_TOOLS: [&str; 2] = ["age", "rage"];
+
+/// Native X25519 recipients, including the ones of plugins like age-plugin-yubikey, or SSH public keys
+pub fn is_valid_recipient(recipient: &str) -> bool {
+    recipient.starts_with("age1")
+        || recipient.starts_with("ssh-ed25519 ")
+        || recipient.starts_with("ssh-rsa ")
+}
+
+/// Encrypts the data to every recipient, returning the ASCII armored age file
+pub fn encrypt_to_recipients(data: &[u8], recipients: &[String]) -> color_eyre::Result<String> {
+    let mut args = vec![String::from("--encrypt"), String::from("--armor")];
+    for recipient in recipients {
+        args.push(String::from("--recipient"));
+        args.push(recipient.to_owned());
+    }
+    let output = run_age(&args, data)?;
+    String::from_utf8(output).map_err(|e| eyre!("Invalid age output: {e}"))
+}
+
+/// Decrypts the ASCII armored age file with the identity file, like an age key or an SSH private key
+pub fn decrypt_with_identity(armored: &str, identity: &Path) -> color_eyre::Result<Vec<u8>> {
+    let args = [
+        String::from("--decrypt"),
+        String::from("--identity"),
+        identity.to_string_lossy().into_owned(),
+    ];
+    run_age(&args, armored.as_bytes())
+}
+
+/// Pipes the input into the first age tool found, its prompts are shown on the terminal
+fn run_age(args: &[String], input: &[u8]) -> color_eyre::Result<Vec<u8>> {
+    for tool in AGE_TOOLS {
+        let mut child = match Command::new(tool)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()
+        {
+            Ok(child) => child,
+            Err(e) if e.kind() == ErrorKind::NotFound => continue,
+            Err(e) => return Err(eyre!("Cannot run {tool}: {e}")),
+        };
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(input)?;
+        }
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            return Err(eyre!("{tool} failed with {}", output.status));
+        }
+        return Ok(output.stdout);
+    }
+    Err(eyre!("Cannot find age, make sure age or rage is installed"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::is_valid_recipient;
+
+    #[test]
+    fn test_valid_recipient() {
+        assert!(is_valid_recipient(
+            "age1ql3z7hjy54pw3hyww5ayyfg7zqgvc7w3j2elw8zmrj2kg5sfn9aqmcac8p"
+        ));
+        assert!(is_valid_recipient(
+            "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIHsKLqeplhpW+uObz5dvMgjz1OxfM/XXUB+VHtZ6isGN alice@example.com"
+        ));
+        assert!(!is_valid_recipient("alice@example.com"));
+    }
+}
@@ -1,27 +1,165 @@
+use std::time::{Duration, Instant};
+
 use argon2::{Config, Variant, Version};
 use chacha20poly1305::aead::Aead;
 use chacha20poly1305::{Key, KeyInit, XChaCha20Poly1305, XNonce};
 use color_eyre::eyre::{eyre, ErrReport};
 use data_encoding::BASE64;
+use serde::{Deserialize, Serialize};
+use zeroize::{Zeroize, Zeroizing};
 
 use super::encrypted_database::EncryptedDatabase;
+use super::keyfile::keyfile_hash;
+use super::yubikey::challenge_response;
 
 const ARGON2ID_SALT_LENGTH: usize = 16;
 const XCHACHA20_POLY1305_NONCE_LENGTH: usize = 24;
 const XCHACHA20_POLY1305_KEY_LENGTH: usize = 32;
-const KEY_DERIVATION_CONFIG: Config = Config {
-    variant: Variant::Argon2id,
-    version: Version::Version13,
-    mem_cost: 32768,
-    time_cost: 4,
-    lanes: 4,
-    secret: &[],
-    ad: &[],
-    hash_length: XCHACHA20_POLY1305_KEY_LENGTH as u32,
-};
+// Calibration starts from the default memory cost, so it is never lowered, doubling it up to the maximum one
+const CALIBRATION_MIN_MEMORY: u32 = 32768;
+const CALIBRATION_MAX_MEMORY: u32 = 1048576;
+const CALIBRATION_MAX_ITERATIONS: u32 = 32;
+const CALIBRATION_MAX_PARALLELISM: u32 = 4;
+
+/// Derived database key, wiped from memory when dropped
+pub type DatabaseKey = Zeroizing<Vec<u8>>;
+
+/// Argon2id parameters, stored in the encrypted database since they are needed to derive its key
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[serde(default)]
+pub struct KdfParams {
+    /// Memory cost in KiB
+    pub memory: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+/// Parameters used by every database created before they became configurable
+impl Default for KdfParams {
+    fn default() -> Self {
+        Self {
+            memory: 32768,
+            iterations: 4,
+            parallelism: 4,
+        }
+    }
+}
+
+impl KdfParams {
+    pub fn is_default(&self) -> bool {
+        *self == KdfParams::default()
+    }
 
-pub fn argon_derive_key(password_bytes: &[u8], salt: &[u8]) -> color_eyre::Result<Vec<u8>> {
-    argon2::hash_raw(password_bytes, salt, &KEY_DERIVATION_CONFIG).map_err(ErrReport::from)
+    /// True if brute forcing a key derived with these parameters is cheaper than with the other ones
+    /// Trading memory for iterations, like 64 MiB and 2 passes against 32 MiB and 4, is not weaker
+    pub fn is_weaker_than(&self, other: &KdfParams) -> bool {
+        self.cost() < other.cost()
+    }
+
+    fn cost(&self) -> u128 {
+        u128::from(self.memory) * u128::from(self.iterations) * u128::from(self.parallelism)
+    }
+
+    fn argon2_config(&self) -> Config<'static> {
+        Config {
+            variant: Variant::Argon2id,
+            version: Version::Version13,
+            mem_cost: self.memory,
+            time_cost: self.iterations,
+            lanes: self.parallelism,
+            secret: &[],
+            ad: &[],
+            hash_length: XCHACHA20_POLY1305_KEY_LENGTH as u32,
+        }
+    }
+}
+
+/// Derives the database encryption key using Argon2id
+pub fn argon_derive_key(
+    password_bytes: &[u8],
+    salt: &[u8],
+    params: &KdfParams,
+) -> color_eyre::Result<Vec<u8>> {
+    argon2::hash_raw(password_bytes, salt, &params.argon2_config())
+        .map_err(|e| eyre!("Invalid key derivation parameters {params:?}: {e}"))
+}
+
+/// Derives the database key, combining the password with the keyfile hash and the YubiKey response to the salt if enrolled
+pub fn derive_database_key(
+    password_bytes: &[u8],
+    salt: &[u8],
+    yubikey_slot: Option<u8>,
+    keyfile_hash: Option<&[u8]>,
+    params: &KdfParams,
+) -> color_eyre::Result<DatabaseKey> {
+    let mut key_material = Zeroizing::new(password_bytes.to_vec());
+    if let Some(hash) = keyfile_hash {
+        key_material.extend_from_slice(hash);
+    }
+    if let Some(slot) = yubikey_slot {
+        let response = Zeroizing::new(challenge_response(slot, salt)?);
+        key_material.extend_from_slice(&response);
+    }
+    argon_derive_key(&key_material, salt, params).map(Zeroizing::new)
+}
+
+/// Benchmarks this machine to find the parameters whose key derivation takes about the target time.
+/// The memory cost is raised first, since it is what makes brute forcing expensive on GPUs.
+pub fn calibrate_kdf_params(target: Duration) -> color_eyre::Result<KdfParams> {
+    let parallelism = std::thread::available_parallelism()
+        .map(|n| n.get() as u32)
+        .unwrap_or(1)
+        .min(CALIBRATION_MAX_PARALLELISM);
+    let mut params = KdfParams {
+        memory: CALIBRATION_MIN_MEMORY,
+        iterations: 2,
+        parallelism,
+    };
+    let salt = gen_salt()?;
+    let mut elapsed = time_derivation(&params, &salt)?;
+    while elapsed * 2 <= target && params.memory < CALIBRATION_MAX_MEMORY {
+        params.memory *= 2;
+        elapsed = time_derivation(&params, &salt)?;
+    }
+    // The derivation time grows linearly with the iterations
+    if elapsed < target {
+        let scale = target.as_secs_f64() / elapsed.as_secs_f64().max(f64::EPSILON);
+        params.iterations = ((params.iterations as f64 * scale) as u32)
+            .clamp(params.iterations, CALIBRATION_MAX_ITERATIONS);
+    }
+    Ok(params)
+}
+
+fn time_derivation(params: &KdfParams, salt: &[u8]) -> color_eyre::Result<Duration> {
+    let start = Instant::now();
+    argon_derive_key(b"calibration", salt, params)?;
+    Ok(start.elapsed())
+}
+
+/// Reads the YubiKey slot needed to unlock the serialized encrypted database, if any
+pub fn required_yubikey_slot(encrypted_text: &str) -> Option<u8> {
+    serde_json::from_str::<EncryptedDatabase>(encrypted_text)
+        .ok()
+        .and_then(|d| d.yubikey_slot())
+}
+
+/// Checks if the serialized encrypted database can be unlocked only with its keyfile
+pub fn requires_keyfile(encrypted_text: &str) -> bool {
+    serde_json::from_str::<EncryptedDatabase>(encrypted_text).is_ok_and(|d| d.keyfile())
+}
+
+/// Reads the database key encrypted to the age recipients of the serialized encrypted database
+pub fn stored_age_key(encrypted_text: &str) -> Option<String> {
+    serde_json::from_str::<EncryptedDatabase>(encrypted_text)
+        .ok()
+        .and_then(|d| d.age_key().map(String::from))
+}
+
+/// Reads the key derivation parameters of the serialized encrypted database
+pub fn stored_kdf_params(encrypted_text: &str) -> KdfParams {
+    serde_json::from_str::<EncryptedDatabase>(encrypted_text)
+        .map(|d| d.kdf_params())
+        .unwrap_or_default()
 }
 
 pub fn gen_salt() -> color_eyre::Result<[u8; ARGON2ID_SALT_LENGTH]> {
@@ -30,8 +168,9 @@ pub fn gen_salt() -> color_eyre::Result<[u8; ARGON2ID_SALT_LENGTH]> {
     Ok(salt)
 }
 
+/// Encrypts the plain text using XChaCha20-Poly1305
 pub fn encrypt_string_with_key(
-    plain_text: String,
+    mut plain_text: String,
     key: &Vec<u8>,
     salt: &[u8],
 ) -> color_eyre::Result<EncryptedDatabase> {
@@ -44,9 +183,9 @@ pub fn encrypt_string_with_key(
     getrandom::getrandom(&mut nonce_bytes).map_err(ErrReport::from)?;
 
     let nonce = XNonce::from_slice(&nonce_bytes);
-    let cipher_text = aead
-        .encrypt(nonce, plain_text.as_bytes())
-        .map_err(|e| eyre!("Error during encryption: {e}"))?;
+    let cipher_text = aead.encrypt(nonce, plain_text.as_bytes());
+    plain_text.zeroize();
+    let cipher_text = cipher_text.map_err(|e| eyre!("Error during encryption: {e}"))?;
     Ok(EncryptedDatabase::new(
         1,
         BASE64.encode(&nonce_bytes),
@@ -55,13 +194,52 @@ pub fn encrypt_string_with_key(
     ))
 }
 
+/// Decrypts the serialized encrypted database, returning the plain text, the derived key and the salt
 pub fn decrypt_string(
     encrypted_text: &str,
     password: &str,
-) -> color_eyre::Result<(String, Vec<u8>, Vec<u8>)> {
+) -> color_eyre::Result<(String, DatabaseKey, Vec<u8>)> {
     //encrypted text is an encrypted database json serialized object
-    let encrypted_database: EncryptedDatabase = serde_json::from_str(encrypted_text)
-        .map_err(|e| eyre!("Error during encrypted database deserialization: {e}"))?;
+    let encrypted_database = parse_encrypted_database(encrypted_text)?;
+    let salt = BASE64.decode(encrypted_database.salt().as_bytes()).unwrap();
+
+    let keyfile_hash = match encrypted_database.keyfile() {
+        true => Some(keyfile_hash().ok_or(eyre!(
+            "This database needs its keyfile, use --keyfile or COTP_KEYFILE"
+        ))?),
+        false => None,
+    };
+    let key = derive_database_key(
+        password.as_bytes(),
+        salt.as_slice(),
+        encrypted_database.yubikey_slot(),
+        keyfile_hash.as_ref().map(|h| h.as_slice()),
+        &encrypted_database.kdf_params(),
+    )?;
+    decrypt_with_key(&encrypted_database, key)
+}
+
+/// Decrypts the serialized encrypted database with an already known key, like the one unlocked by an age identity
+pub fn decrypt_string_with_key(
+    encrypted_text: &str,
+    key: Vec<u8>,
+) -> color_eyre::Result<(String, DatabaseKey, Vec<u8>)> {
+    let key = DatabaseKey::new(key);
+    if key.len() != XCHACHA20_POLY1305_KEY_LENGTH {
+        return Err(eyre!("Invalid database key length"));
+    }
+    decrypt_with_key(&parse_encrypted_database(encrypted_text)?, key)
+}
+
+fn parse_encrypted_database(encrypted_text: &str) -> color_eyre::Result<EncryptedDatabase> {
+    serde_json::from_str(encrypted_text)
+        .map_err(|e| eyre!("Error during encrypted database deserialization: {e}"))
+}
+
+fn decrypt_with_key(
+    encrypted_database: &EncryptedDatabase,
+    key: DatabaseKey,
+) -> color_eyre::Result<(String, DatabaseKey, Vec<u8>)> {
     let nonce = BASE64
         .decode(encrypted_database.nonce().as_bytes())
         .expect("Cannot decode Base64 nonce");
@@ -70,8 +248,6 @@ pub fn decrypt_string(
         .expect("Cannot decode Base64 cipher");
     let salt = BASE64.decode(encrypted_database.salt().as_bytes()).unwrap();
 
-    let key: Vec<u8> = argon_derive_key(password.as_bytes(), salt.as_slice())?;
-
     let wrapped_key = Key::from_slice(&key);
 
     let aead = XChaCha20Poly1305::new(wrapped_key);
@@ -87,16 +263,116 @@ pub fn decrypt_string(
 mod tests {
     use crate::crypto::cryptography::{argon_derive_key, gen_salt};
 
-    use super::{decrypt_string, encrypt_string_with_key};
+    use super::{
+        decrypt_string, decrypt_string_with_key, derive_database_key, encrypt_string_with_key,
+        required_yubikey_slot, requires_keyfile, stored_age_key, stored_kdf_params, KdfParams,
+    };
 
     #[test]
     fn test_encryption() {
         let salt = gen_salt().unwrap();
-        let key = argon_derive_key(b"pa$$w0rd", salt.as_ref()).unwrap();
+        let key = argon_derive_key(b"pa$$w0rd", salt.as_ref(), &KdfParams::default()).unwrap();
         let encrypted =
             encrypt_string_with_key(String::from("Secret data@#[]ò"), &key, salt.as_ref()).unwrap();
         let (decrypted, _key, _salt) =
             decrypt_string(&serde_json::to_string(&encrypted).unwrap(), "pa$$w0rd").unwrap();
         assert_eq!(String::from("Secret data@#[]ò"), decrypted);
     }
+
+    #[test]
+    fn test_required_yubikey_slot() {
+        let salt = gen_salt().unwrap();
+        let key = argon_derive_key(b"pa$$w0rd", salt.as_ref(), &KdfParams::default()).unwrap();
+        let encrypted = encrypt_string_with_key(String::from("data"), &key, salt.as_ref()).unwrap();
+        let without_yubikey = serde_json::to_string(&encrypted).unwrap();
+        let with_yubikey = serde_json::to_string(&encrypted.with_yubikey_slot(Some(2))).unwrap();
+
+        assert!(!without_yubikey.contains("yubikey_slot"));
+        assert_eq!(None, required_yubikey_slot(&without_yubikey));
+        assert_eq!(Some(2), required_yubikey_slot(&with_yubikey));
+    }
+
+    #[test]
+    fn test_custom_kdf_params() {
+        let params = KdfParams {
+            memory: 8192,
+            iterations: 2,
+            parallelism: 1,
+        };
+        let salt = gen_salt().unwrap();
+        let key = argon_derive_key(b"pa$$w0rd", salt.as_ref(), &params).unwrap();
+        let encrypted = encrypt_string_with_key(String::from("data"), &key, salt.as_ref())
+            .unwrap()
+            .with_kdf_params(params);
+        let serialized = serde_json::to_string(&encrypted).unwrap();
+
+        assert_eq!(params, stored_kdf_params(&serialized));
+        assert_eq!("data", decrypt_string(&serialized, "pa$$w0rd").unwrap().0);
+
+        // Databases without parameters use the ones of the previous releases
+        let legacy =
+            serde_json::to_string(&encrypted.with_kdf_params(KdfParams::default())).unwrap();
+        assert!(!legacy.contains("kdf"));
+        assert_eq!(KdfParams::default(), stored_kdf_params(&legacy));
+        assert!(params.is_weaker_than(&KdfParams::default()));
+        assert!(!KdfParams::default().is_weaker_than(&params));
+        let more_memory = KdfParams {
+            memory: 65536,
+            iterations: 2,
+            parallelism: 4,
+        };
+        assert!(!more_memory.is_weaker_than(&KdfParams::default()));
+        assert!(!KdfParams::default().is_weaker_than(&more_memory));
+    }
+
+    #[test]
+    fn test_keyfile_key() {
+        let params = KdfParams {
+            memory: 8192,
+            iterations: 1,
+            parallelism: 1,
+        };
+        let salt = gen_salt().unwrap();
+        let without_keyfile = derive_database_key(b"pa$$w0rd", &salt, None, None, &params).unwrap();
+        let with_keyfile =
+            derive_database_key(b"pa$$w0rd", &salt, None, Some(&[7; 32]), &params).unwrap();
+
+        assert_eq!(
+            argon_derive_key(b"pa$$w0rd", &salt, &params).unwrap(),
+            *without_keyfile
+        );
+        assert_ne!(without_keyfile, with_keyfile);
+
+        let encrypted = encrypt_string_with_key(String::from("data"), &with_keyfile, &salt)
+            .unwrap()
+            .with_keyfile(true);
+        let serialized = serde_json::to_string(&encrypted).unwrap();
+        assert!(requires_keyfile(&serialized));
+        // No keyfile has been given to this process
+        assert!(decrypt_string(&serialized, "pa$$w0rd")
+            .is_err_and(|e| e.to_string().contains("keyfile")));
+        assert!(!requires_keyfile(
+            &serde_json::to_string(&encrypted.with_keyfile(false)).unwrap()
+        ));
+    }
+
+    #[test]
+    fn test_age_key() {
+        let salt = gen_salt().unwrap();
+        let key = argon_derive_key(b"pa$$w0rd", salt.as_ref(), &KdfParams::default()).unwrap();
+        let encrypted = encrypt_string_with_key(String::from("data"), &key, salt.as_ref())
+            .unwrap()
+            .with_age_key(Some(String::from("armored key")));
+        let serialized = serde_json::to_string(&encrypted).unwrap();
+
+        assert_eq!(
+            Some(String::from("armored key")),
+            stored_age_key(&serialized)
+        );
+        // The recipients are in the encrypted database, not in the envelope
+        assert!(!serialized.contains("age_recipients"));
+        // The key unlocked by the age identity is enough to decrypt the database
+        assert_eq!("data", decrypt_string_with_key(&serialized, key).unwrap().0);
+        assert!(decrypt_string_with_key(&serialized, vec![0; 16]).is_err());
+    }
 }
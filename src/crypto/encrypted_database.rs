@@ -1,11 +1,25 @@
 use serde::{Deserialize, Serialize};
 
+use super::cryptography::KdfParams;
+
 #[derive(Serialize, Deserialize)]
 pub struct EncryptedDatabase {
     version: u16,
     nonce: String,
     salt: String,
     cipher: String,
+    /// Slot of the YubiKey whose response to the salt is part of the key derivation input
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    yubikey_slot: Option<u8>,
+    /// Argon2 parameters, missing in databases using the default ones
+    #[serde(default, skip_serializing_if = "KdfParams::is_default")]
+    kdf: KdfParams,
+    /// The hash of a keyfile is part of the key derivation input
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    keyfile: bool,
+    /// Database key encrypted to the age recipients, ASCII armored
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    age_key: Option<String>,
 }
 
 impl EncryptedDatabase {
@@ -15,9 +29,33 @@ impl EncryptedDatabase {
             nonce,
             salt,
             cipher,
+            yubikey_slot: None,
+            kdf: KdfParams::default(),
+            keyfile: false,
+            age_key: None,
         }
     }
 
+    pub fn with_yubikey_slot(mut self, yubikey_slot: Option<u8>) -> EncryptedDatabase {
+        self.yubikey_slot = yubikey_slot;
+        self
+    }
+
+    pub fn with_kdf_params(mut self, kdf: KdfParams) -> EncryptedDatabase {
+        self.kdf = kdf;
+        self
+    }
+
+    pub fn with_keyfile(mut self, keyfile: bool) -> EncryptedDatabase {
+        self.keyfile = keyfile;
+        self
+    }
+
+    pub fn with_age_key(mut self, age_key: Option<String>) -> EncryptedDatabase {
+        self.age_key = age_key;
+        self
+    }
+
     pub fn nonce(&self) -> &str {
         &self.nonce
     }
@@ -27,4 +65,16 @@ impl EncryptedDatabase {
     pub fn cipher(&self) -> &str {
         &self.cipher
     }
+    pub fn yubikey_slot(&self) -> Option<u8> {
+        self.yubikey_slot
+    }
+    pub fn kdf_params(&self) -> KdfParams {
+        self.kdf
+    }
+    pub fn keyfile(&self) -> bool {
+        self.keyfile
+    }
+    pub fn age_key(&self) -> Option<&str> {
+        self.age_key.as_deref()
+    }
 }
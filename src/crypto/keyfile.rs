@@ -0,0 +1,58 @@
+use std::{fs, path::Path, sync::OnceLock};
+
+use color_eyre::eyre::eyre;
+use sha2::{Digest, Sha256};
+
+/// Random bytes written into generated keyfiles
+const GENERATED_KEYFILE_LENGTH: usize = 64;
+
+/// Hash of the keyfile given to unlock the database
+static KEYFILE_HASH: OnceLock<[u8; 32]> = OnceLock::new();
+
+/// Hashes the keyfile content, which can be any non empty file
+pub fn hash_keyfile(path: &Path) -> color_eyre::Result<[u8; 32]> {
+    let content =
+        fs::read(path).map_err(|e| eyre!("Cannot read the keyfile {}: {e}", path.display()))?;
+    if content.is_empty() {
+        return Err(eyre!("The keyfile {} is empty", path.display()));
+    }
+    Ok(Sha256::digest(content).into())
+}
+
+/// Uses the keyfile to unlock the database for the rest of the execution
+pub fn set_keyfile(path: &Path) -> color_eyre::Result<()> {
+    let hash = hash_keyfile(path)?;
+    KEYFILE_HASH
+        .set(hash)
+        .map_err(|_| eyre!("A keyfile has already been given"))
+}
+
+pub fn keyfile_hash() -> Option<[u8; 32]> {
+    KEYFILE_HASH.get().copied()
+}
+
+/// Writes a new keyfile made of random bytes
+pub fn generate_keyfile(path: &Path) -> color_eyre::Result<()> {
+    let mut content = [0u8; GENERATED_KEYFILE_LENGTH];
+    getrandom::getrandom(&mut content)?;
+    fs::write(path, content).map_err(|e| eyre!("Cannot create the keyfile {}: {e}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{generate_keyfile, hash_keyfile};
+
+    #[test]
+    fn test_keyfile() {
+        let path = std::env::temp_dir().join(format!("cotp-keyfile-test-{}", std::process::id()));
+        generate_keyfile(&path).unwrap();
+        let hash = hash_keyfile(&path).unwrap();
+
+        generate_keyfile(&path).unwrap();
+        assert_ne!(hash, hash_keyfile(&path).unwrap());
+
+        std::fs::write(&path, b"").unwrap();
+        assert!(hash_keyfile(&path).is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+}
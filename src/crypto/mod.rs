@@ -1,2 +1,6 @@
+pub mod age;
 pub mod cryptography;
 pub mod encrypted_database;
+pub mod keyfile;
+pub mod secretstream;
+pub mod yubikey;
@@ -0,0 +1,144 @@
+/*
+Single message implementation of the libsodium crypto_secretstream_xchacha20poly1305 construction,
+used by the apps that encrypt their exports with libsodium, like Ente Auth.
+See https://doc.libsodium.org/secret-key_cryptography/secretstream
+*/
+
+use chacha20::cipher::consts::U10;
+use chacha20::cipher::{KeyIvInit, StreamCipher, StreamCipherSeek};
+use chacha20::{hchacha, ChaCha20};
+use poly1305::universal_hash::KeyInit;
+use poly1305::Poly1305;
+use zeroize::Zeroize;
+
+pub const HEADER_LENGTH: usize = 24;
+pub const KEY_LENGTH: usize = 32;
+/// The encrypted tag byte and the Poly1305 MAC
+const ADDITIONAL_BYTES: usize = 17;
+const BLOCK_LENGTH: usize = 64;
+/// Marks the last message of the stream
+const TAG_FINAL: u8 = 3;
+
+struct StreamState {
+    key: [u8; KEY_LENGTH],
+    nonce: [u8; 12],
+}
+
+impl StreamState {
+    fn new(key: &[u8; KEY_LENGTH], header: &[u8; HEADER_LENGTH]) -> Self {
+        let mut subkey = hchacha::<U10>(key.into(), header[..16].into());
+        let mut state = StreamState {
+            key: subkey.into(),
+            nonce: [0; 12],
+        };
+        subkey.zeroize();
+        state.nonce[0] = 1;
+        state.nonce[4..].copy_from_slice(&header[16..]);
+        state
+    }
+
+    /// XORs the data with the ChaCha20 keystream, starting from the given block
+    fn apply_keystream(&self, data: &mut [u8], block: u32) {
+        let mut cipher = ChaCha20::new(&self.key.into(), &self.nonce.into());
+        cipher.seek(block as u64 * BLOCK_LENGTH as u64);
+        cipher.apply_keystream(data);
+    }
+
+    fn mac(&self, tag_block: &[u8; BLOCK_LENGTH], ciphertext: &[u8]) -> [u8; 16] {
+        let mut poly_key = [0u8; 32];
+        self.apply_keystream(&mut poly_key, 0);
+        let poly = Poly1305::new(&poly_key.into());
+        poly_key.zeroize();
+        // No additional data is used. libsodium pads the ciphertext with its length modulo 16,
+        // so the authenticated data is not always aligned to the Poly1305 blocks
+        let padding = ciphertext.len() % 16;
+        let mut authenticated = Vec::with_capacity(BLOCK_LENGTH + ciphertext.len() + padding + 16);
+        authenticated.extend_from_slice(tag_block);
+        authenticated.extend_from_slice(ciphertext);
+        authenticated.resize(authenticated.len() + padding + 8, 0);
+        authenticated.extend_from_slice(&((BLOCK_LENGTH + ciphertext.len()) as u64).to_le_bytes());
+        poly.compute_unpadded(&authenticated).into()
+    }
+}
+
+impl Drop for StreamState {
+    fn drop(&mut self) {
+        self.key.zeroize();
+    }
+}
+
+/// Encrypts the message as the only one of a new stream, returning the stream header and the ciphertext
+pub fn encrypt(
+    key: &[u8; KEY_LENGTH],
+    message: &[u8],
+) -> Result<([u8; HEADER_LENGTH], Vec<u8>), getrandom::Error> {
+    let mut header = [0u8; HEADER_LENGTH];
+    getrandom::getrandom(&mut header)?;
+    let state = StreamState::new(key, &header);
+
+    let mut tag_block = [0u8; BLOCK_LENGTH];
+    tag_block[0] = TAG_FINAL;
+    state.apply_keystream(&mut tag_block, 1);
+
+    let mut ciphertext = Vec::with_capacity(message.len() + ADDITIONAL_BYTES);
+    ciphertext.push(tag_block[0]);
+    ciphertext.extend_from_slice(message);
+    state.apply_keystream(&mut ciphertext[1..], 2);
+    let mac = state.mac(&tag_block, &ciphertext[1..]);
+    ciphertext.extend_from_slice(&mac);
+    Ok((header, ciphertext))
+}
+
+/// Decrypts a stream made of a single message
+pub fn decrypt(
+    key: &[u8; KEY_LENGTH],
+    header: &[u8; HEADER_LENGTH],
+    ciphertext: &[u8],
+) -> Result<Vec<u8>, String> {
+    if ciphertext.len() < ADDITIONAL_BYTES {
+        return Err(String::from("The encrypted data is too short"));
+    }
+    let state = StreamState::new(key, header);
+    let (encrypted, mac) = ciphertext[1..].split_at(ciphertext.len() - ADDITIONAL_BYTES);
+
+    let mut tag_block = [0u8; BLOCK_LENGTH];
+    tag_block[0] = ciphertext[0];
+    state.apply_keystream(&mut tag_block, 1);
+    let tag = tag_block[0];
+    tag_block[0] = ciphertext[0];
+
+    let expected_mac = state.mac(&tag_block, encrypted);
+    let matching = expected_mac
+        .iter()
+        .zip(mac)
+        .fold(0, |acc, (a, b)| acc | (a ^ b));
+    if matching != 0 {
+        return Err(String::from("Failed to decrypt, wrong password?"));
+    }
+    if tag != TAG_FINAL {
+        return Err(String::from(
+            "Encrypted streams made of many messages are not supported",
+        ));
+    }
+    let mut message = encrypted.to_vec();
+    state.apply_keystream(&mut message, 2);
+    Ok(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decrypt, encrypt};
+
+    #[test]
+    fn test_round_trip() {
+        let key = [7u8; 32];
+        let (header, ciphertext) = encrypt(&key, b"otpauth://totp/A:a?secret=AAAA").unwrap();
+
+        assert_eq!(
+            b"otpauth://totp/A:a?secret=AAAA".to_vec(),
+            decrypt(&key, &header, &ciphertext).unwrap()
+        );
+        assert!(decrypt(&[8u8; 32], &header, &ciphertext).is_err());
+        assert!(decrypt(&key, &header, &ciphertext[..16]).is_err());
+    }
+}
@@ -0,0 +1,61 @@
+use std::process::Command;
+
+use color_eyre::eyre::eyre;
+
+/// HMAC-SHA1 responses are made of 20 bytes
+const RESPONSE_LENGTH: usize = 20;
+
+/// Sends the challenge to the YubiKey slot configured for HMAC-SHA1 challenge-response.
+/// The ykchalresp (yubikey-personalization) and ykman (yubikey-manager) tools are supported.
+pub fn challenge_response(slot: u8, challenge: &[u8]) -> color_eyre::Result<Vec<u8>> {
+    let challenge = hex::encode(challenge);
+    let tools: [(&str, Vec<String>); 2] = [
+        (
+            "ykchalresp",
+            vec![format!("-{slot}"), String::from("-x"), challenge.to_owned()],
+        ),
+        (
+            "ykman",
+            vec![
+                String::from("otp"),
+                String::from("calculate"),
+                slot.to_string(),
+                challenge.to_owned(),
+            ],
+        ),
+    ];
+    eprintln!("Waiting for the YubiKey, touch it if it is blinking...");
+    tools
+        .iter()
+        .find_map(|(program, args)| {
+            Command::new(program)
+                .args(args)
+                .output()
+                .ok()
+                .filter(|output| output.status.success())
+                .and_then(|output| parse_response(&String::from_utf8_lossy(&output.stdout)))
+        })
+        .ok_or(eyre!(
+            "Cannot get a response from the YubiKey in slot {slot}, make sure it is plugged in and ykchalresp or ykman is installed"
+        ))
+}
+
+fn parse_response(output: &str) -> Option<Vec<u8>> {
+    hex::decode(output.trim())
+        .ok()
+        .filter(|response| response.len() == RESPONSE_LENGTH)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_response;
+
+    #[test]
+    fn test_parse_response() {
+        let response = parse_response("b34a4bc1d5ce8d28e2ad4ae3c6e1cba5e6ec1ef1\n").unwrap();
+
+        assert_eq!(20, response.len());
+        assert_eq!(0xb3, response[0]);
+        assert!(parse_response("Yubikey core error: no yubikey present").is_none());
+    }
+}
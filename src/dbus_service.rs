@@ -0,0 +1,118 @@
+//! D-Bus service giving the codes of the unlocked database to the other programs of the desktop session.
+//!
+//! Every process of the same user connected to the session bus can call it, like desktop extensions and launcher scripts.
+
+use cotp::otp::otp_element::OTPDatabase;
+
+#[cfg(target_os = "linux")]
+pub const BUS_NAME: &str = "io.github.replydev.Cotp";
+#[cfg(target_os = "linux")]
+pub const OBJECT_PATH: &str = "/io/github/replydev/Cotp";
+
+/// Entries returned by ListEntries and Search: index starting from 1, issuer, label and type
+#[cfg(target_os = "linux")]
+type Entry = (u32, String, String, String);
+
+#[cfg(target_os = "linux")]
+struct CotpService {
+    database: std::sync::Arc<std::sync::Mutex<OTPDatabase>>,
+}
+
+#[cfg(target_os = "linux")]
+impl CotpService {
+    fn entries(database: &OTPDatabase, indexes: impl Iterator<Item = usize>) -> Vec<Entry> {
+        indexes
+            .filter_map(|i| database.get_element(i).map(|e| (i, e)))
+            .map(|(i, e)| {
+                (
+                    i as u32 + 1,
+                    e.issuer.clone(),
+                    e.label.clone(),
+                    e.type_.to_string(),
+                )
+            })
+            .collect()
+    }
+
+    /// Code of the only element matching the query, recording its usage
+    fn use_code(&self, query: &str) -> zbus::fdo::Result<String> {
+        let mut database = self.database.lock().unwrap();
+        let index = match database.find_matching(query)[..] {
+            [index] => index,
+            [] => {
+                return Err(zbus::fdo::Error::Failed(format!(
+                    "No code found matching \"{query}\""
+                )))
+            }
+            _ => {
+                return Err(zbus::fdo::Error::Failed(format!(
+                    "More than one code matches \"{query}\""
+                )))
+            }
+        };
+        // Safe to unwrap because the index comes from the database
+        let code = database
+            .get_element(index)
+            .unwrap()
+            .get_otp_code()
+            .map_err(|e| zbus::fdo::Error::Failed(e.to_string()))?;
+        database.record_usage(index);
+        Ok(code)
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[zbus::interface(name = "io.github.replydev.Cotp1")]
+impl CotpService {
+    /// Current code of the only entry matching the query
+    fn get_code(&self, query: &str) -> zbus::fdo::Result<String> {
+        self.use_code(query)
+    }
+
+    /// Types the current code of the only entry matching the query into the focused window
+    fn type_code(&self, query: &str) -> zbus::fdo::Result<()> {
+        let code = self.use_code(query)?;
+        crate::autotype::type_string(&code).map_err(|e| zbus::fdo::Error::Failed(e.to_string()))
+    }
+
+    fn list_entries(&self) -> Vec<Entry> {
+        let database = self.database.lock().unwrap();
+        Self::entries(&database, 0..database.elements_ref().len())
+    }
+
+    fn search(&self, query: &str) -> Vec<Entry> {
+        let database = self.database.lock().unwrap();
+        let indexes = database.find_matching(query);
+        Self::entries(&database, indexes.into_iter())
+    }
+}
+
+/// Serves the database on the session bus until the process receives SIGINT or SIGTERM,
+/// then gives it back to be saved with the updated usage counters
+#[cfg(target_os = "linux")]
+pub fn serve(database: OTPDatabase) -> color_eyre::Result<OTPDatabase> {
+    use std::sync::{Arc, Mutex};
+
+    // Blocked before the connection starts its threads, so they do not receive the signals
+    let signals = crate::signals::TerminationSignals::block()?;
+
+    let database = Arc::new(Mutex::new(database));
+    let service = CotpService {
+        database: database.clone(),
+    };
+    let connection = zbus::blocking::connection::Builder::session()?
+        .name(BUS_NAME)?
+        .serve_at(OBJECT_PATH, service)?
+        .build()?;
+    println!("Serving the codes on the session bus as {BUS_NAME}, press Ctrl+C to stop");
+
+    signals.wait()?;
+    drop(connection);
+    let mut database = database.lock().unwrap();
+    Ok(std::mem::take(&mut *database))
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn serve(_database: OTPDatabase) -> color_eyre::Result<OTPDatabase> {
+    Err(color_eyre::eyre::eyre!("D-Bus is only available on Linux"))
+}
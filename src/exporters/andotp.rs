@@ -7,8 +7,17 @@ impl From<OTPDatabase> for AndOtpDatabase {
     }
 }
 
-impl<'a> From<&'a OTPDatabase> for &'a AndOtpDatabase {
-    fn from(value: &'a OTPDatabase) -> Self {
-        &value.elements
+/// andOTP stores the time of the last use in milliseconds
+impl From<&OTPDatabase> for AndOtpDatabase {
+    fn from(value: &OTPDatabase) -> Self {
+        value
+            .elements
+            .iter()
+            .cloned()
+            .map(|mut element| {
+                element.last_used = element.last_used.saturating_mul(1000);
+                element
+            })
+            .collect()
     }
 }
@@ -0,0 +1,87 @@
+use crate::otp::otp_element::OTPDatabase;
+
+/// Header names, the same fields understood by the CSV importer
+const HEADER: [&str; 9] = [
+    "issuer",
+    "label",
+    "secret",
+    "algorithm",
+    "digits",
+    "period",
+    "type",
+    "counter",
+    "t0",
+];
+
+/// CSV export with a header row, omitting the secrets when redacted for inventories and audits
+pub fn to_csv(database: &OTPDatabase, redact_secrets: bool) -> String {
+    let mut csv = format!("{}\n", HEADER.join(","));
+    for element in database.elements_ref() {
+        let secret = if redact_secrets {
+            ""
+        } else {
+            element.secret.as_str()
+        };
+        let row = [
+            escape(&element.issuer),
+            escape(&element.label),
+            escape(secret),
+            element.algorithm.to_string(),
+            element.digits.to_string(),
+            element.period.to_string(),
+            element.type_.to_string(),
+            element.counter.map(|c| c.to_string()).unwrap_or_default(),
+            element.time_offset().to_string(),
+        ];
+        csv.push_str(&row.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+/// Quotes the value if needed, doubling its quotes
+fn escape(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::importers::csv::parse_records;
+    use crate::otp::otp_element::{OTPDatabase, OTPElement};
+    use crate::otp::{otp_algorithm::OTPAlgorithm, otp_type::OTPType};
+
+    use super::to_csv;
+
+    #[test]
+    fn test_csv_export() {
+        let mut element = OTPElement::default();
+        element.secret = String::from("JBSWY3DPEHPK3PXP");
+        element.issuer = String::from("Mail, \"personal\"");
+        element.label = String::from("bob");
+        element.digits = 8;
+        element.type_ = OTPType::Hotp;
+        element.algorithm = OTPAlgorithm::Sha256;
+        element.counter = Some(4);
+        let database = OTPDatabase {
+            elements: vec![element],
+            ..Default::default()
+        };
+
+        let csv = to_csv(&database, false);
+        assert_eq!(
+            "issuer,label,secret,algorithm,digits,period,type,counter,t0\n\"Mail, \"\"personal\"\"\",bob,JBSWY3DPEHPK3PXP,SHA256,8,30,HOTP,4,0\n",
+            csv
+        );
+        assert_eq!(
+            "Mail, \"personal\"",
+            parse_records(&csv, ',').unwrap()[1][0]
+        );
+
+        let redacted = to_csv(&database, true);
+        assert!(!redacted.contains("JBSWY3DPEHPK3PXP"));
+    }
+}
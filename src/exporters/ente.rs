@@ -0,0 +1,134 @@
+/*
+Export into the Ente Auth format, a plain text list of OTP URIs carrying the Ente display settings
+in their codeDisplay parameter, optionally encrypted with a password like Ente does.
+*/
+
+use argon2::{Config, Variant, Version};
+use color_eyre::eyre::{eyre, ErrReport};
+use data_encoding::BASE64;
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroize;
+
+use crate::crypto::secretstream::{self, KEY_LENGTH};
+use crate::otp::otp_element::{OTPDatabase, OTPElement};
+
+const SALT_LENGTH: usize = 16;
+// Same parameters of the libsodium interactive limits, used by Ente for its exports
+const MEM_LIMIT: u64 = 64 * 1024 * 1024;
+const OPS_LIMIT: u32 = 2;
+// Ente stores the last usage in microseconds
+const MICROSECONDS: u64 = 1_000_000;
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnteEncryptedExport {
+    pub version: u32,
+    pub kdf_params: EnteKdfParams,
+    pub encrypted_data: String,
+    pub encryption_nonce: String,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnteKdfParams {
+    /// Memory used by Argon2id, in bytes
+    pub mem_limit: u64,
+    pub ops_limit: u32,
+    pub salt: String,
+}
+
+/// Settings shown by Ente next to each code, stored as JSON in the codeDisplay URI parameter
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct EnteCodeDisplay {
+    pub pinned: bool,
+    pub trashed: bool,
+    pub last_used_at: u64,
+    pub tap_count: u64,
+    pub tags: Vec<String>,
+}
+
+impl From<&OTPElement> for EnteCodeDisplay {
+    fn from(element: &OTPElement) -> Self {
+        EnteCodeDisplay {
+            pinned: element.pinned,
+            trashed: false,
+            last_used_at: element.last_used * MICROSECONDS,
+            tap_count: element.usage_count,
+            tags: element.tags.to_owned(),
+        }
+    }
+}
+
+impl EnteCodeDisplay {
+    /// Copies the display settings into the imported element
+    pub fn apply(&self, element: &mut OTPElement) {
+        element.pinned = self.pinned;
+        element.last_used = self.last_used_at / MICROSECONDS;
+        element.usage_count = self.tap_count;
+        element.tags = self.tags.to_owned();
+    }
+}
+
+impl EnteKdfParams {
+    /// Derives the export key with Argon2id, as libsodium crypto_pwhash does
+    pub fn derive_key(&self, password: &str) -> color_eyre::Result<[u8; KEY_LENGTH]> {
+        let salt = BASE64
+            .decode(self.salt.as_bytes())
+            .map_err(|e| eyre!("Invalid Ente export salt: {e}"))?;
+        let config = Config {
+            variant: Variant::Argon2id,
+            version: Version::Version13,
+            mem_cost: (self.mem_limit / 1024) as u32,
+            time_cost: self.ops_limit,
+            lanes: 1,
+            secret: &[],
+            ad: &[],
+            hash_length: KEY_LENGTH as u32,
+        };
+        let mut hash = argon2::hash_raw(password.as_bytes(), &salt, &config)?;
+        let key = hash.as_slice().try_into().map_err(ErrReport::from);
+        hash.zeroize();
+        key
+    }
+}
+
+/// Ente OTP URI, the cotp one with the display settings appended. It already has the issuer parameter.
+pub fn ente_uri(element: &OTPElement) -> color_eyre::Result<String> {
+    let code_display = serde_json::to_string(&EnteCodeDisplay::from(element))?;
+    Ok(format!(
+        "{}&codeDisplay={}",
+        element.get_otpauth_uri(),
+        urlencoding::encode(&code_display)
+    ))
+}
+
+/// Plain text Ente export, one OTP URI per line
+pub fn to_ente_text(database: &OTPDatabase) -> color_eyre::Result<String> {
+    database
+        .elements_ref()
+        .iter()
+        .map(|element| ente_uri(element).map(|uri| format!("{uri}\n")))
+        .collect()
+}
+
+/// Encrypts the plain text export with the given password
+pub fn encrypt_ente_export(text: &str, password: &str) -> color_eyre::Result<EnteEncryptedExport> {
+    let mut salt = [0u8; SALT_LENGTH];
+    getrandom::getrandom(&mut salt)?;
+    let kdf_params = EnteKdfParams {
+        mem_limit: MEM_LIMIT,
+        ops_limit: OPS_LIMIT,
+        salt: BASE64.encode(&salt),
+    };
+    let mut key = kdf_params.derive_key(password)?;
+    let result = secretstream::encrypt(&key, text.as_bytes());
+    key.zeroize();
+    let (header, ciphertext) = result?;
+    Ok(EnteEncryptedExport {
+        version: 1,
+        kdf_params,
+        encrypted_data: BASE64.encode(&ciphertext),
+        encryption_nonce: BASE64.encode(&header),
+    })
+}
@@ -0,0 +1,71 @@
+use std::{
+    fs,
+    io::Write,
+    path::PathBuf,
+    process::{Command, Stdio},
+    thread,
+};
+
+use zeroize::Zeroize;
+
+const GPG_EXTENSION: &str = ".gpg";
+
+/// Appends the .gpg extension used for OpenPGP encrypted files
+pub fn gpg_file_name(file_name: &str) -> String {
+    format!("{file_name}{GPG_EXTENSION}")
+}
+
+/// Encrypts the contents to every OpenPGP recipient with the gpg command, so that only the encrypted file is written
+pub fn do_export_gpg(
+    mut contents: String,
+    recipients: &[String],
+    exported_path: PathBuf,
+) -> Result<PathBuf, String> {
+    if contents.is_empty() {
+        return Err("No contents to export, skipping...".to_owned());
+    }
+    let result = encrypt(contents.as_bytes().to_vec(), recipients);
+    contents.zeroize();
+    fs::write(&exported_path, result?).map_err(|e| format!("Cannot write the export: {e}"))?;
+    Ok(exported_path)
+}
+
+fn encrypt(mut plain_text: Vec<u8>, recipients: &[String]) -> Result<Vec<u8>, String> {
+    let mut args = vec![
+        String::from("--batch"),
+        String::from("--yes"),
+        String::from("--encrypt"),
+    ];
+    for recipient in recipients {
+        args.push(String::from("--recipient"));
+        args.push(recipient.to_owned());
+    }
+    let mut child = Command::new("gpg")
+        .args(&args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .spawn()
+        .map_err(|e| format!("Cannot run gpg, make sure GnuPG is installed: {e}"))?;
+    let mut stdin = child.stdin.take().ok_or("Cannot write to gpg")?;
+    // Written from another thread, since gpg may fill the output pipe before reading the whole input
+    let writer = thread::spawn(move || {
+        let result = stdin.write_all(&plain_text);
+        plain_text.zeroize();
+        result
+    });
+    let output = child
+        .wait_with_output()
+        .map_err(|e| format!("Cannot run gpg: {e}"))?;
+    writer
+        .join()
+        .map_err(|_| "Cannot write to gpg")?
+        .map_err(|e| format!("Cannot write to gpg: {e}"))?;
+    if !output.status.success() {
+        return Err(format!(
+            "gpg failed with {}, check that the recipients public keys are imported and trusted",
+            output.status
+        ));
+    }
+    Ok(output.stdout)
+}
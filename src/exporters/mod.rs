@@ -1,28 +1,48 @@
-use std::{fs::File, io::Write, path::PathBuf};
+use std::path::PathBuf;
 
 use serde::Serialize;
-use zeroize::Zeroize;
+use zeroize::Zeroizing;
+
+use crate::utils::write_private;
 
 pub mod andotp;
+pub mod csv;
+pub mod ente;
 pub mod freeotp_plus;
+pub mod gpg;
 pub mod otp_uri;
+pub mod qr_png;
 
+/// Serializes the exported contents as JSON and writes them into the exported file
 pub fn do_export<T>(to_be_saved: &T, exported_path: PathBuf) -> Result<PathBuf, String>
+where
+    T: ?Sized + Serialize,
+{
+    do_export_text(to_export_json(to_be_saved)?, exported_path)
+}
+
+/// Serializes the exported contents as JSON
+pub fn to_export_json<T>(to_be_saved: &T) -> Result<String, String>
 where
     T: ?Sized + Serialize,
 {
     match serde_json::to_string(to_be_saved) {
-        Ok(mut contents) => {
+        Ok(contents) => {
             if contents == "[]" {
                 return Err("No contents to export, skipping...".to_owned());
             }
-            let mut file = File::create(&exported_path).expect("Cannot create file");
-            let contents_bytes = contents.as_bytes();
-            file.write_all(contents_bytes)
-                .expect("Failed to write contents");
-            contents.zeroize();
-            Ok(exported_path)
+            Ok(contents)
         }
         Err(e) => Err(format!("{e:?}")),
     }
 }
+
+/// Writes already formatted contents into the exported file, which only the user can read
+pub fn do_export_text(contents: String, exported_path: PathBuf) -> Result<PathBuf, String> {
+    let contents = Zeroizing::new(contents);
+    if contents.is_empty() {
+        return Err("No contents to export, skipping...".to_owned());
+    }
+    write_private(&exported_path, contents.as_bytes()).map_err(|e| e.to_string())?;
+    Ok(exported_path)
+}
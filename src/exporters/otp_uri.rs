@@ -1,3 +1,5 @@
+use std::str::FromStr;
+
 use crate::otp::otp_element::OTPDatabase;
 use serde::{Deserialize, Serialize};
 
@@ -13,3 +15,53 @@ impl<'a> From<&'a OTPDatabase> for OtpUriList {
         OtpUriList { items }
     }
 }
+
+impl OtpUriList {
+    /// Plain text representation, one OTP URI per line
+    pub fn to_text(&self) -> String {
+        self.items
+            .iter()
+            .map(|uri| format!("{uri}\n"))
+            .collect::<String>()
+    }
+}
+
+/// Checks if the file content is a plain text OTP URIs list instead of a JSON one
+pub fn is_plain_text_list(content: &str) -> bool {
+    content.trim_start().starts_with("otpauth://")
+}
+
+impl FromStr for OtpUriList {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let items = s
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(|line| line.to_owned())
+            .collect();
+        Ok(OtpUriList { items })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_plain_text_list, OtpUriList};
+
+    #[test]
+    fn test_plain_text_round_trip() {
+        let list = OtpUriList {
+            items: vec![
+                "otpauth://totp/A:a?secret=AAAA".to_string(),
+                "otpauth://hotp/B:b?secret=BBBB&counter=1".to_string(),
+            ],
+        };
+
+        let text = list.to_text();
+        assert!(is_plain_text_list(&text));
+
+        let parsed: OtpUriList = text.parse().unwrap();
+        assert_eq!(list.items, parsed.items);
+    }
+}
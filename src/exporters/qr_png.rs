@@ -0,0 +1,63 @@
+use std::{fs, path::PathBuf};
+
+use image::{GrayImage, Luma};
+use qrcode::{Color, QrCode};
+
+use crate::otp::otp_element::{OTPDatabase, OTPElement};
+
+const MODULE_SIZE: u32 = 8;
+const QUIET_ZONE_MODULES: u32 = 4;
+
+/// Renders every element into a standalone QR code PNG image inside the given directory
+pub fn export_qr_images(database: &OTPDatabase, directory: PathBuf) -> Result<PathBuf, String> {
+    if database.elements_ref().is_empty() {
+        return Err("No contents to export, skipping...".to_owned());
+    }
+    fs::create_dir_all(&directory).map_err(|e| format!("Cannot create directory: {e}"))?;
+    for (i, element) in database.elements_ref().iter().enumerate() {
+        let image = render_qr_code(element.get_otpauth_uri().as_str())?;
+        let file_name = format!("{}_{}", i + 1, qr_image_name(element));
+        image
+            .save(directory.join(file_name))
+            .map_err(|e| format!("Cannot save QR code image: {e}"))?;
+    }
+    Ok(directory)
+}
+
+/// Renders the content into a QR code image, with a quiet zone around it to ease the scanning
+pub fn render_qr_code(content: &str) -> Result<GrayImage, String> {
+    let code = QrCode::new(content).map_err(|e| format!("Cannot create QR code: {e}"))?;
+    let width = code.width() as u32;
+    let colors = code.to_colors();
+    let size = (width + 2 * QUIET_ZONE_MODULES) * MODULE_SIZE;
+    Ok(GrayImage::from_fn(size, size, |x, y| {
+        let x = (x / MODULE_SIZE).checked_sub(QUIET_ZONE_MODULES);
+        let y = (y / MODULE_SIZE).checked_sub(QUIET_ZONE_MODULES);
+        match x.zip(y).filter(|(x, y)| *x < width && *y < width) {
+            Some((x, y)) if colors[(y * width + x) as usize] == Color::Dark => Luma([0]),
+            _ => Luma([255]),
+        }
+    }))
+}
+
+/// Name of the QR code image of the element, like GitHub_alice.png
+pub fn qr_image_name(element: &OTPElement) -> String {
+    format!(
+        "{}_{}.png",
+        sanitize(&element.issuer),
+        sanitize(&element.label)
+    )
+}
+
+fn sanitize(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' || c == '.' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
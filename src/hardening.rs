@@ -0,0 +1,87 @@
+//! Keeps the decrypted secrets out of core dumps and swap.
+//!
+//! Every step is best effort: cotp keeps working if the operating system refuses it,
+//! and the help of the dashboard tells whether the memory could be locked.
+
+use std::sync::OnceLock;
+
+use crate::i18n::tr;
+
+static MEMORY_LOCK: OnceLock<MemoryLock> = OnceLock::new();
+
+/// Whether the memory of the process is kept out of swap
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+pub enum MemoryLock {
+    Locked,
+    /// The RLIMIT_MEMLOCK limit is not unlimited, so locking the memory would make the allocations fail
+    Limited,
+    /// The operating system refused to lock it
+    Failed,
+    /// Not implemented on this system
+    Unsupported,
+}
+
+impl MemoryLock {
+    pub fn description(self) -> &'static str {
+        tr(match self {
+            MemoryLock::Locked => "memory-locked",
+            MemoryLock::Limited => "memory-limited",
+            MemoryLock::Failed => "memory-lock-failed",
+            MemoryLock::Unsupported => "memory-lock-unsupported",
+        })
+    }
+}
+
+/// The outcome of [`harden_process`]
+pub fn memory_lock() -> MemoryLock {
+    MEMORY_LOCK
+        .get()
+        .copied()
+        .unwrap_or(MemoryLock::Unsupported)
+}
+
+/// Disables core dumps and, when the memory lock limit allows it, locks the process memory
+pub fn harden_process() {
+    let _ = MEMORY_LOCK.set(harden());
+}
+
+#[cfg(target_os = "linux")]
+fn harden() -> MemoryLock {
+    use nix::sys::mman::{mlockall, MlockAllFlags};
+    use nix::sys::prctl::set_dumpable;
+    use nix::sys::resource::{getrlimit, setrlimit, Resource, RLIM_INFINITY};
+
+    disable_core_dumps();
+    // Also prevents other processes of the same user from attaching with ptrace
+    let _ = set_dumpable(false);
+    // With a limited amount of lockable memory the future allocations would fail,
+    // like the ones of the Argon2 key derivation, so the memory is locked only if unlimited
+    if !matches!(getrlimit(Resource::RLIMIT_MEMLOCK), Ok((_, RLIM_INFINITY)))
+        || setrlimit(Resource::RLIMIT_MEMLOCK, RLIM_INFINITY, RLIM_INFINITY).is_err()
+    {
+        return MemoryLock::Limited;
+    }
+    match mlockall(MlockAllFlags::MCL_CURRENT | MlockAllFlags::MCL_FUTURE) {
+        Ok(()) => MemoryLock::Locked,
+        Err(_) => MemoryLock::Failed,
+    }
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+fn harden() -> MemoryLock {
+    disable_core_dumps();
+    MemoryLock::Unsupported
+}
+
+#[cfg(not(unix))]
+fn harden() -> MemoryLock {
+    MemoryLock::Unsupported
+}
+
+#[cfg(unix)]
+fn disable_core_dumps() {
+    use nix::sys::resource::{setrlimit, Resource};
+
+    let _ = setrlimit(Resource::RLIMIT_CORE, 0, 0);
+}
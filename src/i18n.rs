@@ -0,0 +1,164 @@
+//! Translations of the dashboard and command line messages, read from the Fluent catalogs in `locales/`.
+//!
+//! Only the simple messages of Fluent are supported: `id = value` lines, indented continuation lines, comments and
+//! `{ $name }` variables. Messages missing from a translation fall back to English.
+
+use std::collections::HashMap;
+use std::sync::{LazyLock, OnceLock};
+
+/// Catalogs built into the binary, English first being the fallback
+const CATALOGS: [(&str, &str); 2] = [
+    ("en", include_str!("../locales/en.ftl")),
+    ("it", include_str!("../locales/it.ftl")),
+];
+
+static ENGLISH: LazyLock<Catalog> = LazyLock::new(|| Catalog::parse(CATALOGS[0].1));
+static CATALOG: OnceLock<Catalog> = OnceLock::new();
+
+struct Catalog {
+    messages: HashMap<String, String>,
+}
+
+impl Catalog {
+    fn parse(source: &str) -> Self {
+        let mut messages = HashMap::new();
+        let mut current: Option<(String, String)> = None;
+        for line in source.lines() {
+            // Indented lines continue the value of the previous message
+            if line.starts_with([' ', '\t']) && !line.trim().is_empty() {
+                if let Some((_, value)) = &mut current {
+                    if !value.is_empty() {
+                        value.push('\n');
+                    }
+                    value.push_str(line.trim());
+                    continue;
+                }
+            }
+            messages.extend(current.take());
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some((id, value)) = line.split_once('=') {
+                current = Some((id.trim().to_owned(), value.trim().to_owned()));
+            }
+        }
+        messages.extend(current);
+        Catalog { messages }
+    }
+
+    fn get(&self, id: &str) -> Option<&str> {
+        self.messages.get(id).map(String::as_str)
+    }
+}
+
+/// Chooses the catalog of the language of the environment, English if it is not translated
+pub(crate) fn init() {
+    let language = detect_from(|name| std::env::var(name).ok());
+    if let Some((_, source)) = CATALOGS.iter().find(|(code, _)| Some(*code) == language) {
+        // Called once at startup, so it is never set already
+        let _ = CATALOG.set(Catalog::parse(source));
+    }
+}
+
+/// First translated language among the ones of the environment variables, read in the order of gettext
+fn detect_from(var: impl Fn(&str) -> Option<String>) -> Option<&'static str> {
+    let locale = ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .into_iter()
+        .filter_map(&var)
+        .find(|value| !value.is_empty())?;
+    // The C locale disables the translations, LANGUAGE included
+    if locale == "C" || locale == "POSIX" {
+        return None;
+    }
+    // LANGUAGE lists the preferred languages, like it:en
+    let preferred = var("LANGUAGE").unwrap_or_default();
+    preferred
+        .split(':')
+        .chain(std::iter::once(locale.as_str()))
+        .filter_map(|locale| {
+            // Like it_IT.UTF-8 or it@euro
+            let language = locale.split(['_', '.', '@']).next()?;
+            CATALOGS
+                .iter()
+                .map(|(code, _)| *code)
+                .find(|code| *code == language)
+        })
+        .next()
+}
+
+/// The translated message, the English one if it is missing or the id if it is unknown
+pub(crate) fn tr(id: &'static str) -> &'static str {
+    CATALOG
+        .get()
+        .and_then(|catalog| catalog.get(id))
+        .or_else(|| ENGLISH.get(id))
+        .unwrap_or(id)
+}
+
+/// The translated message with its `{ $name }` variables replaced by the values
+pub(crate) fn tr_args(id: &'static str, args: &[(&str, &str)]) -> String {
+    replace_variables(tr(id), args)
+}
+
+fn replace_variables(message: &str, args: &[(&str, &str)]) -> String {
+    args.iter()
+        .fold(message.to_owned(), |message, (name, value)| {
+            message
+                .replace(&format!("{{ ${name} }}"), value)
+                .replace(&format!("{{${name}}}"), value)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{detect_from, replace_variables, Catalog, CATALOGS, ENGLISH};
+
+    #[test]
+    fn test_catalog() {
+        let catalog = Catalog::parse(
+            "# Comment\ncopied = Copied { $name } code\nlong = First line\n    second line\n\nempty =\n",
+        );
+        assert_eq!(Some("Copied { $name } code"), catalog.get("copied"));
+        assert_eq!(Some("First line\nsecond line"), catalog.get("long"));
+        assert_eq!(Some(""), catalog.get("empty"));
+        assert_eq!(None, catalog.get("missing"));
+        assert_eq!(
+            "Copied GitHub code",
+            replace_variables(catalog.get("copied").unwrap(), &[("name", "GitHub")])
+        );
+    }
+
+    #[test]
+    fn test_translations_are_complete() {
+        for (code, source) in CATALOGS {
+            let catalog = Catalog::parse(source);
+            for id in ENGLISH.messages.keys() {
+                assert!(catalog.get(id).is_some(), "{id} is missing in {code}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_detect_language() {
+        let detect = |vars: &[(&str, &str)]| {
+            detect_from(|name| {
+                vars.iter()
+                    .find(|(n, _)| *n == name)
+                    .map(|(_, v)| v.to_string())
+            })
+        };
+        assert_eq!(Some("it"), detect(&[("LANG", "it_IT.UTF-8")]));
+        assert_eq!(
+            Some("en"),
+            detect(&[("LC_ALL", "en_US.UTF-8"), ("LANG", "it_IT.UTF-8")])
+        );
+        assert_eq!(
+            Some("it"),
+            detect(&[("LANGUAGE", "fr:it"), ("LANG", "en_US.UTF-8")])
+        );
+        assert_eq!(None, detect(&[("LANGUAGE", "it"), ("LANG", "C")]));
+        assert_eq!(None, detect(&[("LANG", "de_DE.UTF-8")]));
+        assert_eq!(None, detect(&[]));
+    }
+}
@@ -1,20 +1,15 @@
+use data_encoding::BASE32_NOPAD;
 use serde::{Deserialize, Serialize};
 
-use crate::otp::{otp_algorithm::OTPAlgorithm, otp_element::OTPElement, otp_type::OTPType};
+use crate::otp::otp_element::{Icon, OTPElement};
+use crate::otp::{otp_algorithm::OTPAlgorithm, otp_type::OTPType};
 
 #[derive(Serialize, Deserialize)]
 pub struct AegisJson {
     //version: u64,
-    //header: AegisHeader,
     db: AegisDb,
 }
 
-#[derive(Deserialize)]
-struct AegisHeader {
-    //slots: Option<String>,
-    //params: Option<String>,
-}
-
 #[derive(Serialize, Deserialize)]
 pub(crate) struct AegisDb {
     //version: u64,
@@ -28,23 +23,45 @@ struct AegisElement {
     //uuid: String,
     name: String,
     issuer: String,
-    //icon: Option<String>,
+    /// Base64 encoded logo
+    #[serde(default)]
+    icon: Option<String>,
+    #[serde(default)]
+    icon_mime: Option<String>,
+    #[serde(default)]
+    note: String,
     info: AegisInfo,
 }
 
 impl From<AegisElement> for OTPElement {
     fn from(value: AegisElement) -> Self {
-        OTPElement {
-            secret: value.info.secret,
-            issuer: value.issuer,
-            label: value.name,
-            digits: value.info.digits,
-            type_: OTPType::from(value._type.as_str()),
-            algorithm: OTPAlgorithm::from(value.info.algo.as_str()),
-            period: value.info.period.unwrap_or(30),
-            counter: value.info.counter,
-            pin: None,
-        }
+        let type_ = OTPType::from(value._type.as_str());
+        // Aegis encodes every secret in base32, while MOTP secrets are hex strings
+        let secret = match type_ {
+            OTPType::Motp => BASE32_NOPAD
+                .decode(value.info.secret.as_bytes())
+                .map(hex::encode)
+                .unwrap_or(value.info.secret),
+            _ => value.info.secret,
+        };
+        let mut element = OTPElement::default();
+        element.secret = secret;
+        element.issuer = value.issuer;
+        element.label = value.name;
+        element.digits = value.info.digits;
+        element.type_ = type_;
+        element.algorithm = OTPAlgorithm::from(value.info.algo.as_str());
+        element.period = value.info.period.unwrap_or(30);
+        element.counter = value.info.counter;
+        element.pin = value.info.pin;
+        element.note = value.note;
+        element.icon = value.icon.map(|image| Icon {
+            image: Some(image),
+            mime: value.icon_mime,
+            text: None,
+            color: None,
+        });
+        element
     }
 }
 
@@ -71,4 +88,43 @@ struct AegisInfo {
     digits: u64,
     period: Option<u64>,
     counter: Option<u64>,
+    /// Used by Yandex and MOTP codes
+    pin: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::otp::{otp_algorithm::OTPAlgorithm, otp_element::OTPElement, otp_type::OTPType};
+
+    use super::AegisJson;
+
+    #[test]
+    fn test_yandex_pin_import() {
+        let json = r#"{"version":1,"db":{"version":2,"entries":[{"type":"yandex","uuid":"0b0e1e5e-0d8e-4c4b-9d5c-1a3e2b7d9f10","name":"user","issuer":"Yandex","icon":"PHN2Zy8+","icon_mime":"image/svg+xml","note":"Work account","info":{"secret":"6SB2IKNM6OBZPAVBVTOHDKS4FAAAAAAADFUTQMBTRY","algo":"SHA256","digits":8,"period":30,"pin":"5239"}}]}}"#;
+        let aegis: AegisJson = serde_json::from_str(json).unwrap();
+
+        let elements: Vec<OTPElement> = aegis.try_into().unwrap();
+
+        assert_eq!(OTPType::Yandex, elements[0].type_);
+        assert_eq!(OTPAlgorithm::Sha256, elements[0].algorithm);
+        assert_eq!(8, elements[0].digits);
+        assert_eq!(Some("5239".to_string()), elements[0].pin);
+        assert_eq!("Work account", elements[0].note);
+        let icon = elements[0].icon.as_ref().unwrap();
+        assert_eq!(Some("PHN2Zy8+"), icon.image.as_deref());
+        assert_eq!(Some("image/svg+xml"), icon.mime.as_deref());
+    }
+
+    #[test]
+    fn test_motp_secret_import() {
+        let json = r#"{"version":1,"db":{"version":2,"entries":[{"type":"motp","uuid":"5c9d1a8e-3f2b-4e6a-8c7d-9b0a1e2f3d4c","name":"user","issuer":"MOTP","icon":null,"info":{"secret":"4MKSV7XGEWM4Q","algo":"MD5","digits":6,"period":10,"pin":"1234"}}]}}"#;
+        let aegis: AegisJson = serde_json::from_str(json).unwrap();
+
+        let elements: Vec<OTPElement> = aegis.try_into().unwrap();
+
+        assert_eq!(OTPType::Motp, elements[0].type_);
+        assert_eq!("e3152afee62599c8", elements[0].secret);
+        assert_eq!(10, elements[0].period);
+        assert_eq!(Some("1234".to_string()), elements[0].pin);
+    }
 }
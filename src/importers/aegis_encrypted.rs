@@ -1,4 +1,4 @@
-use aes_gcm::aead::generic_array::GenericArray;
+use aes_gcm::aead::consts::U12;
 use aes_gcm::aead::Aead;
 use aes_gcm::{Aes256Gcm, KeyInit, Nonce}; // Or `Aes128Gcm`
 use data_encoding::BASE64;
@@ -49,42 +49,49 @@ impl TryFrom<AegisEncryptedDatabase> for Vec<OTPElement> {
     type Error = String;
 
     fn try_from(aegis_encrypted: AegisEncryptedDatabase) -> Result<Self, Self::Error> {
-        let mut password = utils::password("Insert your Aegis password: ", 0);
-        let master_key: Option<Vec<u8>> = get_master_key(&aegis_encrypted, &password);
+        let mut password =
+            utils::password("Insert your Aegis password: ", 0).map_err(|e| e.to_string())?;
+        let result = decrypt_database(&aegis_encrypted, &password);
         password.zeroize();
+        result
+    }
+}
 
-        match master_key {
-            Some(mut master_key) => {
-                let content = BASE64
-                    .decode(aegis_encrypted.db.as_bytes())
-                    .map_err(|e| format!("Error during base64 decoding: {e:?}"))?;
-
-                let key = GenericArray::clone_from_slice(master_key.as_slice());
-                master_key.zeroize();
-                let cipher = Aes256Gcm::new(&key);
-
-                let decrypted_db = cipher
-                    .decrypt(
-                        Nonce::from_slice(
-                            Vec::from_hex(&aegis_encrypted.header.params.nonce)
-                                .expect("Failed to parse hex nonce")
-                                .as_slice(),
-                        ),
-                        [
-                            content,
-                            Vec::from_hex(&aegis_encrypted.header.params.tag)
-                                .expect("Failed to parse hex tag"),
-                        ]
-                        .concat()
-                        .as_slice(),
-                    )
-                    .map_err(|e| format!("Failed to derive master key: {e:?}"))?;
-
-                map_results(decrypted_db)
-            }
-            None => Err("Failed to derive master key".to_string()),
-        }
+fn decrypt_database(
+    aegis_encrypted: &AegisEncryptedDatabase,
+    password: &str,
+) -> Result<Vec<OTPElement>, String> {
+    let mut master_key =
+        get_master_key(aegis_encrypted, password).ok_or("Failed to derive master key")?;
+
+    let content = BASE64
+        .decode(aegis_encrypted.db.as_bytes())
+        .map_err(|e| format!("Error during base64 decoding: {e:?}"))?;
+
+    let cipher = Aes256Gcm::new_from_slice(master_key.as_slice());
+    master_key.zeroize();
+    let cipher = cipher.map_err(|_| "Invalid master key length")?;
+
+    let nonce = decode_nonce(&aegis_encrypted.header.params.nonce)?;
+    let tag = decode_hex(&aegis_encrypted.header.params.tag, "tag")?;
+    let decrypted_db = cipher
+        .decrypt(&nonce, [content, tag].concat().as_slice())
+        .map_err(|e| format!("Failed to decrypt the Aegis database: {e:?}"))?;
+
+    map_results(decrypted_db)
+}
+
+fn decode_hex(value: &str, field: &str) -> Result<Vec<u8>, String> {
+    Vec::from_hex(value).map_err(|e| format!("Failed to parse hex {field}: {e:?}"))
+}
+
+/// AES-GCM nonces are 12 bytes long, other lengths would make the decryption panic
+fn decode_nonce(value: &str) -> Result<Nonce<U12>, String> {
+    let nonce = decode_hex(value, "nonce")?;
+    if nonce.len() != 12 {
+        return Err(format!("Invalid nonce length: {}", nonce.len()));
     }
+    Ok(*Nonce::from_slice(nonce.as_slice()))
 }
 
 fn get_master_key(aegis_encrypted: &AegisEncryptedDatabase, password: &str) -> Option<Vec<u8>> {
@@ -116,9 +123,10 @@ fn map_results(decrypted_db: Vec<u8>) -> Result<Vec<OTPElement>, String> {
 }
 
 fn get_params(slot: &AegisEncryptedSlot) -> Result<Params, String> {
-    let n = slot.n.unwrap();
-    let p = slot.p.unwrap();
-    let r = slot.r.unwrap();
+    let (n, r, p) = match (slot.n, slot.r, slot.p) {
+        (Some(n), Some(r), Some(p)) => (n, r, p),
+        _ => return Err("Missing scrypt parameters in password slot".to_string()),
+    };
 
     Params::new(
         (n as f32).log2() as u8,
@@ -130,7 +138,10 @@ fn get_params(slot: &AegisEncryptedSlot) -> Result<Params, String> {
 }
 
 fn calc_master_key(slot: &AegisEncryptedSlot, password: &str) -> Result<Vec<u8>, String> {
-    let salt = Vec::from_hex(slot.salt.as_ref().unwrap()).expect("Failed to parse hex salt");
+    let salt = decode_hex(
+        slot.salt.as_ref().ok_or("Missing salt in password slot")?,
+        "salt",
+    )?;
     let mut output: [u8; 32] = [0; 32];
     let params = get_params(slot)?;
 
@@ -142,24 +153,65 @@ fn calc_master_key(slot: &AegisEncryptedSlot, password: &str) -> Result<Vec<u8>,
     ) {
         return Err(format!("Error during scrypt key derivation: {e:?}"));
     }
-    let key = GenericArray::clone_from_slice(output.as_slice());
+    let cipher = Aes256Gcm::new(&output.into());
     output.zeroize();
 
-    let cipher = Aes256Gcm::new(&key);
     let cipher_text = [
-        Vec::from_hex(&slot.key).expect("Failed to parse hex key"),
-        Vec::from_hex(&slot.key_params.tag).expect("Failed to parse hex tag"),
+        decode_hex(&slot.key, "key")?,
+        decode_hex(&slot.key_params.tag, "tag")?,
     ]
     .concat();
+    let nonce = decode_nonce(&slot.key_params.nonce)?;
 
     cipher
-        .decrypt(
-            Nonce::from_slice(
-                Vec::from_hex(&slot.key_params.nonce)
-                    .expect("Failed to parse hex nonce")
-                    .as_slice(),
-            ),
-            cipher_text.as_slice(),
-        )
+        .decrypt(&nonce, cipher_text.as_slice())
         .map_err(|e| format!("Failed to derive master key: {e:?}"))
 }
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::otp::{otp_algorithm::OTPAlgorithm, otp_type::OTPType};
+
+    use super::{decrypt_database, AegisEncryptedDatabase};
+
+    fn read_sample() -> AegisEncryptedDatabase {
+        let json = fs::read_to_string("test_samples/aegis_encrypted.json")
+            .expect("Cannot read input file for test");
+        serde_json::from_str(json.as_str()).expect("Cannot deserialize Aegis encrypted database")
+    }
+
+    #[test]
+    fn test_decryption() {
+        let elements = decrypt_database(&read_sample(), "test").unwrap();
+
+        assert_eq!(2, elements.len());
+        assert_eq!("Deno", elements[0].issuer);
+        assert_eq!("Mason", elements[0].label);
+        assert_eq!("4SJHB4GSD43FZBAI7C2HLRJGPQ", elements[0].secret);
+        assert_eq!(OTPType::Totp, elements[0].type_);
+        assert_eq!(OTPType::Hotp, elements[1].type_);
+        assert_eq!(OTPAlgorithm::Sha256, elements[1].algorithm);
+        assert_eq!(7, elements[1].digits);
+        assert_eq!(Some(3), elements[1].counter);
+    }
+
+    #[test]
+    fn test_wrong_password() {
+        assert!(decrypt_database(&read_sample(), "wrong password").is_err());
+    }
+
+    #[test]
+    fn test_invalid_nonce() {
+        let mut database = read_sample();
+        database.header.params.nonce = String::from("00");
+        assert!(decrypt_database(&database, "test").is_err());
+
+        let mut database = read_sample();
+        for slot in database.header.slots.iter_mut() {
+            slot.key_params.nonce = String::from("0000");
+        }
+        assert!(decrypt_database(&database, "test").is_err());
+    }
+}
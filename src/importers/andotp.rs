@@ -0,0 +1,51 @@
+use serde::Deserialize;
+
+use crate::otp::otp_element::OTPElement;
+
+/// Plain andOTP backup, with the fields of the cotp codes but the time of the last use in milliseconds
+#[derive(Deserialize)]
+#[serde(transparent)]
+pub struct AndOtpJson(Vec<OTPElement>);
+
+impl TryFrom<AndOtpJson> for Vec<OTPElement> {
+    type Error = String;
+
+    fn try_from(andotp: AndOtpJson) -> Result<Self, Self::Error> {
+        Ok(andotp
+            .0
+            .into_iter()
+            .map(|mut element| {
+                element.last_used /= 1000;
+                element
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::exporters::to_export_json;
+    use crate::otp::from_otp_uri::FromOtpUri;
+    use crate::otp::otp_element::{OTPDatabase, OTPElement};
+
+    use super::AndOtpJson;
+
+    #[test]
+    fn test_andotp_last_used() {
+        let mut element =
+            OTPElement::from_otp_uri("otpauth://totp/GitHub:alice?secret=JBSWY3DPEHPK3PXP")
+                .unwrap();
+        element.last_used = 1700000000;
+        let database: OTPDatabase = vec![element].into();
+
+        let andotp: Vec<OTPElement> = (&database).into();
+        let json = to_export_json(&andotp).unwrap();
+        assert!(json.contains(r#""last_used":1700000000000"#));
+
+        let imported: Vec<OTPElement> = serde_json::from_str::<AndOtpJson>(&json)
+            .unwrap()
+            .try_into()
+            .unwrap();
+        assert_eq!(1700000000, imported[0].last_used);
+    }
+}
@@ -0,0 +1,133 @@
+use std::{error::Error, fs::read, path::PathBuf};
+
+use aes_gcm::aead::Aead;
+use aes_gcm::{Aes256Gcm, KeyInit, Nonce};
+use pbkdf2::pbkdf2_hmac;
+use serde::Deserialize;
+use sha1::Sha1;
+use zeroize::Zeroize;
+
+use crate::otp::{otp_algorithm::OTPAlgorithm, otp_element::OTPElement, otp_type::OTPType};
+use crate::utils;
+
+// The backup starts with the PBKDF2 iterations, the salt and the AES-GCM nonce
+const ITERATIONS_LENGTH: usize = 4;
+const SALT_LENGTH: usize = 12;
+const NONCE_LENGTH: usize = 12;
+const KEY_LENGTH: usize = 32;
+
+#[derive(Deserialize)]
+struct AndOTPElement {
+    secret: String,
+    #[serde(default)]
+    issuer: String,
+    #[serde(default)]
+    label: String,
+    digits: u64,
+    #[serde(rename = "type")]
+    type_: String,
+    algorithm: String,
+    period: Option<u64>,
+    counter: Option<u64>,
+    #[serde(default)]
+    tags: Vec<String>,
+    /// Milliseconds since the Unix epoch
+    #[serde(default)]
+    last_used: u64,
+}
+
+impl From<AndOTPElement> for OTPElement {
+    fn from(andotp: AndOTPElement) -> Self {
+        let type_ = OTPType::from(andotp.type_.as_str());
+        let mut element = OTPElement::default();
+        element.secret = andotp.secret;
+        element.issuer = andotp.issuer;
+        element.label = andotp.label;
+        element.digits = andotp.digits;
+        element.type_ = type_;
+        element.algorithm = OTPAlgorithm::from(andotp.algorithm.as_str());
+        element.period = andotp.period.unwrap_or(30);
+        element.counter = (type_ == OTPType::Hotp).then(|| andotp.counter.unwrap_or_default());
+        element.tags = andotp.tags;
+        element.last_used = andotp.last_used / 1000;
+        element
+    }
+}
+
+/// Imports an andOTP encrypted backup, asking for its password
+pub fn import_from_andotp_encrypted(path: PathBuf) -> Result<Vec<OTPElement>, Box<dyn Error>> {
+    let content = read(path)?;
+    let mut password = utils::password("Insert your andOTP backup password: ", 0)?;
+    let result = decrypt_backup(&content, &password);
+    password.zeroize();
+    Ok(result?)
+}
+
+fn decrypt_backup(content: &[u8], password: &str) -> Result<Vec<OTPElement>, String> {
+    let header_length = ITERATIONS_LENGTH + SALT_LENGTH + NONCE_LENGTH;
+    if content.len() <= header_length {
+        return Err(String::from("The andOTP backup is too short"));
+    }
+    let (iterations, rest) = content.split_at(ITERATIONS_LENGTH);
+    let (salt, rest) = rest.split_at(SALT_LENGTH);
+    let (nonce, ciphertext) = rest.split_at(NONCE_LENGTH);
+    // Safe to unwrap because the slice has the right length
+    let iterations = u32::from_be_bytes(iterations.try_into().unwrap());
+
+    let mut key = [0u8; KEY_LENGTH];
+    pbkdf2_hmac::<Sha1>(password.as_bytes(), salt, iterations, &mut key);
+    let cipher = Aes256Gcm::new(&key.into());
+    key.zeroize();
+
+    let mut decrypted = cipher
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| String::from("Failed to decrypt the andOTP backup, wrong password?"))?;
+    let elements = serde_json::from_slice::<Vec<AndOTPElement>>(&decrypted)
+        .map_err(|e| format!("Invalid andOTP backup: {e}"));
+    decrypted.zeroize();
+    Ok(elements?.into_iter().map(OTPElement::from).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use aes_gcm::aead::Aead;
+    use aes_gcm::{Aes256Gcm, KeyInit, Nonce};
+    use pbkdf2::pbkdf2_hmac;
+    use sha1::Sha1;
+
+    use crate::otp::otp_type::OTPType;
+
+    use super::decrypt_backup;
+
+    fn encrypt_backup(json: &str, password: &str) -> Vec<u8> {
+        let iterations: u32 = 1000;
+        let salt = [7u8; 12];
+        let nonce = [9u8; 12];
+        let mut key = [0u8; 32];
+        pbkdf2_hmac::<Sha1>(password.as_bytes(), &salt, iterations, &mut key);
+        let ciphertext = Aes256Gcm::new(&key.into())
+            .encrypt(Nonce::from_slice(&nonce), json.as_bytes())
+            .unwrap();
+        [&iterations.to_be_bytes()[..], &salt, &nonce, &ciphertext].concat()
+    }
+
+    #[test]
+    fn test_decrypt_backup() {
+        let json = r#"[
+            {"secret":"JBSWY3DPEHPK3PXP","issuer":"Example","label":"alice","digits":6,"type":"TOTP","algorithm":"SHA1","thumbnail":"Default","last_used":1700000000000,"used_frequency":0,"period":30,"tags":["work"]},
+            {"secret":"JBSWY3DPEHPK3PXP","issuer":"Counter","label":"bob","digits":8,"type":"HOTP","algorithm":"SHA256","thumbnail":"Default","last_used":0,"used_frequency":0,"counter":5,"tags":[]}
+        ]"#;
+        let backup = encrypt_backup(json, "password");
+
+        let elements = decrypt_backup(&backup, "password").unwrap();
+        assert_eq!(2, elements.len());
+        assert_eq!(vec![String::from("work")], elements[0].tags);
+        assert_eq!(1700000000, elements[0].last_used);
+        assert_eq!(OTPType::Hotp, elements[1].type_);
+        assert_eq!(Some(5), elements[1].counter);
+        assert_eq!(8, elements[1].digits);
+
+        assert!(decrypt_backup(&backup, "wrong").is_err());
+        assert!(decrypt_backup(&backup[..20], "password").is_err());
+    }
+}
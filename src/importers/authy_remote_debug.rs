@@ -1,14 +1,20 @@
 /*
 Import from JSON file exported from a script executed from remote debugging.
 For more information see https://gist.github.com/gboudreau/94bb0c11a6209c82418d01a59d958c93
+The decrypted tokens returned by the Authy API, with the authenticator_tokens and apps lists, are supported too.
 */
 
-use crate::otp::{otp_algorithm::OTPAlgorithm, otp_element::OTPElement, otp_type::OTPType};
+use crate::otp::{otp_element::OTPElement, otp_type::OTPType};
+use data_encoding::BASE32_NOPAD;
 use serde::Deserialize;
 
 const URL_INDEX: usize = 3;
 const PARAMETERS_INDEX: usize = 1;
 const DIGITS_DEFAULT_VALUE: u64 = 6;
+const PERIOD_DEFAULT_VALUE: u64 = 30;
+// Authy own tokens, like the ones of Twilio or Authy itself, last 10 seconds and have 7 digits
+const APP_PERIOD: u64 = 10;
+const APP_DIGITS_DEFAULT_VALUE: u64 = 7;
 
 #[derive(Deserialize)]
 struct AuthyExportedJsonElement {
@@ -17,10 +23,43 @@ struct AuthyExportedJsonElement {
     uri: String,
 }
 
+#[derive(Deserialize)]
+struct AuthyAuthenticatorToken {
+    name: String,
+    original_name: Option<String>,
+    issuer: Option<String>,
+    digits: Option<u64>,
+    /// Base32 encoded secret
+    decrypted_seed: String,
+}
+
+#[derive(Deserialize)]
+struct AuthyApp {
+    name: String,
+    digits: Option<u64>,
+    /// Hex encoded secret
+    secret_seed: String,
+}
+
+#[derive(Deserialize)]
+struct AuthyTokens {
+    #[serde(default)]
+    authenticator_tokens: Vec<AuthyAuthenticatorToken>,
+    #[serde(default)]
+    apps: Vec<AuthyApp>,
+}
+
 // Newtype pattern to bypass compiler check for impl From for Vec<AuthyExportedJsonElement>
 // https://rust-unofficial.github.io/patterns/patterns/behavioural/newtype.html
 #[derive(Deserialize)]
-pub struct AuthyExportedList(Vec<AuthyExportedJsonElement>);
+pub struct AuthyExportedList(AuthyExport);
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum AuthyExport {
+    RemoteDebug(Vec<AuthyExportedJsonElement>),
+    Tokens(AuthyTokens),
+}
 
 impl AuthyExportedJsonElement {
     pub fn get_type(&self) -> String {
@@ -30,6 +69,17 @@ impl AuthyExportedJsonElement {
     }
 
     pub fn get_digits(&self) -> u64 {
+        self.get_parameter("digits").unwrap_or(DIGITS_DEFAULT_VALUE)
+    }
+
+    pub fn get_period(&self) -> u64 {
+        self.get_parameter("period")
+            .filter(|period| *period > 0)
+            .unwrap_or(PERIOD_DEFAULT_VALUE)
+    }
+
+    fn get_parameter(&self, name: &str) -> Option<u64> {
+        let prefix = format!("{name}=");
         let args: Vec<&str> = self.uri.split('/').collect();
         args.get(URL_INDEX)
             .and_then(|s| {
@@ -41,16 +91,14 @@ impl AuthyExportedJsonElement {
                 }
             })
             .and_then(|s| {
-                let mut args: Vec<&str> =
-                    s.split('&').filter(|s| s.starts_with("digits=")).collect();
-                if args.first().is_some() {
+                let mut args: Vec<&str> = s.split('&').filter(|s| s.starts_with(&prefix)).collect();
+                if !args.is_empty() {
                     Some(args.swap_remove(0))
                 } else {
                     None
                 }
             })
-            .and_then(|s| s.parse::<u64>().ok())
-            .unwrap_or(DIGITS_DEFAULT_VALUE)
+            .and_then(|s| s[prefix.len()..].parse::<u64>().ok())
     }
 
     pub fn get_issuer(&self) -> String {
@@ -75,22 +123,111 @@ impl From<AuthyExportedJsonElement> for OTPElement {
         let type_ = OTPType::from(input.get_type().as_str());
         let counter: Option<u64> = (type_ == OTPType::Hotp).then_some(0);
         let digits = input.get_digits();
-        OTPElement {
-            secret: input.secret.to_uppercase().replace('=', ""),
-            issuer: input.get_issuer(),
-            label: input.name,
-            digits,
-            type_,
-            algorithm: OTPAlgorithm::Sha1,
-            period: 30,
-            counter,
-            pin: None,
+        let period = input.get_period();
+        let mut element = OTPElement::default();
+        element.secret = input.secret.to_uppercase().replace('=', "");
+        element.issuer = input.get_issuer();
+        element.label = input.name;
+        element.digits = digits;
+        element.type_ = type_;
+        element.period = period;
+        element.counter = counter;
+        element
+    }
+}
+
+impl From<AuthyAuthenticatorToken> for OTPElement {
+    fn from(token: AuthyAuthenticatorToken) -> Self {
+        let issuer = token
+            .issuer
+            .or(token.original_name)
+            .filter(|issuer| !issuer.is_empty() && *issuer != token.name)
+            .unwrap_or_default();
+        let mut element = OTPElement::default();
+        element.secret = token.decrypted_seed.to_uppercase().replace(['=', ' '], "");
+        element.issuer = issuer;
+        element.label = token.name;
+        element.digits = token.digits.unwrap_or(DIGITS_DEFAULT_VALUE);
+        element.period = PERIOD_DEFAULT_VALUE;
+        element
+    }
+}
+
+impl TryFrom<AuthyApp> for OTPElement {
+    type Error = String;
+
+    fn try_from(app: AuthyApp) -> Result<Self, Self::Error> {
+        let secret = hex::decode(&app.secret_seed)
+            .map_err(|e| format!("Invalid secret of the {} token: {e}", app.name))?;
+        let mut element = OTPElement::default();
+        element.secret = BASE32_NOPAD.encode(&secret);
+        element.issuer = app.name.to_owned();
+        element.label = app.name;
+        element.digits = app.digits.unwrap_or(APP_DIGITS_DEFAULT_VALUE);
+        element.period = APP_PERIOD;
+        Ok(element)
+    }
+}
+
+impl TryFrom<AuthyExportedList> for Vec<OTPElement> {
+    type Error = String;
+
+    fn try_from(exported_list: AuthyExportedList) -> Result<Self, Self::Error> {
+        match exported_list.0 {
+            AuthyExport::RemoteDebug(elements) => {
+                Ok(elements.into_iter().map(|e| e.into()).collect())
+            }
+            AuthyExport::Tokens(tokens) => tokens
+                .authenticator_tokens
+                .into_iter()
+                .map(|t| Ok(t.into()))
+                .chain(tokens.apps.into_iter().map(OTPElement::try_from))
+                .collect(),
         }
     }
 }
 
-impl From<AuthyExportedList> for Vec<OTPElement> {
-    fn from(exported_list: AuthyExportedList) -> Self {
-        exported_list.0.into_iter().map(|e| e.into()).collect()
+#[cfg(test)]
+mod tests {
+    use crate::otp::otp_element::OTPElement;
+
+    use super::AuthyExportedList;
+
+    fn import(json: &str) -> Vec<OTPElement> {
+        serde_json::from_str::<AuthyExportedList>(json)
+            .unwrap()
+            .try_into()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_remote_debug_export() {
+        let elements = import(
+            r#"[{"name":"Twilio","secret":"jbswy3dpehpk3pxp","uri":"otpauth://totp/Twilio?secret=JBSWY3DPEHPK3PXP&digits=7&period=10"}]"#,
+        );
+
+        assert_eq!("JBSWY3DPEHPK3PXP", elements[0].secret);
+        assert_eq!(7, elements[0].digits);
+        assert_eq!(10, elements[0].period);
+    }
+
+    #[test]
+    fn test_tokens_export() {
+        let elements = import(
+            r#"{
+                "authenticator_tokens": [{"account_type":"github","digits":6,"name":"alice","original_name":"GitHub","decrypted_seed":"jbsw y3dp ehpk 3pxp"}],
+                "apps": [{"name":"Authy","digits":7,"secret_seed":"48656c6c6f21deadbeef"}]
+            }"#,
+        );
+
+        assert_eq!(2, elements.len());
+        assert_eq!("GitHub", elements[0].issuer);
+        assert_eq!("alice", elements[0].label);
+        assert_eq!("JBSWY3DPEHPK3PXP", elements[0].secret);
+        assert_eq!(30, elements[0].period);
+        assert_eq!("JBSWY3DPEHPK3PXP", elements[1].secret);
+        assert_eq!(7, elements[1].digits);
+        assert_eq!(10, elements[1].period);
+        assert!(elements[1].get_otp_code().is_ok_and(|code| code.len() == 7));
     }
 }
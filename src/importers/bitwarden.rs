@@ -0,0 +1,131 @@
+/*
+Import the TOTP secrets stored in the login items of an unencrypted Bitwarden JSON export.
+*/
+
+use color_eyre::eyre::{eyre, ErrReport};
+use serde::Deserialize;
+
+use crate::otp::{from_otp_uri::FromOtpUri, otp_element::OTPElement, otp_type::OTPType};
+
+const STEAM_PREFIX: &str = "steam://";
+
+#[derive(Deserialize)]
+pub struct BitwardenExport {
+    #[serde(default)]
+    encrypted: bool,
+    #[serde(default)]
+    items: Vec<BitwardenItem>,
+}
+
+#[derive(Deserialize)]
+struct BitwardenItem {
+    name: String,
+    login: Option<BitwardenLogin>,
+}
+
+#[derive(Deserialize)]
+struct BitwardenLogin {
+    username: Option<String>,
+    /// Either an otpauth:// URI, a steam:// secret or a plain base32 secret
+    totp: Option<String>,
+}
+
+impl BitwardenItem {
+    fn to_element(&self, totp: &str) -> color_eyre::Result<OTPElement> {
+        let totp = totp.trim();
+        let mut element = if totp.starts_with("otpauth://") {
+            OTPElement::from_otp_uri(totp)?
+        } else {
+            let (type_, digits, secret) = match totp.strip_prefix(STEAM_PREFIX) {
+                Some(secret) => (OTPType::Steam, 5, secret),
+                None => (OTPType::Totp, 6, totp),
+            };
+            let mut element = OTPElement::default();
+            element.secret = secret.to_uppercase().replace([' ', '='], "");
+            element.digits = digits;
+            element.type_ = type_;
+            element
+        };
+        // Entries are named after the Bitwarden item and its username
+        if !self.name.is_empty() {
+            element.issuer = self.name.to_owned();
+        }
+        if let Some(username) = self
+            .login
+            .as_ref()
+            .and_then(|login| login.username.as_ref())
+            .filter(|username| !username.is_empty())
+        {
+            element.label = username.to_owned();
+        }
+        if !element.valid_secret() {
+            return Err(eyre!("Invalid TOTP secret in the {} item", self.name));
+        }
+        Ok(element)
+    }
+}
+
+impl TryFrom<BitwardenExport> for Vec<OTPElement> {
+    type Error = ErrReport;
+
+    fn try_from(export: BitwardenExport) -> Result<Self, Self::Error> {
+        if export.encrypted {
+            return Err(eyre!(
+                "Encrypted Bitwarden exports are not supported, please export the vault as unencrypted JSON"
+            ));
+        }
+        export
+            .items
+            .iter()
+            .filter_map(|item| {
+                let totp = item.login.as_ref()?.totp.as_deref()?;
+                Some(item.to_element(totp))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::otp::{otp_algorithm::OTPAlgorithm, otp_element::OTPElement, otp_type::OTPType};
+
+    use super::BitwardenExport;
+
+    #[test]
+    fn test_bitwarden_import() {
+        let json = r#"{
+            "encrypted": false,
+            "folders": [],
+            "items": [
+                {"type": 1, "name": "GitHub", "login": {"username": "alice", "password": "x", "totp": "jbsw y3dp ehpk 3pxp"}},
+                {"type": 1, "name": "Mail", "login": {"username": "", "totp": "otpauth://totp/Provider:bob?secret=JBSWY3DPEHPK3PXP&algorithm=SHA256&digits=8"}},
+                {"type": 1, "name": "Steam", "login": {"username": "carol", "totp": "steam://JBSWY3DPEHPK3PXP"}},
+                {"type": 1, "name": "No TOTP", "login": {"username": "dave", "totp": null}},
+                {"type": 2, "name": "Note"}
+            ]
+        }"#;
+        let export: BitwardenExport = serde_json::from_str(json).unwrap();
+        let elements: Vec<OTPElement> = export.try_into().unwrap();
+
+        assert_eq!(3, elements.len());
+        assert_eq!("GitHub", elements[0].issuer);
+        assert_eq!("alice", elements[0].label);
+        assert_eq!("JBSWY3DPEHPK3PXP", elements[0].secret);
+        assert_eq!("Mail", elements[1].issuer);
+        assert_eq!("bob", elements[1].label);
+        assert_eq!(OTPAlgorithm::Sha256, elements[1].algorithm);
+        assert_eq!(8, elements[1].digits);
+        assert_eq!(OTPType::Steam, elements[2].type_);
+        assert_eq!(5, elements[2].digits);
+    }
+
+    #[test]
+    fn test_encrypted_bitwarden_export() {
+        let export: BitwardenExport =
+            serde_json::from_str(r#"{"encrypted": true, "encKeyValidation_DO_NOT_EDIT": "x"}"#)
+                .unwrap();
+        let result: color_eyre::Result<Vec<OTPElement>> = export.try_into();
+
+        assert!(result.is_err());
+    }
+}
@@ -18,17 +18,15 @@ impl From<ConvertedJson> for OTPElement {
     fn from(converted_json: ConvertedJson) -> Self {
         let counter: Option<u64> = (OTPType::from(converted_json.type_.as_str()) == OTPType::Hotp)
             .then_some(converted_json.counter);
-        OTPElement {
-            secret: converted_json.secret,
-            issuer: converted_json.issuer.unwrap_or_default(),
-            label: converted_json.label.unwrap_or_default(),
-            digits: converted_json.digits,
-            type_: OTPType::from(converted_json.type_.as_str()),
-            algorithm: OTPAlgorithm::from(converted_json.algorithm.as_str()),
-            period: 30,
-            counter,
-            pin: None,
-        }
+        let mut element = OTPElement::default();
+        element.secret = converted_json.secret;
+        element.issuer = converted_json.issuer.unwrap_or_default();
+        element.label = converted_json.label.unwrap_or_default();
+        element.digits = converted_json.digits;
+        element.type_ = OTPType::from(converted_json.type_.as_str());
+        element.algorithm = OTPAlgorithm::from(converted_json.algorithm.as_str());
+        element.counter = counter;
+        element
     }
 }
 
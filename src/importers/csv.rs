@@ -0,0 +1,274 @@
+/*
+Import from CSV files, like spreadsheets of OTP secrets, mapping the columns to the OTP fields.
+Columns are numbered starting from 1, as shown by spreadsheet applications.
+*/
+
+use std::{error::Error, fs::read_to_string, path::PathBuf, str::FromStr};
+
+use color_eyre::eyre::eyre;
+
+use crate::otp::{otp_algorithm::OTPAlgorithm, otp_element::OTPElement, otp_type::OTPType};
+
+const FIELDS: [&str; 9] = [
+    "issuer",
+    "label",
+    "secret",
+    "digits",
+    "period",
+    "algorithm",
+    "type",
+    "counter",
+    "t0",
+];
+
+/// Column index of each OTP field, only the secret one is required
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct CsvMapping {
+    issuer: Option<usize>,
+    label: Option<usize>,
+    secret: usize,
+    digits: Option<usize>,
+    period: Option<usize>,
+    algorithm: Option<usize>,
+    type_: Option<usize>,
+    counter: Option<usize>,
+    t0: Option<usize>,
+}
+
+pub struct CsvOptions {
+    /// Like issuer=1,label=2,secret=3. If missing, the header names or the issuer, label and secret order are used
+    pub mapping: Option<String>,
+    /// The first row contains the column names
+    pub header: bool,
+    pub delimiter: char,
+}
+
+impl CsvMapping {
+    fn from_pairs<'a>(
+        pairs: impl Iterator<Item = (&'a str, usize)>,
+    ) -> color_eyre::Result<CsvMapping> {
+        let mut secret = None;
+        let mut mapping = CsvMapping::default();
+        for (field, column) in pairs {
+            let column = Some(column);
+            match field.trim().to_lowercase().as_str() {
+                "issuer" => mapping.issuer = column,
+                "label" => mapping.label = column,
+                "secret" => secret = column,
+                "digits" => mapping.digits = column,
+                "period" => mapping.period = column,
+                "algorithm" => mapping.algorithm = column,
+                "type" => mapping.type_ = column,
+                "counter" => mapping.counter = column,
+                "t0" => mapping.t0 = column,
+                _ => {
+                    return Err(eyre!(
+                        "Unknown CSV field {field}, valid fields are: {}",
+                        FIELDS.join(", ")
+                    ))
+                }
+            }
+        }
+        mapping.secret = secret.ok_or(eyre!("The secret column is required"))?;
+        Ok(mapping)
+    }
+
+    /// Maps the columns whose header is the name of a field
+    fn from_header(header: &[String]) -> color_eyre::Result<CsvMapping> {
+        CsvMapping::from_pairs(
+            header
+                .iter()
+                .enumerate()
+                .filter(|(_, name)| FIELDS.contains(&name.trim().to_lowercase().as_str()))
+                .map(|(index, name)| (name.as_str(), index)),
+        )
+    }
+
+    fn to_element(&self, record: &[String]) -> color_eyre::Result<OTPElement> {
+        let get = |column: Option<usize>| {
+            column
+                .and_then(|c| record.get(c))
+                .map(|value| value.trim())
+                .filter(|value| !value.is_empty())
+        };
+        let secret = get(Some(self.secret)).ok_or(eyre!("Missing secret"))?;
+        let type_ = get(self.type_).map(OTPType::from).unwrap_or(OTPType::Totp);
+        let default_digits = if type_ == OTPType::Steam { 5 } else { 6 };
+        let mut element = OTPElement::default();
+        element.secret = secret.to_uppercase().replace([' ', '='], "");
+        element.issuer = get(self.issuer).unwrap_or_default().to_owned();
+        element.label = get(self.label).unwrap_or_default().to_owned();
+        element.digits = get(self.digits)
+            .map(str::parse)
+            .transpose()?
+            .unwrap_or(default_digits);
+        element.type_ = type_;
+        element.algorithm = get(self.algorithm)
+            .map(OTPAlgorithm::from)
+            .unwrap_or(OTPAlgorithm::Sha1);
+        element.period = get(self.period).map(str::parse).transpose()?.unwrap_or(30);
+        element.counter = match type_ {
+            OTPType::Hotp => Some(get(self.counter).map(str::parse).transpose()?.unwrap_or(0)),
+            _ => None,
+        };
+        element.t0 = match type_ {
+            OTPType::Totp => get(self.t0).map(str::parse).transpose()?.unwrap_or(0),
+            _ => 0,
+        };
+        if !element.valid_secret() {
+            return Err(eyre!("Invalid secret"));
+        }
+        Ok(element)
+    }
+}
+
+impl FromStr for CsvMapping {
+    type Err = color_eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let pairs = s
+            .split(',')
+            .map(|pair| {
+                let (field, column) = pair
+                    .split_once('=')
+                    .ok_or(eyre!("Invalid mapping {pair}, use field=column"))?;
+                let column: usize = column.trim().parse()?;
+                if column == 0 {
+                    return Err(eyre!("Columns are numbered starting from 1"));
+                }
+                Ok((field, column - 1))
+            })
+            .collect::<color_eyre::Result<Vec<(&str, usize)>>>()?;
+        CsvMapping::from_pairs(pairs.into_iter())
+    }
+}
+
+/// Splits the CSV text into records, handling quoted values like RFC 4180 does
+pub fn parse_records(text: &str, delimiter: char) -> color_eyre::Result<Vec<Vec<String>>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut value = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                value.push('"');
+            }
+            '"' if quoted => quoted = false,
+            '"' if value.is_empty() => quoted = true,
+            c if quoted => value.push(c),
+            c if c == delimiter => record.push(std::mem::take(&mut value)),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                record.push(std::mem::take(&mut value));
+                records.push(std::mem::take(&mut record));
+            }
+            c => value.push(c),
+        }
+    }
+    if quoted {
+        return Err(eyre!("Unterminated quoted value"));
+    }
+    if !value.is_empty() || !record.is_empty() {
+        record.push(value);
+        records.push(record);
+    }
+    // Skip blank lines
+    records.retain(|r| r.iter().any(|v| !v.trim().is_empty()));
+    Ok(records)
+}
+
+fn elements_from_csv(text: &str, options: &CsvOptions) -> color_eyre::Result<Vec<OTPElement>> {
+    let mut records = parse_records(text, options.delimiter)?.into_iter();
+    let header = if options.header { records.next() } else { None };
+    let mapping = match (&options.mapping, &header) {
+        (Some(mapping), _) => mapping.parse()?,
+        (None, Some(header)) => CsvMapping::from_header(header)?,
+        (None, None) => "issuer=1,label=2,secret=3".parse()?,
+    };
+    let first_row = if options.header { 2 } else { 1 };
+    records
+        .enumerate()
+        .map(|(index, record)| {
+            mapping
+                .to_element(&record)
+                .map_err(|e| eyre!("Row {}: {e}", index + first_row))
+        })
+        .collect()
+}
+
+/// Imports the CSV file, mapping its columns following the options
+pub fn import_from_csv(
+    path: PathBuf,
+    options: &CsvOptions,
+) -> Result<Vec<OTPElement>, Box<dyn Error>> {
+    let text = read_to_string(path)?;
+    Ok(elements_from_csv(&text, options)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::otp::{otp_algorithm::OTPAlgorithm, otp_type::OTPType};
+
+    use super::{elements_from_csv, parse_records, CsvMapping, CsvOptions};
+
+    #[test]
+    fn test_parse_records() {
+        let records =
+            parse_records("a,\"b, \"\"quoted\"\"\",c\r\n\n1,2,\"multi\nline\"", ',').unwrap();
+
+        assert_eq!(
+            vec![
+                vec!["a", "b, \"quoted\"", "c"],
+                vec!["1", "2", "multi\nline"]
+            ],
+            records
+        );
+        assert!(parse_records("\"unterminated", ',').is_err());
+    }
+
+    #[test]
+    fn test_mapping() {
+        let mapping: CsvMapping = "secret=3, issuer=1,digits=4".parse().unwrap();
+
+        assert_eq!(2, mapping.secret);
+        assert_eq!(Some(0), mapping.issuer);
+        assert_eq!(Some(3), mapping.digits);
+        assert!("issuer=1".parse::<CsvMapping>().is_err());
+        assert!("secret=0".parse::<CsvMapping>().is_err());
+        assert!("seed=1".parse::<CsvMapping>().is_err());
+    }
+
+    #[test]
+    fn test_csv_import() {
+        let options = CsvOptions {
+            mapping: None,
+            header: true,
+            delimiter: ';',
+        };
+        let text = "Notes;Issuer;Label;Secret;Algorithm;Digits;Type;Counter;T0\n\
+            work;GitHub;alice;jbsw y3dp ehpk 3pxp;sha256;8;;;-60\n\
+            ;Mail;bob;JBSWY3DPEHPK3PXP;;;hotp;4;\n";
+        let elements = elements_from_csv(text, &options).unwrap();
+
+        assert_eq!(2, elements.len());
+        assert_eq!("GitHub", elements[0].issuer);
+        assert_eq!("JBSWY3DPEHPK3PXP", elements[0].secret);
+        assert_eq!(OTPAlgorithm::Sha256, elements[0].algorithm);
+        assert_eq!(8, elements[0].digits);
+        assert_eq!(-60, elements[0].t0);
+        assert_eq!(OTPType::Hotp, elements[1].type_);
+        assert_eq!(Some(4), elements[1].counter);
+
+        let invalid = elements_from_csv(
+            "GitHub;alice;not base32!",
+            &CsvOptions {
+                header: false,
+                ..options
+            },
+        );
+        assert!(invalid.is_err_and(|e| e.to_string().starts_with("Row 1")));
+    }
+}
@@ -0,0 +1,141 @@
+/*
+Import the third party accounts of Duo Mobile, stored in the accounts.json file of the app data,
+like /data/data/com.duosecurity.duomobile/files/duokit/accounts.json on Android.
+Duo accounts, protected with push notifications, cannot be used outside Duo Mobile.
+*/
+
+use color_eyre::eyre::{eyre, ErrReport};
+use serde::Deserialize;
+
+use crate::otp::{otp_element::OTPElement, otp_type::OTPType};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DuoAccount {
+    #[serde(default)]
+    name: String,
+    /// Only set for Duo accounts, used to approve push notifications
+    pkey: Option<String>,
+    otp_generator: Option<DuoOtpGenerator>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DuoOtpGenerator {
+    otp_secret: String,
+    /// Only counter based accounts have a counter
+    counter: Option<u64>,
+}
+
+// Newtype pattern to permit impl to Vec<OTPElement>
+#[derive(Deserialize)]
+pub struct DuoAccounts(Vec<DuoAccount>);
+
+impl DuoAccount {
+    fn is_third_party(&self) -> bool {
+        self.pkey.is_none() && self.otp_generator.is_some()
+    }
+}
+
+impl From<&DuoOtpGenerator> for OTPElement {
+    fn from(generator: &DuoOtpGenerator) -> Self {
+        let type_ = if generator.counter.is_some() {
+            OTPType::Hotp
+        } else {
+            OTPType::Totp
+        };
+        let mut element = OTPElement::default();
+        element.secret = generator.otp_secret.to_uppercase().replace([' ', '='], "");
+        element.type_ = type_;
+        element.counter = generator.counter;
+        element
+    }
+}
+
+impl TryFrom<DuoAccounts> for Vec<OTPElement> {
+    type Error = ErrReport;
+
+    fn try_from(accounts: DuoAccounts) -> Result<Self, Self::Error> {
+        let (third_party, duo): (Vec<DuoAccount>, Vec<DuoAccount>) =
+            accounts.0.into_iter().partition(DuoAccount::is_third_party);
+        let duo_names: Vec<String> = duo.into_iter().map(|account| account.name).collect();
+        if third_party.is_empty() && !duo_names.is_empty() {
+            return Err(eyre!(
+                "Only Duo push accounts found, they cannot be used outside Duo Mobile: {}",
+                duo_names.join(", ")
+            ));
+        }
+        if !duo_names.is_empty() {
+            eprintln!(
+                "Skipped {} Duo push accounts, they cannot be used outside Duo Mobile: {}",
+                duo_names.len(),
+                duo_names.join(", ")
+            );
+        }
+        third_party
+            .into_iter()
+            .map(|account| {
+                // Safe to unwrap because third party accounts have an OTP generator
+                let mut element = OTPElement::from(account.otp_generator.as_ref().unwrap());
+                // Names are usually written like Issuer (label) or Issuer: label
+                match account.name.split_once(['(', ':']) {
+                    Some((issuer, label)) => {
+                        element.issuer = issuer.trim().to_owned();
+                        element.label = label.trim().trim_end_matches(')').trim().to_owned();
+                    }
+                    None => element.issuer = account.name.to_owned(),
+                }
+                if element.valid_secret() {
+                    Ok(element)
+                } else {
+                    Err(eyre!("Invalid OTP secret in the {} account", account.name))
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::otp::{otp_element::OTPElement, otp_type::OTPType};
+
+    use super::DuoAccounts;
+
+    fn import(json: &str) -> color_eyre::Result<Vec<OTPElement>> {
+        serde_json::from_str::<DuoAccounts>(json)
+            .unwrap()
+            .try_into()
+    }
+
+    #[test]
+    fn test_duo_import() {
+        let elements = import(
+            r#"[
+                {"name": "GitHub (alice)", "accountType": "OtpAccount", "otpGenerator": {"otpSecret": "JBSWY3DPEHPK3PXP"}, "logoUri": "..."},
+                {"name": "Mail: bob", "otpGenerator": {"otpSecret": "jbswy3dpehpk3pxp", "counter": 7}},
+                {"name": "Work", "pkey": "DP0123456789ABCDEF", "akey": "x", "otpGenerator": {"otpSecret": "AAAA", "counter": 1}}
+            ]"#,
+        )
+        .unwrap();
+
+        assert_eq!(2, elements.len());
+        assert_eq!(
+            ("GitHub", "alice"),
+            (elements[0].issuer.as_str(), elements[0].label.as_str())
+        );
+        assert_eq!(OTPType::Totp, elements[0].type_);
+        assert_eq!(
+            ("Mail", "bob"),
+            (elements[1].issuer.as_str(), elements[1].label.as_str())
+        );
+        assert_eq!(OTPType::Hotp, elements[1].type_);
+        assert_eq!(Some(7), elements[1].counter);
+    }
+
+    #[test]
+    fn test_only_push_accounts() {
+        let result = import(r#"[{"name": "Work", "pkey": "DP0123456789ABCDEF"}]"#);
+
+        assert!(result.is_err_and(|e| e.to_string().contains("Work")));
+    }
+}
@@ -0,0 +1,120 @@
+/*
+Import from Ente Auth exports, both the plain text OTP URIs list and the password encrypted JSON.
+*/
+
+use std::{error::Error, fs::read_to_string, path::PathBuf};
+
+use color_eyre::eyre::eyre;
+use data_encoding::BASE64;
+use url::Url;
+use zeroize::Zeroize;
+
+use crate::crypto::secretstream::{self, HEADER_LENGTH};
+use crate::exporters::ente::{EnteCodeDisplay, EnteEncryptedExport};
+use crate::otp::{from_otp_uri::FromOtpUri, otp_element::OTPElement};
+use crate::utils;
+
+/// Imports an Ente export, asking for its password if encrypted
+pub fn import_from_ente(path: PathBuf) -> Result<Vec<OTPElement>, Box<dyn Error>> {
+    let mut content = read_to_string(path)?;
+    if !content.trim_start().starts_with('{') {
+        let elements = elements_from_text(&content);
+        content.zeroize();
+        return Ok(elements?);
+    }
+    let export: EnteEncryptedExport = serde_json::from_str(&content)?;
+    let mut password = utils::password("Insert your Ente export password: ", 0)?;
+    let text = decrypt_export(&export, &password);
+    password.zeroize();
+    let mut text = text?;
+    let elements = elements_from_text(&text);
+    text.zeroize();
+    Ok(elements?)
+}
+
+fn decrypt_export(export: &EnteEncryptedExport, password: &str) -> color_eyre::Result<String> {
+    let header: [u8; HEADER_LENGTH] = BASE64
+        .decode(export.encryption_nonce.as_bytes())?
+        .try_into()
+        .map_err(|_| eyre!("Invalid Ente export nonce"))?;
+    let ciphertext = BASE64.decode(export.encrypted_data.as_bytes())?;
+    let mut key = export.kdf_params.derive_key(password)?;
+    let decrypted = secretstream::decrypt(&key, &header, &ciphertext);
+    key.zeroize();
+    String::from_utf8(decrypted.map_err(|e| eyre!("{e}"))?).map_err(|e| eyre!("{e}"))
+}
+
+fn elements_from_text(text: &str) -> color_eyre::Result<Vec<OTPElement>> {
+    let mut elements = Vec::new();
+    for line in text.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        let code_display: EnteCodeDisplay = Url::parse(line)?
+            .query_pairs()
+            .find(|(key, _)| key == "codeDisplay")
+            .map(|(_, value)| serde_json::from_str(&value))
+            .transpose()?
+            .unwrap_or_default();
+        // Codes in the Ente trash are exported too
+        if code_display.trashed {
+            continue;
+        }
+        let mut element = OTPElement::from_otp_uri(line)?;
+        code_display.apply(&mut element);
+        elements.push(element);
+    }
+    Ok(elements)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::exporters::ente::{encrypt_ente_export, to_ente_text, EnteEncryptedExport};
+    use crate::otp::otp_element::OTPDatabase;
+    use crate::otp::{otp_algorithm::OTPAlgorithm, otp_type::OTPType};
+
+    use super::{decrypt_export, elements_from_text};
+
+    // Encrypted with libsodium, like the Ente apps do
+    const ENCRYPTED_EXPORT: &str = r#"{"version": 1, "kdfParams": {"memLimit": 1048576, "opsLimit": 2, "salt": "AAECAwQFBgcICQoLDA0ODw=="}, "encryptedData": "Nr4cGkCoXp9C+5aAKzCSk/p/zNnfn7DwvDsEjAKiRAcmCceyssX+Z6Tg341vqsJciy8Yv0qyOEA9krivugKYNBqm6YAoVqsB270h6Pxxy4nIypAMZ8GakmDilJ644+IvUDV5hFFbtBVokXvN8HFzLGafenOFm6/o2an/qqUHpbjf6wsvGKLuC0Z1vjhScfLRRMDSabcr0r7oWUSlP7RrgyjWRBdAfNBGUhH3p9Sy0usgj8bM8wXU/RSgeoPT+xHAy/lmYk6V97ECjca2A0soAyEF9kCvzBwPxD7At/2he7q52X792/5Ms3e4lVZk3hfDPOp/DjWU6brkq7CFrq/K9i8Tl1dZehUkqjoBQHyiaIVprlnpUuceSJUKRVSXi/2zChLt3a5E", "encryptionNonce": "vTnRqzQyDll6xf7x3Tzj6E5r5v3vCSnC"}"#;
+
+    #[test]
+    fn test_encrypted_ente_import() {
+        let export: EnteEncryptedExport = serde_json::from_str(ENCRYPTED_EXPORT).unwrap();
+        assert!(decrypt_export(&export, "wrong").is_err());
+
+        let text = decrypt_export(&export, "password").unwrap();
+        let elements = elements_from_text(&text).unwrap();
+
+        assert_eq!(2, elements.len());
+        assert_eq!(
+            ("GitHub", "alice"),
+            (elements[0].issuer.as_str(), elements[0].label.as_str())
+        );
+        assert!(elements[0].pinned);
+        assert_eq!(3, elements[0].usage_count);
+        assert_eq!(vec![String::from("work")], elements[0].tags);
+        assert_eq!(OTPType::Hotp, elements[1].type_);
+        assert_eq!(Some(4), elements[1].counter);
+        assert_eq!(OTPAlgorithm::Sha256, elements[1].algorithm);
+        assert_eq!(8, elements[1].digits);
+    }
+
+    #[test]
+    fn test_ente_round_trip() {
+        let text = "otpauth://totp/Mail:bob?secret=JBSWY3DPEHPK3PXP&period=60&codeDisplay=%7B%22pinned%22%3Atrue%2C%22lastUsedAt%22%3A1700000000000000%2C%22tapCount%22%3A2%2C%22tags%22%3A%5B%22mail%22%5D%7D\n\
+            otpauth://totp/Old:carol?secret=JBSWY3DPEHPK3PXP&codeDisplay=%7B%22trashed%22%3Atrue%7D\n";
+        let elements = elements_from_text(text).unwrap();
+        assert_eq!(1, elements.len());
+        assert_eq!(1700000000, elements[0].last_used);
+
+        let database = OTPDatabase {
+            elements,
+            ..Default::default()
+        };
+        let exported = to_ente_text(&database).unwrap();
+        assert_eq!(1, exported.matches("issuer=").count());
+        let encrypted = encrypt_ente_export(&exported, "password").unwrap();
+        let decrypted = decrypt_export(&encrypted, "password").unwrap();
+
+        let reimported = elements_from_text(&decrypted).unwrap();
+        assert_eq!(database.elements, reimported);
+    }
+}
@@ -0,0 +1,110 @@
+/*
+Import from FreeOTP, reading the tokens.xml shared preferences file of the Android app
+or a JSON list of the tokens stored inside it.
+FreeOTP+ backups and the files converted by the older converters/freeotp.py script are supported too.
+*/
+
+use std::{error::Error, fs::read_to_string, path::PathBuf};
+
+use serde::Deserialize;
+
+use crate::otp::otp_element::OTPElement;
+
+use super::converted::ConvertedJsonList;
+use super::freeotp_plus::{FreeOTPElement, FreeOTPPlusJson};
+
+const TOKEN_ORDER_KEY: &str = "tokenOrder";
+
+/// Android shared preferences, where each token is stored as JSON in a string entry
+#[derive(Deserialize)]
+struct SharedPreferences {
+    #[serde(rename = "string", default)]
+    strings: Vec<SharedPreference>,
+}
+
+#[derive(Deserialize)]
+struct SharedPreference {
+    #[serde(rename = "@name")]
+    name: String,
+    #[serde(rename = "$text", default)]
+    value: String,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum FreeOTPJson {
+    FreeOTPPlus(FreeOTPPlusJson),
+    Tokens(Vec<FreeOTPElement>),
+    Converted(ConvertedJsonList),
+}
+
+/// Imports a FreeOTP tokens.xml file or a JSON export
+pub fn import_from_freeotp(path: PathBuf) -> Result<Vec<OTPElement>, Box<dyn Error>> {
+    let content = read_to_string(path)?;
+    if content.trim_start().starts_with('<') {
+        return Ok(import_from_tokens_xml(&content)?);
+    }
+    let elements = match serde_json::from_str::<FreeOTPJson>(&content)? {
+        FreeOTPJson::FreeOTPPlus(json) => json.try_into()?,
+        FreeOTPJson::Tokens(tokens) => tokens.into_iter().map(OTPElement::from).collect(),
+        FreeOTPJson::Converted(converted) => converted.try_into()?,
+    };
+    Ok(elements)
+}
+
+fn import_from_tokens_xml(xml: &str) -> Result<Vec<OTPElement>, String> {
+    let preferences: SharedPreferences =
+        quick_xml::de::from_str(xml).map_err(|e| format!("Invalid FreeOTP tokens file: {e}"))?;
+    let order: Vec<String> = preferences
+        .strings
+        .iter()
+        .find(|preference| preference.name == TOKEN_ORDER_KEY)
+        .and_then(|preference| serde_json::from_str(&preference.value).ok())
+        .unwrap_or_default();
+
+    let mut tokens = preferences
+        .strings
+        .into_iter()
+        .filter(|preference| preference.name != TOKEN_ORDER_KEY)
+        .map(
+            |preference| match serde_json::from_str::<FreeOTPElement>(&preference.value) {
+                Ok(token) => Ok((preference.name, token)),
+                Err(e) => Err(format!("Invalid FreeOTP token {}: {e}", preference.name)),
+            },
+        )
+        .collect::<Result<Vec<(String, FreeOTPElement)>, String>>()?;
+    // Tokens missing from the saved order go to the bottom
+    tokens.sort_by_key(|(name, _)| order.iter().position(|n| n == name).unwrap_or(usize::MAX));
+    Ok(tokens
+        .into_iter()
+        .map(|(_, token)| OTPElement::from(token))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::otp::{otp_algorithm::OTPAlgorithm, otp_type::OTPType};
+
+    use super::import_from_tokens_xml;
+
+    #[test]
+    fn test_tokens_xml_import() {
+        let xml = r#"<?xml version='1.0' encoding='utf-8' standalone='yes' ?>
+<map>
+    <string name="Mail:bob">{&quot;algo&quot;:&quot;SHA256&quot;,&quot;counter&quot;:4,&quot;digits&quot;:8,&quot;issuerExt&quot;:&quot;Mail&quot;,&quot;issuerInt&quot;:&quot;Mail&quot;,&quot;label&quot;:&quot;bob&quot;,&quot;period&quot;:30,&quot;secret&quot;:[72,101,108,108,111,33,-34,-83,-66,-17],&quot;type&quot;:&quot;HOTP&quot;}</string>
+    <string name="tokenOrder">[&quot;GitHub:alice&quot;,&quot;Mail:bob&quot;]</string>
+    <string name="GitHub:alice">{&quot;algo&quot;:&quot;SHA1&quot;,&quot;digits&quot;:6,&quot;issuerExt&quot;:&quot;GitHub&quot;,&quot;label&quot;:&quot;alice&quot;,&quot;period&quot;:60,&quot;secret&quot;:&quot;JBSWY3DPEHPK3PXP&quot;,&quot;type&quot;:&quot;TOTP&quot;}</string>
+</map>"#;
+        let elements = import_from_tokens_xml(xml).unwrap();
+
+        assert_eq!(2, elements.len());
+        assert_eq!("GitHub", elements[0].issuer);
+        assert_eq!(60, elements[0].period);
+        assert_eq!(None, elements[0].counter);
+        assert_eq!("JBSWY3DPEHPK3PXP", elements[1].secret);
+        assert_eq!(OTPType::Hotp, elements[1].type_);
+        assert_eq!(OTPAlgorithm::Sha256, elements[1].algorithm);
+        assert_eq!(Some(4), elements[1].counter);
+        assert_eq!(8, elements[1].digits);
+    }
+}
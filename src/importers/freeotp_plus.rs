@@ -1,5 +1,5 @@
 use data_encoding::BASE32_NOPAD;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 
 use crate::otp::{otp_algorithm::OTPAlgorithm, otp_element::OTPElement, otp_type::OTPType};
 
@@ -34,13 +34,17 @@ impl FreeOTPPlusJson {
 #[derive(Serialize, Deserialize, PartialEq, Debug)]
 pub struct FreeOTPElement {
     pub algo: String,
+    #[serde(default)]
     pub counter: u64,
     pub digits: u64,
-    #[serde(rename = "issuerExt")]
+    #[serde(rename = "issuerExt", default)]
     pub issuer_ext: String,
     #[serde(rename = "label")]
     pub _label: String,
+    #[serde(default = "default_period")]
     pub period: u64,
+    /// Signed bytes, FreeOTP tokens may also store the secret as a base32 or hex string
+    #[serde(deserialize_with = "deserialize_secret")]
     pub secret: Vec<i8>,
     #[serde(rename = "type")]
     pub _type: String,
@@ -48,22 +52,21 @@ pub struct FreeOTPElement {
 
 impl From<FreeOTPElement> for OTPElement {
     fn from(token: FreeOTPElement) -> Self {
-        let counter: Option<u64> = if token.algo.to_uppercase().as_str() == "HOTP" {
+        let counter: Option<u64> = if token._type.to_uppercase().as_str() == "HOTP" {
             Some(token.counter)
         } else {
             None
         };
-        OTPElement {
-            counter,
-            secret: encode_secret(&token.secret),
-            issuer: token.issuer_ext,
-            label: token._label,
-            digits: token.digits,
-            type_: OTPType::from(token._type.as_str()),
-            algorithm: OTPAlgorithm::from(token.algo.as_str()),
-            period: token.period,
-            pin: None,
-        }
+        let mut element = OTPElement::default();
+        element.counter = counter;
+        element.secret = encode_secret(&token.secret);
+        element.issuer = token.issuer_ext;
+        element.label = token._label;
+        element.digits = token.digits;
+        element.type_ = OTPType::from(token._type.as_str());
+        element.algorithm = OTPAlgorithm::from(token.algo.as_str());
+        element.period = token.period;
+        element
     }
 }
 
@@ -74,6 +77,34 @@ impl TryFrom<FreeOTPPlusJson> for Vec<OTPElement> {
     }
 }
 
+fn default_period() -> u64 {
+    30
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum FreeOTPSecret {
+    Bytes(Vec<i8>),
+    Text(String),
+}
+
+fn deserialize_secret<'de, D>(deserializer: D) -> Result<Vec<i8>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let bytes = match FreeOTPSecret::deserialize(deserializer)? {
+        FreeOTPSecret::Bytes(bytes) => return Ok(bytes),
+        FreeOTPSecret::Text(text) => {
+            let text = text.replace([' ', '='], "");
+            BASE32_NOPAD
+                .decode(text.to_uppercase().as_bytes())
+                .or_else(|_| hex::decode(&text))
+                .map_err(|_| serde::de::Error::custom("Invalid base32 or hex secret"))?
+        }
+    };
+    Ok(bytes.into_iter().map(|b| b as i8).collect())
+}
+
 fn encode_secret(secret: &[i8]) -> String {
     BASE32_NOPAD.encode(
         secret
@@ -90,7 +121,7 @@ mod tests {
 
     use crate::{
         importers::{freeotp_plus::FreeOTPElement, importer::import_from_path},
-        otp::{otp_algorithm::OTPAlgorithm, otp_element::OTPElement, otp_type::OTPType},
+        otp::{otp_algorithm::OTPAlgorithm, otp_element::OTPElement},
     };
 
     use std::fs;
@@ -119,30 +150,18 @@ mod tests {
             "test_samples/freeotp_plus_example1.json",
         ));
 
+        let element = |secret: &str, issuer: &str, label: &str, algorithm| {
+            let mut element = OTPElement::default();
+            element.secret = secret.to_string();
+            element.issuer = issuer.to_string();
+            element.label = label.to_string();
+            element.algorithm = algorithm;
+            element
+        };
         assert_eq!(
             vec![
-                OTPElement {
-                    secret: "AAAAAAAAAAAAAAAA".to_string(),
-                    issuer: "Example2".to_string(),
-                    label: "Label2".to_string(),
-                    digits: 6,
-                    type_: OTPType::Totp,
-                    algorithm: OTPAlgorithm::Sha1,
-                    period: 30,
-                    counter: None,
-                    pin: None
-                },
-                OTPElement {
-                    secret: "AAAAAAAA".to_string(),
-                    issuer: "Example1".to_string(),
-                    label: "Label1".to_string(),
-                    digits: 6,
-                    type_: OTPType::Totp,
-                    algorithm: OTPAlgorithm::Sha256,
-                    period: 30,
-                    counter: None,
-                    pin: None
-                }
+                element("AAAAAAAAAAAAAAAA", "Example2", "Label2", OTPAlgorithm::Sha1),
+                element("AAAAAAAA", "Example1", "Label1", OTPAlgorithm::Sha256),
             ],
             imported.unwrap()
         )
@@ -0,0 +1,295 @@
+/*
+Import otpauth-migration:// URIs obtained by scanning the QR codes shown in the Google Authenticator "Transfer accounts" screen.
+The data parameter contains a base64 encoded protobuf message, the schema can be found here:
+https://github.com/qistoph/otp_export/blob/master/OtpMigration.proto
+*/
+
+use std::str::FromStr;
+
+use color_eyre::eyre::{eyre, ErrReport};
+use data_encoding::{BASE32_NOPAD, BASE64};
+use url::Url;
+
+use crate::otp::{otp_algorithm::OTPAlgorithm, otp_element::OTPElement, otp_type::OTPType};
+
+const MIGRATION_SCHEME: &str = "otpauth-migration";
+
+#[derive(Debug, PartialEq, Default)]
+struct MigrationPayload {
+    otp_parameters: Vec<OtpParameters>,
+    batch_size: u64,
+    batch_index: u64,
+    batch_id: u64,
+}
+
+#[derive(Debug, PartialEq, Default)]
+struct OtpParameters {
+    secret: Vec<u8>,
+    name: String,
+    issuer: String,
+    algorithm: u64,
+    digits: u64,
+    type_: u64,
+    counter: u64,
+}
+
+/// List of migration payloads, one for each scanned QR code
+pub struct GoogleAuthenticatorMigration(Vec<MigrationPayload>);
+
+/// Checks if the file content is a list of otpauth-migration:// URIs instead of a converted JSON backup
+pub fn is_migration_export(content: &str) -> bool {
+    content
+        .trim_start()
+        .starts_with(format!("{MIGRATION_SCHEME}://").as_str())
+}
+
+impl FromStr for GoogleAuthenticatorMigration {
+    type Err = ErrReport;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(parse_migration_uri)
+            .collect::<color_eyre::Result<Vec<MigrationPayload>>>()
+            .map(GoogleAuthenticatorMigration)
+    }
+}
+
+impl TryFrom<GoogleAuthenticatorMigration> for Vec<OTPElement> {
+    type Error = ErrReport;
+
+    fn try_from(migration: GoogleAuthenticatorMigration) -> Result<Self, Self::Error> {
+        check_batches(&migration.0)?;
+        migration
+            .0
+            .into_iter()
+            .flat_map(|payload| payload.otp_parameters)
+            .map(OTPElement::try_from)
+            .collect()
+    }
+}
+
+impl TryFrom<OtpParameters> for OTPElement {
+    type Error = ErrReport;
+
+    fn try_from(parameters: OtpParameters) -> Result<Self, Self::Error> {
+        let type_ = match parameters.type_ {
+            1 => OTPType::Hotp,
+            0 | 2 => OTPType::Totp,
+            other => return Err(eyre!("Unsupported OTP type: {other}")),
+        };
+        let algorithm = match parameters.algorithm {
+            0 | 1 => OTPAlgorithm::Sha1,
+            2 => OTPAlgorithm::Sha256,
+            3 => OTPAlgorithm::Sha512,
+            4 => OTPAlgorithm::Md5,
+            other => return Err(eyre!("Unsupported algorithm: {other}")),
+        };
+        let digits = match parameters.digits {
+            2 => 8,
+            _ => 6,
+        };
+        // Google Authenticator may store the name as "issuer:label"
+        let label = parameters
+            .name
+            .strip_prefix(format!("{}:", parameters.issuer).as_str())
+            .map(|l| l.to_owned())
+            .unwrap_or(parameters.name);
+
+        let mut element = OTPElement::default();
+        element.secret = BASE32_NOPAD.encode(&parameters.secret);
+        element.issuer = parameters.issuer;
+        element.label = label;
+        element.digits = digits;
+        element.type_ = type_;
+        element.algorithm = algorithm;
+        element.counter = (type_ == OTPType::Hotp).then_some(parameters.counter);
+        Ok(element)
+    }
+}
+
+fn parse_migration_uri(uri: &str) -> color_eyre::Result<MigrationPayload> {
+    let parsed_uri = Url::parse(uri).map_err(ErrReport::from)?;
+    if parsed_uri.scheme() != MIGRATION_SCHEME {
+        return Err(eyre!("Not a Google Authenticator migration URI: {uri}"));
+    }
+    // Unescaped '+' characters are decoded as spaces in query parameters
+    let data = parsed_uri
+        .query_pairs()
+        .find(|(k, _v)| k == "data")
+        .map(|(_k, v)| v.replace(' ', "+"))
+        .ok_or(eyre!("Missing data parameter in migration URI"))?;
+    let bytes = BASE64.decode(data.as_bytes()).map_err(ErrReport::from)?;
+    parse_payload(&bytes)
+}
+
+/// Ensures that every QR code of every migration batch has been provided
+fn check_batches(payloads: &[MigrationPayload]) -> color_eyre::Result<()> {
+    let mut batch_ids: Vec<u64> = payloads.iter().map(|p| p.batch_id).collect();
+    batch_ids.sort_unstable();
+    batch_ids.dedup();
+    for batch_id in batch_ids {
+        let batch: Vec<&MigrationPayload> =
+            payloads.iter().filter(|p| p.batch_id == batch_id).collect();
+        let batch_size = batch.iter().map(|p| p.batch_size).max().unwrap_or(1);
+        if let Some(missing) =
+            (0..batch_size).find(|index| !batch.iter().any(|p| p.batch_index == *index))
+        {
+            return Err(eyre!(
+                "Missing QR code {} of {batch_size} for migration batch {batch_id}",
+                missing + 1
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn parse_payload(bytes: &[u8]) -> color_eyre::Result<MigrationPayload> {
+    let mut payload = MigrationPayload::default();
+    let mut reader = ProtobufReader::new(bytes);
+    while let Some((field, value)) = reader.next_field()? {
+        match (field, value) {
+            (1, WireValue::Bytes(b)) => payload.otp_parameters.push(parse_otp_parameters(b)?),
+            (3, WireValue::Varint(v)) => payload.batch_size = v,
+            (4, WireValue::Varint(v)) => payload.batch_index = v,
+            (5, WireValue::Varint(v)) => payload.batch_id = v,
+            _ => {}
+        }
+    }
+    Ok(payload)
+}
+
+fn parse_otp_parameters(bytes: &[u8]) -> color_eyre::Result<OtpParameters> {
+    let mut parameters = OtpParameters::default();
+    let mut reader = ProtobufReader::new(bytes);
+    while let Some((field, value)) = reader.next_field()? {
+        match (field, value) {
+            (1, WireValue::Bytes(b)) => parameters.secret = b.to_vec(),
+            (2, WireValue::Bytes(b)) => parameters.name = String::from_utf8(b.to_vec())?,
+            (3, WireValue::Bytes(b)) => parameters.issuer = String::from_utf8(b.to_vec())?,
+            (4, WireValue::Varint(v)) => parameters.algorithm = v,
+            (5, WireValue::Varint(v)) => parameters.digits = v,
+            (6, WireValue::Varint(v)) => parameters.type_ = v,
+            (7, WireValue::Varint(v)) => parameters.counter = v,
+            _ => {}
+        }
+    }
+    Ok(parameters)
+}
+
+enum WireValue<'a> {
+    Varint(u64),
+    Bytes(&'a [u8]),
+    Fixed,
+}
+
+/// Minimal protobuf wire format reader, enough to decode the migration payload
+struct ProtobufReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> ProtobufReader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, position: 0 }
+    }
+
+    fn next_field(&mut self) -> color_eyre::Result<Option<(u64, WireValue<'a>)>> {
+        if self.position >= self.bytes.len() {
+            return Ok(None);
+        }
+        let key = self.read_varint()?;
+        let value = match key & 0x7 {
+            0 => WireValue::Varint(self.read_varint()?),
+            1 => self.skip(8)?,
+            2 => {
+                let length = self.read_varint()? as usize;
+                WireValue::Bytes(self.take(length)?)
+            }
+            5 => self.skip(4)?,
+            wire_type => return Err(eyre!("Unsupported protobuf wire type {wire_type}")),
+        };
+        Ok(Some((key >> 3, value)))
+    }
+
+    fn read_varint(&mut self) -> color_eyre::Result<u64> {
+        let mut value: u64 = 0;
+        for shift in (0..64).step_by(7) {
+            let byte = *self
+                .bytes
+                .get(self.position)
+                .ok_or(eyre!("Truncated protobuf varint"))?;
+            self.position += 1;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(eyre!("Invalid protobuf varint"))
+    }
+
+    fn take(&mut self, length: usize) -> color_eyre::Result<&'a [u8]> {
+        let end = self
+            .position
+            .checked_add(length)
+            .filter(|end| *end <= self.bytes.len())
+            .ok_or(eyre!("Truncated protobuf message"))?;
+        let slice = &self.bytes[self.position..end];
+        self.position = end;
+        Ok(slice)
+    }
+
+    fn skip(&mut self, length: usize) -> color_eyre::Result<WireValue<'a>> {
+        self.take(length).map(|_| WireValue::Fixed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::otp::{otp_algorithm::OTPAlgorithm, otp_element::OTPElement, otp_type::OTPType};
+
+    use super::{is_migration_export, GoogleAuthenticatorMigration};
+
+    // Two accounts in the first QR code of a two codes batch
+    const FIRST_QR: &str = "otpauth-migration://offline?data=CjUKCkhlbGxvId6tvu8SGEV4YW1wbGU6YWxpY2VAZ29vZ2xlLmNvbRoHRXhhbXBsZSABKAEwAgoeCgZTZWNyZXQSBGJvYmIaBkdpdEh1YiACKAIwATgHEAEYAiAAKNKF2MwE";
+    // One account in the second QR code of the same batch
+    const SECOND_QR: &str =
+        "otpauth-migration://offline?data=ChgKBGFiY2QSBGNhcm8aBE1haWwgAygBMAIQARgCIAEo0oXYzAQ%3D";
+
+    #[test]
+    fn test_migration_import() {
+        let content = format!("{FIRST_QR}\n{SECOND_QR}\n");
+        assert!(is_migration_export(&content));
+
+        let migration: GoogleAuthenticatorMigration = content.parse().unwrap();
+        let elements: Vec<OTPElement> = migration.try_into().unwrap();
+
+        assert_eq!(3, elements.len());
+
+        assert_eq!("JBSWY3DPEHPK3PXP", elements[0].secret);
+        assert_eq!("Example", elements[0].issuer);
+        assert_eq!("alice@google.com", elements[0].label);
+        assert_eq!(OTPType::Totp, elements[0].type_);
+        assert_eq!(OTPAlgorithm::Sha1, elements[0].algorithm);
+        assert_eq!(6, elements[0].digits);
+        assert_eq!(None, elements[0].counter);
+
+        assert_eq!("GitHub", elements[1].issuer);
+        assert_eq!("bobb", elements[1].label);
+        assert_eq!(OTPType::Hotp, elements[1].type_);
+        assert_eq!(OTPAlgorithm::Sha256, elements[1].algorithm);
+        assert_eq!(8, elements[1].digits);
+        assert_eq!(Some(7), elements[1].counter);
+
+        assert_eq!(OTPAlgorithm::Sha512, elements[2].algorithm);
+    }
+
+    #[test]
+    fn test_missing_batch_element() {
+        let migration: GoogleAuthenticatorMigration = FIRST_QR.parse().unwrap();
+        let result: color_eyre::Result<Vec<OTPElement>> = migration.try_into();
+
+        assert!(result.is_err());
+    }
+}
@@ -1,4 +1,4 @@
-use std::{error::Error, fmt::Debug, fs::read_to_string, path::PathBuf};
+use std::{error::Error, fmt::Debug, fs::read_to_string, path::PathBuf, str::FromStr};
 
 use serde::Deserialize;
 
@@ -15,3 +15,16 @@ where
     let mapped: Vec<OTPElement> = deserialized.try_into().map_err(|e| format!("{:?}", e))?;
     Ok(mapped)
 }
+
+/// Common flow for the importers which read plain text backups
+pub fn import_from_text_path<T>(path: PathBuf) -> Result<Vec<OTPElement>, Box<dyn Error>>
+where
+    T: FromStr + TryInto<Vec<OTPElement>>,
+    <T as FromStr>::Err: Debug,
+    <T as TryInto<Vec<OTPElement>>>::Error: Debug,
+{
+    let text = read_to_string(path)?;
+    let parsed: T = text.parse().map_err(|e| format!("{:?}", e))?;
+    let mapped: Vec<OTPElement> = parsed.try_into().map_err(|e| format!("{:?}", e))?;
+    Ok(mapped)
+}
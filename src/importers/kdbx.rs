@@ -0,0 +1,273 @@
+/*
+Import the OTP secrets stored in a KeePass or KeePassXC database.
+Supported are the KeePassXC otp attribute, the legacy TOTP Seed and TOTP Settings attributes,
+the KeePass 2 TimeOtp and HmacOtp attributes and otpauth:// URIs written in any other field.
+*/
+
+use std::{error::Error, fs::File, path::PathBuf};
+
+use color_eyre::eyre::eyre;
+use data_encoding::BASE32_NOPAD;
+use keepass::{db::Entry, Database, DatabaseKey};
+use zeroize::Zeroize;
+
+use crate::otp::{
+    from_otp_uri::FromOtpUri, otp_algorithm::OTPAlgorithm, otp_element::OTPElement,
+    otp_type::OTPType,
+};
+use crate::utils;
+
+const OTP_FIELD: &str = "otp";
+const TOTP_SEED_FIELD: &str = "TOTP Seed";
+const TOTP_SETTINGS_FIELD: &str = "TOTP Settings";
+const OTPAUTH_SCHEME: &str = "otpauth://";
+
+/// Opens the database, asking for its password, and imports the entries having an OTP secret
+pub fn import_from_kdbx(
+    path: PathBuf,
+    keyfile: Option<PathBuf>,
+) -> Result<Vec<OTPElement>, Box<dyn Error>> {
+    let mut password = utils::password("Insert your KeePass database password: ", 0)?;
+    let mut key = DatabaseKey::new();
+    if !password.is_empty() {
+        key = key.with_password(&password);
+    }
+    password.zeroize();
+    if let Some(keyfile) = keyfile {
+        key = key.with_keyfile(&mut File::open(keyfile)?)?;
+    }
+    let database = Database::open(&mut File::open(path)?, key)
+        .map_err(|e| format!("Cannot open the KeePass database: {e}"))?;
+    Ok(elements_from_database(&database)?)
+}
+
+fn elements_from_database(database: &Database) -> color_eyre::Result<Vec<OTPElement>> {
+    let recycled: Vec<_> = database
+        .recycle_bin()
+        .map(|bin| bin.entries().map(|e| e.id()).collect())
+        .unwrap_or_default();
+    database
+        .iter_all_entries()
+        .filter(|entry| !recycled.contains(&entry.id()))
+        .filter_map(|entry| element_from_entry(&entry))
+        .collect()
+}
+
+fn element_from_entry(entry: &Entry) -> Option<color_eyre::Result<OTPElement>> {
+    let title = entry.get_title().unwrap_or_default();
+    let username = entry.get_username().unwrap_or_default();
+    let mut element = if let Some(otp) = entry.get(OTP_FIELD) {
+        if otp.trim().starts_with(OTPAUTH_SCHEME) {
+            OTPElement::from_otp_uri(otp.trim())
+        } else {
+            from_keeotp(otp)
+        }
+    } else if let Some(seed) = entry.get(TOTP_SEED_FIELD) {
+        from_totp_settings(seed, entry.get(TOTP_SETTINGS_FIELD))
+    } else if let Some(result) = from_keepass_fields(entry, "TimeOtp", OTPType::Totp)
+        .or_else(|| from_keepass_fields(entry, "HmacOtp", OTPType::Hotp))
+    {
+        result
+    } else {
+        let uri = entry
+            .fields
+            .values()
+            .map(|value| value.as_str())
+            .find_map(|value| {
+                value
+                    .split_whitespace()
+                    .find(|word| word.starts_with(OTPAUTH_SCHEME))
+            })?;
+        OTPElement::from_otp_uri(uri)
+    };
+    if let Ok(element) = element.as_mut() {
+        if element.issuer.is_empty() {
+            element.issuer = title.to_owned();
+        }
+        if element.label.is_empty() {
+            element.label = username.to_owned();
+        }
+    }
+    Some(element.and_then(|element| {
+        if element.valid_secret() {
+            Ok(element)
+        } else {
+            Err(eyre!("Invalid OTP secret in the {title} entry"))
+        }
+    }))
+}
+
+fn totp_element(secret: &str) -> OTPElement {
+    let mut element = OTPElement::default();
+    element.secret = secret.to_uppercase().replace([' ', '='], "");
+    element
+}
+
+/// Parses the KeeOtp format, like key=SECRET&step=30&size=6&otpHashMode=Sha256
+fn from_keeotp(otp: &str) -> color_eyre::Result<OTPElement> {
+    let parameters: Vec<(&str, &str)> = otp
+        .trim()
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .collect();
+    let get = |name: &str| {
+        parameters
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| urlencoding::decode(value).map(|v| v.into_owned()))
+            .transpose()
+    };
+    let secret = get("key")?.ok_or(eyre!("Missing secret in the otp attribute"))?;
+    let mut element = totp_element(&secret);
+    if let Some(step) = get("step")? {
+        element.period = step.parse()?;
+    }
+    if let Some(size) = get("size")? {
+        element.digits = size.parse()?;
+    }
+    if let Some(algorithm) = get("otpHashMode")? {
+        element.algorithm = OTPAlgorithm::from(algorithm.as_str());
+    }
+    Ok(element)
+}
+
+/// Parses the legacy KeePassXC attributes, where the settings are like 30;6 or 30;S for Steam codes
+fn from_totp_settings(seed: &str, settings: Option<&str>) -> color_eyre::Result<OTPElement> {
+    let mut element = totp_element(seed);
+    let mut settings = settings.unwrap_or_default().split(';');
+    if let Some(period) = settings.next().filter(|s| !s.is_empty()) {
+        element.period = period.parse()?;
+    }
+    match settings.next() {
+        Some("S") => {
+            element.type_ = OTPType::Steam;
+            element.digits = 5;
+        }
+        Some(digits) if !digits.is_empty() => element.digits = digits.parse()?,
+        _ => {}
+    }
+    Ok(element)
+}
+
+/// Parses the attributes used by KeePass 2, like TimeOtp-Secret-Base32 and TimeOtp-Period
+fn from_keepass_fields(
+    entry: &Entry,
+    prefix: &str,
+    type_: OTPType,
+) -> Option<color_eyre::Result<OTPElement>> {
+    let field = |name: &str| entry.get(&format!("{prefix}-{name}"));
+    let secret = if let Some(secret) = field("Secret-Base32") {
+        Ok(secret.to_owned())
+    } else if let Some(secret) = field("Secret-Hex") {
+        hex::decode(secret.replace(' ', ""))
+            .map(|s| BASE32_NOPAD.encode(&s))
+            .map_err(|e| eyre!("Invalid {prefix} hex secret: {e}"))
+    } else if let Some(secret) = field("Secret-Base64") {
+        data_encoding::BASE64
+            .decode(secret.as_bytes())
+            .map(|s| BASE32_NOPAD.encode(&s))
+            .map_err(|e| eyre!("Invalid {prefix} base64 secret: {e}"))
+    } else {
+        Ok(BASE32_NOPAD.encode(field("Secret")?.as_bytes()))
+    };
+    Some(secret.and_then(|secret| {
+        let mut element = totp_element(&secret);
+        element.type_ = type_;
+        if let Some(length) = field("Length") {
+            element.digits = length.parse()?;
+        }
+        if let Some(period) = field("Period") {
+            element.period = period.parse()?;
+        }
+        if let Some(algorithm) = field("Algorithm") {
+            // Written like HMAC-SHA-256
+            element.algorithm = OTPAlgorithm::from(algorithm);
+        }
+        if type_ == OTPType::Hotp {
+            element.counter = Some(field("Counter").map(str::parse).transpose()?.unwrap_or(0));
+        }
+        Ok(element)
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use keepass::{db::fields, Database};
+
+    use crate::otp::{otp_algorithm::OTPAlgorithm, otp_type::OTPType};
+
+    use super::elements_from_database;
+
+    #[test]
+    fn test_kdbx_import() {
+        let mut database = Database::new();
+        let mut root = database.root_mut();
+        let entries: [&[(&str, &str)]; 6] = [
+            &[
+                (fields::TITLE, "GitHub"),
+                (fields::USERNAME, "alice"),
+                ("otp", "otpauth://totp/?secret=JBSWY3DPEHPK3PXP&digits=8"),
+            ],
+            &[
+                (fields::TITLE, "Legacy"),
+                (
+                    "otp",
+                    "key=JBSWY3DPEHPK3PXP&step=60&size=7&otpHashMode=Sha256",
+                ),
+            ],
+            &[
+                (fields::TITLE, "Steam"),
+                ("TOTP Seed", "JBSWY3DPEHPK3PXP"),
+                ("TOTP Settings", "30;S"),
+            ],
+            &[
+                (fields::TITLE, "KeePass"),
+                ("HmacOtp-Secret-Hex", "48656c6c6f21deadbeef"),
+                ("HmacOtp-Counter", "4"),
+            ],
+            &[
+                (fields::TITLE, "Notes"),
+                (
+                    fields::NOTES,
+                    "backup: otpauth://totp/Mail:bob?secret=JBSWY3DPEHPK3PXP",
+                ),
+            ],
+            &[(fields::TITLE, "No OTP"), (fields::PASSWORD, "hunter2")],
+        ];
+        for fields in entries {
+            let mut entry = root.add_entry();
+            for (key, value) in fields {
+                entry.set_unprotected(*key, *value);
+            }
+        }
+
+        let mut elements = elements_from_database(&database).unwrap();
+        elements.sort_by(|a, b| a.issuer.cmp(&b.issuer));
+
+        assert_eq!(5, elements.len());
+        assert_eq!(
+            ("GitHub", "alice", 8),
+            (
+                elements[0].issuer.as_str(),
+                elements[0].label.as_str(),
+                elements[0].digits
+            )
+        );
+        assert_eq!(OTPType::Hotp, elements[1].type_);
+        assert_eq!("JBSWY3DPEHPK3PXP", elements[1].secret);
+        assert_eq!(Some(4), elements[1].counter);
+        assert_eq!(
+            (60, 7, OTPAlgorithm::Sha256),
+            (
+                elements[2].period,
+                elements[2].digits,
+                elements[2].algorithm
+            )
+        );
+        assert_eq!(
+            ("Mail", "bob"),
+            (elements[3].issuer.as_str(), elements[3].label.as_str())
+        );
+        assert_eq!(OTPType::Steam, elements[4].type_);
+    }
+}
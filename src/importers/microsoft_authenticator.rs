@@ -0,0 +1,144 @@
+/*
+Import the accounts of the Microsoft Authenticator PhoneFactor database, dumped by the converters/mauth.py script.
+Third party accounts store a base32 secret, while Microsoft personal and work accounts store a base64 one,
+or none at all when they can only be approved with push notifications.
+Files converted by the older version of the script are still supported.
+*/
+
+use data_encoding::{BASE32_NOPAD, BASE64};
+use serde::Deserialize;
+
+use crate::otp::otp_element::OTPElement;
+
+use super::converted::ConvertedJsonList;
+
+const THIRD_PARTY_ACCOUNT_TYPE: u64 = 0;
+// Microsoft account codes have 8 digits, as shown by the app
+const MICROSOFT_ACCOUNT_DIGITS: u64 = 8;
+
+#[derive(Deserialize)]
+pub struct MicrosoftAccount {
+    account_type: u64,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    username: String,
+    oath_secret_key: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum MicrosoftAuthenticatorExport {
+    Accounts(Vec<MicrosoftAccount>),
+    Converted(ConvertedJsonList),
+}
+
+impl MicrosoftAccount {
+    /// Base32 secret of the account, None if it only supports push notifications
+    fn secret(&self) -> Option<String> {
+        let secret = self
+            .oath_secret_key
+            .as_deref()
+            .map(|s| s.replace(['-', ' '], ""))
+            .filter(|s| !s.is_empty())?;
+        if self.account_type == THIRD_PARTY_ACCOUNT_TYPE {
+            Some(secret.to_uppercase().replace('=', ""))
+        } else {
+            BASE64
+                .decode(secret.as_bytes())
+                .ok()
+                .map(|s| BASE32_NOPAD.encode(&s))
+        }
+    }
+
+    fn to_element(&self, secret: String) -> OTPElement {
+        let digits = if self.account_type == THIRD_PARTY_ACCOUNT_TYPE {
+            6
+        } else {
+            MICROSOFT_ACCOUNT_DIGITS
+        };
+        let mut element = OTPElement::default();
+        element.secret = secret;
+        element.issuer = self.name.to_owned();
+        element.label = self.username.to_owned();
+        element.digits = digits;
+        element
+    }
+}
+
+fn accounts_to_elements(accounts: Vec<MicrosoftAccount>) -> (Vec<OTPElement>, Vec<String>) {
+    let mut elements = Vec::with_capacity(accounts.len());
+    let mut skipped = Vec::new();
+    for account in accounts {
+        match account.secret() {
+            Some(secret) => elements.push(account.to_element(secret)),
+            None => skipped.push(format!("{} ({})", account.name, account.username)),
+        }
+    }
+    (elements, skipped)
+}
+
+impl TryFrom<MicrosoftAuthenticatorExport> for Vec<OTPElement> {
+    type Error = String;
+
+    fn try_from(export: MicrosoftAuthenticatorExport) -> Result<Self, Self::Error> {
+        match export {
+            MicrosoftAuthenticatorExport::Accounts(accounts) => {
+                let (elements, skipped) = accounts_to_elements(accounts);
+                if !skipped.is_empty() {
+                    eprintln!(
+                        "Skipped {} push notification only accounts: {}",
+                        skipped.len(),
+                        skipped.join(", ")
+                    );
+                }
+                Ok(elements)
+            }
+            MicrosoftAuthenticatorExport::Converted(converted) => converted.try_into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::otp::otp_element::OTPElement;
+
+    use super::{accounts_to_elements, MicrosoftAuthenticatorExport};
+
+    #[test]
+    fn test_microsoft_authenticator_import() {
+        let json = r#"[
+            {"account_type": 0, "name": "GitHub", "username": "alice", "oath_secret_key": "jbsw y3dp-ehpk-3pxp"},
+            {"account_type": 1, "name": "Microsoft", "username": "bob@outlook.com", "oath_secret_key": "SGVsbG8h3q2+7w=="},
+            {"account_type": 2, "name": "Contoso", "username": "carol@contoso.com", "oath_secret_key": ""},
+            {"account_type": 1, "name": "Microsoft", "username": "dave@outlook.com", "oath_secret_key": null}
+        ]"#;
+        let MicrosoftAuthenticatorExport::Accounts(accounts) = serde_json::from_str(json).unwrap()
+        else {
+            panic!("Accounts not recognized");
+        };
+        let (elements, skipped) = accounts_to_elements(accounts);
+
+        assert_eq!(2, elements.len());
+        assert_eq!("JBSWY3DPEHPK3PXP", elements[0].secret);
+        assert_eq!(6, elements[0].digits);
+        assert_eq!("JBSWY3DPEHPK3PXP", elements[1].secret);
+        assert_eq!(8, elements[1].digits);
+        assert_eq!(
+            vec![
+                "Contoso (carol@contoso.com)",
+                "Microsoft (dave@outlook.com)"
+            ],
+            skipped
+        );
+    }
+
+    #[test]
+    fn test_converted_import() {
+        let json = r#"[{"label": "alice", "secret": "JBSWY3DPEHPK3PXP", "issuer": "GitHub", "digits": 6, "type": "TOTP", "counter": 0, "algorithm": "SHA1"}]"#;
+        let export: MicrosoftAuthenticatorExport = serde_json::from_str(json).unwrap();
+        let elements: Vec<OTPElement> = export.try_into().unwrap();
+
+        assert_eq!("GitHub", elements[0].issuer);
+    }
+}
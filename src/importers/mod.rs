@@ -1,7 +1,19 @@
 pub mod aegis;
 pub mod aegis_encrypted;
+pub mod andotp;
+pub mod andotp_encrypted;
 pub mod authy_remote_debug;
+pub mod bitwarden;
 pub mod converted;
+pub mod csv;
+pub mod duo;
+pub mod ente;
+pub mod freeotp;
 pub mod freeotp_plus;
+pub mod google_authenticator;
 pub mod importer;
+pub mod kdbx;
+pub mod microsoft_authenticator;
 pub mod otp_uri;
+pub mod qr_image;
+pub mod two_fas;
@@ -0,0 +1,97 @@
+use std::{error::Error, path::PathBuf};
+
+use color_eyre::eyre::{eyre, ErrReport};
+use image::DynamicImage;
+
+use crate::otp::{from_otp_uri::FromOtpUri, otp_element::OTPElement};
+
+use super::google_authenticator::{is_migration_export, GoogleAuthenticatorMigration};
+
+/// Contents of the QR codes found in an image
+pub struct QrCodeContents(Vec<String>);
+
+/// Common flow to import codes from PNG/JPEG images containing QR codes
+pub fn import_from_qr_image(path: PathBuf) -> Result<Vec<OTPElement>, Box<dyn Error>> {
+    let image = image::open(path)?;
+    let contents = decode_qr_codes(&image);
+    let mapped: Vec<OTPElement> = contents.try_into().map_err(|e| format!("{:?}", e))?;
+    Ok(mapped)
+}
+
+/// Decodes every QR code found in the image
+pub fn decode_qr_codes(image: &DynamicImage) -> QrCodeContents {
+    let mut prepared = rqrr::PreparedImage::prepare(image.to_luma8());
+    let contents = prepared
+        .detect_grids()
+        .into_iter()
+        .filter_map(|grid| grid.decode().ok())
+        .map(|(_meta, content)| content)
+        .collect();
+    QrCodeContents(contents)
+}
+
+impl QrCodeContents {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl TryFrom<QrCodeContents> for Vec<OTPElement> {
+    type Error = ErrReport;
+
+    fn try_from(contents: QrCodeContents) -> Result<Self, Self::Error> {
+        if contents.is_empty() {
+            return Err(eyre!("No QR code found in the image"));
+        }
+        let (migrations, uris): (Vec<String>, Vec<String>) = contents
+            .0
+            .into_iter()
+            .partition(|content| is_migration_export(content));
+
+        let mut elements = uris
+            .iter()
+            .map(|uri| OTPElement::from_otp_uri(uri))
+            .collect::<color_eyre::Result<Vec<OTPElement>>>()?;
+
+        if !migrations.is_empty() {
+            let migration: GoogleAuthenticatorMigration = migrations.join("\n").parse()?;
+            elements.append(&mut migration.try_into()?);
+        }
+        Ok(elements)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{DynamicImage, GrayImage, Luma};
+
+    use crate::{exporters::qr_png::render_qr_code, otp::otp_element::OTPElement};
+
+    use super::decode_qr_codes;
+
+    fn render(content: &str) -> DynamicImage {
+        DynamicImage::ImageLuma8(render_qr_code(content).unwrap())
+    }
+
+    #[test]
+    fn test_otp_uri_qr_code() {
+        let image = render("otpauth://totp/Example:alice?secret=JBSWY3DPEHPK3PXP&digits=8");
+
+        let elements: Vec<OTPElement> = decode_qr_codes(&image).try_into().unwrap();
+
+        assert_eq!(1, elements.len());
+        assert_eq!("Example", elements[0].issuer);
+        assert_eq!("alice", elements[0].label);
+        assert_eq!("JBSWY3DPEHPK3PXP", elements[0].secret);
+        assert_eq!(8, elements[0].digits);
+    }
+
+    #[test]
+    fn test_image_without_qr_code() {
+        let image = DynamicImage::ImageLuma8(GrayImage::from_pixel(64, 64, Luma([255])));
+
+        let result: color_eyre::Result<Vec<OTPElement>> = decode_qr_codes(&image).try_into();
+
+        assert!(result.is_err());
+    }
+}
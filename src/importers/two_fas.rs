@@ -0,0 +1,249 @@
+/*
+Import from the .2fas backup files of 2FAS Authenticator, both plain and password encrypted.
+Encrypted backups store the services in servicesEncrypted, as base64 ciphertext, salt and nonce separated by colons.
+*/
+
+use aes_gcm::aead::Aead;
+use aes_gcm::{Aes256Gcm, KeyInit, Nonce};
+use data_encoding::BASE64;
+use pbkdf2::pbkdf2_hmac;
+use serde::Deserialize;
+use sha2::Sha256;
+use zeroize::Zeroize;
+
+use crate::otp::otp_element::{Icon, OTPElement};
+use crate::otp::{otp_algorithm::OTPAlgorithm, otp_type::OTPType};
+use crate::utils;
+
+const ITERATIONS: u32 = 10_000;
+const KEY_LENGTH: usize = 32;
+const NONCE_LENGTH: usize = 12;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TwoFasBackup {
+    #[serde(default)]
+    services: Vec<TwoFasService>,
+    services_encrypted: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TwoFasService {
+    name: String,
+    secret: String,
+    otp: TwoFasOtp,
+    icon: Option<TwoFasIcon>,
+    /// Free text typed by the user, shown by 2FAS as additional info
+    #[serde(rename = "additionalInfo")]
+    additional_info: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TwoFasOtp {
+    issuer: Option<String>,
+    account: Option<String>,
+    label: Option<String>,
+    digits: Option<u64>,
+    period: Option<u64>,
+    algorithm: Option<String>,
+    token_type: Option<String>,
+    counter: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct TwoFasIcon {
+    label: Option<TwoFasIconLabel>,
+}
+
+/// Text shown by 2FAS in place of the service logo, usually the first letters of the issuer
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TwoFasIconLabel {
+    text: String,
+    background_color: Option<String>,
+}
+
+impl TwoFasIconLabel {
+    /// Colors of the 2FAS labels, the other ones are left to the color of the issuer
+    fn color(&self) -> Option<&'static str> {
+        match self.background_color.as_deref()? {
+            "Red" => Some("#e53935"),
+            "Orange" => Some("#fb8c00"),
+            "Yellow" => Some("#fdd835"),
+            "Green" => Some("#43a047"),
+            "Turquoise" => Some("#00897b"),
+            "LightBlue" => Some("#039be5"),
+            "Indigo" => Some("#3949ab"),
+            "Pink" => Some("#d81b60"),
+            "Purple" => Some("#8e24aa"),
+            "Brown" => Some("#6d4c41"),
+            _ => None,
+        }
+    }
+}
+
+impl TwoFasService {
+    fn issuer(&self) -> String {
+        let icon_label = self
+            .icon
+            .as_ref()
+            .and_then(|icon| icon.label.as_ref())
+            .map(|label| label.text.as_str());
+        [
+            self.otp.issuer.as_deref(),
+            Some(self.name.as_str()),
+            icon_label,
+        ]
+        .into_iter()
+        .flatten()
+        .find(|issuer| !issuer.is_empty())
+        .unwrap_or_default()
+        .to_owned()
+    }
+
+    /// The label of the service, the logos of the 2FAS collection are not in the backup
+    fn icon(&self) -> Option<Icon> {
+        let label = self.icon.as_ref()?.label.as_ref()?;
+        Some(Icon {
+            image: None,
+            mime: None,
+            text: Some(label.text.clone()).filter(|text| !text.is_empty()),
+            color: label.color().map(String::from),
+        })
+    }
+}
+
+impl From<TwoFasService> for OTPElement {
+    fn from(service: TwoFasService) -> Self {
+        let issuer = service.issuer();
+        let icon = service.icon();
+        let type_ = OTPType::from(service.otp.token_type.as_deref().unwrap_or("TOTP"));
+        let mut element = OTPElement::default();
+        element.secret = service.secret.to_uppercase().replace([' ', '='], "");
+        element.issuer = issuer;
+        element.label = service
+            .otp
+            .account
+            .or(service.otp.label)
+            .unwrap_or_default();
+        element.digits = service
+            .otp
+            .digits
+            .unwrap_or(if type_ == OTPType::Steam { 5 } else { 6 });
+        element.type_ = type_;
+        element.algorithm = OTPAlgorithm::from(service.otp.algorithm.as_deref().unwrap_or("SHA1"));
+        element.period = service.otp.period.filter(|p| *p > 0).unwrap_or(30);
+        element.counter = (type_ == OTPType::Hotp).then(|| service.otp.counter.unwrap_or_default());
+        element.note = service.additional_info.unwrap_or_default();
+        element.icon = icon;
+        element
+    }
+}
+
+impl TryFrom<TwoFasBackup> for Vec<OTPElement> {
+    type Error = String;
+
+    fn try_from(backup: TwoFasBackup) -> Result<Self, Self::Error> {
+        let services = match backup.services_encrypted.as_deref() {
+            Some(encrypted) if backup.services.is_empty() => {
+                let mut password = utils::password("Insert your 2FAS backup password: ", 0)
+                    .map_err(|e| e.to_string())?;
+                let result = decrypt_services(encrypted, &password);
+                password.zeroize();
+                result?
+            }
+            _ => backup.services,
+        };
+        Ok(services.into_iter().map(OTPElement::from).collect())
+    }
+}
+
+fn decrypt_services(encrypted: &str, password: &str) -> Result<Vec<TwoFasService>, String> {
+    let parts = encrypted
+        .split(':')
+        .map(|part| BASE64.decode(part.as_bytes()))
+        .collect::<Result<Vec<Vec<u8>>, _>>()
+        .map_err(|e| format!("Error during base64 decoding: {e:?}"))?;
+    let [ciphertext, salt, nonce] = parts.as_slice() else {
+        return Err(String::from("Invalid 2FAS encrypted services"));
+    };
+    if nonce.len() != NONCE_LENGTH {
+        return Err(String::from("Invalid 2FAS encrypted services nonce"));
+    }
+
+    let mut key = [0u8; KEY_LENGTH];
+    pbkdf2_hmac::<Sha256>(password.as_bytes(), salt, ITERATIONS, &mut key);
+    let cipher = Aes256Gcm::new(&key.into());
+    key.zeroize();
+
+    // The authentication tag is appended to the ciphertext
+    let mut decrypted = cipher
+        .decrypt(Nonce::from_slice(nonce), ciphertext.as_slice())
+        .map_err(|_| String::from("Failed to decrypt the 2FAS backup, wrong password?"))?;
+    let services = serde_json::from_slice::<Vec<TwoFasService>>(&decrypted)
+        .map_err(|e| format!("Invalid 2FAS backup: {e}"));
+    decrypted.zeroize();
+    services
+}
+
+#[cfg(test)]
+mod tests {
+    use aes_gcm::aead::Aead;
+    use aes_gcm::{Aes256Gcm, KeyInit, Nonce};
+    use data_encoding::BASE64;
+    use pbkdf2::pbkdf2_hmac;
+    use sha2::Sha256;
+
+    use crate::otp::{otp_algorithm::OTPAlgorithm, otp_element::OTPElement, otp_type::OTPType};
+
+    use super::{decrypt_services, TwoFasBackup, ITERATIONS};
+
+    const SERVICES: &str = r#"[
+        {"name":"GitHub","secret":"JBSWY3DPEHPK3PXP","updatedAt":1700000000000,"otp":{"label":"GitHub:alice","account":"alice","issuer":"GitHub","digits":6,"period":30,"algorithm":"SHA1","tokenType":"TOTP","source":"Link"},"additionalInfo":"Recovery codes in the safe","order":{"position":0},"icon":{"selected":"IconCollection","label":{"text":"GH","backgroundColor":"Orange"},"iconCollection":{"id":"a5b3fb65-4ec5-43e6-8ec1-49e24ca9e7ad"}}},
+        {"name":"","secret":"JBSWY3DPEHPK3PXP","otp":{"account":"bob","digits":8,"algorithm":"SHA256","tokenType":"HOTP","counter":3},"icon":{"selected":"Label","label":{"text":"VPN","backgroundColor":"Blue"}}}
+    ]"#;
+
+    #[test]
+    fn test_two_fas_import() {
+        let json = format!(r#"{{"services":{SERVICES},"groups":[],"schemaVersion":4}}"#);
+        let backup: TwoFasBackup = serde_json::from_str(&json).unwrap();
+        let elements: Vec<OTPElement> = backup.try_into().unwrap();
+
+        assert_eq!(2, elements.len());
+        assert_eq!("GitHub", elements[0].issuer);
+        assert_eq!("alice", elements[0].label);
+        assert_eq!("Recovery codes in the safe", elements[0].note);
+        assert_eq!("VPN", elements[1].issuer);
+        assert_eq!(OTPType::Hotp, elements[1].type_);
+        assert_eq!(Some(3), elements[1].counter);
+        assert_eq!(OTPAlgorithm::Sha256, elements[1].algorithm);
+        assert_eq!(8, elements[1].digits);
+
+        let icon = elements[0].icon.as_ref().unwrap();
+        assert_eq!(Some("GH"), icon.text.as_deref());
+        assert_eq!(Some("#fb8c00"), icon.color.as_deref());
+        assert_eq!(None, elements[1].icon.as_ref().unwrap().color);
+    }
+
+    #[test]
+    fn test_decrypt_services() {
+        let salt = [3u8; 256];
+        let nonce = [5u8; 12];
+        let mut key = [0u8; 32];
+        pbkdf2_hmac::<Sha256>(b"password", &salt, ITERATIONS, &mut key);
+        let ciphertext = Aes256Gcm::new(&key.into())
+            .encrypt(Nonce::from_slice(&nonce), SERVICES.as_bytes())
+            .unwrap();
+        let encrypted = [&ciphertext[..], &salt, &nonce]
+            .map(|part| BASE64.encode(part))
+            .join(":");
+
+        let services = decrypt_services(&encrypted, "password").unwrap();
+        assert_eq!(2, services.len());
+        assert_eq!("JBSWY3DPEHPK3PXP", services[0].secret);
+
+        assert!(decrypt_services(&encrypted, "wrong").is_err());
+        assert!(decrypt_services("invalid", "password").is_err());
+    }
+}
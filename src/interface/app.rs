@@ -1,22 +1,51 @@
 use std::error;
+use std::time::{Duration, Instant};
 
+use crate::clipboard::ScheduledClear;
+use crate::i18n::{tr, tr_args};
+use crate::interface::auto_lock::AutoLock;
+use crate::interface::big_code::{big_code, scale, BIG_CODE_HEIGHT};
 use crate::interface::enums::Focus;
 use crate::interface::enums::Page;
-use crate::interface::enums::Page::{Main, Qrcode};
-use crate::otp::otp_element::OTPDatabase;
+use crate::interface::enums::Page::{
+    Form as FormPage, Main, NearbyCodes, Presentation, Qrcode, RecoveryCodes,
+};
+use crate::interface::enums::SortMode;
+use crate::interface::form::{Form, FormField};
+use crate::interface::graphics::{qr_graphic, Graphic, GraphicsProtocol};
+use crate::interface::password_prompt::PasswordPrompt;
+use crate::interface::qr_guard::QrGuard;
+use crate::interface::row::{Badge, HIDDEN_CODE};
+use cotp::backup::prepare_save;
+use cotp::config::DefaultsConfig;
+use cotp::crypto::cryptography::DatabaseKey;
+use cotp::otp::otp_element::OTPDatabase;
 use ratatui::layout::Rect;
 use ratatui::layout::{Alignment, Constraint, Direction, Layout};
-use ratatui::style::{Color, Modifier, Style};
+use ratatui::style::{Modifier, Style};
 use ratatui::terminal::Frame;
+use ratatui::text::{Line, Span};
 use ratatui::widgets::{Block, Borders, Cell, Clear, Gauge, Paragraph, Row, Table, Wrap};
 
+use crate::interface::journal::Journal;
+use crate::interface::keymap::{Action, Keymap};
+use crate::interface::layout::{MainLayout, TableColumns};
 use crate::interface::stateful_table::{fill_table, StatefulTable};
-use crate::utils::percentage;
+use crate::interface::status_bar::{Severity, StatusBar};
+use crate::interface::text_input::TextInput;
+use crate::interface::theme::Theme;
+use cotp::utils::{format_timestamp, get_current_timestamp, period_percentage};
+use zeroize::{Zeroize, Zeroizing};
 
 use super::enums::PopupAction;
 use super::popup::centered_rect;
 
 const LARGE_APPLICATION_WIDTH: u16 = 75;
+const DEFAULT_PERIOD: u64 = 30;
+/// Codes shown before and after the current one
+const NEARBY_CODES_WINDOW: u64 = 1;
+/// Lines above the first table row: the top border, the header and its margin
+const TABLE_HEADER_HEIGHT: u16 = 3;
 
 /// Application result type.
 pub type AppResult<T> = Result<T, Box<dyn error::Error>>;
@@ -28,21 +57,75 @@ pub struct App<'a> {
     title: String,
     pub(crate) table: StatefulTable,
     pub(crate) database: &'a mut OTPDatabase,
+    /// Key and salt the database is encrypted with, needed to lock the dashboard and to check the password
+    pub(crate) key: Option<&'a mut DatabaseKey>,
+    pub(crate) salt: &'a [u8],
     progress: u16,
-    /// Text to print replacing the percentage
-    pub(crate) label_text: String,
-    pub(crate) print_percentage: bool,
+    /// Second in which the codes were last generated
+    last_refresh: u64,
+    pub(crate) status: StatusBar,
     pub(crate) current_page: Page,
-    pub(crate) search_query: String,
+    pub(crate) search_query: TextInput,
     pub(crate) focus: Focus,
     pub(crate) popup: Popup,
+    /// Show only the codes having this tag
+    pub(crate) tag_filter: Option<String>,
+    pub(crate) sort_mode: SortMode,
+    /// Time after which copied codes are cleared from the clipboard
+    pub(crate) clipboard_timeout: Option<Duration>,
+    /// Digits and period of the codes added from the dashboard
+    pub(crate) code_defaults: DefaultsConfig,
+    pub(crate) scheduled_clear: Option<ScheduledClear>,
+    /// Changes which can be undone before saving
+    pub(crate) journal: Journal,
+    /// Values inserted in the add or edit page
+    pub(crate) form: Option<Form>,
+    /// Keys bound to the dashboard actions
+    pub(crate) keymap: Keymap,
+    /// Navigate with j, k, gg, G and repeat searches with n, N
+    pub(crate) vim_mode: bool,
+    /// The first g of gg has been typed
+    pub(crate) pending_g: bool,
+    /// Where the search bar and the table were last drawn, to handle mouse clicks
+    pub(crate) search_bar_area: Rect,
+    pub(crate) table_area: Rect,
+    /// Time and row of the last click, to detect double clicks
+    pub(crate) last_click: Option<(Instant, usize)>,
+    pub(crate) theme: Theme,
+    /// Show the codes only after they are revealed or copied
+    pub(crate) hide_codes: bool,
+    /// Database index of the element whose code is shown while codes are hidden
+    pub(crate) revealed: Option<usize>,
+    /// Characters per group of the shown codes, for the elements without their own, 0 to not group them
+    pub(crate) digit_group: u64,
+    /// Leaves the QR code page after a while
+    pub(crate) qr_guard: QrGuard,
+    /// Show the secret in the URI below the QR code
+    pub(crate) uri_revealed: bool,
+    /// How the QR codes are drawn
+    pub(crate) graphics: GraphicsProtocol,
+    /// Image drawn by the terminal over the last rendered frame
+    pub(crate) graphic: Option<Graphic>,
+    /// Columns of the table and their widths
+    pub(crate) columns: TableColumns,
+    /// Show every detail of the selected code and its code in big digits next to the table
+    pub(crate) detail_pane: bool,
+    /// Locks the dashboard after some time without input, None to never lock it
+    pub(crate) auto_lock: Option<AutoLock>,
+    /// Asks the password again before deleting codes or showing their secrets, None to never ask it
+    pub(crate) password_prompt: Option<PasswordPrompt>,
 }
 
 pub struct Popup {
-    pub(crate) text: String,
+    /// Wiped when replaced, since it may show secrets
+    pub(crate) text: Zeroizing<String>,
     pub(crate) action: PopupAction,
+    /// Text typed in the popups asking a value, like the tag of the marked codes
+    pub(crate) input: TextInput,
     pub(crate) percent_x: u16,
     pub(crate) percent_y: u16,
+    /// First line shown, for the text longer than the popup like the help
+    pub(crate) scroll: u16,
 }
 
 impl<'a> App<'a> {
@@ -57,67 +140,529 @@ impl<'a> App<'a> {
             title,
             table: StatefulTable::new(database.elements_ref()),
             database,
-            progress: percentage(),
-            label_text: String::from(""),
-            print_percentage: true,
+            key: None,
+            salt: &[],
+            progress: period_percentage(DEFAULT_PERIOD, 0),
+            last_refresh: get_current_timestamp(),
+            status: StatusBar::default(),
             current_page: Main,
-            search_query: String::from(""),
+            search_query: TextInput::default(),
             focus: Focus::MainPage,
             popup: Popup {
-                text: String::from(""),
+                text: Zeroizing::default(),
                 action: PopupAction::EditOtp,
+                input: TextInput::default(),
                 percent_x: 60,
                 percent_y: 20,
+                scroll: 0,
             },
+            tag_filter: None,
+            sort_mode: SortMode::Issuer,
+            clipboard_timeout: None,
+            code_defaults: DefaultsConfig::default(),
+            scheduled_clear: None,
+            journal: Journal::default(),
+            form: None,
+            keymap: Keymap::default(),
+            vim_mode: false,
+            pending_g: false,
+            search_bar_area: Rect::default(),
+            table_area: Rect::default(),
+            last_click: None,
+            theme: Theme::default(),
+            hide_codes: false,
+            revealed: None,
+            digit_group: 0,
+            qr_guard: QrGuard::default(),
+            uri_revealed: false,
+            graphics: GraphicsProtocol::Unicode,
+            graphic: None,
+            columns: TableColumns::default(),
+            detail_pane: false,
+            auto_lock: None,
+            password_prompt: None,
         }
     }
 
     /// Handles the tick event of the terminal.
     pub fn tick(&mut self, force_update: bool) {
-        // Update progress bar
-        let new_progress = percentage();
-        // Check for new cycle
-        if force_update || new_progress < self.progress {
-            // Update codes
+        if !self.is_locked() && self.auto_lock.as_ref().is_some_and(AutoLock::is_expired) {
+            self.lock();
+        }
+        self.status.tick(Instant::now());
+        // The key cannot be held while typing the password, so the page is kept as if it was
+        if self.focus == Focus::PasswordPrompt && self.qr_guard.is_hold() {
+            self.qr_guard.press();
+        }
+        if self.current_page == Qrcode && self.qr_guard.is_expired(Instant::now()) {
+            self.current_page = Main;
+        }
+        // Codes can have different periods, so the table is refreshed every second
+        let now = get_current_timestamp();
+        if force_update || now != self.last_refresh {
+            // The codes are changed only by the updates forced after them
+            if force_update {
+                self.table.update_duplicates(self.database.elements_ref());
+            }
+            // Update codes and countdowns
             self.table.items.clear();
-            fill_table(&mut self.table, self.database.elements_ref());
+            fill_table(
+                &mut self.table,
+                self.database.elements_ref(),
+                self.tag_filter.as_deref(),
+                self.sort_mode,
+                self.digit_group,
+            );
+            self.last_refresh = now;
+        }
+        // The progress bar follows the period of the selected code
+        let (period, t0) = self.selected_period();
+        self.progress = period_percentage(period, t0);
+    }
+
+    pub(crate) fn is_locked(&self) -> bool {
+        self.database.is_locked()
+    }
+
+    /// Saves the changes, then encrypts the database in memory and wipes the key and every shown secret
+    fn lock(&mut self) {
+        let (Some(auto_lock), Some(key)) = (&mut self.auto_lock, &mut self.key) else {
+            return;
+        };
+        // Unsaved changes that fail to be saved stay encrypted in memory, until unlocking saves them on exit
+        if self.database.is_modified() || self.database.is_usage_modified() {
+            let warnings = prepare_save(self.database);
+            if let Err(e) = self.database.save(key, self.salt) {
+                auto_lock.error = Some(tr_args("lock-unsaved", &[("error", &e.to_string())]));
+            } else if !warnings.is_empty() {
+                auto_lock.error = Some(warnings.join(". "));
+            }
+        }
+        if let Err(e) = self.database.lock(key, self.salt) {
+            auto_lock.record_activity();
+            self.status
+                .error(tr_args("lock-failed", &[("error", &e.to_string())]));
+            return;
+        }
+        key.zeroize();
+        auto_lock.password.zeroize();
+        auto_lock.quit_requested = false;
+        self.table.items.clear();
+        self.table.clear_marks();
+        self.journal = Journal::default();
+        self.form = None;
+        self.current_page = Main;
+        self.focus = Focus::MainPage;
+        self.popup.text.zeroize();
+        self.revealed = None;
+        if let Some(password_prompt) = &mut self.password_prompt {
+            password_prompt.forget();
         }
-        self.progress = new_progress;
+        if let Some(scheduled_clear) = self.scheduled_clear.take() {
+            scheduled_clear.clear_now();
+        }
+    }
+
+    /// Unlocks the database with the password typed in the lock screen
+    pub(crate) fn unlock(&mut self) {
+        let (Some(auto_lock), Some(key)) = (&mut self.auto_lock, &mut self.key) else {
+            return;
+        };
+        match self.database.unlock(&auto_lock.password) {
+            Ok(unlocked_key) => {
+                **key = unlocked_key;
+                auto_lock.error = None;
+                auto_lock.record_activity();
+            }
+            Err(e) => auto_lock.error = Some(e.to_string()),
+        }
+        auto_lock.password.zeroize();
+        self.tick(true);
     }
 
     /// Renders the user interface widgets.
     pub fn render(&mut self, frame: &mut Frame<'_>) {
+        self.graphic = None;
+        if self.is_locked() {
+            self.render_lock_screen(frame);
+            return;
+        }
         match &self.current_page {
             Main => self.render_main_page(frame),
             Qrcode => self.render_qrcode_page(frame),
+            NearbyCodes => self.render_nearby_codes_page(frame),
+            RecoveryCodes => self.render_recovery_codes_page(frame),
+            FormPage => self.render_form_page(frame),
+            Presentation => self.render_presentation_page(frame),
+        }
+        // The main page draws its own popups
+        if self.focus == Focus::Popup && self.current_page != Main {
+            self.render_alert(frame);
+        }
+        if self.focus == Focus::PasswordPrompt {
+            self.render_password_prompt(frame);
+        }
+        // The images would cover the popups
+        if self.focus != Focus::MainPage {
+            self.graphic = None;
+        }
+    }
+
+    fn render_qrcode_page(&mut self, frame: &mut Frame<'_>) {
+        let Some(element) = self
+            .table
+            .selected_element_index()
+            .and_then(|index| self.database.elements_ref().get(index))
+        else {
+            let paragraph = Paragraph::new(tr("no-element-selected"))
+                .block(
+                    Block::default()
+                        .title(tr("popup-nope"))
+                        .borders(Borders::ALL),
+                )
+                .style(self.theme.text())
+                .alignment(Alignment::Center)
+                .wrap(Wrap { trim: true });
+            self.render_paragraph(frame, paragraph);
+            return;
+        };
+        let title = Line::from(vec![
+            Badge::new(element).span(),
+            Span::raw(" "),
+            element.name().into(),
+        ]);
+        let block = Block::default().title(title).borders(Borders::ALL);
+        let inner = block.inner(frame.size());
+        let uri = Zeroizing::new(element.get_otpauth_uri());
+        // The URI below the QR code hides the secret until it is revealed, like the codes
+        let (shown_uri, hint) = if self.uri_revealed {
+            (uri.clone(), "qrcode-hide-secret")
+        } else {
+            (Zeroizing::new(mask_uri(&uri)), "qrcode-show-secret")
+        };
+        let hint = tr_args(hint, &[("keys", &self.keymap.keys(Action::Reveal))]);
+        let uri_height = (shown_uri.chars().count() as u16).div_ceil(inner.width.max(1)) + 1;
+        let rects = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(uri_height)])
+            .split(inner);
+        frame.render_widget(block, frame.size());
+        // Drawn by the terminal over the empty area, after the widgets
+        self.graphic = qr_graphic(self.graphics, &uri, rects[0]);
+        if self.graphic.is_none() {
+            // The QR code encodes the secret, so it is wiped after being drawn
+            let qrcode = Zeroizing::new(element.get_qrcode());
+            let paragraph = Paragraph::new(qrcode.as_str())
+                .style(self.theme.text())
+                .alignment(Alignment::Center)
+                .wrap(Wrap { trim: true });
+            frame.render_widget(paragraph, rects[0]);
         }
+        let paragraph = Paragraph::new(vec![Line::from(shown_uri.as_str()), Line::from(hint)])
+            .style(self.theme.text())
+            .alignment(Alignment::Center)
+            .wrap(Wrap { trim: true });
+        frame.render_widget(paragraph, rects[1]);
+    }
+
+    fn render_nearby_codes_page(&self, frame: &mut Frame<'_>) {
+        let Some(element) = self
+            .table
+            .selected_element_index()
+            .and_then(|index| self.database.get_element(index))
+        else {
+            let paragraph = Paragraph::new(tr("no-element-selected"))
+                .block(
+                    Block::default()
+                        .title(tr("popup-nope"))
+                        .borders(Borders::ALL),
+                )
+                .style(self.theme.text())
+                .alignment(Alignment::Center);
+            self.render_paragraph(frame, paragraph);
+            return;
+        };
+        let title = if element.label.is_empty() {
+            element.issuer.to_owned()
+        } else {
+            format!("{} - {}", &element.issuer, &element.label)
+        };
+        let lines = match element.get_nearby_codes(NEARBY_CODES_WINDOW) {
+            Ok(codes) => {
+                let now = get_current_timestamp();
+                codes
+                    .into_iter()
+                    .map(|nearby| {
+                        let name = tr(match nearby.offset {
+                            0 => "nearby-current",
+                            o if o < 0 => "nearby-previous",
+                            _ => "nearby-next",
+                        });
+                        let seconds =
+                            |id, seconds: u64| tr_args(id, &[("seconds", &seconds.to_string())]);
+                        let when = match (nearby.validity, nearby.counter) {
+                            (Some((from, _)), _) if from > now => {
+                                seconds("nearby-valid-in", from - now)
+                            }
+                            (Some((_, until)), _) if until <= now => {
+                                seconds("nearby-expired", now - until)
+                            }
+                            (Some((_, until)), _) => seconds("nearby-left", until - now),
+                            (None, Some(counter)) => {
+                                tr_args("nearby-counter", &[("counter", &counter.to_string())])
+                            }
+                            (None, None) => String::new(),
+                        };
+                        let line = Line::from(format!(
+                            "{name:<8}  {}  {when:<16}",
+                            element.group_code(&nearby.code, self.digit_group)
+                        ));
+                        if nearby.offset == 0 {
+                            line.style(Style::default().add_modifier(Modifier::BOLD))
+                        } else {
+                            line
+                        }
+                    })
+                    .collect()
+            }
+            Err(e) => vec![Line::from(e.to_string())],
+        };
+        let paragraph = Paragraph::new(lines)
+            .block(Block::default().title(title).borders(Borders::ALL))
+            .style(self.theme.text())
+            .alignment(Alignment::Center);
+        self.render_paragraph(frame, paragraph);
+    }
+
+    fn render_recovery_codes_page(&self, frame: &mut Frame<'_>) {
+        let Some(element) = self
+            .table
+            .selected_element_index()
+            .and_then(|index| self.database.get_element(index))
+        else {
+            let paragraph = Paragraph::new(tr("no-element-selected"))
+                .block(
+                    Block::default()
+                        .title(tr("popup-nope"))
+                        .borders(Borders::ALL),
+                )
+                .style(self.theme.text())
+                .alignment(Alignment::Center);
+            self.render_paragraph(frame, paragraph);
+            return;
+        };
+        let title = if element.label.is_empty() {
+            format!("{} - {}", &element.issuer, tr("popup-recovery-codes"))
+        } else {
+            format!(
+                "{} - {} - {}",
+                &element.issuer,
+                &element.label,
+                tr("popup-recovery-codes")
+            )
+        };
+        let lines: Vec<Line> = if element.recovery_codes.is_empty() {
+            vec![Line::from(tr("no-recovery-codes"))]
+        } else {
+            element
+                .recovery_codes
+                .iter()
+                .enumerate()
+                .map(|(i, code)| {
+                    let line = Line::from(format!("{:>3}  {}", i + 1, code.code));
+                    // Used codes are still listed, to know which ones were given by the service
+                    if code.is_used() {
+                        line.style(Style::default().add_modifier(Modifier::CROSSED_OUT))
+                    } else {
+                        line
+                    }
+                })
+                .collect()
+        };
+        let paragraph = Paragraph::new(lines)
+            .block(Block::default().title(title).borders(Borders::ALL))
+            .style(self.theme.text())
+            .alignment(Alignment::Center);
+        self.render_paragraph(frame, paragraph);
     }
 
-    fn render_qrcode_page(&self, frame: &mut Frame<'_>) {
-        let paragraph = self
+    fn render_presentation_page(&self, frame: &mut Frame<'_>) {
+        let Some((row, element)) = self
             .table
             .state
             .selected()
-            .and_then(|index| self.database.elements_ref().get(index))
-            .map(|element| {
-                let title = if element.label.is_empty() {
-                    element.issuer.to_owned()
+            .and_then(|i| self.table.items.get(i))
+            .and_then(|row| Some((row, self.database.get_element(row.element_index)?)))
+        else {
+            let paragraph = Paragraph::new(tr("no-element-selected"))
+                .block(
+                    Block::default()
+                        .title(tr("popup-nope"))
+                        .borders(Borders::ALL),
+                )
+                .style(self.theme.text())
+                .alignment(Alignment::Center);
+            self.render_paragraph(frame, paragraph);
+            return;
+        };
+        let rects = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Min(BIG_CODE_HEIGHT as u16),
+                Constraint::Length(3),
+            ])
+            .margin(1)
+            .split(frame.size());
+
+        let code = if self.hide_codes && self.revealed != Some(row.element_index) {
+            String::from(HIDDEN_CODE)
+        } else {
+            row.shown_code()
+        };
+        let code = code.as_str();
+        // The largest digits fitting the screen, inside the borders
+        let (width, height) = (
+            rects[0].width.saturating_sub(2) as usize,
+            rects[0].height.saturating_sub(2) as usize,
+        );
+        let lines = match big_code(code) {
+            Some(big) => {
+                let big_width = big[0].chars().count();
+                let factor = (width / big_width.max(1))
+                    .min(height / BIG_CODE_HEIGHT)
+                    .max(1);
+                if big_width * factor <= width {
+                    scale(&big, factor)
                 } else {
-                    format!("{} - {}", &element.issuer, &element.label)
+                    vec![code.to_owned()]
+                }
+            }
+            None => vec![code.to_owned()],
+        };
+        let padding = height.saturating_sub(lines.len()) / 2;
+        let lines: Vec<Line> = std::iter::repeat_n(Line::from(""), padding)
+            .chain(lines.into_iter().map(Line::from))
+            .collect();
+        let title = Line::from(vec![
+            Badge::new(element).span(),
+            Span::raw(" "),
+            element.name().into(),
+        ]);
+        let paragraph = Paragraph::new(lines)
+            .block(Block::default().title(title).borders(Borders::ALL))
+            .style(
+                self.theme
+                    .text()
+                    .fg(self.theme.accent)
+                    .add_modifier(Modifier::BOLD),
+            )
+            .alignment(Alignment::Center);
+        frame.render_widget(paragraph, rects[0]);
+
+        let (percent, label) = match element.remaining_seconds() {
+            Some(seconds) => (
+                period_percentage(element.period, element.time_offset()),
+                format!("{seconds}s"),
+            ),
+            None => (
+                100,
+                element
+                    .counter
+                    .map(|counter| {
+                        tr_args("presentation-counter", &[("counter", &counter.to_string())])
+                    })
+                    .unwrap_or_default(),
+            ),
+        };
+        let countdown = Gauge::default()
+            .block(Block::default().borders(Borders::ALL))
+            .gauge_style(
+                Style::default()
+                    .bg(self.theme.gauge_background)
+                    .fg(self.theme.gauge_foreground)
+                    .add_modifier(Modifier::BOLD),
+            )
+            .percent(percent)
+            .label(label);
+        frame.render_widget(countdown, rects[1]);
+    }
+
+    fn render_lock_screen(&self, frame: &mut Frame<'_>) {
+        let Some(auto_lock) = &self.auto_lock else {
+            return;
+        };
+        let minutes = auto_lock.timeout.as_secs() / 60;
+        let mut lines = vec![
+            Line::from(if minutes == 1 {
+                tr("lock-title-one").to_owned()
+            } else {
+                tr_args("lock-title", &[("minutes", &minutes.to_string())])
+            }),
+            Line::from(""),
+            Line::from(tr_args(
+                "password-field",
+                &[("password", &"*".repeat(auto_lock.password.chars().count()))],
+            )),
+        ];
+        if let Some(error) = &auto_lock.error {
+            lines.push(Line::from(""));
+            lines.push(Line::styled(
+                error.to_owned(),
+                Style::default().fg(self.theme.error),
+            ));
+        }
+        lines.push(Line::from(""));
+        lines.push(Line::from(tr("lock-hint")));
+        let paragraph = Paragraph::new(lines)
+            .block(Block::default().title("cotp").borders(Borders::ALL))
+            .style(self.theme.text())
+            .alignment(Alignment::Center)
+            .wrap(Wrap { trim: true });
+        self.render_paragraph(frame, paragraph);
+    }
+
+    fn render_form_page(&self, frame: &mut Frame<'_>) {
+        let Some(form) = &self.form else {
+            return;
+        };
+        let mut lines: Vec<Line> = form
+            .fields()
+            .into_iter()
+            .map(|field| {
+                let selected = field == form.selected;
+                let value = match field {
+                    FormField::Secret if !selected => "*".repeat(form.value(field).chars().count()),
+                    _ if field.is_choice() => format!("< {} >", form.value(field)),
+                    _ if selected => format!("{}_", form.value(field)),
+                    _ => form.value(field),
                 };
-                Paragraph::new(element.get_qrcode())
-                    .block(Block::default().title(title).borders(Borders::ALL))
-                    .style(Style::default().fg(Color::White).bg(Color::Reset))
-                    .alignment(Alignment::Center)
-                    .wrap(Wrap { trim: true })
+                let name_style = if selected {
+                    Style::default()
+                        .fg(self.theme.accent)
+                        .add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default()
+                };
+                Line::from(vec![
+                    Span::styled(format!("{:>10}: ", field.name()), name_style),
+                    Span::raw(value),
+                ])
             })
-            .unwrap_or_else(|| {
-                Paragraph::new("No element is selected")
-                    .block(Block::default().title("Nope").borders(Borders::ALL))
-                    .style(Style::default().fg(Color::White).bg(Color::Reset))
-                    .alignment(Alignment::Center)
-                    .wrap(Wrap { trim: true })
-            });
+            .collect();
+        lines.push(Line::from(""));
+        if let Some(error) = &form.error {
+            lines.push(Line::styled(
+                error.to_owned(),
+                Style::default().fg(self.theme.error),
+            ));
+            lines.push(Line::from(""));
+        }
+        lines.push(Line::from(tr("form-hint-fields")));
+        lines.push(Line::from(tr("form-hint-save")));
+        let paragraph = Paragraph::new(lines)
+            .block(Block::default().title(form.title()).borders(Borders::ALL))
+            .style(self.theme.text())
+            .wrap(Wrap { trim: false });
         self.render_paragraph(frame, paragraph);
     }
 
@@ -131,74 +676,231 @@ impl<'a> App<'a> {
     }
 
     fn render_main_page(&mut self, frame: &mut Frame<'_>) {
-        let height = frame.size().height;
-        let rects = Layout::default()
-            .direction(Direction::Vertical)
-            .constraints(
-                [
-                    Constraint::Length(3),          // Search bar
-                    Constraint::Length(height - 8), // Table + Info Box
-                    Constraint::Length(1),          // Progress bar
-                ]
-                .as_ref(),
-            )
-            .margin(2)
-            .split(frame.size());
+        let searching = self.focus == Focus::SearchBar || !self.search_query.is_empty();
+        let Some(layout) = MainLayout::new(frame.size(), searching) else {
+            self.search_bar_area = Rect::default();
+            self.table_area = Rect::default();
+            let paragraph = Paragraph::new(tr("terminal-too-small"))
+                .style(self.theme.text())
+                .alignment(Alignment::Center)
+                .wrap(Wrap { trim: true });
+            self.render_paragraph(frame, paragraph);
+            return;
+        };
 
-        let search_bar_title = "Press CTRL + F to search a code...";
-        let search_bar = Paragraph::new(&*self.search_query)
-            .block(
-                Block::default()
-                    .title(search_bar_title)
-                    .borders(Borders::ALL)
-                    .border_style(Style::default().fg(if self.focus == Focus::SearchBar {
-                        Color::LightRed
-                    } else {
-                        Color::White
-                    })),
-            )
-            .style(Style::default().fg(Color::White).bg(Color::Reset))
-            .alignment(Alignment::Center)
-            .wrap(Wrap { trim: true });
+        let search_bar = match layout.search_bar {
+            // A single line on short terminals, like the search of vim
+            Some(area) if area.height < 3 => {
+                let mut line = self.search_query.line(self.focus == Focus::SearchBar);
+                line.spans.insert(0, Span::raw("/"));
+                Paragraph::new(line).style(self.theme.text())
+            }
+            _ => {
+                let search_bar_title = tr_args(
+                    "search-title",
+                    &[("keys", &self.keymap.keys(Action::Search))],
+                );
+                Paragraph::new(self.search_query.line(self.focus == Focus::SearchBar))
+                    .block(
+                        Block::default()
+                            .title(search_bar_title)
+                            .borders(Borders::ALL)
+                            .border_style(Style::default().fg(if self.focus == Focus::SearchBar {
+                                self.theme.accent
+                            } else {
+                                self.theme.foreground
+                            })),
+                    )
+                    .style(self.theme.text())
+                    .alignment(Alignment::Center)
+                    .wrap(Wrap { trim: true })
+            }
+        };
 
-        let progress_label = if self.print_percentage {
-            format!("{}%", self.progress)
-        } else {
-            self.label_text.to_owned()
+        self.search_bar_area = layout.search_bar.unwrap_or_default();
+        let (status_line, pending) = self.status_line();
+        let progress_label = match layout.status_bar {
+            Some(_) => format!("{}%", self.progress),
+            // Short terminals show the status in the gauge
+            None => {
+                let status: String = status_line.spans.iter().map(|s| &*s.content).collect();
+                [format!("{}%", self.progress), status, pending.clone()]
+                    .into_iter()
+                    .filter(|part| !part.is_empty())
+                    .collect::<Vec<_>>()
+                    .join(" - ")
+            }
         };
         let progress_bar = Gauge::default()
             .block(Block::default())
             .gauge_style(
                 Style::default()
-                    .bg(Color::White)
-                    .fg(Color::DarkGray)
+                    .bg(self.theme.gauge_background)
+                    .fg(self.theme.gauge_foreground)
                     .add_modifier(Modifier::BOLD),
             )
             .percent(self.progress)
             .label(progress_label);
 
-        frame.render_widget(search_bar, rects[0]);
-        self.render_table_box(frame, rects[1]);
-        frame.render_widget(progress_bar, rects[2]);
+        if let Some(area) = layout.search_bar {
+            frame.render_widget(search_bar, area);
+        }
+        self.render_table_box(frame, layout.table);
+        if let Some(area) = layout.progress_bar {
+            frame.render_widget(progress_bar, area);
+        }
+        if let Some(area) = layout.status_bar {
+            let rects = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([
+                    Constraint::Min(0),
+                    Constraint::Length(pending.chars().count() as u16),
+                ])
+                .split(area);
+            frame.render_widget(
+                Paragraph::new(status_line).style(self.theme.text()),
+                rects[0],
+            );
+            frame.render_widget(
+                Paragraph::new(pending)
+                    .style(self.theme.text())
+                    .alignment(Alignment::Right),
+                rects[1],
+            );
+        }
         if self.focus == Focus::Popup {
             self.render_alert(frame);
         }
     }
 
+    /// The vim mode and the last message on the left, the pending operations or the help key on the right
+    fn status_line(&self) -> (Line<'static>, String) {
+        let mut spans = Vec::new();
+        if self.vim_mode {
+            let mode = if self.focus == Focus::SearchBar {
+                "-- SEARCH -- "
+            } else {
+                "-- NORMAL -- "
+            };
+            spans.push(Span::styled(
+                mode,
+                Style::default().add_modifier(Modifier::BOLD),
+            ));
+        }
+        let message = self.status.message();
+        if let Some((text, severity)) = message {
+            let style = match severity {
+                Severity::Info => Style::default(),
+                Severity::Error => Style::default().fg(self.theme.error),
+            };
+            spans.push(Span::styled(text.to_owned(), style));
+        }
+        let mut pending = Vec::new();
+        if !self.table.marked.is_empty() {
+            let count = self.table.marked.len().to_string();
+            pending.push(tr_args("status-marked", &[("count", &count)]));
+        }
+        if let Some(scheduled_clear) = self.scheduled_clear.as_ref().filter(|s| s.is_pending()) {
+            let seconds = scheduled_clear.remaining_seconds().to_string();
+            pending.push(tr_args("status-clipboard-clear", &[("seconds", &seconds)]));
+        }
+        if pending.is_empty() && message.is_none() {
+            let keys = self.keymap.keys(Action::Help);
+            pending.push(tr_args("status-help", &[("keys", &keys)]));
+        }
+        (Line::from(spans), pending.join(" - "))
+    }
+
     fn render_alert(&mut self, frame: &mut Frame<'_>) {
-        let block = Block::default().title("Alert").borders(Borders::ALL);
-        let paragraph = Paragraph::new(&*self.popup.text)
+        // The help is a list of keys, easier to read aligned to the left
+        let (title, alignment) = if self.popup.action == PopupAction::GeneralInfo {
+            (tr("popup-help"), Alignment::Left)
+        } else {
+            (tr("popup-alert"), Alignment::Center)
+        };
+        let block = Block::default().title(title).borders(Borders::ALL);
+        // The lines ending with a colon are the titles of the help sections
+        let mut lines: Vec<Line> = self
+            .popup
+            .text
+            .lines()
+            .map(|line| {
+                if line.ends_with(':') {
+                    Line::styled(line, Style::default().add_modifier(Modifier::BOLD))
+                } else {
+                    Line::from(line)
+                }
+            })
+            .collect();
+        if matches!(
+            self.popup.action,
+            PopupAction::TagCodes | PopupAction::ExportCodes | PopupAction::SaveQrCode
+        ) {
+            lines.push(Line::from(""));
+            lines.push(self.popup.input.line(true));
+        }
+        let area = centered_rect(self.popup.percent_x, self.popup.percent_y, frame.size());
+        // Stops scrolling when the last line is shown, the long lines are wrapped so they are counted by their width
+        let inner_width = area.width.saturating_sub(2).max(1) as usize;
+        let height: usize = lines
+            .iter()
+            .map(|line| line.width().max(1).div_ceil(inner_width))
+            .sum();
+        let max_scroll = height.saturating_sub(area.height.saturating_sub(2) as usize);
+        self.popup.scroll = self.popup.scroll.min(max_scroll as u16);
+        let paragraph = Paragraph::new(lines)
             .block(block)
+            .style(self.theme.text())
+            .alignment(alignment)
+            .wrap(Wrap { trim: true })
+            .scroll((self.popup.scroll, 0));
+        frame.render_widget(Clear, area);
+        //this clears out the background
+        frame.render_widget(paragraph, area);
+    }
+
+    fn render_password_prompt(&self, frame: &mut Frame<'_>) {
+        let Some(password_prompt) = &self.password_prompt else {
+            return;
+        };
+        let prompt = password_prompt
+            .action
+            .map_or(tr("protected-continue"), |action| action.prompt());
+        let mut lines = vec![
+            Line::from(prompt),
+            Line::from(""),
+            Line::from(tr_args(
+                "password-field",
+                &[(
+                    "password",
+                    &"*".repeat(password_prompt.password.chars().count()),
+                )],
+            )),
+        ];
+        if let Some(error) = &password_prompt.error {
+            lines.push(Line::styled(
+                error.to_owned(),
+                Style::default().fg(self.theme.error),
+            ));
+        }
+        let paragraph = Paragraph::new(lines)
+            .block(
+                Block::default()
+                    .title(tr("popup-password"))
+                    .borders(Borders::ALL),
+            )
+            .style(self.theme.text())
             .alignment(Alignment::Center)
             .wrap(Wrap { trim: true });
-        let area = centered_rect(self.popup.percent_x, self.popup.percent_y, frame.size());
+        let area = centered_rect(60, 30, frame.size());
         frame.render_widget(Clear, area);
-        //this clears out the background
         frame.render_widget(paragraph, area);
     }
 
     fn render_table_box(&mut self, frame: &mut Frame<'_>, area: Rect) {
-        let constraints = if self.is_large_application(frame) {
+        let constraints = if self.detail_pane {
+            vec![Constraint::Percentage(60), Constraint::Percentage(40)]
+        } else if self.is_large_application(frame) {
             vec![Constraint::Percentage(80), Constraint::Percentage(20)]
         } else {
             vec![Constraint::Percentage(100)]
@@ -208,46 +910,65 @@ impl<'a> App<'a> {
             .direction(Direction::Horizontal)
             .split(area);
 
-        let header_cells = ["Id", "Issuer", "Label", "OTP"]
-            .iter()
-            .map(|h| Cell::from(*h).style(Style::default().fg(Color::Black)));
+        // Narrow tables leave out some columns
+        let (columns, widths) = self.columns.fitting(chunks[0].width);
+        let header_cells = columns.iter().map(|column| {
+            Cell::from(column.title()).style(Style::default().fg(self.theme.header_foreground))
+        });
         let header = Row::new(header_cells)
             .style(
                 Style::default()
-                    .bg(Color::White)
+                    .bg(self.theme.header_background)
                     .add_modifier(Modifier::BOLD),
             )
             .height(1)
             .bottom_margin(1);
         let rows = self.table.items.iter().map(|item| {
-            Row::new(item.cells())
+            let hide_code = self.hide_codes && self.revealed != Some(item.element_index);
+            let style = if self.table.marked.contains(&item.element_index) {
+                Style::default()
+                    .fg(self.theme.accent)
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                Style::default()
+            };
+            let mut cells: Vec<Option<Cell>> = item
+                .cells(&self.theme, hide_code)
+                .into_iter()
+                .map(Some)
+                .collect();
+            let cells: Vec<Cell> = columns
+                .iter()
+                .filter_map(|column| cells.get_mut(column.index())?.take())
+                .collect();
+            Row::new(cells)
+                .style(style)
                 .height(item.height())
                 .bottom_margin(1)
         });
 
-        const TABLE_WIDTHS: &[Constraint] = &[
-            Constraint::Percentage(5),
-            Constraint::Percentage(35),
-            Constraint::Percentage(35),
-            Constraint::Percentage(25),
-        ];
+        let mut title = format!("{} - Sort: {}", self.title, self.sort_mode);
+        if let Some(tag) = &self.tag_filter {
+            title.push_str(" - Tag: ");
+            title.push_str(tag);
+        }
 
-        let t = Table::new(rows, TABLE_WIDTHS)
+        let t = Table::new(rows, widths)
             .header(header)
             .block(
                 Block::default()
                     .borders(Borders::TOP | Borders::BOTTOM)
-                    .title(self.title.as_str()),
+                    .title(title),
             )
             .highlight_style(
                 Style::default()
-                    .bg(Color::White)
-                    .fg(Color::Black)
+                    .bg(self.theme.highlight_background)
+                    .fg(self.theme.highlight_foreground)
                     .add_modifier(Modifier::BOLD),
             )
             .highlight_symbol("-> ");
 
-        let selected_element = match self.table.state.selected() {
+        let selected_element = match self.table.selected_element_index() {
             Some(index) => self.database.get_element(index),
             None => None,
         };
@@ -256,39 +977,232 @@ impl<'a> App<'a> {
                 "
             Type: {}
             Algorithm: {}
+            Period: {}
             Counter: {}
             Pin: {}
+            Tags: {}
+            Domains: {}
+            Note: {}
+            Recovery codes: {}
             ",
                 element.type_,
                 element.algorithm,
+                match element.remaining_seconds() {
+                    Some(_) if element.time_offset() != 0 => {
+                        format!("{}s from {}", element.period, element.time_offset())
+                    }
+                    Some(_) => format!("{}s", element.period),
+                    None => String::from("N/A"),
+                },
                 element
                     .counter
                     .map(|e| e.to_string())
                     .unwrap_or_else(|| String::from("N/A")),
-                element.pin.clone().unwrap_or_else(|| String::from("N/A"))
+                element.pin.clone().unwrap_or_else(|| String::from("N/A")),
+                if element.tags.is_empty() {
+                    String::from("N/A")
+                } else {
+                    element.tags.join(", ")
+                },
+                if element.domains.is_empty() {
+                    String::from("N/A")
+                } else {
+                    element.domains.join(", ")
+                },
+                if element.note.is_empty() {
+                    "N/A"
+                } else {
+                    &element.note
+                },
+                match element.recovery_codes.len() {
+                    0 => String::from("N/A"),
+                    total => format!(
+                        "{} unused of {total}",
+                        element
+                            .recovery_codes
+                            .iter()
+                            .filter(|c| !c.is_used())
+                            .count()
+                    ),
+                }
             )
         } else {
             String::from("")
         };
 
-        text.push_str(
+        text.push_str(&format!(
             "
         
-        Press I to get help
+        {}
         ",
-        );
+            tr_args("status-help", &[("keys", &self.keymap.keys(Action::Help))])
+        ));
         let paragraph = Paragraph::new(text)
-            .block(Block::default().title("Code info").borders(Borders::ALL))
-            .style(Style::default().fg(Color::White).bg(Color::Reset))
+            .block(
+                Block::default()
+                    .title(tr("popup-code-info"))
+                    .borders(Borders::ALL),
+            )
+            .style(self.theme.text())
             .alignment(Alignment::Left)
             .wrap(Wrap { trim: true });
+        self.table_area = chunks[0];
         frame.render_stateful_widget(t, chunks[0], &mut self.table.state);
-        if self.is_large_application(frame) {
+        if self.detail_pane {
+            frame.render_widget(self.detail_pane_paragraph(chunks[1].width), chunks[1]);
+        } else if self.is_large_application(frame) {
             frame.render_widget(paragraph, chunks[1]);
         }
     }
 
+    /// Every detail of the selected code, below its code in big digits if they fit in the pane
+    fn detail_pane_paragraph(&self, width: u16) -> Paragraph<'_> {
+        let block = Block::default()
+            .title(tr("popup-details"))
+            .borders(Borders::ALL);
+        let selected = self
+            .table
+            .state
+            .selected()
+            .and_then(|i| self.table.items.get(i));
+        let Some((row, element)) =
+            selected.and_then(|row| Some((row, self.database.get_element(row.element_index)?)))
+        else {
+            return Paragraph::new(tr("no-element-selected"))
+                .block(block)
+                .style(self.theme.text())
+                .alignment(Alignment::Center);
+        };
+
+        let code = if self.hide_codes && self.revealed != Some(row.element_index) {
+            String::from(HIDDEN_CODE)
+        } else {
+            row.shown_code()
+        };
+        let code = code.as_str();
+        let code_style = Style::default()
+            .fg(self.theme.accent)
+            .add_modifier(Modifier::BOLD);
+        let mut lines = vec![Line::from("")];
+        match big_code(code).filter(|big| big[0].chars().count() + 2 <= width as usize) {
+            Some(big) => lines.extend(big.into_iter().map(|line| Line::styled(line, code_style))),
+            None => lines.push(Line::styled(code.to_owned(), code_style)),
+        }
+        lines.push(Line::from(match element.remaining_seconds() {
+            Some(seconds) => tr_args("detail-expires", &[("seconds", &seconds.to_string())]),
+            None => String::new(),
+        }));
+        lines.push(Line::from(""));
+
+        let or_na = |value: String| {
+            if value.is_empty() {
+                tr("not-available").to_owned()
+            } else {
+                value
+            }
+        };
+        let details = [
+            ("column-issuer", or_na(element.issuer.to_owned())),
+            ("column-label", or_na(element.label.to_owned())),
+            ("column-type", element.type_.to_string()),
+            ("field-algorithm", element.algorithm.to_string()),
+            ("field-digits", element.digits.to_string()),
+            (
+                "field-period",
+                match element.remaining_seconds() {
+                    Some(_) if element.time_offset() != 0 => tr_args(
+                        "field-period-from",
+                        &[
+                            ("period", &element.period.to_string()),
+                            ("t0", &element.time_offset().to_string()),
+                        ],
+                    ),
+                    Some(_) => format!("{}s", element.period),
+                    None => tr("not-available").to_owned(),
+                },
+            ),
+            (
+                "field-counter",
+                or_na(element.counter.map(|c| c.to_string()).unwrap_or_default()),
+            ),
+            ("column-tags", or_na(element.tags.join(", "))),
+            ("field-domains", or_na(element.domains.join(", "))),
+            ("field-note", or_na(element.note.to_owned())),
+            (
+                "field-last-used",
+                match element.last_used {
+                    0 => tr("field-never").to_owned(),
+                    time => format_timestamp(time),
+                },
+            ),
+            ("field-times-used", element.usage_count.to_string()),
+            (
+                "field-added",
+                match element.created_at {
+                    0 => tr("not-available").to_owned(),
+                    time => format_timestamp(time),
+                },
+            ),
+        ];
+        lines.extend(details.into_iter().map(|(name, value)| {
+            Line::from(vec![
+                Span::styled(
+                    format!("{:>10}: ", tr(name)),
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+                Span::raw(value),
+            ])
+            .alignment(Alignment::Left)
+        }));
+        Paragraph::new(lines)
+            .block(block)
+            .style(self.theme.text())
+            .alignment(Alignment::Center)
+            .wrap(Wrap { trim: false })
+    }
+
+    /// Period and T0 of the selected time based code, or the default ones
+    fn selected_period(&self) -> (u64, i64) {
+        self.table
+            .selected_element_index()
+            .and_then(|index| self.database.get_element(index))
+            .filter(|element| element.remaining_seconds().is_some())
+            .map_or((DEFAULT_PERIOD, 0), |element| {
+                (element.period, element.time_offset())
+            })
+    }
+
+    /// Returns the table row drawn at the given terminal position
+    pub(crate) fn table_row_at(&self, column: u16, row: u16) -> Option<usize> {
+        let area = self.table_area;
+        let first_row = area.y + TABLE_HEADER_HEIGHT;
+        // The bottom border is the last line of the area
+        if column < area.x
+            || column >= area.x + area.width
+            || row < first_row
+            || row + 1 >= area.y + area.height
+        {
+            return None;
+        }
+        self.table.row_at(row - first_row)
+    }
+
     fn is_large_application(&self, frame: &mut Frame<'_>) -> bool {
         frame.size().width >= LARGE_APPLICATION_WIDTH
     }
 }
+
+/// The otpauth URI with the secret and the pin replaced by dots
+fn mask_uri(uri: &str) -> String {
+    let Some((base, query)) = uri.split_once('?') else {
+        return uri.to_owned();
+    };
+    let query: Vec<String> = query
+        .split('&')
+        .map(|pair| match pair.split_once('=') {
+            Some((key @ ("secret" | "pin"), _)) => format!("{key}=••••••"),
+            _ => pair.to_owned(),
+        })
+        .collect();
+    format!("{base}?{}", query.join("&"))
+}
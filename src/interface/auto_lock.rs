@@ -0,0 +1,35 @@
+use std::time::{Duration, Instant};
+
+use zeroize::Zeroizing;
+
+/// Locks the dashboard after a period without input, the database key is wiped until the password is typed again
+pub struct AutoLock {
+    pub(crate) timeout: Duration,
+    last_activity: Instant,
+    /// Typed in the lock screen
+    pub(crate) password: Zeroizing<String>,
+    /// Reason of the last failed unlock
+    pub(crate) error: Option<String>,
+    /// Ctrl-C was pressed once with unsaved changes, pressing it again discards them
+    pub(crate) quit_requested: bool,
+}
+
+impl AutoLock {
+    pub fn new(timeout: Duration) -> Self {
+        AutoLock {
+            timeout,
+            last_activity: Instant::now(),
+            password: Zeroizing::default(),
+            error: None,
+            quit_requested: false,
+        }
+    }
+
+    pub fn record_activity(&mut self) {
+        self.last_activity = Instant::now();
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.last_activity.elapsed() >= self.timeout
+    }
+}
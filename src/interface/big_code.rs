@@ -0,0 +1,64 @@
+/// Digits drawn with blocks, five lines tall, to read the code from afar
+const BIG_DIGITS: [[&str; 5]; 10] = [
+    ["███", "█ █", "█ █", "█ █", "███"],
+    [" █ ", "██ ", " █ ", " █ ", "███"],
+    ["███", "  █", "███", "█  ", "███"],
+    ["███", "  █", "███", "  █", "███"],
+    ["█ █", "█ █", "███", "  █", "  █"],
+    ["███", "█  ", "███", "  █", "███"],
+    ["███", "█  ", "███", "█ █", "███"],
+    ["███", "  █", "  █", "  █", "  █"],
+    ["███", "█ █", "███", "█ █", "███"],
+    ["███", "█ █", "███", "  █", "███"],
+];
+
+pub(crate) const BIG_CODE_HEIGHT: usize = 5;
+
+/// Lines drawing the code in big digits, None if it contains other characters like the Steam codes
+pub(crate) fn big_code(code: &str) -> Option<[String; BIG_CODE_HEIGHT]> {
+    let mut lines: [String; BIG_CODE_HEIGHT] = Default::default();
+    for (i, c) in code.chars().enumerate() {
+        let glyph = match c {
+            ' ' => ["   "; BIG_CODE_HEIGHT],
+            _ => BIG_DIGITS[c.to_digit(10)? as usize],
+        };
+        for (line, part) in lines.iter_mut().zip(glyph) {
+            if i > 0 {
+                line.push(' ');
+            }
+            line.push_str(part);
+        }
+    }
+    Some(lines)
+}
+
+/// Enlarges the big digits, each block becoming a square of blocks as large as the factor
+pub(crate) fn scale(lines: &[String], factor: usize) -> Vec<String> {
+    lines
+        .iter()
+        .flat_map(|line| {
+            let scaled: String = line
+                .chars()
+                .flat_map(|c| std::iter::repeat_n(c, factor))
+                .collect();
+            std::iter::repeat_n(scaled, factor)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{big_code, scale};
+
+    #[test]
+    fn test_big_code() {
+        let lines = big_code("17").unwrap();
+        assert_eq!(" █  ███", lines[0]);
+        assert_eq!("███   █", lines[4]);
+        assert!(big_code("5XK2B").is_none());
+
+        let scaled = scale(&lines, 2);
+        assert_eq!(10, scaled.len());
+        assert_eq!("  ██    ██████", scaled[1]);
+    }
+}
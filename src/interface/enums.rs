@@ -1,20 +1,97 @@
+use std::fmt;
+
 #[derive(Eq, PartialEq, Debug)]
 pub enum Focus {
     MainPage,
     SearchBar,
     Popup,
+    /// The password is typed again before a protected action
+    PasswordPrompt,
 }
 
 #[derive(Eq, PartialEq, Debug)]
 pub enum PopupAction {
     EditOtp,
     DeleteOtp,
+    /// The tag added to or removed from the codes is typed in the popup
+    TagCodes,
+    /// The path of the exported codes is typed in the popup
+    ExportCodes,
+    /// The exported file already exists, its path is kept in the input of the popup
+    OverwriteExport,
+    /// The path of the QR code image is typed in the popup
+    SaveQrCode,
+    /// The columns of the table are shown or hidden by their number
+    Columns,
     GeneralInfo,
     SaveBeforeQuit,
 }
 
+/// Order in which the codes are shown in the main table
+#[derive(Eq, PartialEq, Debug, Clone, Copy)]
+pub enum SortMode {
+    Issuer,
+    Label,
+    /// Most recently added first
+    CreationTime,
+    /// Most copied first
+    UsageCount,
+    /// Most recently copied first
+    LastUsed,
+    /// Same order as the database, elements can be moved up and down
+    Manual,
+}
+
+impl SortMode {
+    pub fn next(self) -> Self {
+        match self {
+            SortMode::Issuer => SortMode::Label,
+            SortMode::Label => SortMode::CreationTime,
+            SortMode::CreationTime => SortMode::UsageCount,
+            SortMode::UsageCount => SortMode::LastUsed,
+            SortMode::LastUsed => SortMode::Manual,
+            SortMode::Manual => SortMode::Issuer,
+        }
+    }
+
+    /// Sort mode of the configuration file, like usage-count
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "issuer" => Some(SortMode::Issuer),
+            "label" => Some(SortMode::Label),
+            "creation-time" => Some(SortMode::CreationTime),
+            "usage-count" => Some(SortMode::UsageCount),
+            "last-used" => Some(SortMode::LastUsed),
+            "manual" => Some(SortMode::Manual),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for SortMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let to_string = match self {
+            SortMode::Issuer => "Issuer",
+            SortMode::Label => "Label",
+            SortMode::CreationTime => "Creation time",
+            SortMode::UsageCount => "Usage count",
+            SortMode::LastUsed => "Recently used",
+            SortMode::Manual => "Manual",
+        };
+        write!(f, "{to_string}")
+    }
+}
+
 #[derive(Eq, PartialEq, Debug)]
 pub enum Page {
     Main,
     Qrcode,
+    /// Previous, current and next codes of the selected element
+    NearbyCodes,
+    /// Recovery codes of the selected element
+    RecoveryCodes,
+    /// Add or edit a code
+    Form,
+    /// Code of the selected element filling the screen, to read it from afar
+    Presentation,
 }
@@ -16,6 +16,7 @@ pub enum Event {
     /// Mouse click/scroll.
     Mouse(MouseEvent),
     /// Terminal resize.
+    #[allow(dead_code)]
     Resize(u16, u16),
     /// Focus gained
     FocusGained(),
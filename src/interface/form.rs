@@ -0,0 +1,406 @@
+use clap::ValueEnum;
+use cotp::config::DefaultsConfig;
+use cotp::otp::algorithms::ocra_maker::OcraSuite;
+use cotp::otp::{otp_algorithm::OTPAlgorithm, otp_element::OTPElement, otp_type::OTPType};
+use cotp::utils::url_host;
+use zeroize::Zeroize;
+
+use crate::i18n::{tr, tr_args};
+
+/// Greater values would overflow the HOTP code truncation
+const MAX_DIGITS: u64 = 9;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum FormField {
+    Issuer,
+    Label,
+    Secret,
+    Type,
+    Algorithm,
+    Digits,
+    Period,
+    Counter,
+    Pin,
+    Suite,
+    Domains,
+    Note,
+}
+
+impl FormField {
+    pub fn name(&self) -> &'static str {
+        tr(match self {
+            FormField::Issuer => "column-issuer",
+            FormField::Label => "column-label",
+            FormField::Secret => "field-secret",
+            FormField::Type => "column-type",
+            FormField::Algorithm => "field-algorithm",
+            FormField::Digits => "field-digits",
+            FormField::Period => "field-period",
+            FormField::Counter => "field-counter",
+            FormField::Pin => "field-pin",
+            FormField::Suite => "field-suite",
+            FormField::Domains => "field-domains",
+            FormField::Note => "field-note",
+        })
+    }
+
+    /// Fields whose value is chosen from a list instead of typed
+    pub fn is_choice(&self) -> bool {
+        matches!(self, FormField::Type | FormField::Algorithm)
+    }
+}
+
+/// State of the page used to add or edit a code inside the dashboard
+pub struct Form {
+    /// Edited element and its index, None when adding a new one
+    pub edited: Option<(usize, OTPElement)>,
+    pub selected: FormField,
+    pub error: Option<String>,
+    /// Digits and period chosen when the type of a new code changes
+    defaults: DefaultsConfig,
+    issuer: String,
+    label: String,
+    secret: String,
+    otp_type: OTPType,
+    algorithm: OTPAlgorithm,
+    digits: String,
+    period: String,
+    counter: String,
+    pin: String,
+    suite: String,
+    /// Comma separated websites of the code
+    domains: String,
+    note: String,
+}
+
+impl Form {
+    pub fn add(defaults: DefaultsConfig) -> Self {
+        Form {
+            edited: None,
+            selected: FormField::Issuer,
+            error: None,
+            defaults,
+            issuer: String::new(),
+            label: String::new(),
+            secret: String::new(),
+            otp_type: OTPType::Totp,
+            algorithm: OTPAlgorithm::Sha1,
+            digits: defaults.digits.to_string(),
+            period: defaults.period.to_string(),
+            counter: String::from("0"),
+            pin: String::new(),
+            suite: String::new(),
+            domains: String::new(),
+            note: String::new(),
+        }
+    }
+
+    pub fn edit(index: usize, element: &OTPElement) -> Self {
+        Form {
+            edited: Some((index, element.clone())),
+            selected: FormField::Issuer,
+            error: None,
+            defaults: DefaultsConfig::default(),
+            issuer: element.issuer.to_owned(),
+            label: element.label.to_owned(),
+            secret: element.secret.to_owned(),
+            otp_type: element.type_,
+            algorithm: element.algorithm,
+            digits: element.digits.to_string(),
+            period: element.period.to_string(),
+            counter: element.counter.unwrap_or(0).to_string(),
+            pin: element.pin.to_owned().unwrap_or_default(),
+            suite: element.ocra_suite.to_owned().unwrap_or_default(),
+            domains: element.domains.join(", "),
+            note: element.note.to_owned(),
+        }
+    }
+
+    pub fn title(&self) -> &'static str {
+        if self.edited.is_some() {
+            tr("form-edit")
+        } else {
+            tr("form-add")
+        }
+    }
+
+    /// Fields shown for the current OTP type
+    pub fn fields(&self) -> Vec<FormField> {
+        let mut fields = vec![
+            FormField::Issuer,
+            FormField::Label,
+            FormField::Secret,
+            FormField::Type,
+        ];
+        // The algorithm and digits of OCRA codes are part of the suite
+        if self.otp_type == OTPType::Ocra {
+            fields.extend([FormField::Suite, FormField::Counter, FormField::Pin]);
+        } else {
+            fields.extend([FormField::Algorithm, FormField::Digits, FormField::Period]);
+        }
+        match self.otp_type {
+            OTPType::Hotp => fields.push(FormField::Counter),
+            OTPType::Yandex | OTPType::Motp => fields.push(FormField::Pin),
+            _ => {}
+        }
+        fields.extend([FormField::Domains, FormField::Note]);
+        fields
+    }
+
+    pub fn value(&self, field: FormField) -> String {
+        match field {
+            FormField::Type => self.otp_type.to_string(),
+            FormField::Algorithm => self.algorithm.to_string(),
+            _ => self.text(field).map(|t| t.to_owned()).unwrap_or_default(),
+        }
+    }
+
+    fn text(&self, field: FormField) -> Option<&String> {
+        match field {
+            FormField::Issuer => Some(&self.issuer),
+            FormField::Label => Some(&self.label),
+            FormField::Secret => Some(&self.secret),
+            FormField::Digits => Some(&self.digits),
+            FormField::Period => Some(&self.period),
+            FormField::Counter => Some(&self.counter),
+            FormField::Pin => Some(&self.pin),
+            FormField::Suite => Some(&self.suite),
+            FormField::Domains => Some(&self.domains),
+            FormField::Note => Some(&self.note),
+            FormField::Type | FormField::Algorithm => None,
+        }
+    }
+
+    fn text_mut(&mut self, field: FormField) -> Option<&mut String> {
+        match field {
+            FormField::Issuer => Some(&mut self.issuer),
+            FormField::Label => Some(&mut self.label),
+            FormField::Secret => Some(&mut self.secret),
+            FormField::Digits => Some(&mut self.digits),
+            FormField::Period => Some(&mut self.period),
+            FormField::Counter => Some(&mut self.counter),
+            FormField::Pin => Some(&mut self.pin),
+            FormField::Suite => Some(&mut self.suite),
+            FormField::Domains => Some(&mut self.domains),
+            FormField::Note => Some(&mut self.note),
+            FormField::Type | FormField::Algorithm => None,
+        }
+    }
+
+    pub fn next_field(&mut self, forward: bool) {
+        let fields = self.fields();
+        let position = fields.iter().position(|f| *f == self.selected).unwrap_or(0);
+        let next = if forward {
+            (position + 1) % fields.len()
+        } else {
+            (position + fields.len() - 1) % fields.len()
+        };
+        self.selected = fields[next];
+    }
+
+    pub fn push_char(&mut self, c: char) {
+        let selected = self.selected;
+        if let Some(text) = self.text_mut(selected) {
+            text.push(c);
+        } else {
+            self.cycle(true);
+        }
+    }
+
+    pub fn pop_char(&mut self) {
+        let selected = self.selected;
+        if let Some(text) = self.text_mut(selected) {
+            text.pop();
+        }
+    }
+
+    /// Chooses the next or previous value of the selected choice field
+    pub fn cycle(&mut self, forward: bool) {
+        match self.selected {
+            FormField::Type => {
+                self.otp_type = cycle_value(OTPType::value_variants(), self.otp_type, forward);
+                // Use the defaults of the new type, unless the code already exists
+                if self.edited.is_none() {
+                    self.digits = match self.otp_type {
+                        OTPType::Steam => 5,
+                        OTPType::Yandex => 8,
+                        _ => self.defaults.digits,
+                    }
+                    .to_string();
+                    self.period = match self.otp_type {
+                        OTPType::Motp => 10,
+                        _ => self.defaults.period,
+                    }
+                    .to_string();
+                }
+            }
+            FormField::Algorithm => {
+                self.algorithm =
+                    cycle_value(OTPAlgorithm::value_variants(), self.algorithm, forward)
+            }
+            _ => {}
+        }
+    }
+
+    /// Validates the inserted values, building the resulting element
+    pub fn to_element(&self) -> Result<OTPElement, String> {
+        if self.label.trim().is_empty() {
+            return Err(tr("form-label-required").to_owned());
+        }
+        let secret: String = self.secret.chars().filter(|c| !c.is_whitespace()).collect();
+        let secret = match self.otp_type {
+            OTPType::Motp => secret.to_lowercase(),
+            _ => secret.to_uppercase().trim_end_matches('=').to_owned(),
+        };
+        let digits = self
+            .digits
+            .parse::<u64>()
+            .ok()
+            .filter(|d| (1..=MAX_DIGITS).contains(d))
+            .ok_or_else(|| tr_args("form-invalid-digits", &[("max", &MAX_DIGITS.to_string())]))?;
+        let period = self
+            .period
+            .parse::<u64>()
+            .ok()
+            .filter(|p| *p > 0)
+            .ok_or(tr("form-invalid-period"))?;
+        let suite = match self.otp_type {
+            OTPType::Ocra => Some(
+                self.suite
+                    .trim()
+                    .parse::<OcraSuite>()
+                    .map_err(|e| e.to_string())?,
+            ),
+            _ => None,
+        };
+        let counter = match self.otp_type {
+            OTPType::Ocra if !suite.as_ref().is_some_and(|s| s.uses_counter) => None,
+            OTPType::Hotp | OTPType::Ocra => Some(
+                self.counter
+                    .parse::<u64>()
+                    .map_err(|_| tr("form-invalid-counter"))?,
+            ),
+            _ => None,
+        };
+        let pin = match self.otp_type {
+            OTPType::Yandex | OTPType::Motp if self.pin.is_empty() => {
+                return Err(tr("form-pin-required").to_owned())
+            }
+            OTPType::Yandex | OTPType::Motp => Some(self.pin.to_owned()),
+            OTPType::Ocra if suite.as_ref().is_some_and(|s| s.pin_hash.is_some()) => {
+                Some(self.pin.to_owned()).filter(|p| !p.is_empty())
+            }
+            _ => None,
+        };
+        let domains = self
+            .domains
+            .split(',')
+            .filter(|d| !d.trim().is_empty())
+            .map(|d| {
+                url_host(d).ok_or_else(|| tr_args("form-invalid-domain", &[("domain", d.trim())]))
+            })
+            .collect::<Result<Vec<String>, String>>()?;
+        // Values which are not shown in the form are kept from the edited element
+        let mut element = match &self.edited {
+            Some((_, edited)) => edited.clone(),
+            None => OTPElement::default(),
+        };
+        element.secret = secret;
+        element.issuer = self.issuer.trim().to_owned();
+        element.label = self.label.trim().to_owned();
+        element.digits = suite.as_ref().map_or(digits, |s| s.digits);
+        element.type_ = self.otp_type;
+        element.algorithm = suite.as_ref().map_or(self.algorithm, |s| s.algorithm);
+        element.period = period;
+        element.counter = counter;
+        element.pin = pin;
+        element.ocra_suite = suite.map(|_| self.suite.trim().to_owned());
+        element.domains = domains;
+        element.note = self.note.trim().to_owned();
+        // OCRA responses need a challenge, so only their secret is checked
+        let valid_code = element.type_ == OTPType::Ocra || element.get_otp_code().is_ok();
+        if !element.valid_secret() || !valid_code {
+            return Err(tr("form-invalid-secret").to_owned());
+        }
+        Ok(element)
+    }
+}
+
+impl Drop for Form {
+    /// The typed secret and PIN are wiped when the page is closed
+    fn drop(&mut self) {
+        self.secret.zeroize();
+        self.pin.zeroize();
+    }
+}
+
+fn cycle_value<T: Copy + PartialEq>(values: &[T], current: T, forward: bool) -> T {
+    let position = values.iter().position(|v| *v == current).unwrap_or(0);
+    let next = if forward {
+        (position + 1) % values.len()
+    } else {
+        (position + values.len() - 1) % values.len()
+    };
+    values[next]
+}
+
+#[cfg(test)]
+mod tests {
+    use cotp::config::DefaultsConfig;
+    use cotp::otp::otp_type::OTPType;
+
+    use super::{Form, FormField};
+
+    #[test]
+    fn test_add_form_validation() {
+        let mut form = Form::add(DefaultsConfig::default());
+        for c in "Example".chars() {
+            form.push_char(c);
+        }
+        assert_eq!(Err(String::from("Label is required")), form.to_element());
+
+        form.next_field(true);
+        "alice".chars().for_each(|c| form.push_char(c));
+        form.next_field(true);
+        "jbsw y3dp ehpk 3pxp"
+            .chars()
+            .for_each(|c| form.push_char(c));
+        form.next_field(true);
+        assert_eq!(FormField::Type, form.selected);
+        form.cycle(true);
+
+        let element = form.to_element().unwrap();
+        assert_eq!(OTPType::Hotp, element.type_);
+        assert_eq!("JBSWY3DPEHPK3PXP", element.secret);
+        assert_eq!(Some(0), element.counter);
+        assert!(form.fields().contains(&FormField::Counter));
+
+        form.selected = FormField::Domains;
+        "GitHub.com, https://gist.github.com/"
+            .chars()
+            .for_each(|c| form.push_char(c));
+        assert_eq!(
+            vec!["github.com", "gist.github.com"],
+            form.to_element().unwrap().domains
+        );
+        ",a b".chars().for_each(|c| form.push_char(c));
+        assert_eq!(
+            Err(String::from("Invalid domain \"a b\"")),
+            form.to_element()
+        );
+        (0..4).for_each(|_| form.pop_char());
+
+        form.selected = FormField::Note;
+        " Recovery codes in the safe "
+            .chars()
+            .for_each(|c| form.push_char(c));
+        assert_eq!(
+            "Recovery codes in the safe",
+            form.to_element().unwrap().note
+        );
+
+        form.selected = FormField::Secret;
+        form.push_char('!');
+        assert_eq!(Err(String::from("Invalid secret")), form.to_element());
+    }
+}
@@ -0,0 +1,260 @@
+//! QR codes drawn as images by the terminals supporting the kitty graphics protocol, the iTerm2 inline images
+//! or sixel, since the unicode blocks are broken by some fonts.
+
+use std::fmt;
+use std::io::Cursor;
+
+use base64::{engine::general_purpose, Engine as _};
+use cotp::exporters::qr_png::render_qr_code;
+use image::imageops::{self, FilterType};
+use image::{GrayImage, ImageFormat};
+use ratatui::layout::Rect;
+use zeroize::Zeroizing;
+
+/// Pixels of a cell when the terminal does not tell them
+const DEFAULT_CELL_SIZE: (u32, u32) = (10, 20);
+/// Kitty reads the base64 image in chunks at most this long
+const KITTY_CHUNK_SIZE: usize = 4096;
+/// Deletes every image drawn with the kitty graphics protocol
+const KITTY_DELETE_IMAGES: &str = "\x1b_Ga=d\x1b\\";
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GraphicsProtocol {
+    Kitty,
+    Iterm2,
+    Sixel,
+    /// Blocks of characters, working everywhere
+    Unicode,
+}
+
+impl GraphicsProtocol {
+    /// Protocol of the configuration file, auto to detect it
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "auto" => Some(GraphicsProtocol::detect()),
+            "kitty" => Some(GraphicsProtocol::Kitty),
+            "iterm2" => Some(GraphicsProtocol::Iterm2),
+            "sixel" => Some(GraphicsProtocol::Sixel),
+            "unicode" => Some(GraphicsProtocol::Unicode),
+            _ => None,
+        }
+    }
+
+    /// Guesses the protocol from the environment variables set by the terminals
+    pub fn detect() -> Self {
+        detect_from(|name| std::env::var(name).ok())
+    }
+}
+
+fn detect_from(var: impl Fn(&str) -> Option<String>) -> GraphicsProtocol {
+    let term = var("TERM").unwrap_or_default();
+    let term_program = var("TERM_PROGRAM").unwrap_or_default();
+    // Multiplexers do not pass the images through
+    if var("TMUX").is_some() || term.starts_with("screen") {
+        GraphicsProtocol::Unicode
+    } else if var("KITTY_WINDOW_ID").is_some() || term == "xterm-kitty" || term == "xterm-ghostty" {
+        GraphicsProtocol::Kitty
+    } else if ["iTerm.app", "WezTerm"].contains(&term_program.as_str())
+        || var("LC_TERMINAL").as_deref() == Some("iTerm2")
+    {
+        GraphicsProtocol::Iterm2
+    } else if term.contains("sixel") || term.starts_with("foot") || term == "mlterm" {
+        GraphicsProtocol::Sixel
+    } else {
+        GraphicsProtocol::Unicode
+    }
+}
+
+/// Image written into the terminal after the widgets, at the top left corner of its area
+#[derive(PartialEq, Eq)]
+pub(crate) struct Graphic {
+    pub(crate) area: Rect,
+    /// Escape sequence drawing the image, containing the secret encoded in the QR code
+    pub(crate) sequence: Zeroizing<String>,
+    protocol: GraphicsProtocol,
+}
+
+/// Leaves out the sequence, which encodes the secret
+impl fmt::Debug for Graphic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Graphic")
+            .field("area", &self.area)
+            .field("protocol", &self.protocol)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Graphic {
+    /// Escape sequence removing the image, the others are removed by drawing over them
+    pub(crate) fn erase_sequence(&self) -> Option<&'static str> {
+        (self.protocol == GraphicsProtocol::Kitty).then_some(KITTY_DELETE_IMAGES)
+    }
+}
+
+/// The QR code of the content as large as it fits in the area, None for the unicode protocol
+pub(crate) fn qr_graphic(protocol: GraphicsProtocol, content: &str, area: Rect) -> Option<Graphic> {
+    if protocol == GraphicsProtocol::Unicode || area.width == 0 || area.height == 0 {
+        return None;
+    }
+    let (cell_width, cell_height) = cell_size();
+    // Cells are not square, so the image takes fewer columns than rows
+    let side = (u32::from(area.width) * cell_width).min(u32::from(area.height) * cell_height);
+    let columns = (side / cell_width) as u16;
+    let rows = (side / cell_height) as u16;
+    let area = Rect::new(
+        area.x + (area.width - columns) / 2,
+        area.y + (area.height - rows) / 2,
+        columns,
+        rows,
+    );
+    let image = render_qr_code(content).ok()?;
+    let sequence = match protocol {
+        GraphicsProtocol::Kitty => kitty_sequence(&png(&image)?, columns, rows),
+        GraphicsProtocol::Iterm2 => iterm2_sequence(&png(&image)?, columns, rows),
+        GraphicsProtocol::Sixel => {
+            sixel_sequence(&imageops::resize(&image, side, side, FilterType::Nearest))
+        }
+        GraphicsProtocol::Unicode => return None,
+    };
+    Some(Graphic {
+        area,
+        sequence: Zeroizing::new(sequence),
+        protocol,
+    })
+}
+
+/// Width and height in pixels of the terminal cells
+fn cell_size() -> (u32, u32) {
+    crossterm::terminal::window_size()
+        .ok()
+        .filter(|size| size.width > 0 && size.height > 0 && size.columns > 0 && size.rows > 0)
+        .map_or(DEFAULT_CELL_SIZE, |size| {
+            (
+                u32::from(size.width / size.columns),
+                u32::from(size.height / size.rows),
+            )
+        })
+}
+
+fn png(image: &GrayImage) -> Option<Zeroizing<Vec<u8>>> {
+    let mut bytes = Zeroizing::new(Vec::new());
+    image
+        .write_to(&mut Cursor::new(&mut *bytes), ImageFormat::Png)
+        .ok()?;
+    Some(bytes)
+}
+
+/// The PNG image scaled into the cells, sent in chunks
+fn kitty_sequence(png: &[u8], columns: u16, rows: u16) -> String {
+    let encoded = Zeroizing::new(general_purpose::STANDARD.encode(png));
+    let chunks: Vec<&[u8]> = encoded.as_bytes().chunks(KITTY_CHUNK_SIZE).collect();
+    let mut sequence = String::new();
+    for (i, chunk) in chunks.iter().enumerate() {
+        let more = u8::from(i + 1 < chunks.len());
+        // The chunks are base64, so they are valid UTF-8
+        let chunk = std::str::from_utf8(chunk).unwrap_or_default();
+        if i == 0 {
+            sequence.push_str(&format!(
+                "\x1b_Ga=T,f=100,q=2,c={columns},r={rows},m={more};{chunk}\x1b\\"
+            ));
+        } else {
+            sequence.push_str(&format!("\x1b_Gm={more};{chunk}\x1b\\"));
+        }
+    }
+    sequence
+}
+
+fn iterm2_sequence(png: &[u8], columns: u16, rows: u16) -> String {
+    let encoded = Zeroizing::new(general_purpose::STANDARD.encode(png));
+    format!(
+        "\x1b]1337;File=inline=1;size={};width={columns};height={rows};preserveAspectRatio=1:{}\x07",
+        png.len(),
+        encoded.as_str()
+    )
+}
+
+/// Black and white sixel image, each character drawing a column of six pixels
+fn sixel_sequence(image: &GrayImage) -> String {
+    let (width, height) = image.dimensions();
+    let mut sequence = format!("\x1bPq\"1;1;{width};{height}#0;2;0;0;0#1;2;100;100;100");
+    for top in (0..height).step_by(6) {
+        for (color, dark) in [(0, true), (1, false)] {
+            sequence.push_str(&format!("#{color}"));
+            let sixels: Vec<u8> = (0..width)
+                .map(|x| {
+                    (0..6)
+                        .filter(|dy| top + dy < height)
+                        .filter(|dy| (image.get_pixel(x, top + dy)[0] < 128) == dark)
+                        .fold(0, |bits, dy| bits | 1 << dy)
+                })
+                .collect();
+            // Repeated characters are run length encoded
+            for run in sixels.chunk_by(|a, b| a == b) {
+                let c = char::from(63 + run[0]);
+                if run.len() > 3 {
+                    sequence.push_str(&format!("!{}{c}", run.len()));
+                } else {
+                    sequence.extend(std::iter::repeat_n(c, run.len()));
+                }
+            }
+            // Back to the start of the band, to draw the other color
+            sequence.push('$');
+        }
+        sequence.push('-');
+    }
+    sequence.push_str("\x1b\\");
+    sequence
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{GrayImage, Luma};
+
+    use super::{detect_from, kitty_sequence, sixel_sequence, GraphicsProtocol};
+
+    #[test]
+    fn test_detect_protocol() {
+        let detect = |vars: &[(&str, &str)]| {
+            detect_from(|name| {
+                vars.iter()
+                    .find(|(n, _)| *n == name)
+                    .map(|(_, v)| v.to_string())
+            })
+        };
+        assert_eq!(GraphicsProtocol::Kitty, detect(&[("TERM", "xterm-kitty")]));
+        assert_eq!(
+            GraphicsProtocol::Iterm2,
+            detect(&[("TERM_PROGRAM", "iTerm.app")])
+        );
+        assert_eq!(GraphicsProtocol::Sixel, detect(&[("TERM", "foot")]));
+        assert_eq!(
+            GraphicsProtocol::Unicode,
+            detect(&[("TERM", "xterm-kitty"), ("TMUX", "/tmp/tmux")])
+        );
+        assert_eq!(
+            GraphicsProtocol::Unicode,
+            detect(&[("TERM", "xterm-256color")])
+        );
+    }
+
+    #[test]
+    fn test_kitty_chunks() {
+        let sequence = kitty_sequence(&[0; 4000], 10, 5);
+        assert!(sequence.starts_with("\x1b_Ga=T,f=100,q=2,c=10,r=5,m=1;AAAA"));
+        assert!(sequence.contains("\x1b\\\x1b_Gm=0;"));
+        assert_eq!(2, sequence.matches("\x1b_G").count());
+    }
+
+    #[test]
+    fn test_sixel() {
+        // Black first column, white second one
+        let image = GrayImage::from_fn(2, 7, |x, _| Luma([if x == 0 { 0 } else { 255 }]));
+        assert_eq!(
+            "\x1bPq\"1;1;2;7#0;2;0;0;0#1;2;100;100;100#0~?$#1?~$-#0@?$#1?@$-\x1b\\",
+            sixel_sequence(&image)
+        );
+
+        let image = GrayImage::from_pixel(5, 1, Luma([0]));
+        assert!(sixel_sequence(&image).contains("#0!5@$#1!5?$"));
+    }
+}
@@ -1,33 +1,244 @@
-use crate::clipboard::{copy_string_to_clipboard, CopyType};
-use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use crate::clipboard::{copy_string_to_clipboard, read_clipboard, CopyType, ScheduledClear};
+use std::path::Path;
+use std::time::{Duration, Instant};
+use zeroize::{Zeroize, Zeroizing};
 
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
+
+use crate::hardening;
+use crate::i18n::{tr, tr_args};
 use crate::interface::app::{App, AppResult};
 use crate::interface::enums::Page::*;
-use crate::otp::otp_type::OTPType;
+use cotp::exporters::do_export;
+use cotp::exporters::qr_png::{qr_image_name, render_qr_code};
+use cotp::otp::audit::{audit_element, Finding};
+use cotp::otp::otp_element::{same_tag, OTPDatabase, OTPElement};
+use cotp::otp::otp_type::OTPType;
+use cotp::search::Regex;
 
 use super::app::Popup;
 use super::enums::Page;
+use super::enums::SortMode;
 use super::enums::{Focus, PopupAction};
+use super::form::Form as CodeForm;
+use super::journal::Operation;
+use super::keymap::Action;
+use super::layout::Column;
+use super::password_prompt::ProtectedAction;
+use super::text_input::TextInput;
 
 /// Handles the key events and updates the state of [`App`].
 pub fn handle_key_events(key_event: KeyEvent, app: &mut App) -> AppResult<()> {
+    if let Some(auto_lock) = &mut app.auto_lock {
+        auto_lock.record_activity();
+    }
+    if app.is_locked() {
+        lock_screen_handler(key_event, app);
+        return Ok(());
+    }
+    if app.current_page == Form && app.focus != Focus::Popup {
+        form_handler(key_event, app);
+        return Ok(());
+    }
     match app.focus {
         Focus::MainPage => main_handler(key_event, app),
         Focus::SearchBar => search_bar_handler(key_event, app),
         Focus::Popup => popup_handler(key_event, app),
+        Focus::PasswordPrompt => password_prompt_handler(key_event, app),
+    }
+    Ok(())
+}
+
+/// Inserts the pasted text into the search bar, the popup input or the form, all at once
+pub fn handle_paste_event(text: &str, app: &mut App) {
+    if let Some(auto_lock) = &mut app.auto_lock {
+        auto_lock.record_activity();
+    }
+    if app.is_locked() {
+        return;
+    }
+    match app.focus {
+        Focus::SearchBar => {
+            app.search_query.insert_str(text);
+            search_and_select(app);
+        }
+        Focus::Popup
+            if matches!(
+                app.popup.action,
+                PopupAction::TagCodes | PopupAction::ExportCodes | PopupAction::SaveQrCode
+            ) =>
+        {
+            app.popup.input.insert_str(text)
+        }
+        Focus::MainPage if app.current_page == Form => {
+            if let Some(form) = &mut app.form {
+                text.chars()
+                    .filter(|c| !c.is_control())
+                    .for_each(|c| form.push_char(c));
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Two clicks on the same row within this time copy its code
+const DOUBLE_CLICK_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Handles the mouse events and updates the state of [`App`].
+pub fn handle_mouse_events(mouse_event: MouseEvent, app: &mut App) -> AppResult<()> {
+    if let Some(auto_lock) = &mut app.auto_lock {
+        auto_lock.record_activity();
+    }
+    if app.is_locked() || app.current_page != Main || app.focus == Focus::Popup {
+        return Ok(());
+    }
+    match mouse_event.kind {
+        MouseEventKind::ScrollDown => {
+            app.table.next();
+        }
+        MouseEventKind::ScrollUp => {
+            app.table.previous();
+        }
+        MouseEventKind::Down(MouseButton::Left) => {
+            let (column, row) = (mouse_event.column, mouse_event.row);
+            if app.search_bar_area.contains((column, row).into()) {
+                app.focus = Focus::SearchBar;
+                return Ok(());
+            }
+            app.focus = Focus::MainPage;
+            if let Some(index) = app.table_row_at(column, row) {
+                let double_click = app.last_click.is_some_and(|(time, clicked)| {
+                    clicked == index && time.elapsed() <= DOUBLE_CLICK_INTERVAL
+                });
+                app.table.state.select(Some(index));
+                if double_click {
+                    app.last_click = None;
+                    let result = copy_selected_code_to_clipboard(app);
+                    app.status.show(result);
+                } else {
+                    app.last_click = Some((Instant::now(), index));
+                }
+            }
+        }
+        _ => {}
     }
     Ok(())
 }
 
+fn lock_screen_handler(key_event: KeyEvent, app: &mut App) {
+    let Some(auto_lock) = &mut app.auto_lock else {
+        return;
+    };
+    match key_event.code {
+        KeyCode::Char('c') if key_event.modifiers == KeyModifiers::CONTROL => {
+            // The database is saved on exit only if unlocked, so the unsaved changes need the password
+            if !app.database.is_modified() || auto_lock.quit_requested {
+                app.running = false;
+            } else if auto_lock.password.is_empty() {
+                auto_lock.quit_requested = true;
+                auto_lock.error = Some(tr("lock-quit-unsaved").to_owned());
+            } else {
+                app.unlock();
+                app.running = app.is_locked();
+            }
+        }
+        KeyCode::Char(c) => auto_lock.password.push(c),
+        KeyCode::Backspace => {
+            auto_lock.password.pop();
+        }
+        KeyCode::Esc => auto_lock.password.zeroize(),
+        KeyCode::Enter => app.unlock(),
+        _ => {}
+    }
+}
+
+/// Runs the action if the password was typed recently, otherwise asks it first
+fn run_protected(app: &mut App, action: ProtectedAction) {
+    if app.key.is_some() {
+        if let Some(password_prompt) = &mut app.password_prompt {
+            if !password_prompt.is_confirmed() {
+                password_prompt.action = Some(action);
+                app.focus = Focus::PasswordPrompt;
+                return;
+            }
+        }
+    }
+    match action {
+        ProtectedAction::Delete => show_delete_popup(app),
+        ProtectedAction::Edit => {
+            let edited = app
+                .table
+                .selected_element_index()
+                .and_then(|index| Some((index, app.database.get_element(index)?)));
+            if let Some((index, element)) = edited {
+                app.form = Some(CodeForm::edit(index, element));
+                app.current_page = Form;
+            }
+        }
+        ProtectedAction::QrCode => {
+            handle_switch_page(app, Qrcode);
+            app.qr_guard.open();
+            app.uri_revealed = false;
+        }
+        ProtectedAction::RecoveryCodes => handle_switch_page(app, RecoveryCodes),
+        ProtectedAction::Export => show_input_popup(
+            app,
+            tr("export-path"),
+            PopupAction::ExportCodes,
+            "exported.cotp",
+        ),
+    }
+}
+
+fn password_prompt_handler(key_event: KeyEvent, app: &mut App) {
+    let (Some(password_prompt), Some(key)) = (&mut app.password_prompt, &app.key) else {
+        app.focus = Focus::MainPage;
+        return;
+    };
+    match key_event.code {
+        KeyCode::Char('c') if key_event.modifiers == KeyModifiers::CONTROL => handle_exit(app),
+        KeyCode::Char(c) => password_prompt.password.push(c),
+        KeyCode::Backspace => {
+            password_prompt.password.pop();
+        }
+        KeyCode::Esc => {
+            password_prompt.password.zeroize();
+            password_prompt.forget();
+            app.focus = Focus::MainPage;
+        }
+        KeyCode::Enter => {
+            let result = app
+                .database
+                .is_password_correct(&password_prompt.password, key, app.salt);
+            password_prompt.password.zeroize();
+            match result {
+                Ok(true) => {
+                    password_prompt.confirm();
+                    let action = password_prompt.action.take();
+                    app.focus = Focus::MainPage;
+                    if let Some(action) = action {
+                        run_protected(app, action);
+                    }
+                }
+                Ok(false) => password_prompt.error = Some(tr("password-wrong").to_owned()),
+                Err(e) => password_prompt.error = Some(e.to_string()),
+            }
+        }
+        _ => {}
+    }
+}
+
 fn popup_handler(key_event: KeyEvent, app: &mut App) {
     match app.popup.action {
         PopupAction::EditOtp => todo!(),
         PopupAction::DeleteOtp => match key_event.code {
             KeyCode::Char('y') | KeyCode::Char('Y') => {
-                if let Err(e) = delete_selected_code(app) {
-                    app.popup.text = e;
-                    return;
-                }
+                let result = if app.table.marked.is_empty() {
+                    delete_selected_code(app)
+                } else {
+                    delete_marked_codes(app)
+                };
+                app.status.show(result);
                 app.focus = Focus::MainPage;
                 // Force table render
                 app.tick(true);
@@ -37,8 +248,84 @@ fn popup_handler(key_event: KeyEvent, app: &mut App) {
             }
             _ => {}
         },
+        PopupAction::TagCodes | PopupAction::ExportCodes | PopupAction::SaveQrCode => {
+            match key_event.code {
+                KeyCode::Esc => app.focus = Focus::MainPage,
+                KeyCode::Enter => {
+                    let input = std::mem::take(&mut app.popup.input);
+                    let input = input.value();
+                    if app.popup.action == PopupAction::ExportCodes
+                        && Path::new(input.trim()).exists()
+                    {
+                        show_popup(
+                            Popup {
+                                text: tr_args("export-overwrite", &[("path", input.trim())]).into(),
+                                input: TextInput::new(input.trim()),
+                                percent_x: 60,
+                                percent_y: 20,
+                                scroll: 0,
+                                action: PopupAction::OverwriteExport,
+                            },
+                            app,
+                        );
+                        return;
+                    }
+                    let result = match app.popup.action {
+                        PopupAction::TagCodes => tag_codes(app, input.trim()),
+                        PopupAction::SaveQrCode => {
+                            save_selected_qrcode(app, Path::new(input.trim()))
+                        }
+                        _ => export_codes(app, Path::new(input.trim())),
+                    };
+                    app.status.show(result);
+                    app.focus = Focus::MainPage;
+                }
+                _ => {
+                    app.popup.input.handle_key(key_event);
+                }
+            }
+        }
+        PopupAction::Columns => match key_event.code {
+            KeyCode::Char(c) => {
+                let column = c
+                    .to_digit(10)
+                    .and_then(|n| Column::ALL.get((n as usize).checked_sub(1)?));
+                if let Some(&column) = column {
+                    app.columns.toggle(column);
+                    app.popup.text = columns_menu_text(app).into();
+                }
+            }
+            KeyCode::Esc | KeyCode::Enter => app.focus = Focus::MainPage,
+            _ => {}
+        },
         PopupAction::GeneralInfo => match key_event.code {
-            KeyCode::Char('I') | KeyCode::Char('i') | KeyCode::Esc | KeyCode::Enter => {
+            KeyCode::Esc | KeyCode::Enter => {
+                app.focus = Focus::MainPage;
+            }
+            // Scrolling past the end is limited when rendering, knowing the height of the popup
+            KeyCode::Up | KeyCode::Char('k') => {
+                app.popup.scroll = app.popup.scroll.saturating_sub(1)
+            }
+            KeyCode::Down | KeyCode::Char('j') => {
+                app.popup.scroll = app.popup.scroll.saturating_add(1)
+            }
+            KeyCode::PageUp => app.popup.scroll = app.popup.scroll.saturating_sub(10),
+            KeyCode::Home => app.popup.scroll = 0,
+            KeyCode::PageDown => app.popup.scroll = app.popup.scroll.saturating_add(10),
+            KeyCode::End => app.popup.scroll = u16::MAX,
+            _ if app.keymap.action(key_event) == Some(Action::Help) => {
+                app.focus = Focus::MainPage;
+            }
+            _ => {}
+        },
+        PopupAction::OverwriteExport => match key_event.code {
+            KeyCode::Char('y') | KeyCode::Char('Y') => {
+                let input = std::mem::take(&mut app.popup.input);
+                let result = export_codes(app, Path::new(input.value()));
+                app.status.show(result);
+                app.focus = Focus::MainPage;
+            }
+            KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
                 app.focus = Focus::MainPage;
             }
             _ => {}
@@ -48,7 +335,7 @@ fn popup_handler(key_event: KeyEvent, app: &mut App) {
                 app.running = false;
             }
             KeyCode::Char('n') | KeyCode::Char('N') => {
-                app.database.needs_modification = false;
+                app.database.discard_modifications();
                 app.running = false;
             }
             KeyCode::Esc => {
@@ -59,198 +346,645 @@ fn popup_handler(key_event: KeyEvent, app: &mut App) {
     }
 }
 
+/// The readline keys edit the query, see [`TextInput`]
 fn search_bar_handler(key_event: KeyEvent, app: &mut App) {
+    let control = key_event.modifiers == KeyModifiers::CONTROL;
     match key_event.code {
-        KeyCode::Char(c) => {
-            if key_event.modifiers == KeyModifiers::CONTROL {
-                match c {
-                    'f' | 'F' => {
-                        app.search_query.clear();
-                        app.focus = Focus::MainPage;
-                    }
-                    'c' | 'C' => handle_exit(app),
-                    'w' | 'W' => app.search_query.clear(),
-                    _ => {}
-                }
-            } else {
-                app.search_query.push(c);
-                search_and_select(app);
-            }
+        KeyCode::Char('f' | 'F') if control => {
+            app.search_query.clear();
+            app.focus = Focus::MainPage;
         }
+        KeyCode::Char('c' | 'C') if control => handle_exit(app),
+        // In vim mode Enter confirms the search, so n and N can move through the matches
+        KeyCode::Enter if app.vim_mode => app.focus = Focus::MainPage,
         KeyCode::Enter => {
-            app.label_text = copy_selected_code_to_clipboard(app);
-            app.print_percentage = false;
+            let result = copy_selected_code_to_clipboard(app);
+            app.status.show(result);
             app.focus = Focus::MainPage;
         }
         KeyCode::Esc => {
             app.focus = Focus::MainPage;
         }
-        KeyCode::Backspace => {
-            app.search_query.pop();
-        }
         KeyCode::Up | KeyCode::Down => {
             app.focus = Focus::MainPage;
             main_handler(key_event, app);
         }
-        _ => {}
+        _ => {
+            if app.search_query.handle_key(key_event) {
+                search_and_select(app);
+            }
+        }
     }
 }
 
 fn main_handler(key_event: KeyEvent, app: &mut App) {
-    match key_event.code {
-        // exit application on ESC
-        KeyCode::Esc => {
-            handle_exit(app);
-        }
-        // exit application on Ctrl-D
-        KeyCode::Char('d') | KeyCode::Char('D') | KeyCode::Char('c') => {
-            if key_event.modifiers == KeyModifiers::CONTROL {
-                handle_exit(app);
-            } else if app.table.state.selected().is_some() {
-                // Ask the user if he wants to delete the OTP Code
-                show_popup(
-                    Popup {
-                        text: String::from("Do you want to delete the selected OTP Code? [Y/N]"),
-                        percent_x: 60,
-                        percent_y: 20,
-                        action: PopupAction::DeleteOtp,
-                    },
-                    app,
-                )
-            }
-        }
-        // exit application on Q
-        KeyCode::Char('q') | KeyCode::Char('Q') => {
-            if app.focus != Focus::SearchBar {
-                handle_exit(app);
-            }
-        }
+    if app.vim_mode && vim_handler(key_event, app) {
+        return;
+    }
+    // Shift-v is the same as v for the keymap, so the range of marks cannot be configured
+    if key_event.code == KeyCode::Char('V') {
+        app.table.mark_range();
+        return;
+    }
+    let Some(action) = app.keymap.action(key_event) else {
+        return;
+    };
+    match action {
+        Action::Quit => handle_exit(app),
+
+        Action::Delete => run_protected(app, ProtectedAction::Delete),
+
+        // Move the selected code when sorting manually
+        Action::MoveUp => handle_move(app, false),
+
+        Action::MoveDown => handle_move(app, true),
 
         // Move into the table
-        KeyCode::Up => {
-            app.print_percentage = true;
+        Action::Previous => {
             app.current_page = Main;
             app.table.previous();
         }
 
-        KeyCode::Down => {
-            app.print_percentage = true;
+        Action::Next => {
             app.current_page = Main;
             app.table.next();
         }
 
-        KeyCode::Char('+') => {
+        Action::IncrementCounter => {
             app.current_page = Main;
             handle_counter_switch(app, true);
         }
 
-        KeyCode::Char('-') => {
+        Action::DecrementCounter => {
             app.current_page = Main;
             handle_counter_switch(app, false);
         }
 
-        KeyCode::Char('k') | KeyCode::Char('K') => handle_switch_page(app, Qrcode),
-
-        KeyCode::Char('i') | KeyCode::Char('I') => {
-            let info_text = String::from(
-                "
-            Press:
-            d -> Delete selected code
-            + -> Increment the HOTP counter
-            - -> Decrement the HOTP counter
-            k -> Show QRCode of the selected element
-            Enter -> Copy the OTP Code to the clipboard
-            CTRL-F -> Search codes
-            CTRL-W -> Clear the search query
-            q, CTRL-D, Esc -> Exit the application
-            ",
+        // Only showing the QR code needs the password, not hiding it
+        // The key repeats while it is held
+        Action::QrCode if app.current_page == Qrcode && app.qr_guard.is_hold() => {
+            app.qr_guard.press()
+        }
+
+        Action::QrCode if app.current_page == Qrcode => handle_switch_page(app, Qrcode),
+
+        Action::QrCode => run_protected(app, ProtectedAction::QrCode),
+
+        Action::SaveQrCode | Action::CopyUri if app.current_page != Qrcode => {
+            app.status.error(tr_args(
+                "qrcode-first",
+                &[("keys", &app.keymap.keys(Action::QrCode))],
+            ));
+        }
+
+        Action::SaveQrCode => {
+            let file_name = app
+                .table
+                .selected_element_index()
+                .and_then(|index| app.database.get_element(index))
+                .map(qr_image_name);
+            if let Some(file_name) = file_name {
+                show_input_popup(app, tr("qrcode-path"), PopupAction::SaveQrCode, &file_name);
+            }
+        }
+
+        Action::CopyUri => {
+            let result = copy_selected_uri(app);
+            app.status.show(result);
+        }
+
+        Action::NearbyCodes => handle_switch_page(app, NearbyCodes),
+
+        Action::Presentation => handle_switch_page(app, Presentation),
+
+        Action::RecoveryCodes if app.current_page == RecoveryCodes => {
+            handle_switch_page(app, RecoveryCodes)
+        }
+
+        Action::RecoveryCodes => run_protected(app, ProtectedAction::RecoveryCodes),
+
+        Action::Pin => handle_pin_switch(app),
+
+        Action::HideCodes => {
+            app.hide_codes = !app.hide_codes;
+            app.revealed = None;
+        }
+
+        Action::Reveal if app.current_page == Qrcode => app.uri_revealed = !app.uri_revealed,
+
+        Action::Reveal => {
+            let selected = app.table.selected_element_index();
+            app.revealed = if app.revealed == selected {
+                None
+            } else {
+                selected
+            };
+        }
+
+        Action::DetailPane => app.detail_pane = !app.detail_pane,
+
+        Action::Columns => show_popup(
+            Popup {
+                text: columns_menu_text(app).into(),
+                input: TextInput::default(),
+                percent_x: 40,
+                percent_y: 50,
+                scroll: 0,
+                action: PopupAction::Columns,
+            },
+            app,
+        ),
+
+        Action::Mark => app.table.toggle_mark(),
+
+        Action::Tag => {
+            let count = app.table.marked_or_selected().len();
+            if count > 0 {
+                let text = if count == 1 {
+                    tr("tag-prompt-one").to_owned()
+                } else {
+                    tr_args("tag-prompt", &[("count", &count.to_string())])
+                };
+                show_input_popup(app, &text, PopupAction::TagCodes, "");
+            }
+        }
+
+        Action::Export => {
+            if !app.table.marked_or_selected().is_empty() {
+                run_protected(app, ProtectedAction::Export);
+            }
+        }
+
+        Action::Sort => {
+            app.current_page = Main;
+            app.sort_mode = app.sort_mode.next();
+            app.tick(true);
+        }
+
+        Action::TagFilter => handle_tag_filter_switch(app),
+
+        Action::Redo => handle_undo_redo(app, true),
+
+        Action::Undo => handle_undo_redo(app, false),
+
+        Action::Add => {
+            app.form = Some(CodeForm::add(app.code_defaults));
+            app.current_page = Form;
+        }
+
+        Action::Edit => run_protected(app, ProtectedAction::Edit),
+
+        Action::Help => {
+            let text = format!(
+                "{}\n{}:\n{}\n",
+                app.keymap.help_text(app.vim_mode),
+                tr("help-security"),
+                hardening::memory_lock().description()
             );
             show_popup(
                 Popup {
-                    text: info_text,
-                    percent_x: 40,
-                    percent_y: 50,
+                    text: text.into(),
+                    input: TextInput::default(),
+                    percent_x: 60,
+                    percent_y: 80,
+                    scroll: 0,
                     action: PopupAction::GeneralInfo,
                 },
                 app,
             );
         }
 
-        KeyCode::Char('f') | KeyCode::Char('F') => {
-            if key_event.modifiers == KeyModifiers::CONTROL {
-                app.focus = Focus::SearchBar;
+        Action::Search => {
+            // Like in vim every search starts from an empty query
+            if app.vim_mode {
+                app.search_query.clear();
             }
+            app.focus = Focus::SearchBar;
         }
 
-        KeyCode::Char('/') => app.focus = Focus::SearchBar,
-
-        KeyCode::Enter => {
-            app.label_text = copy_selected_code_to_clipboard(app);
-            app.print_percentage = false;
+        Action::Copy => {
+            let result = copy_selected_code_to_clipboard(app);
+            app.status.show(result);
         }
-        _ => {}
     }
 }
 
+/// Handles the vim keys which cannot be configured, returning true if the key was consumed
+fn vim_handler(key_event: KeyEvent, app: &mut App) -> bool {
+    let pending_g = std::mem::take(&mut app.pending_g);
+    if !(key_event.modifiers - KeyModifiers::SHIFT).is_empty() {
+        return false;
+    }
+    match key_event.code {
+        KeyCode::Char('g') if pending_g => app.table.first(),
+        KeyCode::Char('g') => app.pending_g = true,
+        KeyCode::Char('G') => app.table.last(),
+        KeyCode::Char('n') => app.table.search_next(app.search_query.value(), true),
+        KeyCode::Char('N') => app.table.search_next(app.search_query.value(), false),
+        _ => return false,
+    }
+    app.current_page = Main;
+    true
+}
+
 fn delete_selected_code(app: &mut App) -> Result<String, String> {
-    match app.table.state.selected() {
-        Some(selected) => {
-            if app.database.elements_ref().len() > selected {
-                app.database.delete_element(selected);
+    match app
+        .table
+        .state
+        .selected()
+        .zip(app.table.selected_element_index())
+    {
+        Some((selected, index)) => {
+            if let Some(element) = app.database.get_element(index).cloned() {
+                app.journal
+                    .apply(Operation::Delete { index, element }, app.database);
                 app.table.items.remove(selected);
-                if selected >= app.database.elements_ref().len() {
+                if selected >= app.table.items.len() {
                     app.table.previous();
-                } else if app.database.elements_ref().is_empty() {
+                } else if app.table.items.is_empty() {
                     app.table.state.select(None)
                 }
-                Ok("Done".to_string())
+                Ok(tr("code-trashed").to_string())
             } else {
-                Err("Index out of bounds".to_string())
+                Err(tr("index-out-of-bounds").to_string())
             }
         }
-        None => Err("No code selected".to_string()),
+        None => Err(tr("no-code-selected").to_string()),
     }
 }
 
-fn copy_selected_code_to_clipboard(app: &mut App) -> String {
-    match app.table.state.selected() {
-        Some(selected) => match app.table.items.get(selected) {
-            Some(element) => match element.values.get(3) {
-                Some(otp_code) => {
-                    if let Ok(result) = copy_string_to_clipboard(otp_code) {
-                        match result {
-                            CopyType::Native => "Copied!".to_string(),
-                            CopyType::OSC52 => "Remote copied!".to_string(),
-                        }
-                    } else {
-                        "Cannot copy".to_string()
-                    }
-                }
-                None => "Cannot get OTP Code column".to_string(),
-            },
-            None => format!("Cannot fetch element from index: {selected}"),
-        },
-        None => "No code selected".to_string(),
+/// Moves the marked codes to the trash, undone together
+fn delete_marked_codes(app: &mut App) -> Result<String, String> {
+    // From the last one, so the indexes of the others do not change
+    let operations: Vec<Operation> = app
+        .table
+        .marked
+        .iter()
+        .rev()
+        .filter_map(|&index| {
+            let element = app.database.get_element(index)?.clone();
+            Some(Operation::Delete { index, element })
+        })
+        .collect();
+    if operations.is_empty() {
+        return Err(tr("no-code-marked").to_string());
+    }
+    let count = operations.len();
+    app.journal
+        .apply(Operation::Batch(operations), app.database);
+    app.table.clear_marks();
+    app.tick(true);
+    keep_selection_in_table(app);
+    Ok(if count == 1 {
+        tr("code-trashed").to_string()
+    } else {
+        tr_args("codes-trashed", &[("count", &count.to_string())])
+    })
+}
+
+/// Adds the tag to the selected or marked codes, or removes it if they all have it
+fn tag_codes(app: &mut App, tag: &str) -> Result<String, String> {
+    if tag.is_empty() {
+        return Err(tr("tag-empty").to_string());
+    }
+    let elements: Vec<(usize, &OTPElement)> = app
+        .table
+        .marked_or_selected()
+        .into_iter()
+        .filter_map(|index| Some((index, app.database.get_element(index)?)))
+        .collect();
+    let remove = elements.iter().all(|(_, element)| element.has_tag(tag));
+    let operations: Vec<Operation> = elements
+        .into_iter()
+        .filter(|(_, element)| remove || !element.has_tag(tag))
+        .map(|(index, element)| {
+            let mut after = element.clone();
+            if remove {
+                after.tags.retain(|t| !same_tag(t, tag));
+            } else {
+                after.tags.push(tag.to_owned());
+            }
+            Operation::Edit {
+                index,
+                before: Box::new(element.clone()),
+                after: Box::new(after),
+            }
+        })
+        .collect();
+    let count = operations.len();
+    app.journal
+        .apply(Operation::Batch(operations), app.database);
+    app.table.clear_marks();
+    app.tick(true);
+    let id = match (remove, count) {
+        (true, 1) => "tag-removed-one",
+        (true, _) => "tag-removed",
+        (false, 1) => "tag-added-one",
+        (false, _) => "tag-added",
+    };
+    Ok(tr_args(id, &[("tag", tag), ("count", &count.to_string())]))
+}
+
+/// Writes the selected or marked codes into a cotp backup, which cotp import --cotp reads
+fn export_codes(app: &mut App, path: &Path) -> Result<String, String> {
+    let elements: Vec<OTPElement> = app
+        .table
+        .marked_or_selected()
+        .into_iter()
+        .filter_map(|index| app.database.get_element(index).cloned())
+        .collect();
+    let count = elements.len();
+    let exported: OTPDatabase = elements.into();
+    do_export(&exported, path.to_path_buf()).map_err(|e| {
+        tr_args(
+            "export-failed",
+            &[("path", &path.display().to_string()), ("error", &e)],
+        )
+    })?;
+    app.table.clear_marks();
+    let path = path.display().to_string();
+    Ok(if count == 1 {
+        tr_args("code-exported", &[("path", &path)])
+    } else {
+        tr_args(
+            "codes-exported",
+            &[("count", &count.to_string()), ("path", &path)],
+        )
+    })
+}
+
+/// Copies the selected code, returning the message telling which one was copied
+fn copy_selected_code_to_clipboard(app: &mut App) -> Result<String, String> {
+    let selected = app
+        .table
+        .state
+        .selected()
+        .ok_or(tr("no-code-selected").to_string())?;
+    let element = app
+        .table
+        .items
+        .get(selected)
+        .ok_or_else(|| tr_args("element-not-found", &[("index", &selected.to_string())]))?;
+    let otp_code = element
+        .values
+        .get(3)
+        .ok_or(tr("otp-column-missing").to_string())?;
+    // The codes without issuer are named by their label
+    let name = [&element.values[1], &element.values[2]]
+        .into_iter()
+        .find(|value| !value.is_empty())
+        .cloned()
+        .unwrap_or_default();
+    let (index, otp_code) = (element.element_index, Zeroizing::new(otp_code.to_owned()));
+    let result = copy_with_scheduled_clear(app, &otp_code).ok_or(tr("copy-failed").to_string())?;
+    app.database.record_usage(index);
+    app.revealed = Some(index);
+    Ok(match result {
+        CopyType::Native => tr_args("code-copied", &[("name", &name)]),
+        CopyType::OSC52 => tr_args("code-remote-copied", &[("name", &name)]),
+    })
+}
+
+/// Copies the text, cleared from the clipboard after the timeout
+fn copy_with_scheduled_clear(app: &mut App, text: &str) -> Option<CopyType> {
+    // Keep the content copied before the first code, not previously copied codes
+    let previous = match app.scheduled_clear.take() {
+        Some(scheduled_clear) if scheduled_clear.is_pending() => scheduled_clear.cancel(),
+        _ => app.clipboard_timeout.and_then(|_| read_clipboard()),
+    };
+    let result = copy_string_to_clipboard(text).ok()?;
+    app.scheduled_clear = app
+        .clipboard_timeout
+        .map(|timeout| ScheduledClear::new(text, previous, result, timeout));
+    Some(result)
+}
+
+/// Copies the otpauth URI of the QR code shown, to enroll the code in another app
+fn copy_selected_uri(app: &mut App) -> Result<String, String> {
+    let element = app
+        .table
+        .selected_element_index()
+        .and_then(|index| app.database.get_element(index))
+        .ok_or(tr("no-code-selected").to_string())?;
+    let uri = Zeroizing::new(element.get_otpauth_uri());
+    copy_with_scheduled_clear(app, &uri).ok_or(tr("copy-failed").to_string())?;
+    Ok(tr("uri-copied").to_string())
+}
+
+/// Saves the QR code shown as a PNG image
+fn save_selected_qrcode(app: &App, path: &Path) -> Result<String, String> {
+    let element = app
+        .table
+        .selected_element_index()
+        .and_then(|index| app.database.get_element(index))
+        .ok_or(tr("no-code-selected"))?;
+    let uri = Zeroizing::new(element.get_otpauth_uri());
+    let mut path = path.to_path_buf();
+    // The format of the image is chosen by its extension
+    if path.extension().is_none() {
+        path.set_extension("png");
     }
+    let path = path.display().to_string();
+    render_qr_code(&uri)?.save(&path).map_err(|e| {
+        tr_args(
+            "qrcode-save-failed",
+            &[("path", &path), ("error", &e.to_string())],
+        )
+    })?;
+    Ok(tr_args("qrcode-saved", &[("path", &path)]))
 }
 
 fn handle_counter_switch(app: &mut App, increment: bool) {
-    if let Some(selected) = app.table.state.selected() {
-        if let Some(element) = app.database.mut_element(selected) {
+    if let Some(index) = app.table.selected_element_index() {
+        if let Some(element) = app.database.get_element(index) {
             if element.type_ == OTPType::Hotp {
+                let before = element.clone();
+                let mut after = element.clone();
                 // safe to unwrap becouse the element type is HOTP
-                let counter = element.counter.unwrap();
-                element.counter = if increment {
-                    Some(counter.checked_add(1).unwrap_or(u64::MAX))
+                let counter = after.counter.unwrap();
+                after.counter = if increment {
+                    Some(counter.saturating_add(1))
                 } else {
                     Some(counter.saturating_sub(1))
                 };
+                app.journal.apply(
+                    Operation::Edit {
+                        index,
+                        before: Box::new(before),
+                        after: Box::new(after),
+                    },
+                    app.database,
+                );
                 app.tick(true);
             }
         }
     }
 }
 
+fn handle_pin_switch(app: &mut App) {
+    let Some(index) = app.table.selected_element_index() else {
+        return;
+    };
+    if let Some(element) = app.database.get_element(index) {
+        let before = element.clone();
+        let mut after = element.clone();
+        after.pinned = !after.pinned;
+        app.journal.apply(
+            Operation::Edit {
+                index,
+                before: Box::new(before),
+                after: Box::new(after),
+            },
+            app.database,
+        );
+        app.tick(true);
+        // Keep the same element selected after it moved
+        let row = app
+            .table
+            .items
+            .iter()
+            .position(|row| row.element_index == index);
+        app.table.state.select(row);
+    }
+}
+
+fn handle_move(app: &mut App, down: bool) {
+    if app.sort_mode != SortMode::Manual {
+        app.status.error(tr_args(
+            "manual-sorting-needed",
+            &[("keys", &app.keymap.keys(Action::Sort))],
+        ));
+        return;
+    }
+    let Some(selected) = app.table.state.selected() else {
+        return;
+    };
+    let target = if down {
+        selected.checked_add(1)
+    } else {
+        selected.checked_sub(1)
+    };
+    // Rows may be filtered, so swap with the element shown in the target row.
+    // Pinned elements stay above the others, so they are moved only among themselves.
+    let indexes = target.and_then(|target| {
+        let current_row = app.table.items.get(selected)?;
+        let target_row = app.table.items.get(target)?;
+        let pinned = |index| app.database.get_element(index).map(|e| e.pinned);
+        if pinned(current_row.element_index) != pinned(target_row.element_index) {
+            return None;
+        }
+        Some((target, current_row.element_index, target_row.element_index))
+    });
+    if let Some((target, a, b)) = indexes {
+        app.table.clear_marks();
+        app.journal.apply(Operation::Swap { a, b }, app.database);
+        app.tick(true);
+        app.table.state.select(Some(target));
+    }
+}
+
+fn form_handler(key_event: KeyEvent, app: &mut App) {
+    let Some(form) = app.form.as_mut() else {
+        app.current_page = Main;
+        return;
+    };
+    match key_event.code {
+        KeyCode::Esc => {
+            app.form = None;
+            app.current_page = Main;
+        }
+        KeyCode::Tab | KeyCode::Down => form.next_field(true),
+        KeyCode::BackTab | KeyCode::Up => form.next_field(false),
+        KeyCode::Left if form.selected.is_choice() => form.cycle(false),
+        KeyCode::Right if form.selected.is_choice() => form.cycle(true),
+        KeyCode::Backspace => form.pop_char(),
+        KeyCode::Char(c) => form.push_char(c),
+        KeyCode::Enter => match form.to_element() {
+            Ok(element) => {
+                let findings: Vec<String> = audit_element(&element)
+                    .iter()
+                    .map(Finding::to_string)
+                    .collect();
+                let operation = match &form.edited {
+                    Some((index, before)) => Operation::Edit {
+                        index: *index,
+                        before: Box::new(before.clone()),
+                        after: Box::new(element),
+                    },
+                    None => Operation::Add { element },
+                };
+                let message = match operation {
+                    Operation::Add { .. } => tr("code-added"),
+                    _ => tr("code-edited"),
+                };
+                // Weak secrets are saved anyway, like with cotp add
+                if findings.is_empty() {
+                    app.status.info(message);
+                } else {
+                    app.status
+                        .error(format!("{message}. {}", findings.join(". ")));
+                }
+                app.journal.apply(operation, app.database);
+                app.form = None;
+                app.current_page = Main;
+                app.tick(true);
+            }
+            Err(e) => form.error = Some(e),
+        },
+        _ => {}
+    }
+}
+
+fn handle_undo_redo(app: &mut App, redo: bool) {
+    let done = if redo {
+        app.journal.redo(app.database)
+    } else {
+        app.journal.undo(app.database)
+    };
+    app.status.info(tr(match (redo, done) {
+        (false, true) => "undone",
+        (false, false) => "nothing-to-undo",
+        (true, true) => "redone",
+        (true, false) => "nothing-to-redo",
+    }));
+    app.current_page = Main;
+    // Restored or deleted codes change the indexes of the others
+    app.table.clear_marks();
+    app.tick(true);
+    keep_selection_in_table(app);
+}
+
+/// Restored or deleted rows may leave the selection out of the table
+fn keep_selection_in_table(app: &mut App) {
+    let rows = app.table.items.len();
+    match app.table.state.selected() {
+        _ if rows == 0 => app.table.state.select(None),
+        Some(selected) if selected >= rows => app.table.state.select(Some(rows - 1)),
+        None => app.table.state.select(Some(0)),
+        _ => {}
+    }
+}
+
+fn handle_tag_filter_switch(app: &mut App) {
+    // Cycle through the available tags, then go back to show every code
+    let tags = app.database.tags();
+    app.tag_filter = match &app.tag_filter {
+        Some(current) => tags
+            .iter()
+            .skip_while(|t| !same_tag(t, current))
+            .nth(1)
+            .cloned(),
+        None => tags.first().cloned(),
+    };
+    app.current_page = Main;
+    // The marked codes could be hidden by the filter
+    app.table.clear_marks();
+    app.tick(true);
+    app.table
+        .state
+        .select((!app.table.items.is_empty()).then_some(0));
+}
+
 fn handle_switch_page(app: &mut App, page: Page) {
     let default_page = Main;
     if app.current_page == page {
@@ -261,63 +995,96 @@ fn handle_switch_page(app: &mut App, page: Page) {
 }
 
 fn search_and_select(app: &mut App) {
-    // Check for issuer
-    for iter in app.table.items.iter().enumerate() {
-        let (index, row) = iter;
-        if row
-            .values
-            .get(1)
-            .unwrap()
-            .to_lowercase()
-            .starts_with(&app.search_query.to_lowercase())
-        {
-            app.table.state.select(Some(index));
+    // The same matching of cotp list --search, preferring the issuers then the labels starting with the query
+    let query = Regex::lenient(app.search_query.value());
+    for (column, prefix) in [(1, true), (2, true), (1, false), (2, false)] {
+        let found = app.table.items.iter().position(|row| {
+            if prefix {
+                query.is_prefix_match(&row.values[column])
+            } else {
+                query.is_match(&row.values[column])
+            }
+        });
+        if found.is_some() {
+            app.table.state.select(found);
             return;
         }
     }
-    // Check for label
-    for iter in app.table.items.iter().enumerate() {
-        let (index, row) = iter;
-        if row
-            .values
-            .get(2)
-            .unwrap()
-            .to_lowercase()
-            .starts_with(&app.search_query.to_lowercase())
-        {
-            app.table.state.select(Some(index));
-            return;
-        }
+}
+
+fn show_delete_popup(app: &mut App) {
+    let marked = app.table.marked.len();
+    if marked > 0 {
+        show_popup(
+            Popup {
+                text: if marked == 1 {
+                    tr("trash-marked-confirm-one").to_owned()
+                } else {
+                    tr_args("trash-marked-confirm", &[("count", &marked.to_string())])
+                }
+                .into(),
+                input: TextInput::default(),
+                percent_x: 60,
+                percent_y: 20,
+                scroll: 0,
+                action: PopupAction::DeleteOtp,
+            },
+            app,
+        );
+        return;
     }
-    // Check if issuer contains the query
-    for iter in app.table.items.iter().enumerate() {
-        let (index, row) = iter;
-        if row
-            .values
-            .get(1)
-            .unwrap()
-            .to_lowercase()
-            .contains(&app.search_query.to_lowercase())
-        {
-            app.table.state.select(Some(index));
-            return;
-        }
+    let selected = app
+        .table
+        .selected_element_index()
+        .and_then(|index| app.database.get_element(index));
+    if let Some(element) = selected {
+        // Ask the user if they want to delete the OTP Code
+        let text = tr_args(
+            "trash-confirm",
+            &[("issuer", &element.issuer), ("label", &element.label)],
+        );
+        show_popup(
+            Popup {
+                text: text.into(),
+                input: TextInput::default(),
+                percent_x: 60,
+                percent_y: 20,
+                scroll: 0,
+                action: PopupAction::DeleteOtp,
+            },
+            app,
+        )
     }
-    // Check if label contains the query
-    for iter in app.table.items.iter().enumerate() {
-        let (index, row) = iter;
-        if row
-            .values
-            .get(2)
-            .unwrap()
-            .to_lowercase()
-            .contains(&app.search_query.to_lowercase())
-        {
-            app.table.state.select(Some(index));
-            return;
-        }
+}
+
+/// Every column with its number and a mark if it is shown
+fn columns_menu_text(app: &App) -> String {
+    let mut text = format!("{}\n\n", tr("columns-menu"));
+    for (i, column) in Column::ALL.into_iter().enumerate() {
+        let mark = if app.columns.is_shown(column) {
+            'x'
+        } else {
+            ' '
+        };
+        text.push_str(&format!("{} [{mark}] {:<8}\n", i + 1, column.title()));
     }
-    // TODO Handle if no search results
+    text.push_str(&format!("\n{}", tr("columns-menu-close")));
+    text
+}
+
+/// Asks a value, starting from the given one
+fn show_input_popup(app: &mut App, text: &str, action: PopupAction, input: &str) {
+    show_popup(
+        Popup {
+            text: String::from(text).into(),
+            input: TextInput::new(input),
+            percent_x: 60,
+            percent_y: 20,
+            scroll: 0,
+            action,
+        },
+        app,
+    );
 }
 
 fn show_popup(popup: Popup, app: &mut App) {
@@ -329,9 +1096,11 @@ fn handle_exit(app: &mut App) {
     if app.database.is_modified() {
         show_popup(
             Popup {
-                text: String::from("Save changes? [Y/N]"),
+                text: String::from(tr("save-confirm")).into(),
+                input: TextInput::default(),
                 percent_x: 60,
                 percent_y: 20,
+                scroll: 0,
                 action: PopupAction::SaveBeforeQuit,
             },
             app,
@@ -0,0 +1,216 @@
+use cotp::otp::otp_element::{OTPDatabase, OTPElement};
+
+/// Change made to the database from the dashboard, which can be reverted
+pub enum Operation {
+    Add {
+        element: OTPElement,
+    },
+    Delete {
+        index: usize,
+        element: OTPElement,
+    },
+    Edit {
+        index: usize,
+        before: Box<OTPElement>,
+        after: Box<OTPElement>,
+    },
+    Swap {
+        a: usize,
+        b: usize,
+    },
+    /// Operations on the marked codes, undone together in the reverse order
+    Batch(Vec<Operation>),
+}
+
+impl Operation {
+    /// Change recorded in the history of the database, the order of the codes is not worth it
+    fn description(&self) -> Option<String> {
+        match self {
+            Operation::Add { element } => Some(format!("Added {}", element.name())),
+            Operation::Delete { element, .. } => {
+                Some(format!("Moved {} to the trash", element.name()))
+            }
+            Operation::Edit { before, after, .. } => Some(before.edit_description(after)),
+            // Each operation records its own change
+            Operation::Swap { .. } | Operation::Batch(_) => None,
+        }
+    }
+
+    fn apply(&self, database: &mut OTPDatabase) {
+        if let Some(description) = self.description() {
+            database.record_change(description);
+        }
+        match self {
+            Operation::Add { element } => database.add_element(element.clone()),
+            Operation::Delete { index, .. } => database.delete_element(*index),
+            Operation::Edit { index, after, .. } => {
+                database.replace_element(*index, *after.clone())
+            }
+            Operation::Swap { a, b } => database.swap_elements(*a, *b),
+            Operation::Batch(operations) => {
+                for operation in operations {
+                    operation.apply(database);
+                }
+            }
+        }
+    }
+
+    fn revert(&self, database: &mut OTPDatabase) {
+        if let Some(description) = self.description() {
+            database.record_change(format!("Undone: {description}"));
+        }
+        match self {
+            Operation::Add { .. } => {
+                database.remove_element(database.elements_ref().len().saturating_sub(1));
+            }
+            Operation::Delete { index, element } => {
+                database.insert_element(*index, element.clone())
+            }
+            Operation::Edit { index, before, .. } => {
+                database.replace_element(*index, *before.clone())
+            }
+            Operation::Swap { a, b } => database.swap_elements(*a, *b),
+            Operation::Batch(operations) => {
+                for operation in operations.iter().rev() {
+                    operation.revert(database);
+                }
+            }
+        }
+    }
+}
+
+/// Undo and redo stacks of the operations made before saving the database
+#[derive(Default)]
+pub struct Journal {
+    undo: Vec<Operation>,
+    redo: Vec<Operation>,
+}
+
+impl Journal {
+    /// Applies the operation, discarding the operations which could be redone
+    pub fn apply(&mut self, operation: Operation, database: &mut OTPDatabase) {
+        operation.apply(database);
+        self.undo.push(operation);
+        self.redo.clear();
+    }
+
+    /// Reverts the last operation, returning false if there is nothing to undo
+    pub fn undo(&mut self, database: &mut OTPDatabase) -> bool {
+        match self.undo.pop() {
+            Some(operation) => {
+                operation.revert(database);
+                self.redo.push(operation);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Applies again the last undone operation, returning false if there is nothing to redo
+    pub fn redo(&mut self, database: &mut OTPDatabase) -> bool {
+        match self.redo.pop() {
+            Some(operation) => {
+                operation.apply(database);
+                self.undo.push(operation);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cotp::otp::otp_element::{OTPDatabase, OTPElement};
+
+    use super::{Journal, Operation};
+
+    fn element(label: &str) -> OTPElement {
+        crate::test_element("Issuer", label)
+    }
+
+    fn labels(database: &OTPDatabase) -> Vec<&str> {
+        database
+            .elements_ref()
+            .iter()
+            .map(|e| e.label.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_undo_redo() {
+        let mut database: OTPDatabase = vec![element("a"), element("b"), element("c")].into();
+        let mut journal = Journal::default();
+
+        journal.apply(
+            Operation::Delete {
+                index: 1,
+                element: element("b"),
+            },
+            &mut database,
+        );
+        journal.apply(Operation::Swap { a: 0, b: 1 }, &mut database);
+        assert_eq!(vec!["c", "a"], labels(&database));
+        assert_eq!(1, database.trash_ref().len());
+
+        assert!(journal.undo(&mut database));
+        assert!(journal.undo(&mut database));
+        assert_eq!(vec!["a", "b", "c"], labels(&database));
+        assert!(database.trash_ref().is_empty());
+        assert!(!journal.undo(&mut database));
+        let history: Vec<&str> = database
+            .history_ref()
+            .iter()
+            .map(|entry| entry.message.as_str())
+            .collect();
+        assert_eq!(
+            vec![
+                "Moved Issuer - b to the trash",
+                "Undone: Moved Issuer - b to the trash"
+            ],
+            history
+        );
+
+        assert!(journal.redo(&mut database));
+        assert_eq!(vec!["a", "c"], labels(&database));
+
+        journal.apply(
+            Operation::Edit {
+                index: 0,
+                before: Box::new(element("a")),
+                after: Box::new(element("z")),
+            },
+            &mut database,
+        );
+        assert_eq!(vec!["z", "c"], labels(&database));
+        assert!(!journal.redo(&mut database));
+    }
+
+    #[test]
+    fn test_batch() {
+        let mut database: OTPDatabase = vec![element("a"), element("b"), element("c")].into();
+        let mut journal = Journal::default();
+
+        // Deleted from the last, so the indexes of the others do not change
+        journal.apply(
+            Operation::Batch(vec![
+                Operation::Delete {
+                    index: 2,
+                    element: element("c"),
+                },
+                Operation::Delete {
+                    index: 0,
+                    element: element("a"),
+                },
+            ]),
+            &mut database,
+        );
+        assert_eq!(vec!["b"], labels(&database));
+        assert_eq!(2, database.trash_ref().len());
+
+        assert!(journal.undo(&mut database));
+        assert_eq!(vec!["a", "b", "c"], labels(&database));
+        assert!(journal.redo(&mut database));
+        assert_eq!(vec!["b"], labels(&database));
+    }
+}
@@ -0,0 +1,619 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{self, Display, Formatter};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::i18n::tr;
+
+/// Dashboard actions which can be bound to keys
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Action {
+    Add,
+    Edit,
+    Delete,
+    IncrementCounter,
+    DecrementCounter,
+    QrCode,
+    SaveQrCode,
+    CopyUri,
+    NearbyCodes,
+    Presentation,
+    RecoveryCodes,
+    Pin,
+    HideCodes,
+    Reveal,
+    DetailPane,
+    Columns,
+    Mark,
+    Tag,
+    Export,
+    Copy,
+    Search,
+    TagFilter,
+    Sort,
+    MoveUp,
+    MoveDown,
+    Previous,
+    Next,
+    Undo,
+    Redo,
+    Help,
+    Quit,
+}
+
+impl Action {
+    /// Every action, in the order they are listed in the help popup
+    pub const ALL: [Action; 31] = [
+        Action::Add,
+        Action::Edit,
+        Action::Delete,
+        Action::IncrementCounter,
+        Action::DecrementCounter,
+        Action::QrCode,
+        Action::SaveQrCode,
+        Action::CopyUri,
+        Action::NearbyCodes,
+        Action::Presentation,
+        Action::RecoveryCodes,
+        Action::Pin,
+        Action::HideCodes,
+        Action::Reveal,
+        Action::DetailPane,
+        Action::Columns,
+        Action::Mark,
+        Action::Tag,
+        Action::Export,
+        Action::Copy,
+        Action::Search,
+        Action::TagFilter,
+        Action::Sort,
+        Action::MoveUp,
+        Action::MoveDown,
+        Action::Previous,
+        Action::Next,
+        Action::Undo,
+        Action::Redo,
+        Action::Help,
+        Action::Quit,
+    ];
+
+    /// Name used in the `[keys]` section of the configuration file
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::Add => "add",
+            Action::Edit => "edit",
+            Action::Delete => "delete",
+            Action::IncrementCounter => "increment_counter",
+            Action::DecrementCounter => "decrement_counter",
+            Action::QrCode => "qrcode",
+            Action::SaveQrCode => "save_qrcode",
+            Action::CopyUri => "copy_uri",
+            Action::NearbyCodes => "nearby_codes",
+            Action::Presentation => "presentation",
+            Action::RecoveryCodes => "recovery_codes",
+            Action::Pin => "pin",
+            Action::HideCodes => "hide_codes",
+            Action::Reveal => "reveal",
+            Action::DetailPane => "detail_pane",
+            Action::Columns => "columns",
+            Action::Mark => "mark",
+            Action::Tag => "tag",
+            Action::Export => "export",
+            Action::Copy => "copy",
+            Action::Search => "search",
+            Action::TagFilter => "tag_filter",
+            Action::Sort => "sort",
+            Action::MoveUp => "move_up",
+            Action::MoveDown => "move_down",
+            Action::Previous => "previous",
+            Action::Next => "next",
+            Action::Undo => "undo",
+            Action::Redo => "redo",
+            Action::Help => "help",
+            Action::Quit => "quit",
+        }
+    }
+
+    fn description(&self) -> &'static str {
+        tr(match self {
+            Action::Add => "action-add",
+            Action::Edit => "action-edit",
+            Action::Delete => "action-delete",
+            Action::IncrementCounter => "action-increment-counter",
+            Action::DecrementCounter => "action-decrement-counter",
+            Action::QrCode => "action-qrcode",
+            Action::SaveQrCode => "action-save-qrcode",
+            Action::CopyUri => "action-copy-uri",
+            Action::NearbyCodes => "action-nearby-codes",
+            Action::Presentation => "action-presentation",
+            Action::RecoveryCodes => "action-recovery-codes",
+            Action::Pin => "action-pin",
+            Action::HideCodes => "action-hide-codes",
+            Action::Reveal => "action-reveal",
+            Action::DetailPane => "action-detail-pane",
+            Action::Columns => "action-columns",
+            Action::Mark => "action-mark",
+            Action::Tag => "action-tag",
+            Action::Export => "action-export",
+            Action::Copy => "action-copy",
+            Action::Search => "action-search",
+            Action::TagFilter => "action-tag-filter",
+            Action::Sort => "action-sort",
+            Action::MoveUp => "action-move-up",
+            Action::MoveDown => "action-move-down",
+            Action::Previous => "action-previous",
+            Action::Next => "action-next",
+            Action::Undo => "action-undo",
+            Action::Redo => "action-redo",
+            Action::Help => "action-help",
+            Action::Quit => "action-quit",
+        })
+    }
+
+    fn default_keys(&self, vim_mode: bool) -> &'static [&'static str] {
+        match self {
+            Action::Previous if vim_mode => &["Up", "k"],
+            Action::Next if vim_mode => &["Down", "j"],
+            Action::QrCode if vim_mode => &["o"],
+            Action::Add => &["a"],
+            Action::Edit => &["e"],
+            Action::Delete => &["d", "Delete"],
+            Action::IncrementCounter => &["+"],
+            Action::DecrementCounter => &["-"],
+            Action::QrCode => &["k"],
+            Action::SaveQrCode => &["Ctrl-s"],
+            Action::CopyUri => &["Ctrl-y"],
+            Action::NearbyCodes => &["v"],
+            Action::Presentation => &["f"],
+            Action::RecoveryCodes => &["c"],
+            Action::Pin => &["p"],
+            Action::HideCodes => &["h"],
+            Action::Reveal => &["r"],
+            Action::DetailPane => &["Tab"],
+            Action::Columns => &["l"],
+            Action::Mark => &["Space"],
+            Action::Tag => &["t"],
+            Action::Export => &["w"],
+            Action::Copy => &["Enter"],
+            Action::Search => &["Ctrl-f", "/"],
+            Action::TagFilter => &["Ctrl-t"],
+            Action::Sort => &["s"],
+            Action::MoveUp => &["Shift-Up"],
+            Action::MoveDown => &["Shift-Down"],
+            Action::Previous => &["Up"],
+            Action::Next => &["Down"],
+            Action::Undo => &["u"],
+            Action::Redo => &["Ctrl-r"],
+            Action::Help => &["?", "i"],
+            Action::Quit => &["q", "Ctrl-d", "Ctrl-c", "Esc"],
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Action> {
+        Action::ALL.into_iter().find(|a| a.name() == name)
+    }
+
+    fn context(&self) -> Context {
+        match self {
+            Action::Previous
+            | Action::Next
+            | Action::Search
+            | Action::TagFilter
+            | Action::Sort
+            | Action::MoveUp
+            | Action::MoveDown => Context::Navigation,
+            Action::Add
+            | Action::Edit
+            | Action::Delete
+            | Action::IncrementCounter
+            | Action::DecrementCounter
+            | Action::Pin
+            | Action::Copy
+            | Action::Undo
+            | Action::Redo => Context::Codes,
+            Action::Mark | Action::Tag | Action::Export => Context::Selection,
+            Action::QrCode
+            | Action::NearbyCodes
+            | Action::Presentation
+            | Action::RecoveryCodes
+            | Action::HideCodes
+            | Action::Reveal
+            | Action::DetailPane
+            | Action::Columns => Context::Views,
+            Action::SaveQrCode | Action::CopyUri => Context::QrCodePage,
+            Action::Help | Action::Quit => Context::Application,
+        }
+    }
+}
+
+/// Parts of the dashboard grouping the keys of the help popup
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Context {
+    Navigation,
+    Codes,
+    Selection,
+    Views,
+    QrCodePage,
+    SearchBar,
+    Application,
+}
+
+impl Context {
+    const ALL: [Context; 7] = [
+        Context::Navigation,
+        Context::Codes,
+        Context::Selection,
+        Context::Views,
+        Context::QrCodePage,
+        Context::SearchBar,
+        Context::Application,
+    ];
+
+    fn title(self) -> &'static str {
+        tr(match self {
+            Context::Navigation => "context-navigation",
+            Context::Codes => "context-codes",
+            Context::Selection => "context-selection",
+            Context::Views => "context-views",
+            Context::QrCodePage => "context-qrcode-page",
+            Context::SearchBar => "context-search-bar",
+            Context::Application => "context-application",
+        })
+    }
+
+    /// Keys handled outside of the keymap, which cannot be configured
+    fn fixed_keys(self, vim_mode: bool) -> Vec<(&'static str, &'static str)> {
+        match self {
+            Context::Navigation if vim_mode => vec![
+                ("gg, G", tr("help-first-last")),
+                ("n, N", tr("help-search-match")),
+            ],
+            Context::Selection => vec![("V", tr("help-mark-range"))],
+            Context::SearchBar => vec![
+                (
+                    "Enter",
+                    tr(if vim_mode {
+                        "help-confirm-search"
+                    } else {
+                        "help-copy-selected"
+                    }),
+                ),
+                ("Esc", tr("help-leave-search")),
+                ("Left, Right, Home, End", tr("help-move-cursor")),
+                ("Ctrl-Left, Ctrl-Right", tr("help-move-cursor-words")),
+                ("Ctrl-w, Ctrl-u, Ctrl-k", tr("help-delete-text")),
+            ],
+            Context::Application => vec![("Up, Down, PageUp, PageDown", tr("help-scroll"))],
+            _ => vec![],
+        }
+    }
+}
+
+/// A key together with its modifiers, like Ctrl-f
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct KeyBinding {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl KeyBinding {
+    /// Letters are case insensitive, and Shift is ignored for characters
+    /// since it is already part of the typed symbol.
+    fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        match code {
+            KeyCode::Char(c) => KeyBinding {
+                code: KeyCode::Char(c.to_ascii_lowercase()),
+                modifiers: modifiers - KeyModifiers::SHIFT,
+            },
+            _ => KeyBinding { code, modifiers },
+        }
+    }
+
+    /// Parses keys written like `q`, `Enter`, `Ctrl-f` or `Shift-Up`
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let invalid = || format!("Invalid key: \"{value}\"");
+        let mut parts: Vec<&str> = value.split('-').collect();
+        // The minus key itself, alone or with modifiers
+        if value.ends_with('-') {
+            parts.truncate(parts.len() - 2);
+            parts.push("-");
+        }
+        let (key, modifier_names) = parts.split_last().ok_or_else(invalid)?;
+        let mut modifiers = KeyModifiers::NONE;
+        for modifier in modifier_names {
+            modifiers |= match modifier.to_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => return Err(invalid()),
+            };
+        }
+        let code = match key.to_lowercase().as_str() {
+            "enter" => KeyCode::Enter,
+            "esc" | "escape" => KeyCode::Esc,
+            "delete" | "del" => KeyCode::Delete,
+            "backspace" => KeyCode::Backspace,
+            "tab" => KeyCode::Tab,
+            "space" => KeyCode::Char(' '),
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" => KeyCode::PageUp,
+            "pagedown" => KeyCode::PageDown,
+            other => {
+                let mut chars = other.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => KeyCode::Char(c),
+                    (Some('f'), Some(_)) => other[1..]
+                        .parse()
+                        .ok()
+                        .filter(|n| (1..=12).contains(n))
+                        .map(KeyCode::F)
+                        .ok_or_else(invalid)?,
+                    _ => return Err(invalid()),
+                }
+            }
+        };
+        Ok(KeyBinding::new(code, modifiers))
+    }
+}
+
+impl From<KeyEvent> for KeyBinding {
+    fn from(key_event: KeyEvent) -> Self {
+        KeyBinding::new(key_event.code, key_event.modifiers)
+    }
+}
+
+impl Display for KeyBinding {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for (modifier, name) in [
+            (KeyModifiers::CONTROL, "Ctrl-"),
+            (KeyModifiers::ALT, "Alt-"),
+            (KeyModifiers::SHIFT, "Shift-"),
+        ] {
+            if self.modifiers.contains(modifier) {
+                f.write_str(name)?;
+            }
+        }
+        match self.code {
+            KeyCode::Char(' ') => f.write_str("Space"),
+            KeyCode::Char(c) => write!(f, "{c}"),
+            KeyCode::F(n) => write!(f, "F{n}"),
+            KeyCode::PageUp => f.write_str("PageUp"),
+            KeyCode::PageDown => f.write_str("PageDown"),
+            other => write!(f, "{other:?}"),
+        }
+    }
+}
+
+/// Maps the dashboard keys to their actions
+pub struct Keymap {
+    bindings: HashMap<KeyBinding, Action>,
+    keys: HashMap<Action, Vec<KeyBinding>>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Keymap::from_config(&BTreeMap::new(), false).expect("Default keybindings must be valid")
+    }
+}
+
+impl Keymap {
+    /// Builds the keymap from the `[keys]` configuration section.
+    /// Actions which are not configured keep their default keys,
+    /// unless they are taken by a configured action.
+    /// In vim mode j and k move the selection, so the QR code is shown with o.
+    pub fn from_config(
+        config: &BTreeMap<String, Vec<String>>,
+        vim_mode: bool,
+    ) -> Result<Self, String> {
+        let mut keymap = Keymap {
+            bindings: HashMap::new(),
+            keys: HashMap::new(),
+        };
+        let mut configured = Vec::new();
+        for (name, values) in config {
+            let action =
+                Action::from_name(name).ok_or_else(|| format!("Unknown action: \"{name}\""))?;
+            let bindings = values
+                .iter()
+                .map(|v| KeyBinding::parse(v))
+                .collect::<Result<Vec<_>, _>>()?;
+            configured.push((action, bindings));
+        }
+        for action in Action::ALL {
+            if !config.contains_key(action.name()) {
+                let bindings = action
+                    .default_keys(vim_mode)
+                    .iter()
+                    .map(|v| KeyBinding::parse(v))
+                    .collect::<Result<Vec<_>, _>>()?;
+                keymap.bind(action, bindings);
+            }
+        }
+        for (action, bindings) in configured {
+            for binding in &bindings {
+                if let Some(other) = keymap.bindings.remove(binding) {
+                    if config.contains_key(other.name()) {
+                        return Err(format!(
+                            "Key {binding} is bound to both {} and {}",
+                            other.name(),
+                            action.name()
+                        ));
+                    }
+                    if let Some(keys) = keymap.keys.get_mut(&other) {
+                        keys.retain(|b| b != binding);
+                    }
+                }
+            }
+            keymap.bind(action, bindings);
+        }
+        Ok(keymap)
+    }
+
+    fn bind(&mut self, action: Action, bindings: Vec<KeyBinding>) {
+        for binding in &bindings {
+            self.bindings.insert(*binding, action);
+        }
+        self.keys.insert(action, bindings);
+    }
+
+    pub fn action(&self, key_event: KeyEvent) -> Option<Action> {
+        self.bindings.get(&KeyBinding::from(key_event)).copied()
+    }
+
+    /// Keys bound to the action, like "q, Ctrl-d"
+    pub fn keys(&self, action: Action) -> String {
+        self.keys
+            .get(&action)
+            .map(|bindings| {
+                bindings
+                    .iter()
+                    .map(|b| b.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            })
+            .unwrap_or_default()
+    }
+
+    /// Keys of the actions grouped by the part of the dashboard they work in, with the configured bindings
+    pub fn help_text(&self, vim_mode: bool) -> String {
+        let mut sections = Vec::new();
+        for context in Context::ALL {
+            let mut lines: Vec<String> = Action::ALL
+                .iter()
+                .filter(|a| a.context() == context && !self.keys[a].is_empty())
+                .map(|a| format!("{} -> {}", self.keys(*a), a.description()))
+                .collect();
+            lines.extend(
+                context
+                    .fixed_keys(vim_mode)
+                    .into_iter()
+                    .map(|(keys, description)| format!("{keys} -> {description}")),
+            );
+            if !lines.is_empty() {
+                sections.push(format!("{}:\n{}\n", context.title(), lines.join("\n")));
+            }
+        }
+        sections.join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    use super::{Action, KeyBinding, Keymap};
+
+    #[test]
+    fn test_parse_keys() {
+        assert_eq!(
+            KeyBinding::new(KeyCode::Char('f'), KeyModifiers::CONTROL),
+            KeyBinding::parse("Ctrl-F").unwrap()
+        );
+        assert_eq!(
+            KeyBinding::new(KeyCode::Up, KeyModifiers::SHIFT),
+            KeyBinding::parse("shift-up").unwrap()
+        );
+        assert_eq!(
+            KeyBinding::new(KeyCode::Char('-'), KeyModifiers::ALT),
+            KeyBinding::parse("Alt--").unwrap()
+        );
+        assert_eq!(
+            KeyBinding::new(KeyCode::F(5), KeyModifiers::NONE),
+            KeyBinding::parse("F5").unwrap()
+        );
+        assert!(KeyBinding::parse("Hyper-x").is_err());
+        assert!(KeyBinding::parse("abc").is_err());
+        assert_eq!("Ctrl-f", KeyBinding::parse("ctrl-f").unwrap().to_string());
+    }
+
+    #[test]
+    fn test_default_keymap() {
+        let keymap = Keymap::default();
+
+        assert_eq!(
+            Some(Action::Quit),
+            keymap.action(KeyEvent::new(KeyCode::Char('Q'), KeyModifiers::SHIFT))
+        );
+        assert_eq!(
+            Some(Action::Delete),
+            keymap.action(KeyEvent::new(KeyCode::Char('d'), KeyModifiers::NONE))
+        );
+        assert_eq!(
+            Some(Action::Quit),
+            keymap.action(KeyEvent::new(KeyCode::Char('d'), KeyModifiers::CONTROL))
+        );
+        assert_eq!(
+            None,
+            keymap.action(KeyEvent::new(KeyCode::Char('x'), KeyModifiers::NONE))
+        );
+    }
+
+    #[test]
+    fn test_configured_keymap() {
+        let mut config = BTreeMap::new();
+        config.insert("quit".to_string(), vec!["x".to_string()]);
+        let keymap = Keymap::from_config(&config, false).unwrap();
+
+        assert_eq!(
+            Some(Action::Quit),
+            keymap.action(KeyEvent::new(KeyCode::Char('x'), KeyModifiers::NONE))
+        );
+        assert_eq!(
+            None,
+            keymap.action(KeyEvent::new(KeyCode::Char('q'), KeyModifiers::NONE))
+        );
+
+        // Configured keys take precedence over the default ones
+        config.insert("sort".to_string(), vec!["d".to_string()]);
+        let keymap = Keymap::from_config(&config, false).unwrap();
+        assert_eq!(
+            Some(Action::Sort),
+            keymap.action(KeyEvent::new(KeyCode::Char('d'), KeyModifiers::NONE))
+        );
+        assert_eq!("Delete", keymap.keys(Action::Delete));
+
+        config.insert("sort".to_string(), vec!["x".to_string()]);
+        assert!(Keymap::from_config(&config, false).is_err());
+        config.clear();
+        config.insert("fly".to_string(), vec!["x".to_string()]);
+        assert!(Keymap::from_config(&config, false).is_err());
+    }
+
+    #[test]
+    fn test_vim_keymap() {
+        let keymap = Keymap::from_config(&BTreeMap::new(), true).unwrap();
+
+        assert_eq!(
+            Some(Action::Next),
+            keymap.action(KeyEvent::new(KeyCode::Char('j'), KeyModifiers::NONE))
+        );
+        assert_eq!(
+            Some(Action::Previous),
+            keymap.action(KeyEvent::new(KeyCode::Char('k'), KeyModifiers::NONE))
+        );
+        assert_eq!("o", keymap.keys(Action::QrCode));
+    }
+
+    #[test]
+    fn test_help_text() {
+        let mut config = BTreeMap::new();
+        config.insert("sort".to_string(), vec!["Ctrl-o".to_string()]);
+        let help = Keymap::from_config(&config, false)
+            .unwrap()
+            .help_text(false);
+
+        let navigation = help.find("Navigation:").unwrap();
+        let codes = help.find("\nCodes:").unwrap();
+        let sort = help.find("Ctrl-o -> Change the sorting mode").unwrap();
+        assert!(navigation < sort && sort < codes);
+        assert!(help.contains("?, i -> Show this help"));
+        assert!(!help.contains("gg, G"));
+    }
+}
@@ -0,0 +1,295 @@
+use std::collections::BTreeMap;
+
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+
+use crate::i18n::tr;
+
+/// Top border, header, its margin, one row and the bottom border
+const MIN_TABLE_HEIGHT: u16 = 5;
+const MIN_WIDTH: u16 = 20;
+/// Terminals at least this tall keep the margins and the bordered search bar
+const TALL_HEIGHT: u16 = 16;
+
+/// Areas of the main page, None for the bars which do not fit
+#[derive(PartialEq, Eq, Debug)]
+pub(crate) struct MainLayout {
+    pub(crate) search_bar: Option<Rect>,
+    pub(crate) table: Rect,
+    pub(crate) progress_bar: Option<Rect>,
+    pub(crate) status_bar: Option<Rect>,
+}
+
+impl MainLayout {
+    /// Shrinks the bars around the table on short terminals, None if even the table does not fit.
+    /// A one line search bar is kept while searching.
+    pub(crate) fn new(area: Rect, searching: bool) -> Option<Self> {
+        if area.width < MIN_WIDTH || area.height < MIN_TABLE_HEIGHT {
+            return None;
+        }
+        if area.height >= TALL_HEIGHT {
+            let rects = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([
+                    Constraint::Length(3),
+                    Constraint::Min(MIN_TABLE_HEIGHT),
+                    Constraint::Length(1),
+                    Constraint::Length(1),
+                ])
+                .margin(2)
+                .split(area);
+            return Some(MainLayout {
+                search_bar: Some(rects[0]),
+                table: rects[1],
+                progress_bar: Some(rects[2]),
+                status_bar: Some(rects[3]),
+            });
+        }
+        let spare = area.height - MIN_TABLE_HEIGHT;
+        let search_height = match spare {
+            5.. => 3,
+            1.. if searching => 1,
+            _ => 0,
+        };
+        // The progress bar is kept before the status bar
+        let progress_height = u16::from(spare > search_height);
+        let status_height = u16::from(spare > search_height + 1);
+        let table_height = area.height - search_height - progress_height - status_height;
+        let row = |y: u16, height: u16| Rect::new(area.x, area.y + y, area.width, height);
+        Some(MainLayout {
+            search_bar: (search_height > 0).then(|| row(0, search_height)),
+            table: row(search_height, table_height),
+            progress_bar: (progress_height > 0).then(|| row(search_height + table_height, 1)),
+            status_bar: (status_height > 0).then(|| row(area.height - 1, 1)),
+        })
+    }
+}
+
+/// Columns of the main table, in the order of the row values
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum Column {
+    Id,
+    Issuer,
+    Label,
+    Otp,
+    TimeLeft,
+    Type,
+    Tags,
+}
+
+impl Column {
+    pub(crate) const ALL: [Column; 7] = [
+        Column::Id,
+        Column::Issuer,
+        Column::Label,
+        Column::Otp,
+        Column::TimeLeft,
+        Column::Type,
+        Column::Tags,
+    ];
+
+    /// Name used in the configuration file
+    fn name(self) -> &'static str {
+        match self {
+            Column::Id => "id",
+            Column::Issuer => "issuer",
+            Column::Label => "label",
+            Column::Otp => "otp",
+            Column::TimeLeft => "time_left",
+            Column::Type => "type",
+            Column::Tags => "tags",
+        }
+    }
+
+    pub(crate) fn title(self) -> &'static str {
+        tr(match self {
+            Column::Id => "column-id",
+            Column::Issuer => "column-issuer",
+            Column::Label => "column-label",
+            Column::Otp => "column-otp",
+            Column::TimeLeft => "column-time-left",
+            Column::Type => "column-type",
+            Column::Tags => "column-tags",
+        })
+    }
+
+    /// Position of the column in the row values
+    pub(crate) fn index(self) -> usize {
+        self as usize
+    }
+
+    fn default_width(self) -> u16 {
+        match self {
+            Column::Id => 5,
+            Column::Issuer | Column::Label => 30,
+            Column::Otp | Column::Tags => 20,
+            Column::TimeLeft => 15,
+            Column::Type => 10,
+        }
+    }
+
+    /// Narrower tables leave out the column, the issuer and the code are always shown
+    fn min_table_width(self) -> u16 {
+        match self {
+            Column::Issuer | Column::Otp => 0,
+            Column::Label => 40,
+            _ => 60,
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Column> {
+        Column::ALL.into_iter().find(|c| c.name() == name)
+    }
+}
+
+/// Columns shown in the table and their relative widths, from the `[dashboard]` configuration
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub(crate) struct TableColumns {
+    shown: Vec<Column>,
+    widths: [u16; Column::ALL.len()],
+}
+
+impl Default for TableColumns {
+    fn default() -> Self {
+        TableColumns {
+            shown: Column::ALL[..=Column::TimeLeft.index()].to_vec(),
+            widths: Column::ALL.map(Column::default_width),
+        }
+    }
+}
+
+impl TableColumns {
+    pub(crate) fn from_config(
+        names: &[String],
+        widths: &BTreeMap<String, u16>,
+    ) -> Result<Self, String> {
+        let column =
+            |name: &str| Column::from_name(name).ok_or(format!("Unknown column \"{name}\""));
+        let mut columns = TableColumns {
+            shown: names
+                .iter()
+                .map(|name| column(name))
+                .collect::<Result<_, _>>()?,
+            ..Default::default()
+        };
+        if columns.shown.is_empty() {
+            return Err(String::from("At least one column must be shown"));
+        }
+        let shown = &columns.shown;
+        if let Some(index) = (1..shown.len()).find(|&i| shown[..i].contains(&shown[i])) {
+            return Err(format!("The {} column is listed twice", names[index]));
+        }
+        for (name, &width) in widths {
+            if width == 0 {
+                return Err(format!("The width of the {name} column must be positive"));
+            }
+            columns.widths[column(name)?.index()] = width;
+        }
+        Ok(columns)
+    }
+
+    pub(crate) fn is_shown(&self, column: Column) -> bool {
+        self.shown.contains(&column)
+    }
+
+    /// Hides the column, or shows it in its default position, keeping at least one column
+    pub(crate) fn toggle(&mut self, column: Column) {
+        if let Some(position) = self.shown.iter().position(|&c| c == column) {
+            if self.shown.len() > 1 {
+                self.shown.remove(position);
+            }
+        } else {
+            let position = self
+                .shown
+                .iter()
+                .position(|c| c.index() > column.index())
+                .unwrap_or(self.shown.len());
+            self.shown.insert(position, column);
+        }
+    }
+
+    /// Shown columns fitting a table of the given width, with their widths
+    pub(crate) fn fitting(&self, width: u16) -> (Vec<Column>, Vec<Constraint>) {
+        let mut columns: Vec<Column> = self
+            .shown
+            .iter()
+            .copied()
+            .filter(|c| width >= c.min_table_width())
+            .collect();
+        if columns.is_empty() {
+            columns.push(self.shown[0]);
+        }
+        let total: u32 = columns
+            .iter()
+            .map(|c| u32::from(self.widths[c.index()]))
+            .sum();
+        let constraints = columns
+            .iter()
+            .map(|c| Constraint::Ratio(u32::from(self.widths[c.index()]), total))
+            .collect();
+        (columns, constraints)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use ratatui::layout::{Constraint, Rect};
+
+    use super::{Column, MainLayout, TableColumns};
+
+    #[test]
+    fn test_main_layout() {
+        let layout = MainLayout::new(Rect::new(0, 0, 80, 24), false).unwrap();
+        assert_eq!(Some(Rect::new(2, 2, 76, 3)), layout.search_bar);
+        assert_eq!(Rect::new(2, 5, 76, 15), layout.table);
+        assert_eq!(Some(Rect::new(2, 20, 76, 1)), layout.progress_bar);
+        assert_eq!(Some(Rect::new(2, 21, 76, 1)), layout.status_bar);
+
+        let layout = MainLayout::new(Rect::new(0, 0, 80, 8), false).unwrap();
+        assert_eq!(None, layout.search_bar);
+        assert_eq!(Rect::new(0, 0, 80, 6), layout.table);
+        assert_eq!(Some(Rect::new(0, 6, 80, 1)), layout.progress_bar);
+        assert_eq!(Some(Rect::new(0, 7, 80, 1)), layout.status_bar);
+
+        let layout = MainLayout::new(Rect::new(0, 0, 80, 6), true).unwrap();
+        assert_eq!(Some(Rect::new(0, 0, 80, 1)), layout.search_bar);
+        assert_eq!(Rect::new(0, 1, 80, 5), layout.table);
+        assert_eq!(None, layout.progress_bar);
+        assert_eq!(None, layout.status_bar);
+
+        assert_eq!(None, MainLayout::new(Rect::new(0, 0, 80, 4), false));
+        assert_eq!(None, MainLayout::new(Rect::new(0, 0, 15, 24), false));
+    }
+
+    #[test]
+    fn test_table_columns() {
+        let mut widths = BTreeMap::new();
+        widths.insert(String::from("tags"), 50);
+        let names = ["issuer", "otp", "tags"].map(String::from);
+        let mut columns = TableColumns::from_config(&names, &widths).unwrap();
+
+        let (shown, constraints) = columns.fitting(100);
+        assert_eq!(vec![Column::Issuer, Column::Otp, Column::Tags], shown);
+        assert_eq!(Constraint::Ratio(50, 100), constraints[2]);
+        // Narrow tables leave out the tags
+        assert_eq!(vec![Column::Issuer, Column::Otp], columns.fitting(50).0);
+
+        columns.toggle(Column::Label);
+        columns.toggle(Column::Otp);
+        assert_eq!(
+            vec![Column::Issuer, Column::Label, Column::Tags],
+            columns.fitting(100).0
+        );
+        assert_eq!(vec![Column::Issuer, Column::Label], columns.fitting(50).0);
+
+        assert_eq!(5, TableColumns::default().fitting(100).0.len());
+        assert!(TableColumns::from_config(&[String::from("icon")], &BTreeMap::new()).is_err());
+        assert!(TableColumns::from_config(&[], &BTreeMap::new()).is_err());
+        let names = ["otp", "issuer", "otp"].map(String::from);
+        assert_eq!(
+            Err(String::from("The otp column is listed twice")),
+            TableColumns::from_config(&names, &BTreeMap::new()).map(|_| ())
+        );
+    }
+}
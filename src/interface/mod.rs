@@ -1,8 +1,20 @@
 pub mod app;
-mod enums;
+pub mod auto_lock;
+mod big_code;
+pub mod enums;
 pub mod event;
+mod form;
+pub mod graphics;
 pub mod handler;
+mod journal;
+pub mod keymap;
+pub mod layout;
+pub mod password_prompt;
 mod popup;
+pub mod qr_guard;
 mod row;
 pub mod stateful_table;
+mod status_bar;
+pub mod text_input;
+pub mod theme;
 pub mod ui;
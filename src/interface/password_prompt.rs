@@ -0,0 +1,91 @@
+use std::time::{Duration, Instant};
+
+use zeroize::Zeroizing;
+
+use crate::i18n::tr;
+
+/// Dashboard actions which reveal secrets or destroy data, allowed only after typing the password again
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ProtectedAction {
+    Delete,
+    Edit,
+    QrCode,
+    RecoveryCodes,
+    Export,
+}
+
+impl ProtectedAction {
+    /// Asks the password for this action
+    pub fn prompt(&self) -> &'static str {
+        tr(match self {
+            ProtectedAction::Delete => "protected-delete",
+            ProtectedAction::Edit => "protected-edit",
+            ProtectedAction::QrCode => "protected-qrcode",
+            ProtectedAction::RecoveryCodes => "protected-recovery-codes",
+            ProtectedAction::Export => "protected-export",
+        })
+    }
+}
+
+/// Asks the database password again, like sudo does, remembering it for the grace period
+pub struct PasswordPrompt {
+    grace_period: Duration,
+    last_confirmed: Option<Instant>,
+    /// Run once the password is confirmed
+    pub(crate) action: Option<ProtectedAction>,
+    pub(crate) password: Zeroizing<String>,
+    /// Reason of the last failed attempt
+    pub(crate) error: Option<String>,
+}
+
+impl PasswordPrompt {
+    pub fn new(grace_period: Duration) -> Self {
+        PasswordPrompt {
+            grace_period,
+            last_confirmed: None,
+            action: None,
+            password: Zeroizing::default(),
+            error: None,
+        }
+    }
+
+    /// True if the password was typed within the grace period
+    pub fn is_confirmed(&self) -> bool {
+        self.last_confirmed
+            .is_some_and(|time| time.elapsed() < self.grace_period)
+    }
+
+    pub fn confirm(&mut self) {
+        self.last_confirmed = Some(Instant::now());
+        self.error = None;
+    }
+
+    /// Asks the password again for the next protected action
+    pub fn forget(&mut self) {
+        self.last_confirmed = None;
+        self.action = None;
+        self.error = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::PasswordPrompt;
+
+    #[test]
+    fn test_grace_period() {
+        let mut prompt = PasswordPrompt::new(Duration::from_secs(60));
+        assert!(!prompt.is_confirmed());
+        prompt.confirm();
+        assert!(prompt.is_confirmed());
+        prompt.forget();
+        assert!(!prompt.is_confirmed());
+
+        // Without a grace period the password is asked every time
+        let mut prompt = PasswordPrompt::new(Duration::ZERO);
+        prompt.confirm();
+        assert!(!prompt.is_confirmed());
+    }
+}
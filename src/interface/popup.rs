@@ -1,8 +1,24 @@
 use ratatui::layout::{Constraint, Direction};
 use ratatui::layout::{Layout, Rect};
 
+/// Popups are never smaller than this, unless the terminal is, so their text stays readable
+const MIN_POPUP_WIDTH: u16 = 40;
+const MIN_POPUP_HEIGHT: u16 = 7;
+
 /// helper function to create a centered rect using up certain percentage of the available rect `r`
 pub(crate) fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
+    let area = percentage_rect(percent_x, percent_y, r);
+    let width = area.width.max(MIN_POPUP_WIDTH.min(r.width));
+    let height = area.height.max(MIN_POPUP_HEIGHT.min(r.height));
+    Rect::new(
+        r.x + (r.width - width) / 2,
+        r.y + (r.height - height) / 2,
+        width,
+        height,
+    )
+}
+
+fn percentage_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
     let popup_layout = Layout::default()
         .direction(Direction::Vertical)
         .constraints(
@@ -27,3 +43,26 @@ pub(crate) fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
         )
         .split(popup_layout[1])[1]
 }
+
+#[cfg(test)]
+mod tests {
+    use ratatui::layout::Rect;
+
+    use super::centered_rect;
+
+    #[test]
+    fn test_centered_rect() {
+        assert_eq!(
+            Rect::new(20, 20, 60, 10),
+            centered_rect(60, 20, Rect::new(0, 0, 100, 50))
+        );
+        assert_eq!(
+            Rect::new(5, 1, 40, 7),
+            centered_rect(60, 20, Rect::new(0, 0, 50, 10))
+        );
+        assert_eq!(
+            Rect::new(0, 0, 30, 4),
+            centered_rect(60, 20, Rect::new(0, 0, 30, 4))
+        );
+    }
+}
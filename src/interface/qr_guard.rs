@@ -0,0 +1,74 @@
+use std::time::{Duration, Instant};
+
+/// Shortens the time the QR code, which shows the secret, stays on screen and can be captured
+#[derive(Default)]
+pub struct QrGuard {
+    /// The page is left after this time, None to keep it
+    timeout: Option<Duration>,
+    /// The page is shown only while its key is held down, until it is not repeated for this time
+    hold: Option<Duration>,
+    opened_at: Option<Instant>,
+    pressed_at: Option<Instant>,
+}
+
+impl QrGuard {
+    pub fn new(timeout: Option<Duration>, hold: Option<Duration>) -> Self {
+        QrGuard {
+            timeout,
+            hold,
+            ..Default::default()
+        }
+    }
+
+    pub fn is_hold(&self) -> bool {
+        self.hold.is_some()
+    }
+
+    pub fn open(&mut self) {
+        let now = Instant::now();
+        self.opened_at = Some(now);
+        self.pressed_at = Some(now);
+    }
+
+    /// The key of the page was repeated, so it is still held
+    pub fn press(&mut self) {
+        self.pressed_at = Some(Instant::now());
+    }
+
+    /// True if the page must be left, because of the timeout or the key being released
+    pub fn is_expired(&self, now: Instant) -> bool {
+        let elapsed = |time: Option<Instant>| time.map(|time| now.saturating_duration_since(time));
+        let timed_out = self
+            .timeout
+            .zip(elapsed(self.opened_at))
+            .is_some_and(|(timeout, elapsed)| elapsed >= timeout);
+        let released = self
+            .hold
+            .zip(elapsed(self.pressed_at))
+            .is_some_and(|(delay, elapsed)| elapsed >= delay);
+        timed_out || released
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::QrGuard;
+
+    #[test]
+    fn test_qr_guard() {
+        let mut guard = QrGuard::new(Some(Duration::from_secs(10)), None);
+        guard.open();
+        let now = Instant::now();
+        assert!(!guard.is_expired(now + Duration::from_secs(5)));
+        assert!(guard.is_expired(now + Duration::from_secs(11)));
+
+        let mut guard = QrGuard::new(None, Some(Duration::from_secs(1)));
+        guard.open();
+        assert!(!guard.is_expired(now));
+        assert!(!guard.is_expired(now + Duration::from_millis(700)));
+        assert!(guard.is_expired(now + Duration::from_secs(2)));
+        assert!(!QrGuard::default().is_expired(now + Duration::from_secs(3600)));
+    }
+}
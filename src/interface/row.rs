@@ -1,15 +1,119 @@
-use ratatui::style::Color::{Black, Yellow};
-use ratatui::style::Style;
+use std::str::FromStr;
+
+use cotp::otp::otp_element::{group_digits, OTPElement};
+use ratatui::style::{Color, Style};
+use ratatui::text::{Line, Span};
 use ratatui::widgets::Cell;
+use sha2::{Digest, Sha256};
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+use crate::interface::theme::Theme;
 
+/// The values include the generated code, wiped when the row is dropped
+#[derive(Zeroize, ZeroizeOnDrop)]
 pub(crate) struct Row {
+    /// Index of the represented element inside the database
+    pub(crate) element_index: usize,
     pub(crate) values: Vec<String>,
     has_error: bool,
+    /// Seconds before the code changes, None for counter based codes
+    remaining_seconds: Option<u64>,
+    /// The secret is shared with other codes, likely added twice
+    duplicate: bool,
+    /// Characters per group of the shown code, 0 to show it as it is copied
+    digit_group: u64,
+    #[zeroize(skip)]
+    badge: Badge,
+}
+
+/// Colored letter or glyph shown before the issuer, in place of the logo of the service
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub(crate) struct Badge {
+    pub(crate) text: String,
+    pub(crate) color: Color,
+}
+
+/// Characters of the icon text shown in the badge, so it does not hide the issuer
+const BADGE_LENGTH: usize = 2;
+
+/// Colors of the issuers without one, chosen by the name so it never changes
+const BADGE_PALETTE: [Color; 8] = [
+    Color::Red,
+    Color::Green,
+    Color::Yellow,
+    Color::Blue,
+    Color::Magenta,
+    Color::Cyan,
+    Color::LightRed,
+    Color::LightBlue,
+];
+
+impl Badge {
+    /// The text and color of the icon, or the first letter of the issuer in its own color
+    pub(crate) fn new(element: &OTPElement) -> Self {
+        let icon = element.icon.as_ref();
+        let name = if element.issuer.is_empty() {
+            &element.label
+        } else {
+            &element.issuer
+        };
+        let text = match icon.and_then(|icon| icon.text.as_deref()) {
+            // Like the labels of 2FAS, a glyph or a couple of letters
+            Some(text) if !text.is_empty() => text.chars().take(BADGE_LENGTH).collect(),
+            _ => name
+                .chars()
+                .next()
+                .map(|c| c.to_uppercase().collect())
+                .unwrap_or_else(|| String::from("?")),
+        };
+        let color = icon
+            .and_then(|icon| icon.color.as_deref())
+            .and_then(|color| Color::from_str(color).ok())
+            .unwrap_or_else(|| {
+                // The hasher of the standard library can change with every Rust release
+                let digest = Sha256::digest(name.to_lowercase().as_bytes());
+                BADGE_PALETTE[digest[0] as usize % BADGE_PALETTE.len()]
+            });
+        Badge { text, color }
+    }
+
+    pub(crate) fn span(&self) -> Span<'static> {
+        Span::styled(
+            format!(" {} ", self.text),
+            Style::default().fg(Color::Black).bg(self.color),
+        )
+    }
 }
 
+/// Codes are highlighted in their last seconds of validity
+const EXPIRING_SECONDS: u64 = 5;
+
+pub(crate) const HIDDEN_CODE: &str = "******";
+
+/// Position of the issuer in the row values, shown after its badge
+const ISSUER_COLUMN: usize = 1;
+/// Position of the OTP code in the row values
+pub(crate) const CODE_COLUMN: usize = 3;
+
 impl Row {
-    pub(crate) fn new(values: Vec<String>, has_error: bool) -> Self {
-        Row { values, has_error }
+    pub(crate) fn new(
+        element_index: usize,
+        values: Vec<String>,
+        has_error: bool,
+        remaining_seconds: Option<u64>,
+        duplicate: bool,
+        digit_group: u64,
+        badge: Badge,
+    ) -> Self {
+        Row {
+            element_index,
+            values,
+            has_error,
+            remaining_seconds,
+            duplicate,
+            digit_group,
+            badge,
+        }
     }
     pub fn height(&self) -> u16 {
         (self
@@ -21,17 +125,78 @@ impl Row {
             + 1) as u16
     }
 
-    pub fn cells(&self) -> Vec<Cell> {
+    /// The code split in groups of digits, the value copied is the one without spaces
+    pub(crate) fn shown_code(&self) -> String {
+        group_digits(&self.values[CODE_COLUMN], self.digit_group)
+    }
+
+    /// Hidden codes are replaced by asterisks, protecting them from onlookers
+    pub fn cells(&self, theme: &Theme, hide_code: bool) -> Vec<Cell<'_>> {
         self.values
             .iter()
-            .map(|c| {
+            .enumerate()
+            .map(|(i, c)| {
+                let expiring = self
+                    .remaining_seconds
+                    .is_some_and(|seconds| seconds <= EXPIRING_SECONDS);
                 let style = if self.has_error {
-                    Style::default().bg(Yellow).fg(Black)
+                    Style::default()
+                        .bg(theme.error_row_background)
+                        .fg(theme.error_row_foreground)
+                } else if expiring && i == CODE_COLUMN {
+                    Style::default().fg(theme.expiring)
+                } else if self.duplicate && i == ISSUER_COLUMN {
+                    Style::default().fg(theme.duplicate)
                 } else {
                     Style::default()
                 };
-                Cell::from(c.as_str()).style(style)
+                if hide_code && i == CODE_COLUMN && !self.has_error {
+                    Cell::from(HIDDEN_CODE).style(style)
+                } else if i == ISSUER_COLUMN {
+                    Cell::from(Line::from(vec![
+                        self.badge.span(),
+                        Span::raw(" "),
+                        Span::raw(c.as_str()),
+                    ]))
+                    .style(style)
+                } else if i == CODE_COLUMN {
+                    Cell::from(self.shown_code()).style(style)
+                } else {
+                    Cell::from(c.as_str()).style(style)
+                }
             })
             .collect()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use cotp::otp::from_otp_uri::FromOtpUri;
+    use cotp::otp::otp_element::{Icon, OTPElement};
+    use ratatui::style::Color;
+
+    use super::Badge;
+
+    #[test]
+    fn test_badge() {
+        let mut element =
+            OTPElement::from_otp_uri("otpauth://totp/github:alice?secret=JBSWY3DPEHPK3PXP")
+                .unwrap();
+        let badge = Badge::new(&element);
+        assert_eq!("G", badge.text);
+        // Same issuer, same color, in every release
+        element.issuer = String::from("GitHub");
+        assert_eq!(badge.color, Badge::new(&element).color);
+        assert_eq!(Color::Red, badge.color);
+
+        element.icon = Some(Icon {
+            image: None,
+            mime: None,
+            text: Some(String::from("GHE")),
+            color: Some(String::from("#fb8c00")),
+        });
+        let badge = Badge::new(&element);
+        assert_eq!("GH", badge.text);
+        assert_eq!(Color::Rgb(0xfb, 0x8c, 0x00), badge.color);
+    }
+}
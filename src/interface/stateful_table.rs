@@ -1,11 +1,26 @@
-use crate::interface::row::Row;
+use std::cmp::Reverse;
+use std::collections::{BTreeSet, HashSet};
+
+use crate::interface::enums::SortMode;
+use crate::interface::row::{Badge, Row};
 use ratatui::widgets::TableState;
 
-use crate::otp::{otp_element::OTPElement, otp_type::OTPType};
+use cotp::otp::audit::duplicate_groups;
+use cotp::otp::{otp_element::OTPElement, otp_type::OTPType};
+use cotp::search::Regex;
+
+/// Shown next to the id of pinned elements
+const PINNED_MARKER: char = '★';
 
 pub struct StatefulTable {
     pub(crate) state: TableState,
     pub(crate) items: Vec<Row>,
+    /// Database indexes of the elements marked for a batch operation
+    pub(crate) marked: BTreeSet<usize>,
+    /// Database index of the element last marked or unmarked, where the marked ranges start
+    mark_anchor: Option<usize>,
+    /// Database indexes of the elements sharing their secret, found again only when the codes change
+    duplicates: HashSet<usize>,
 }
 
 impl StatefulTable {
@@ -13,10 +28,72 @@ impl StatefulTable {
         let mut table = StatefulTable {
             state: TableState::default(),
             items: vec![],
+            marked: BTreeSet::new(),
+            mark_anchor: None,
+            duplicates: HashSet::new(),
         };
-        fill_table(&mut table, elements);
+        table.update_duplicates(elements);
+        fill_table(&mut table, elements, None, SortMode::Issuer, 0);
         table
     }
+
+    /// Finds the elements sharing their secret, after the codes changed
+    pub fn update_duplicates(&mut self, elements: &[OTPElement]) {
+        self.duplicates = duplicate_groups(elements).into_iter().flatten().collect();
+    }
+
+    /// Returns the database index of the element shown in the selected row
+    pub fn selected_element_index(&self) -> Option<usize> {
+        self.state
+            .selected()
+            .and_then(|i| self.items.get(i))
+            .map(|row| row.element_index)
+    }
+
+    /// Marks the element of the selected row, or unmarks it if it was marked
+    pub fn toggle_mark(&mut self) {
+        let Some(index) = self.selected_element_index() else {
+            return;
+        };
+        if !self.marked.remove(&index) {
+            self.marked.insert(index);
+        }
+        self.mark_anchor = Some(index);
+    }
+
+    /// Marks every row between the last marked or unmarked one and the selected one, like a visual selection
+    pub fn mark_range(&mut self) {
+        let Some(selected) = self.state.selected() else {
+            return;
+        };
+        let anchor = self
+            .mark_anchor
+            .and_then(|anchor| {
+                self.items
+                    .iter()
+                    .position(|row| row.element_index == anchor)
+            })
+            .unwrap_or(selected);
+        let rows = anchor.min(selected)..=anchor.max(selected);
+        self.marked
+            .extend(self.items[rows].iter().map(|row| row.element_index));
+        self.mark_anchor = self.selected_element_index();
+    }
+
+    pub fn clear_marks(&mut self) {
+        self.marked.clear();
+        self.mark_anchor = None;
+    }
+
+    /// Database indexes of the marked elements, or of the selected one if none is marked
+    pub fn marked_or_selected(&self) -> Vec<usize> {
+        if self.marked.is_empty() {
+            self.selected_element_index().into_iter().collect()
+        } else {
+            self.marked.iter().copied().collect()
+        }
+    }
+
     pub fn next(&mut self) {
         let selected = if self.items.is_empty() {
             None
@@ -35,6 +112,61 @@ impl StatefulTable {
         self.state.select(selected);
     }
 
+    /// Returns the index of the row drawn at the given line, counted from the first visible row.
+    /// Rows are separated by an empty line.
+    pub fn row_at(&self, line: u16) -> Option<usize> {
+        let mut top = 0;
+        for (index, row) in self.items.iter().enumerate().skip(self.state.offset()) {
+            let bottom = top + row.height();
+            if line < bottom {
+                return Some(index);
+            }
+            if line == bottom {
+                return None;
+            }
+            top = bottom + 1;
+        }
+        None
+    }
+
+    pub fn first(&mut self) {
+        self.state.select((!self.items.is_empty()).then_some(0));
+    }
+
+    pub fn last(&mut self) {
+        self.state.select(self.items.len().checked_sub(1));
+    }
+
+    /// Selects the next row, or the previous one going backwards, whose issuer or label matches the query,
+    /// a regular expression like in cotp list --search. Wraps around the table like the vim search does.
+    pub fn search_next(&mut self, query: &str, forward: bool) {
+        let length = self.items.len();
+        if length == 0 || query.is_empty() {
+            return;
+        }
+        let query = Regex::lenient(query);
+        let current = self
+            .state
+            .selected()
+            .unwrap_or(if forward { length - 1 } else { 0 });
+        let found = (1..=length)
+            .map(|offset| {
+                if forward {
+                    (current + offset) % length
+                } else {
+                    (current + length - offset) % length
+                }
+            })
+            .find(|&i| {
+                self.items[i].values[1..=2]
+                    .iter()
+                    .any(|value| query.is_match(value))
+            });
+        if found.is_some() {
+            self.state.select(found);
+        }
+    }
+
     pub fn previous(&mut self) {
         let i = match self.state.selected() {
             Some(i) => {
@@ -56,8 +188,22 @@ impl StatefulTable {
     }
 }
 
-pub fn fill_table(table: &mut StatefulTable, elements: &[OTPElement]) {
-    for (i, element) in elements.iter().enumerate() {
+pub fn fill_table(
+    table: &mut StatefulTable,
+    elements: &[OTPElement],
+    tag_filter: Option<&str>,
+    sort_mode: SortMode,
+    digit_group: u64,
+) {
+    let mut filtered: Vec<(usize, &OTPElement)> = elements
+        .iter()
+        .enumerate()
+        .filter(|(_, element)| tag_filter.is_none_or(|tag| element.has_tag(tag)))
+        .collect();
+    sort_elements(&mut filtered, sort_mode);
+    // Pinned elements come first, keeping the sorting among them
+    filtered.sort_by_key(|(_, element)| !element.pinned);
+    for (i, element) in filtered {
         let label = match element.type_ {
             OTPType::Hotp => match element.counter {
                 Some(result) => {
@@ -69,18 +215,136 @@ pub fn fill_table(table: &mut StatefulTable, elements: &[OTPElement]) {
         };
         let result = element.get_otp_code();
 
-        let error = result.is_err();
+        // OCRA codes are only computed for a challenge
+        let error = result.is_err() && element.type_ != OTPType::Ocra;
+        let remaining_seconds = element.remaining_seconds();
         table.items.push(Row::new(
+            i,
             vec![
-                (i + 1).to_string(),
+                if element.pinned {
+                    format!("{} {PINNED_MARKER}", i + 1)
+                } else {
+                    (i + 1).to_string()
+                },
                 element.issuer.to_owned(),
                 label,
                 match result {
                     Ok(code) => code,
                     Err(e) => e.to_string(),
                 },
+                remaining_seconds
+                    .map(|s| format!("{s}s"))
+                    .unwrap_or_else(|| String::from("-")),
+                element.type_.to_string(),
+                element.tags.join(", "),
             ],
             error,
+            remaining_seconds,
+            table.duplicates.contains(&i),
+            // Errors are shown as they are
+            if error {
+                0
+            } else {
+                element.digit_group.unwrap_or(digit_group)
+            },
+            Badge::new(element),
         ));
     }
 }
+
+fn sort_elements(elements: &mut [(usize, &OTPElement)], sort_mode: SortMode) {
+    // Stable sorts, so elements with equal keys keep the database order
+    match sort_mode {
+        SortMode::Issuer => {
+            elements.sort_by_cached_key(|(_, e)| (e.issuer.to_lowercase(), e.label.to_lowercase()))
+        }
+        SortMode::Label => {
+            elements.sort_by_cached_key(|(_, e)| (e.label.to_lowercase(), e.issuer.to_lowercase()))
+        }
+        SortMode::CreationTime => elements.sort_by_key(|(_, e)| Reverse(e.created_at)),
+        SortMode::UsageCount => elements.sort_by_key(|(_, e)| Reverse(e.usage_count)),
+        SortMode::LastUsed => elements.sort_by_key(|(_, e)| Reverse(e.last_used)),
+        SortMode::Manual => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_element;
+
+    use super::StatefulTable;
+
+    #[test]
+    fn test_search_next() {
+        let mut table = StatefulTable::new(&[
+            test_element("Alpha", "me@mail.com"),
+            test_element("Beta", "other"),
+            test_element("Gamma", "me@mail.com"),
+        ]);
+        table.first();
+
+        table.search_next("MAIL", true);
+        assert_eq!(Some(2), table.state.selected());
+        table.search_next("mail", true);
+        assert_eq!(Some(0), table.state.selected());
+        table.search_next("mail", false);
+        assert_eq!(Some(2), table.state.selected());
+        table.search_next("missing", true);
+        assert_eq!(Some(2), table.state.selected());
+
+        table.last();
+        assert_eq!(Some(2), table.state.selected());
+    }
+
+    #[test]
+    fn test_marks() {
+        let mut table = StatefulTable::new(&[
+            test_element("Alpha", "a"),
+            test_element("Beta", "b"),
+            test_element("Gamma", "c"),
+            test_element("Delta", "d"),
+        ]);
+        assert!(table.marked_or_selected().is_empty());
+        table.first();
+        assert_eq!(vec![0], table.marked_or_selected());
+
+        // Rows are sorted by issuer: Alpha, Beta, Delta, Gamma
+        table.next();
+        table.toggle_mark();
+        table.next();
+        table.next();
+        table.mark_range();
+        assert_eq!(vec![1, 2, 3], table.marked_or_selected());
+
+        table.toggle_mark();
+        assert_eq!(vec![1, 3], table.marked_or_selected());
+        table.clear_marks();
+        table.mark_range();
+        assert_eq!(vec![2], table.marked_or_selected());
+    }
+
+    #[test]
+    fn test_pinned_first() {
+        let mut pinned = test_element("Zeta", "z");
+        pinned.pinned = true;
+        let table = StatefulTable::new(&[
+            test_element("Alpha", "a"),
+            pinned,
+            test_element("Beta", "b"),
+        ]);
+
+        let issuers: Vec<&str> = table.items.iter().map(|r| r.values[1].as_str()).collect();
+        assert_eq!(vec!["Zeta", "Alpha", "Beta"], issuers);
+        assert_eq!("2 ★", table.items[0].values[0]);
+    }
+
+    #[test]
+    fn test_row_at() {
+        let table = StatefulTable::new(&[test_element("Alpha", "a"), test_element("Beta", "b")]);
+
+        assert_eq!(Some(0), table.row_at(0));
+        assert_eq!(None, table.row_at(1));
+        assert_eq!(Some(1), table.row_at(2));
+        assert_eq!(None, table.row_at(4));
+    }
+}
@@ -0,0 +1,93 @@
+use std::time::{Duration, Instant};
+
+/// Messages are dismissed after this time, errors stay twice as long
+const MESSAGE_DURATION: Duration = Duration::from_secs(4);
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum Severity {
+    Info,
+    Error,
+}
+
+struct Message {
+    text: String,
+    severity: Severity,
+    shown_at: Instant,
+}
+
+/// Transient messages shown below the table, like the result of the last action
+#[derive(Default)]
+pub(crate) struct StatusBar {
+    message: Option<Message>,
+}
+
+impl StatusBar {
+    pub(crate) fn info(&mut self, text: impl Into<String>) {
+        self.push(text.into(), Severity::Info);
+    }
+
+    pub(crate) fn error(&mut self, text: impl Into<String>) {
+        self.push(text.into(), Severity::Error);
+    }
+
+    /// Shows the result of an action, the error one in the error color
+    pub(crate) fn show(&mut self, result: Result<String, String>) {
+        match result {
+            Ok(text) => self.info(text),
+            Err(text) => self.error(text),
+        }
+    }
+
+    fn push(&mut self, text: String, severity: Severity) {
+        self.message = Some(Message {
+            text,
+            severity,
+            shown_at: Instant::now(),
+        });
+    }
+
+    /// Dismisses the message shown long enough
+    pub(crate) fn tick(&mut self, now: Instant) {
+        let expired = self.message.as_ref().is_some_and(|message| {
+            let duration = match message.severity {
+                Severity::Info => MESSAGE_DURATION,
+                Severity::Error => MESSAGE_DURATION * 2,
+            };
+            now.saturating_duration_since(message.shown_at) >= duration
+        });
+        if expired {
+            self.message = None;
+        }
+    }
+
+    pub(crate) fn message(&self) -> Option<(&str, Severity)> {
+        self.message
+            .as_ref()
+            .map(|message| (message.text.as_str(), message.severity))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{Severity, StatusBar};
+
+    #[test]
+    fn test_status_bar() {
+        let mut status = StatusBar::default();
+        status.show(Err(String::from("Cannot copy")));
+        let now = Instant::now();
+        status.tick(now + Duration::from_secs(5));
+        assert_eq!(Some(("Cannot copy", Severity::Error)), status.message());
+
+        status.info("Copied GitHub code");
+        status.tick(now + Duration::from_secs(1));
+        assert_eq!(
+            Some(("Copied GitHub code", Severity::Info)),
+            status.message()
+        );
+        status.tick(now + Duration::from_secs(5));
+        assert_eq!(None, status.message());
+    }
+}
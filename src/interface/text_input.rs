@@ -0,0 +1,185 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::{Line, Span};
+
+/// Single line text field with the readline keys: moving the cursor by character or word, Home, End,
+/// Ctrl-A, Ctrl-E, deleting the previous word with Ctrl-W and the text before or after the cursor with Ctrl-U, Ctrl-K
+#[derive(Default, Debug)]
+pub struct TextInput {
+    text: String,
+    /// Byte position of the cursor, always on a character boundary
+    cursor: usize,
+}
+
+impl TextInput {
+    /// The cursor starts after the text
+    pub fn new(text: &str) -> Self {
+        TextInput {
+            text: String::from(text),
+            cursor: text.len(),
+        }
+    }
+
+    pub fn value(&self) -> &str {
+        &self.text
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.text.clear();
+        self.cursor = 0;
+    }
+
+    /// Inserts pasted text at the cursor, a single line is kept
+    pub fn insert_str(&mut self, text: &str) {
+        let text: String = text.chars().filter(|c| !c.is_control()).collect();
+        self.text.insert_str(self.cursor, &text);
+        self.cursor += text.len();
+    }
+
+    /// Edits the text, returning false for the keys which are not editing ones
+    pub fn handle_key(&mut self, key_event: KeyEvent) -> bool {
+        let control = key_event.modifiers.contains(KeyModifiers::CONTROL);
+        let alt = key_event.modifiers.contains(KeyModifiers::ALT);
+        match key_event.code {
+            KeyCode::Char('a') if control => self.cursor = 0,
+            KeyCode::Char('e') if control => self.cursor = self.text.len(),
+            KeyCode::Char('w') if control => {
+                let start = self.previous_word();
+                self.text.replace_range(start..self.cursor, "");
+                self.cursor = start;
+            }
+            KeyCode::Char('u') if control => {
+                self.text.replace_range(..self.cursor, "");
+                self.cursor = 0;
+            }
+            KeyCode::Char('k') if control => self.text.truncate(self.cursor),
+            KeyCode::Char('b') if alt => self.cursor = self.previous_word(),
+            KeyCode::Char('f') if alt => self.cursor = self.next_word(),
+            KeyCode::Char(c) if !control && !alt => {
+                self.text.insert(self.cursor, c);
+                self.cursor += c.len_utf8();
+            }
+            KeyCode::Left if control => self.cursor = self.previous_word(),
+            KeyCode::Right if control => self.cursor = self.next_word(),
+            KeyCode::Left => self.cursor = self.previous_char(),
+            KeyCode::Right => self.cursor = self.next_char(),
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::End => self.cursor = self.text.len(),
+            KeyCode::Backspace => {
+                let start = self.previous_char();
+                self.text.replace_range(start..self.cursor, "");
+                self.cursor = start;
+            }
+            KeyCode::Delete => {
+                let end = self.next_char();
+                self.text.replace_range(self.cursor..end, "");
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// The text with the cursor drawn in reverse video
+    pub fn line(&self, show_cursor: bool) -> Line<'_> {
+        if !show_cursor {
+            return Line::from(self.text.as_str());
+        }
+        let end = self.next_char();
+        let under_cursor = if end == self.cursor {
+            " "
+        } else {
+            &self.text[self.cursor..end]
+        };
+        Line::from(vec![
+            Span::raw(&self.text[..self.cursor]),
+            Span::styled(
+                under_cursor,
+                Style::default().add_modifier(Modifier::REVERSED),
+            ),
+            Span::raw(&self.text[end..]),
+        ])
+    }
+
+    fn previous_char(&self) -> usize {
+        self.text[..self.cursor]
+            .char_indices()
+            .next_back()
+            .map_or(0, |(i, _)| i)
+    }
+
+    fn next_char(&self) -> usize {
+        self.text[self.cursor..]
+            .chars()
+            .next()
+            .map_or(self.cursor, |c| self.cursor + c.len_utf8())
+    }
+
+    /// Start of the word before the cursor, skipping the spaces first like readline
+    fn previous_word(&self) -> usize {
+        let before = self.text[..self.cursor].trim_end();
+        before
+            .char_indices()
+            .rev()
+            .find(|(_, c)| c.is_whitespace())
+            .map_or(0, |(i, c)| i + c.len_utf8())
+    }
+
+    /// End of the word after the cursor
+    fn next_word(&self) -> usize {
+        let after = &self.text[self.cursor..];
+        let start = after.len() - after.trim_start().len();
+        after[start..]
+            .char_indices()
+            .find(|(_, c)| c.is_whitespace())
+            .map_or(self.text.len(), |(i, _)| self.cursor + start + i)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    use super::TextInput;
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    fn ctrl(c: char) -> KeyEvent {
+        KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL)
+    }
+
+    #[test]
+    fn test_text_input() {
+        let mut input = TextInput::new("github work");
+        input.handle_key(ctrl('w'));
+        assert_eq!("github ", input.value());
+
+        input.handle_key(key(KeyCode::Home));
+        input.handle_key(key(KeyCode::Right));
+        input.handle_key(key(KeyCode::Char('é')));
+        assert_eq!("géithub ", input.value());
+        input.handle_key(key(KeyCode::Left));
+        input.handle_key(key(KeyCode::Delete));
+        assert_eq!("github ", input.value());
+        input.handle_key(key(KeyCode::Backspace));
+        assert_eq!("ithub ", input.value());
+
+        input.handle_key(KeyEvent::new(KeyCode::Right, KeyModifiers::CONTROL));
+        input.insert_str("lab\n");
+        assert_eq!("ithublab ", input.value());
+        input.handle_key(ctrl('a'));
+        input.handle_key(KeyEvent::new(KeyCode::Char('f'), KeyModifiers::ALT));
+        input.handle_key(ctrl('k'));
+        assert_eq!("ithublab", input.value());
+        input.handle_key(ctrl('u'));
+        assert!(input.is_empty());
+
+        assert!(!input.handle_key(key(KeyCode::Enter)));
+        assert!(!input.handle_key(ctrl('f')));
+    }
+}
@@ -0,0 +1,178 @@
+use std::str::FromStr;
+
+use cotp::config::ThemeConfig;
+use ratatui::style::{Color, Style};
+
+/// Colors used to draw the dashboard
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Theme {
+    pub foreground: Color,
+    pub background: Color,
+    /// Focused search bar and selected form field
+    pub accent: Color,
+    /// Form validation errors
+    pub error: Color,
+    pub header_foreground: Color,
+    pub header_background: Color,
+    /// Selected table row
+    pub highlight_foreground: Color,
+    pub highlight_background: Color,
+    /// Elapsed part of the progress bar
+    pub gauge_foreground: Color,
+    pub gauge_background: Color,
+    /// Codes about to change
+    pub expiring: Color,
+    /// Issuers of the codes sharing their secret with others
+    pub duplicate: Color,
+    /// Rows whose code cannot be generated
+    pub error_row_foreground: Color,
+    pub error_row_background: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::dark()
+    }
+}
+
+impl Theme {
+    pub fn dark() -> Self {
+        Theme {
+            foreground: Color::White,
+            background: Color::Reset,
+            accent: Color::LightRed,
+            error: Color::Red,
+            header_foreground: Color::Black,
+            header_background: Color::White,
+            highlight_foreground: Color::Black,
+            highlight_background: Color::White,
+            gauge_foreground: Color::DarkGray,
+            gauge_background: Color::White,
+            expiring: Color::Yellow,
+            duplicate: Color::Magenta,
+            error_row_foreground: Color::Black,
+            error_row_background: Color::Yellow,
+        }
+    }
+
+    pub fn light() -> Self {
+        Theme {
+            foreground: Color::Black,
+            background: Color::Reset,
+            accent: Color::Red,
+            error: Color::Red,
+            header_foreground: Color::White,
+            header_background: Color::Black,
+            highlight_foreground: Color::White,
+            highlight_background: Color::Blue,
+            gauge_foreground: Color::Gray,
+            gauge_background: Color::Black,
+            expiring: Color::Red,
+            duplicate: Color::Magenta,
+            error_row_foreground: Color::Black,
+            error_row_background: Color::LightYellow,
+        }
+    }
+
+    pub fn high_contrast() -> Self {
+        Theme {
+            foreground: Color::White,
+            background: Color::Black,
+            accent: Color::Yellow,
+            error: Color::LightRed,
+            header_foreground: Color::Black,
+            header_background: Color::Yellow,
+            highlight_foreground: Color::Black,
+            highlight_background: Color::Cyan,
+            gauge_foreground: Color::Yellow,
+            gauge_background: Color::Black,
+            expiring: Color::LightRed,
+            duplicate: Color::LightMagenta,
+            error_row_foreground: Color::White,
+            error_row_background: Color::Red,
+        }
+    }
+
+    /// Returns the built-in theme with the given name
+    pub fn by_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "dark" => Some(Theme::dark()),
+            "light" => Some(Theme::light()),
+            "high-contrast" | "high_contrast" => Some(Theme::high_contrast()),
+            _ => None,
+        }
+    }
+
+    /// Builds the theme from the `[theme]` configuration section,
+    /// the name argument takes precedence over the configured one.
+    pub fn from_config(config: &ThemeConfig, name: Option<&str>) -> Result<Self, String> {
+        let name = name.or(config.name.as_deref()).unwrap_or("dark");
+        let mut theme = Theme::by_name(name).ok_or_else(|| {
+            format!("Unknown theme: \"{name}\", available themes are dark, light and high-contrast")
+        })?;
+        for (key, value) in &config.colors {
+            let color = Color::from_str(value)
+                .map_err(|_| format!("Invalid color for {key}: \"{value}\""))?;
+            *theme
+                .color_mut(key)
+                .ok_or_else(|| format!("Unknown theme color: \"{key}\""))? = color;
+        }
+        Ok(theme)
+    }
+
+    fn color_mut(&mut self, name: &str) -> Option<&mut Color> {
+        match name {
+            "foreground" => Some(&mut self.foreground),
+            "background" => Some(&mut self.background),
+            "accent" => Some(&mut self.accent),
+            "error" => Some(&mut self.error),
+            "header_foreground" => Some(&mut self.header_foreground),
+            "header_background" => Some(&mut self.header_background),
+            "highlight_foreground" => Some(&mut self.highlight_foreground),
+            "highlight_background" => Some(&mut self.highlight_background),
+            "gauge_foreground" => Some(&mut self.gauge_foreground),
+            "gauge_background" => Some(&mut self.gauge_background),
+            "expiring" => Some(&mut self.expiring),
+            "duplicate" => Some(&mut self.duplicate),
+            "error_row_foreground" => Some(&mut self.error_row_foreground),
+            "error_row_background" => Some(&mut self.error_row_background),
+            _ => None,
+        }
+    }
+
+    /// Style of the text drawn on the terminal background
+    pub fn text(&self) -> Style {
+        Style::default().fg(self.foreground).bg(self.background)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cotp::config::ThemeConfig;
+    use ratatui::style::Color;
+
+    use super::Theme;
+
+    #[test]
+    fn test_theme_from_config() {
+        let mut config = ThemeConfig::default();
+        assert_eq!(Ok(Theme::dark()), Theme::from_config(&config, None));
+
+        config.name = Some(String::from("light"));
+        config
+            .colors
+            .insert(String::from("accent"), String::from("#ff8800"));
+        let theme = Theme::from_config(&config, None).unwrap();
+        assert_eq!(Color::Rgb(255, 136, 0), theme.accent);
+        assert_eq!(Theme::light().foreground, theme.foreground);
+
+        let theme = Theme::from_config(&config, Some("high-contrast")).unwrap();
+        assert_eq!(Theme::high_contrast().background, theme.background);
+
+        assert!(Theme::from_config(&config, Some("solarized")).is_err());
+        config
+            .colors
+            .insert(String::from("border"), String::from("red"));
+        assert!(Theme::from_config(&config, None).is_err());
+    }
+}
@@ -1,12 +1,16 @@
-use std::io;
+use std::io::{self, Write};
 
-use crossterm::event::{DisableMouseCapture, EnableMouseCapture};
+use crossterm::cursor::MoveTo;
+use crossterm::event::{
+    DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture,
+};
 use crossterm::terminal::{self, EnterAlternateScreen, LeaveAlternateScreen};
 use ratatui::prelude::Backend;
 use ratatui::Terminal;
 
 use crate::interface::app::{App, AppResult};
 use crate::interface::event::EventHandler;
+use crate::interface::graphics::Graphic;
 
 /// Representation of a terminal user interface.
 ///
@@ -18,12 +22,18 @@ pub struct Tui<B: Backend> {
     terminal: Terminal<B>,
     /// Terminal event handler.
     pub events: EventHandler,
+    /// Image drawn by the terminal, sent again only when it changes
+    shown_graphic: Option<Graphic>,
 }
 
 impl<B: Backend> Tui<B> {
     /// Constructs a new instance of [`Tui`].
     pub fn new(terminal: Terminal<B>, events: EventHandler) -> Self {
-        Self { terminal, events }
+        Self {
+            terminal,
+            events,
+            shown_graphic: None,
+        }
     }
 
     /// Initializes the terminal interface.
@@ -31,7 +41,12 @@ impl<B: Backend> Tui<B> {
     /// It enables the raw mode and sets terminal properties.
     pub fn init(&mut self) -> AppResult<()> {
         terminal::enable_raw_mode()?;
-        crossterm::execute!(io::stderr(), EnterAlternateScreen, EnableMouseCapture)?;
+        crossterm::execute!(
+            io::stderr(),
+            EnterAlternateScreen,
+            EnableMouseCapture,
+            EnableBracketedPaste
+        )?;
         self.terminal.hide_cursor()?;
         self.terminal.clear()?;
         Ok(())
@@ -43,6 +58,26 @@ impl<B: Backend> Tui<B> {
     /// [`rendering`]: crate::app::App::render
     pub fn draw(&mut self, app: &mut App) -> AppResult<()> {
         self.terminal.draw(|frame| app.render(frame))?;
+        let graphic = app.graphic.take();
+        if graphic == self.shown_graphic {
+            return Ok(());
+        }
+        if let Some(shown) = self.shown_graphic.take() {
+            if let Some(erase) = shown.erase_sequence() {
+                write!(io::stderr(), "{erase}")?;
+            }
+            // Redraws every cell, covering the image
+            self.terminal.clear()?;
+            self.terminal.draw(|frame| app.render(frame))?;
+            app.graphic = None;
+        }
+        if let Some(graphic) = &graphic {
+            let mut stderr = io::stderr();
+            crossterm::queue!(stderr, MoveTo(graphic.area.x, graphic.area.y))?;
+            write!(stderr, "{}", graphic.sequence.as_str())?;
+            stderr.flush()?;
+        }
+        self.shown_graphic = graphic;
         Ok(())
     }
 
@@ -51,7 +86,12 @@ impl<B: Backend> Tui<B> {
     /// It disables the raw mode and reverts back the terminal properties.
     pub fn exit(&mut self) -> AppResult<()> {
         terminal::disable_raw_mode()?;
-        crossterm::execute!(io::stderr(), LeaveAlternateScreen, DisableMouseCapture)?;
+        crossterm::execute!(
+            io::stderr(),
+            LeaveAlternateScreen,
+            DisableMouseCapture,
+            DisableBracketedPaste
+        )?;
         self.terminal.show_cursor()?;
         Ok(())
     }
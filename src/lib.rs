@@ -0,0 +1,33 @@
+//! Encrypted storage and generation of one time passwords, as used by the cotp authenticator.
+//!
+//! The [`otp`] module contains the [`OTPDatabase`](otp::otp_element::OTPDatabase) and
+//! [`OTPElement`](otp::otp_element::OTPElement) types together with the supported algorithms,
+//! [`crypto`] encrypts and decrypts the database, while [`importers`] and [`exporters`]
+//! convert codes from and to the formats of other authenticator apps.
+//!
+//! ```
+//! use cotp::otp::{from_otp_uri::FromOtpUri, otp_element::OTPElement};
+//!
+//! let element = OTPElement::from_otp_uri(
+//!     "otpauth://hotp/Example:alice?secret=JBSWY3DPEHPK3PXP&counter=0",
+//! )
+//! .unwrap();
+//! assert_eq!("282760", element.get_otp_code().unwrap());
+//! ```
+#![forbid(unsafe_code)]
+
+pub mod backup;
+pub mod clock;
+pub mod config;
+pub mod crypto;
+pub mod exporters;
+pub mod importers;
+pub mod lock;
+pub mod otp;
+pub mod path;
+pub mod prompt;
+pub mod reading;
+pub mod remote;
+pub mod search;
+pub mod sync;
+pub mod utils;
@@ -0,0 +1,110 @@
+//! Advisory locking of the database file, so that concurrent cotp instances do not corrupt it.
+//!
+//! The lock is taken on a separate `.lock` file next to the database, which is never removed.
+//! The operating system releases it when the process exits, so a crashed instance cannot leave a stale lock.
+
+use std::{
+    fs::{File, OpenOptions, TryLockError},
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, Instant},
+};
+
+use color_eyre::eyre::eyre;
+use sha2::{Digest, Sha256};
+
+use crate::path::get_db_path;
+
+const LOCK_EXTENSION: &str = ".lock";
+const LOCK_TIMEOUT: Duration = Duration::from_secs(5);
+const RETRY_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Held while reading or writing the database, released when dropped
+pub struct DatabaseLock {
+    _file: File,
+}
+
+impl DatabaseLock {
+    /// Lock needed to write the database, no other instance can read or write it meanwhile
+    pub fn exclusive() -> color_eyre::Result<DatabaseLock> {
+        DatabaseLock::acquire(&get_db_path(), false, LOCK_TIMEOUT)
+    }
+
+    /// Lock needed to read the database, which can be shared with other readers
+    pub fn shared() -> color_eyre::Result<DatabaseLock> {
+        DatabaseLock::acquire(&get_db_path(), true, LOCK_TIMEOUT)
+    }
+
+    fn acquire(
+        db_path: &Path,
+        shared: bool,
+        timeout: Duration,
+    ) -> color_eyre::Result<DatabaseLock> {
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(lock_path(db_path))
+            .map_err(|e| eyre!("Cannot create the database lock file: {e}"))?;
+        let started = Instant::now();
+        loop {
+            let result = if shared {
+                file.try_lock_shared()
+            } else {
+                file.try_lock()
+            };
+            match result {
+                Ok(()) => return Ok(DatabaseLock { _file: file }),
+                Err(TryLockError::WouldBlock) if started.elapsed() < timeout => {
+                    thread::sleep(RETRY_INTERVAL)
+                }
+                Err(TryLockError::WouldBlock) => {
+                    return Err(eyre!(
+                        "The database is in use by another cotp instance, try again later"
+                    ))
+                }
+                Err(TryLockError::Error(e)) => return Err(eyre!("Cannot lock the database: {e}")),
+            }
+        }
+    }
+}
+
+fn lock_path(db_path: &Path) -> PathBuf {
+    let mut path = db_path.as_os_str().to_owned();
+    path.push(LOCK_EXTENSION);
+    PathBuf::from(path)
+}
+
+/// Identifies the database content read from disk, to notice changes made by other instances
+pub fn fingerprint(contents: &[u8]) -> [u8; 32] {
+    Sha256::digest(contents).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::Path, time::Duration};
+
+    use super::{lock_path, DatabaseLock};
+
+    #[test]
+    fn test_lock_path() {
+        assert_eq!(
+            Path::new("/data/cotp/db.cotp.lock"),
+            lock_path(Path::new("/data/cotp/db.cotp"))
+        );
+    }
+
+    #[test]
+    fn test_exclusive_lock() {
+        let db_path = std::env::temp_dir().join(format!("cotp-lock-test-{}", std::process::id()));
+        let shared = DatabaseLock::acquire(&db_path, true, Duration::ZERO).unwrap();
+        assert!(DatabaseLock::acquire(&db_path, true, Duration::ZERO).is_ok());
+        assert!(DatabaseLock::acquire(&db_path, false, Duration::ZERO).is_err());
+        drop(shared);
+
+        let exclusive = DatabaseLock::acquire(&db_path, false, Duration::ZERO).unwrap();
+        assert!(DatabaseLock::acquire(&db_path, true, Duration::ZERO).is_err());
+        drop(exclusive);
+        let _ = std::fs::remove_file(lock_path(&db_path));
+    }
+}
@@ -1,83 +1,281 @@
 #![forbid(unsafe_code)]
 use args::CotpArgs;
 use clap::Parser;
-use color_eyre::eyre::eyre;
+use color_eyre::eyre::{eyre, ErrReport};
+use cotp::backup::prepare_save;
+use cotp::clock;
+use cotp::config::{ClockConfig, Config};
+use cotp::crypto::cryptography::DatabaseKey;
+use cotp::crypto::keyfile::set_keyfile;
+use cotp::otp::otp_element::{OTPDatabase, SaveConflict};
+use cotp::path::{has_custom_db_path, set_db_path, set_profile};
+use cotp::reading::{
+    password_from_env, password_from_file, password_from_input, password_from_stdin,
+    read_from_file, read_from_file_with_age_identity, ReadResult,
+};
+use cotp::utils;
 use interface::app::AppResult;
+use interface::auto_lock::AutoLock;
+use interface::enums::SortMode;
 use interface::event::{Event, EventHandler};
-use interface::handler::handle_key_events;
+use interface::graphics::GraphicsProtocol;
+use interface::handler::{handle_key_events, handle_mouse_events, handle_paste_event};
+use interface::keymap::Keymap;
+use interface::layout::TableColumns;
+use interface::password_prompt::PasswordPrompt;
+use interface::qr_guard::QrGuard;
+use interface::theme::Theme;
 use interface::ui::Tui;
-use otp::otp_element::{OTPDatabase, CURRENT_DATABASE_VERSION};
 use ratatui::prelude::CrosstermBackend;
 use ratatui::Terminal;
-use reading::{get_elements_from_input, get_elements_from_stdin, ReadResult};
-use std::{io, vec};
+use std::{io, time::Duration};
+use unlock::{KeyringUnlock, UnlockBackend};
 use zeroize::Zeroize;
 
+mod agent;
 mod args;
 mod argument_functions;
+mod autotype;
 mod clipboard;
-mod crypto;
-mod exporters;
-mod importers;
+mod completions;
+mod dbus_service;
+mod hardening;
+mod i18n;
 mod interface;
-mod otp;
-mod path;
-mod reading;
-mod utils;
+mod native_host;
+mod os_keyring;
+mod output;
+mod picker;
+mod screenshot;
+#[cfg(unix)]
+mod signals;
+mod unlock;
+mod wizard;
 
-fn init(read_password_from_stdin: bool) -> color_eyre::Result<ReadResult> {
+fn init(cotp_args: &CotpArgs) -> color_eyre::Result<ReadResult> {
     match utils::init_app() {
         Ok(first_run) => {
             if first_run {
-                // Let's initialize the database file
-                let mut pw = utils::verified_password("Choose a password: ", 8);
-                let mut database = OTPDatabase {
-                    version: CURRENT_DATABASE_VERSION,
-                    elements: vec![],
-                    ..Default::default()
-                };
-                let save_result = database.save_with_pw(&pw);
-                pw.zeroize();
-                save_result.map(|(key, salt)| (database, key, salt.to_vec()))
-            } else if read_password_from_stdin {
-                get_elements_from_stdin()
+                wizard::run(cotp_args.use_keyring)
+            } else if let Some(identity) = &cotp_args.age_identity {
+                read_from_file_with_age_identity(identity)
+            } else if cotp_args.password_from_stdin {
+                // Scripts cannot answer the upgrade question
+                unlock(password_from_stdin()?, false)
+            } else if let Some(path) = &cotp_args.password_file {
+                unlock(password_from_file(path)?, false)
+            } else if let Some(pw) = password_from_env() {
+                unlock(pw, false)
+            } else if cotp_args.use_keyring || cotp_args.biometric {
+                if cotp_args.biometric {
+                    unlock::confirm_presence()?;
+                }
+                get_elements_from_backend(&KeyringUnlock, !cotp_args.no_upgrade)
             } else {
-                get_elements_from_input()
+                unlock(password_from_input()?, !cotp_args.no_upgrade)
             }
         }
-        Err(()) => Err(eyre!("An error occurred during database creation")),
+        Err(_) => Err(eyre!("An error occurred during database creation")),
     }
 }
 
+/// Exits like the interrupted programs, without an error message, if the user pressed Ctrl-C in a password prompt
+fn exit_if_interrupted(error: &ErrReport) {
+    let interrupted = error
+        .downcast_ref::<io::Error>()
+        .is_some_and(|e| e.kind() == io::ErrorKind::Interrupted);
+    if interrupted {
+        std::process::exit(130)
+    }
+}
+
+/// Unlocks the database with the password given by the backend, falling back to the password prompt
+fn get_elements_from_backend(
+    backend: &dyn UnlockBackend,
+    offer_upgrade: bool,
+) -> color_eyre::Result<ReadResult> {
+    match backend.password() {
+        Ok(pw) => unlock(pw, offer_upgrade).or_else(|_| {
+            eprintln!("The stored password is not valid anymore");
+            unlock(password_from_input()?, offer_upgrade)
+        }),
+        Err(e) => {
+            eprintln!("{e}");
+            unlock(password_from_input()?, offer_upgrade)
+        }
+    }
+}
+
+/// Decrypts the database, then offers to encrypt it again if its key derivation is weaker than the configured one
+fn unlock(mut pw: String, offer_upgrade: bool) -> color_eyre::Result<ReadResult> {
+    let result = read_from_file(&pw).map(|read_result| {
+        if offer_upgrade {
+            upgrade_key(read_result, &pw)
+        } else {
+            read_result
+        }
+    });
+    pw.zeroize();
+    result
+}
+
+/// Encrypts the database again with the configured parameters if the user agrees, keeping the old key on failure
+fn upgrade_key((mut database, key, salt): ReadResult, pw: &str) -> ReadResult {
+    let target = Config::load().map(|c| c.kdf).unwrap_or_default();
+    if !database.needs_key_upgrade(&target)
+        || !utils::confirm(
+            "The database uses weaker key derivation parameters than the configured ones, upgrade it now? [Y/n] ",
+            true,
+        )
+    {
+        return (database, key, salt);
+    }
+    let previous = database.kdf_params();
+    database.set_kdf_params(target);
+    match database.save_with_pw(pw) {
+        Ok((new_key, new_salt)) => {
+            println!("Database upgraded");
+            (database, new_key, new_salt.to_vec())
+        }
+        Err(e) => {
+            eprintln!("Cannot upgrade the database: {e}");
+            database.set_kdf_params(previous);
+            (database, key, salt)
+        }
+    }
+}
+
+/// Applies the configured clock offset, or the one measured with the NTP server, warning if the local clock is wrong
+fn sync_clock(config: &ClockConfig) {
+    let mut offset_ms = config.offset.saturating_mul(1000);
+    if let Some(server) = &config.ntp_server {
+        match clock::query_ntp_offset_ms(server) {
+            Ok(measured) => {
+                let seconds = measured.unsigned_abs() / 1000;
+                if seconds >= config.warn_threshold {
+                    let direction = if measured > 0 { "behind" } else { "ahead of" };
+                    let consequence = if config.compensate {
+                        "using the time of the server"
+                    } else {
+                        "codes may be rejected"
+                    };
+                    eprintln!(
+                        "Warning: the local clock is {seconds} seconds {direction} {server}, {consequence}"
+                    );
+                }
+                if config.compensate {
+                    offset_ms = measured;
+                }
+            }
+            Err(e) => eprintln!("Cannot check the clock with {server}: {e}"),
+        }
+    }
+    clock::set_offset_ms(offset_ms);
+}
+
 fn main() -> AppResult<()> {
     color_eyre::install()?;
+    hardening::harden_process();
+    i18n::init();
 
     let cotp_args: CotpArgs = CotpArgs::parse();
-    let (database, mut key, salt) = match init(cotp_args.password_from_stdin) {
+    if let Some(database_path) = &cotp_args.database_path {
+        if let Err(e) = set_db_path(database_path) {
+            eprintln!("{e}");
+            std::process::exit(-1)
+        }
+    }
+    if let Some(keyfile) = &cotp_args.keyfile {
+        if let Err(e) = set_keyfile(keyfile) {
+            eprintln!("{e}");
+            std::process::exit(-1)
+        }
+    }
+    if let Some(profile_args) = cotp_args.profile_command() {
+        if let Err(e) = argument_functions::profile(profile_args, cotp_args.profile.as_deref()) {
+            exit_if_interrupted(&e);
+            eprintln!("An error occurred: {e}");
+            std::process::exit(-2)
+        }
+        std::process::exit(0)
+    }
+    if let Some(profile) = &cotp_args.profile {
+        if let Err(e) = set_profile(profile) {
+            eprintln!("{e}");
+            std::process::exit(-1)
+        }
+    }
+    // The configured database is the default one, the command line, the environment and the profiles choose others
+    if !has_custom_db_path() && cotp_args.profile.is_none() {
+        if let Some(path) = Config::load().ok().and_then(|c| c.database.path) {
+            if let Err(e) = set_db_path(&path) {
+                eprintln!("{e}");
+                std::process::exit(-1)
+            }
+        }
+    }
+    if let Some(result) = cotp_args.run_without_database() {
+        if let Err(e) = result {
+            eprintln!("An error occurred: {e}");
+            std::process::exit(-2)
+        }
+        std::process::exit(0)
+    }
+    if let Ok(config) = Config::load() {
+        sync_clock(&config.clock);
+    }
+    let (database, mut key, salt) = match init(&cotp_args) {
         Ok(v) => v,
         Err(e) => {
+            exit_if_interrupted(&e);
             println!("{e}");
             std::process::exit(-1);
         }
     };
 
-    let mut reowned_database = match args::args_parser(cotp_args, database) {
+    let mut reowned_database = match args::args_parser(cotp_args, database, &mut key, &salt) {
         Ok(d) => d,
         Err(e) => {
-            eprintln!("An error occurred: {e}");
             key.zeroize();
+            exit_if_interrupted(&e);
+            eprintln!("An error occurred: {e}");
             std::process::exit(-2)
         }
     };
 
-    let error_code = if reowned_database.is_modified() {
-        match reowned_database.save(&key, &salt) {
+    if !reowned_database.is_locked() {
+        let cache_names = Config::load().is_ok_and(|c| c.completion.cache_names);
+        // The completion of the code names is not worth failing the command
+        let _ = completions::update_names_cache(cache_names, reowned_database.elements_ref());
+    }
+
+    let error_code = if reowned_database.is_modified() || reowned_database.is_usage_modified() {
+        let user_modified = reowned_database.is_modified();
+        for warning in prepare_save(&mut reowned_database) {
+            eprintln!("Warning: {warning}");
+        }
+        let result = reowned_database.save(&key, &salt).or_else(|e| {
+            // The other instance changed more than the usage statistics
+            if e.downcast_ref::<SaveConflict>().is_some()
+                && utils::confirm(
+                    &format!("{e}. Overwrite the database with your changes? [y/N] "),
+                    false,
+                )
+            {
+                reowned_database.save_overwriting(&key, &salt)
+            } else {
+                Err(e)
+            }
+        });
+        match result {
             Ok(_) => {
-                println!("Modifications has been persisted");
+                if user_modified {
+                    println!("Modifications has been persisted");
+                }
                 0
             }
-            Err(_) => {
-                eprintln!("An error occurred during database overwriting");
+            Err(e) => {
+                eprintln!("An error occurred during database overwriting: {e}");
                 -1
             }
         }
@@ -88,12 +286,61 @@ fn main() -> AppResult<()> {
     std::process::exit(error_code)
 }
 
-fn dashboard(mut database: OTPDatabase) -> AppResult<OTPDatabase> {
+fn dashboard(
+    mut database: OTPDatabase,
+    key: &mut DatabaseKey,
+    salt: &[u8],
+    clipboard_timeout: Option<u64>,
+    theme: Option<&str>,
+    auto_lock: Option<u64>,
+) -> AppResult<OTPDatabase> {
     if database.elements_ref().is_empty() {
         println!("No codes, type \"cotp -h\" to get help");
     } else {
         // Create an application.
+        let config = Config::load().map_err(|e| e.to_string())?;
+        let keymap = Keymap::from_config(&config.keys, config.dashboard.vim_mode)
+            .map_err(|e| format!("Invalid keybindings in the configuration file: {e}"))?;
         let mut app = interface::app::App::new(&mut database);
+        app.keymap = keymap;
+        app.vim_mode = config.dashboard.vim_mode;
+        app.hide_codes = config.dashboard.hide_codes;
+        app.detail_pane = config.dashboard.detail_pane;
+        app.digit_group = config.dashboard.digit_group;
+        let qr_timeout = config.dashboard.qr_timeout;
+        app.qr_guard = QrGuard::new(
+            (qr_timeout > 0).then(|| Duration::from_secs(qr_timeout)),
+            config
+                .dashboard
+                .qr_hold
+                .then(|| Duration::from_millis(config.dashboard.qr_hold_delay)),
+        );
+        app.graphics =
+            GraphicsProtocol::from_name(&config.dashboard.qr_graphics).ok_or(format!(
+                "Invalid QR graphics \"{}\" in the configuration file",
+                config.dashboard.qr_graphics
+            ))?;
+        app.columns =
+            TableColumns::from_config(&config.dashboard.columns, &config.dashboard.column_widths)
+                .map_err(|e| format!("Invalid columns in the configuration file: {e}"))?;
+        app.theme = Theme::from_config(&config.theme, theme)?;
+        app.sort_mode = SortMode::from_name(&config.dashboard.sort).ok_or(format!(
+            "Invalid sort mode \"{}\" in the configuration file",
+            config.dashboard.sort
+        ))?;
+        app.code_defaults = config.defaults;
+        let clipboard_timeout = clipboard_timeout.unwrap_or(config.dashboard.clipboard_timeout);
+        app.clipboard_timeout =
+            (clipboard_timeout > 0).then(|| Duration::from_secs(clipboard_timeout));
+        let auto_lock = auto_lock.unwrap_or(config.dashboard.auto_lock);
+        app.auto_lock = (auto_lock > 0).then(|| AutoLock::new(Duration::from_secs(auto_lock * 60)));
+        app.password_prompt = config.dashboard.password_prompt.then(|| {
+            PasswordPrompt::new(Duration::from_secs(config.dashboard.password_grace_period))
+        });
+        app.key = Some(key);
+        app.salt = salt;
+        // Sorts the table with the configured mode before the first draw
+        app.tick(true);
 
         // Initialize the terminal user interface.
         let backend = CrosstermBackend::new(io::stderr());
@@ -110,17 +357,32 @@ fn dashboard(mut database: OTPDatabase) -> AppResult<OTPDatabase> {
             match tui.events.next()? {
                 Event::Tick => app.tick(false),
                 Event::Key(key_event) => handle_key_events(key_event, &mut app)?,
-                Event::Mouse(_) => {}
+                Event::Mouse(mouse_event) => handle_mouse_events(mouse_event, &mut app)?,
                 Event::Resize(_, _) => {}
                 Event::FocusGained() => {}
                 Event::FocusLost() => {}
-                Event::Paste(_) => {}
+                Event::Paste(text) => handle_paste_event(&text, &mut app),
             }
         }
 
         // Exit the user interface.
         tui.exit()?;
+
+        // Do not leave the copied code in the clipboard after exiting
+        if let Some(scheduled_clear) = app.scheduled_clear.take() {
+            scheduled_clear.clear_now();
+        }
     }
 
     Ok(database)
 }
+
+/// TOTP element with a valid secret, shared by the tests of the binary, which cannot see the ones of the library
+#[cfg(test)]
+fn test_element(issuer: &str, label: &str) -> cotp::otp::otp_element::OTPElement {
+    let mut element = cotp::otp::otp_element::OTPElement::default();
+    element.secret = String::from("JBSWY3DPEHPK3PXP");
+    element.issuer = String::from(issuer);
+    element.label = String::from(label);
+    element
+}
@@ -0,0 +1,146 @@
+//! Native messaging host of the browser extensions, following the Chrome and Firefox protocol:
+//! every message is JSON preceded by its length as a 32 bits integer in native byte order.
+
+use std::io::{self, Read, Write};
+
+use serde::{Deserialize, Serialize};
+
+use cotp::otp::otp_element::OTPDatabase;
+
+use crate::output::CodeOutput;
+
+/// The browsers limit the messages sent to the host to 64 MiB
+const MAX_REQUEST_SIZE: usize = 64 * 1024 * 1024;
+
+#[derive(Deserialize, PartialEq, Eq, Debug)]
+#[serde(tag = "action", rename_all = "snake_case")]
+enum Request {
+    /// Codes of all the entries
+    List,
+    /// Codes of the entries matching the website
+    Match { url: String },
+    /// Code of an entry, when the extension uses it
+    Code { index: usize },
+}
+
+#[derive(Serialize)]
+struct Response {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    codes: Option<Vec<CodeOutput>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl Response {
+    fn codes(database: &OTPDatabase, indexes: impl Iterator<Item = usize>) -> Self {
+        let codes = indexes
+            .filter_map(|i| database.get_element(i).map(|e| CodeOutput::new(i, e)))
+            .collect();
+        Response {
+            codes: Some(codes),
+            error: None,
+        }
+    }
+
+    fn error(error: impl Into<String>) -> Self {
+        Response {
+            codes: None,
+            error: Some(error.into()),
+        }
+    }
+}
+
+/// Answers the requests until the browser closes the standard input, then gives back the database
+/// to save the usage of the codes
+pub fn serve(mut database: OTPDatabase) -> color_eyre::Result<OTPDatabase> {
+    let mut stdin = io::stdin().lock();
+    let mut stdout = io::stdout().lock();
+    while let Some(message) = read_message(&mut stdin)? {
+        let response = match serde_json::from_slice::<Request>(&message) {
+            Ok(request) => handle_request(&mut database, request),
+            Err(e) => Response::error(format!("Invalid request: {e}")),
+        };
+        write_message(&mut stdout, &serde_json::to_vec(&response)?)?;
+    }
+    Ok(database)
+}
+
+fn handle_request(database: &mut OTPDatabase, request: Request) -> Response {
+    match request {
+        Request::List => Response::codes(database, 0..database.elements_ref().len()),
+        Request::Match { url } => {
+            let indexes = database.find_by_url(&url);
+            Response::codes(database, indexes.into_iter())
+        }
+        Request::Code { index } => {
+            // Indexes start from 1, like the ones of the list command
+            match index
+                .checked_sub(1)
+                .filter(|i| *i < database.elements_ref().len())
+            {
+                Some(i) => {
+                    database.record_usage(i);
+                    Response::codes(database, std::iter::once(i))
+                }
+                None => Response::error(format!("No code with index {index}")),
+            }
+        }
+    }
+}
+
+/// Reads a message, returning None when the input is closed
+fn read_message(reader: &mut impl Read) -> io::Result<Option<Vec<u8>>> {
+    let mut length = [0u8; 4];
+    match reader.read_exact(&mut length) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    }
+    let length = u32::from_ne_bytes(length) as usize;
+    if length > MAX_REQUEST_SIZE {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Native message too long",
+        ));
+    }
+    let mut message = vec![0u8; length];
+    reader.read_exact(&mut message)?;
+    Ok(Some(message))
+}
+
+fn write_message(writer: &mut impl Write, message: &[u8]) -> io::Result<()> {
+    writer.write_all(&(message.len() as u32).to_ne_bytes())?;
+    writer.write_all(message)?;
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_message, write_message, Request};
+
+    #[test]
+    fn test_message_round_trip() {
+        let mut buffer = Vec::new();
+        write_message(
+            &mut buffer,
+            br#"{"action":"match","url":"https://github.com"}"#,
+        )
+        .unwrap();
+        write_message(&mut buffer, br#"{"action":"code","index":2}"#).unwrap();
+
+        let mut reader = buffer.as_slice();
+        let first = read_message(&mut reader).unwrap().unwrap();
+        assert_eq!(
+            Request::Match {
+                url: String::from("https://github.com")
+            },
+            serde_json::from_slice(&first).unwrap()
+        );
+        let second = read_message(&mut reader).unwrap().unwrap();
+        assert_eq!(
+            Request::Code { index: 2 },
+            serde_json::from_slice(&second).unwrap()
+        );
+        assert!(read_message(&mut reader).unwrap().is_none());
+    }
+}
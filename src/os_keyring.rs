@@ -0,0 +1,31 @@
+use color_eyre::eyre::eyre;
+use cotp::path::get_db_path;
+use keyring::{Entry, Error};
+
+const SERVICE_NAME: &str = "cotp";
+
+/// Every database has its own keyring entry, identified by its path
+fn entry() -> color_eyre::Result<Entry> {
+    let db_path = get_db_path();
+    let user = db_path.canonicalize().unwrap_or(db_path);
+    Entry::new(SERVICE_NAME, &user.to_string_lossy())
+        .map_err(|e| eyre!("Cannot access the keyring: {e}"))
+}
+
+/// Fetches the database password from the OS keyring, if stored
+pub fn get_password() -> Option<String> {
+    entry().ok()?.get_password().ok()
+}
+
+pub fn set_password(password: &str) -> color_eyre::Result<()> {
+    entry()?
+        .set_password(password)
+        .map_err(|e| eyre!("Cannot store the password in the keyring: {e}"))
+}
+
+pub fn delete_password() -> color_eyre::Result<()> {
+    match entry()?.delete_credential() {
+        Ok(()) | Err(Error::NoEntry) => Ok(()),
+        Err(e) => Err(eyre!("Cannot remove the password from the keyring: {e}")),
+    }
+}
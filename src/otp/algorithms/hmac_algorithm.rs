@@ -0,0 +1,81 @@
+use std::marker::PhantomData;
+
+use hmac::digest::block_buffer::Eager;
+use hmac::digest::core_api::{
+    BlockSizeUser, BufferKindUser, CoreProxy, FixedOutputCore, UpdateCore,
+};
+use hmac::digest::generic_array::typenum::{IsLess, Le, NonZero, U256};
+use hmac::digest::HashMarker;
+use hmac::{Hmac, Mac};
+use md5::Md5;
+use sha1::Sha1;
+use sha2::{Sha256, Sha512};
+
+use crate::otp::otp_algorithm::OTPAlgorithm;
+
+/// Keyed hash used by the HOTP based codes.
+/// A new algorithm only needs an implementation of this trait and an OTPAlgorithm variant returning it.
+pub trait HmacAlgorithm: Sync {
+    fn hmac(&self, key: &[u8], message: &[u8]) -> Vec<u8>;
+}
+
+/// HMAC built on any hash function of the RustCrypto digest crates, like the SHA-3 ones
+pub struct DigestHmac<D>(PhantomData<D>);
+
+impl<D> HmacAlgorithm for DigestHmac<D>
+where
+    D: CoreProxy + Sync,
+    D::Core: HashMarker
+        + UpdateCore
+        + FixedOutputCore
+        + BufferKindUser<BufferKind = Eager>
+        + Default
+        + Clone,
+    <D::Core as BlockSizeUser>::BlockSize: IsLess<U256>,
+    Le<<D::Core as BlockSizeUser>::BlockSize, U256>: NonZero,
+{
+    fn hmac(&self, key: &[u8], message: &[u8]) -> Vec<u8> {
+        // HMAC accepts keys of any length
+        let mut hmac: Hmac<D> = Hmac::new_from_slice(key).expect("Failed to derive HMAC");
+        hmac.update(message);
+        hmac.finalize().into_bytes().to_vec()
+    }
+}
+
+static SHA1: DigestHmac<Sha1> = DigestHmac(PhantomData);
+static SHA256: DigestHmac<Sha256> = DigestHmac(PhantomData);
+static SHA512: DigestHmac<Sha512> = DigestHmac(PhantomData);
+static MD5: DigestHmac<Md5> = DigestHmac(PhantomData);
+
+impl OTPAlgorithm {
+    pub fn hmac_algorithm(&self) -> &'static dyn HmacAlgorithm {
+        match self {
+            OTPAlgorithm::Sha1 => &SHA1,
+            OTPAlgorithm::Sha256 => &SHA256,
+            OTPAlgorithm::Sha512 => &SHA512,
+            OTPAlgorithm::Md5 => &MD5,
+        }
+    }
+
+    pub fn hmac(&self, key: &[u8], message: &[u8]) -> Vec<u8> {
+        self.hmac_algorithm().hmac(key, message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::otp::otp_algorithm::OTPAlgorithm;
+
+    #[test]
+    fn test_hmac() {
+        // RFC 2202 test vectors
+        assert_eq!(
+            "b617318655057264e28bc0b6fb378c8ef146be00",
+            hex::encode(OTPAlgorithm::Sha1.hmac(&[0x0b; 20], b"Hi There"))
+        );
+        assert_eq!(
+            "9294727a3638bb1c13f48ef8158bfc9d",
+            hex::encode(OTPAlgorithm::Md5.hmac(&[0x0b; 16], b"Hi There"))
+        );
+    }
+}
@@ -1,100 +1,99 @@
 use std::convert::TryInto;
 
-use aes_gcm::aead::generic_array::GenericArray;
 use data_encoding::BASE32_NOPAD;
-use hmac::digest::block_buffer::Eager;
-use hmac::digest::core_api::{
-    BlockSizeUser, BufferKindUser, CoreProxy, FixedOutputCore, UpdateCore,
-};
-use hmac::digest::generic_array::typenum::{IsLess, Le, NonZero, U256};
-use hmac::digest::{HashMarker, OutputSizeUser};
-use hmac::{Hmac, Mac};
-use sha1::Sha1;
-use sha2::{Sha256, Sha512};
 
 use crate::otp::otp_algorithm::OTPAlgorithm;
 use crate::otp::otp_error::OtpError;
 
 pub fn hotp(secret: &str, algorithm: OTPAlgorithm, counter: u64) -> Result<u32, OtpError> {
-    match algorithm {
-        OTPAlgorithm::Sha256 => generate_hotp::<Sha256>(secret, counter),
-        OTPAlgorithm::Sha512 => generate_hotp::<Sha512>(secret, counter),
-        _ => generate_hotp::<Sha1>(secret, counter),
-    }
-}
-
-fn generate_hotp<D>(secret: &str, counter: u64) -> Result<u32, OtpError>
-where
-    D: CoreProxy,
-    D::Core: HashMarker
-        + UpdateCore
-        + FixedOutputCore
-        + BufferKindUser<BufferKind = Eager>
-        + Default
-        + Clone,
-    <D::Core as BlockSizeUser>::BlockSize: IsLess<U256>,
-    Le<<D::Core as BlockSizeUser>::BlockSize, U256>: NonZero,
-{
     // decode the base32 secret
     let secret_decoded = BASE32_NOPAD
         .decode(secret.as_bytes())
         .map_err(|e| OtpError::SecretEncoding(e.kind, e.position))?;
 
-    let hash = hotp_hash::<D>(&secret_decoded, counter);
+    let hash = hotp_hash(algorithm, &secret_decoded, counter);
 
-    // calculate offset
+    // calculate offset, MD5 digests are too short for the last offsets so they take the last 4 bytes
     let offset: usize = match hash.last() {
         Some(result) => *result & 0xf,
         None => return Err(OtpError::InvalidOffset),
     } as usize;
+    let offset = offset.min(hash.len().saturating_sub(4));
 
     // calculate code
-    let code_bytes: [u8; 4] = match hash[offset..offset + 4].try_into() {
-        Ok(x) => x,
-        Err(_) => return Err(OtpError::InvalidDigest),
+    let code_bytes: [u8; 4] = match hash.get(offset..offset + 4).map(|b| b.try_into()) {
+        Some(Ok(x)) => x,
+        _ => return Err(OtpError::InvalidDigest),
     };
     Ok(u32::from_be_bytes(code_bytes) & 0x7fffffff)
 }
 
-pub fn hotp_hash<D>(
-    secret: &[u8],
-    counter: u64,
-) -> GenericArray<u8, <<D as CoreProxy>::Core as OutputSizeUser>::OutputSize>
-where
-    D: CoreProxy,
-    D::Core: HashMarker
-        + UpdateCore
-        + FixedOutputCore
-        + BufferKindUser<BufferKind = Eager>
-        + Default
-        + Clone,
-    <D::Core as BlockSizeUser>::BlockSize: IsLess<U256>,
-    Le<<D::Core as BlockSizeUser>::BlockSize, U256>: NonZero,
-{
-    // calculate HMAC from secret bytes and counter
-    let mut hmac: Hmac<D> = Hmac::new_from_slice(secret).expect("Failed to derive HMAC");
-    hmac.update(&counter.to_be_bytes());
-    hmac.finalize().into_bytes()
+/// HMAC of the counter with the algorithm of the code
+pub fn hotp_hash(algorithm: OTPAlgorithm, secret: &[u8], counter: u64) -> Vec<u8> {
+    algorithm.hmac(secret, &counter.to_be_bytes())
 }
 
 #[cfg(test)]
 mod tests {
-    use sha1::Sha1;
+    use data_encoding::BASE32_NOPAD;
 
-    use crate::otp::algorithms::hotp_maker::generate_hotp;
+    use crate::otp::algorithms::hotp_maker::{hotp, hotp_hash};
+    use crate::otp::otp_algorithm::OTPAlgorithm;
 
     #[test]
     fn test_hotp() {
         assert_eq!(
-            format_code(generate_hotp::<Sha1>("BASE32SECRET3232", 0).unwrap(), 6),
+            format_code(hotp("BASE32SECRET3232", OTPAlgorithm::Sha1, 0).unwrap(), 6),
             "260182"
         );
         assert_eq!(
-            format_code(generate_hotp::<Sha1>("BASE32SECRET3232", 1).unwrap(), 6),
+            format_code(hotp("BASE32SECRET3232", OTPAlgorithm::Sha1, 1).unwrap(), 6),
             "055283"
         );
     }
 
+    #[test]
+    fn test_hotp_algorithms() {
+        // RFC 6238 test vectors at 59 seconds, each algorithm with a seed as long as its output
+        let code = |seed: &[u8], algorithm| {
+            let secret = BASE32_NOPAD.encode(seed);
+            format_code(hotp(&secret, algorithm, 1).unwrap(), 8)
+        };
+        assert_eq!(
+            "94287082",
+            code(b"12345678901234567890", OTPAlgorithm::Sha1)
+        );
+        assert_eq!(
+            "46119246",
+            code(b"12345678901234567890123456789012", OTPAlgorithm::Sha256)
+        );
+        assert_eq!(
+            "90693936",
+            code(
+                b"1234567890123456789012345678901234567890123456789012345678901234",
+                OTPAlgorithm::Sha512
+            )
+        );
+    }
+
+    #[test]
+    fn test_hotp_short_digest() {
+        let secret = BASE32_NOPAD.encode(&[0x0b; 16]);
+        // Counters whose MD5 digest ends with an offset past the last 4 bytes
+        let counters: Vec<u64> = (0..64)
+            .filter(|counter| {
+                let hash = hotp_hash(OTPAlgorithm::Md5, &[0x0b; 16], *counter);
+                hash[15] & 0xf > 12
+            })
+            .collect();
+        assert!(!counters.is_empty());
+        for counter in counters {
+            let hash = hotp_hash(OTPAlgorithm::Md5, &[0x0b; 16], counter);
+            let expected = u32::from_be_bytes(hash[12..16].try_into().unwrap()) & 0x7fffffff;
+            assert_eq!(expected, hotp(&secret, OTPAlgorithm::Md5, counter).unwrap());
+        }
+    }
+
     fn format_code(value: u32, digits: u32) -> String {
         // Get the formatted code
         let s = (value % 10_u32.pow(digits)).to_string();
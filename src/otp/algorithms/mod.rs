@@ -1,5 +1,7 @@
+pub mod hmac_algorithm;
 pub mod hotp_maker;
 pub mod motp_maker;
+pub mod ocra_maker;
 pub mod steam_otp_maker;
 pub mod totp_maker;
 pub mod yandex_otp_maker;
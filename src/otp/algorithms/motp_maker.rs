@@ -1,14 +1,13 @@
-use std::time::SystemTime;
-
 use crate::otp::otp_error::OtpError;
 use md5::{Digest, Md5};
 
-pub fn motp(secret: &str, pin: &str, period: u64, digits: usize) -> Result<String, OtpError> {
-    let seconds = SystemTime::now()
-        .duration_since(SystemTime::UNIX_EPOCH)
-        .unwrap()
-        .as_secs();
-
+pub fn motp(
+    secret: &str,
+    pin: &str,
+    period: u64,
+    digits: usize,
+    seconds: u64,
+) -> Result<String, OtpError> {
     get_motp_code(secret, pin, period, digits, seconds)
 }
 
@@ -19,7 +18,7 @@ fn get_motp_code(
     digits: usize,
     seconds: u64,
 ) -> Result<String, OtpError> {
-    // TODO MOTP Secrets are hex encoded, so do not use BASE32 at all
+    // MOTP secrets are hex strings, which are hashed as they are
     let hex_secret = secret;
     let counter = seconds / period;
     let data = format!("{counter}{hex_secret}{pin}");
@@ -27,7 +26,9 @@ fn get_motp_code(
     let mut md5_hasher = Md5::new();
     md5_hasher.update(data.as_bytes());
     let code = hex::encode(md5_hasher.finalize());
-    Ok(code.as_str()[0..digits].to_owned())
+    code.get(0..digits)
+        .map(|c| c.to_owned())
+        .ok_or(OtpError::InvalidDigits)
 }
 
 #[cfg(test)]
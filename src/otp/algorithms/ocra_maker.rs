@@ -0,0 +1,365 @@
+// OCRA challenge-response algorithm, following RFC 6287 (https://www.rfc-editor.org/rfc/rfc6287)
+
+use std::str::FromStr;
+
+use data_encoding::BASE32_NOPAD;
+
+use crate::otp::otp_algorithm::OTPAlgorithm;
+use crate::otp::otp_error::OtpError;
+use crate::utils::get_current_timestamp;
+
+/// Challenges are padded to this length, in bytes
+const CHALLENGE_LENGTH: usize = 128;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum ChallengeFormat {
+    Alphanumeric,
+    Numeric,
+    Hexadecimal,
+}
+
+/// Parsed suite string, like OCRA-1:HOTP-SHA1-6:QN08, describing which inputs the response is computed from
+#[derive(PartialEq, Eq, Debug)]
+pub struct OcraSuite {
+    suite: String,
+    pub algorithm: OTPAlgorithm,
+    /// 0 for the whole HMAC, without truncation
+    pub digits: u64,
+    pub uses_counter: bool,
+    challenge_format: ChallengeFormat,
+    challenge_max_length: usize,
+    /// Hash of the PIN
+    pub pin_hash: Option<OTPAlgorithm>,
+    session_length: Option<usize>,
+    /// Seconds of the timestamp step
+    time_step: Option<u64>,
+}
+
+impl FromStr for OcraSuite {
+    type Err = OtpError;
+
+    fn from_str(suite: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = suite.split(':').collect();
+        let [version, function, inputs] = parts[..] else {
+            return Err(OtpError::InvalidSuite);
+        };
+        if !version.eq_ignore_ascii_case("OCRA-1") {
+            return Err(OtpError::InvalidSuite);
+        }
+
+        let function: Vec<&str> = function.split('-').collect();
+        let (algorithm, digits) = match function[..] {
+            [hotp, algorithm, digits] if hotp.eq_ignore_ascii_case("HOTP") => {
+                (parse_hash(algorithm)?, digits.parse::<u64>().ok())
+            }
+            _ => return Err(OtpError::InvalidSuite),
+        };
+        let digits = digits
+            .filter(|d| *d == 0 || (4..=10).contains(d))
+            .ok_or(OtpError::InvalidSuite)?;
+
+        let mut parsed = OcraSuite {
+            suite: suite.to_owned(),
+            algorithm,
+            digits,
+            uses_counter: false,
+            challenge_format: ChallengeFormat::Numeric,
+            challenge_max_length: 0,
+            pin_hash: None,
+            session_length: None,
+            time_step: None,
+        };
+        for input in inputs.split('-').map(|i| i.to_uppercase()) {
+            // Split on characters, the suite can contain any text
+            let mut chars = input.chars();
+            let Some(kind) = chars.next() else {
+                return Err(OtpError::InvalidSuite);
+            };
+            let value = chars.as_str();
+            match kind {
+                'C' if value.is_empty() => parsed.uses_counter = true,
+                'Q' => {
+                    let mut chars = value.chars();
+                    parsed.challenge_format = match chars.next() {
+                        Some('A') => ChallengeFormat::Alphanumeric,
+                        Some('N') => ChallengeFormat::Numeric,
+                        Some('H') => ChallengeFormat::Hexadecimal,
+                        _ => return Err(OtpError::InvalidSuite),
+                    };
+                    parsed.challenge_max_length = Some(chars.as_str())
+                        .filter(|length| length.len() == 2)
+                        .and_then(|length| length.parse().ok())
+                        .filter(|l| (4..=64).contains(l))
+                        .ok_or(OtpError::InvalidSuite)?;
+                }
+                'P' => parsed.pin_hash = Some(parse_hash(value)?),
+                'S' => {
+                    parsed.session_length = Some(value.parse().map_err(|_| OtpError::InvalidSuite)?)
+                }
+                'T' => {
+                    let (count, unit) = [("S", 1), ("M", 60), ("H", 3600)]
+                        .into_iter()
+                        .find_map(|(suffix, unit)| value.strip_suffix(suffix).map(|c| (c, unit)))
+                        .ok_or(OtpError::InvalidSuite)?;
+                    let count: u64 = count.parse().map_err(|_| OtpError::InvalidSuite)?;
+                    parsed.time_step = count.checked_mul(unit).filter(|s| *s > 0);
+                    if parsed.time_step.is_none() && count > 0 {
+                        return Err(OtpError::InvalidSuite);
+                    }
+                }
+                _ => return Err(OtpError::InvalidSuite),
+            }
+        }
+        // The challenge is the only mandatory input
+        if parsed.challenge_max_length == 0 {
+            return Err(OtpError::InvalidSuite);
+        }
+        Ok(parsed)
+    }
+}
+
+fn parse_hash(name: &str) -> Result<OTPAlgorithm, OtpError> {
+    match name.to_uppercase().as_str() {
+        "SHA1" => Ok(OTPAlgorithm::Sha1),
+        "SHA256" => Ok(OTPAlgorithm::Sha256),
+        "SHA512" => Ok(OTPAlgorithm::Sha512),
+        _ => Err(OtpError::InvalidSuite),
+    }
+}
+
+/// Values used to compute a response, besides the secret
+pub struct OcraInput<'a> {
+    pub counter: Option<u64>,
+    pub challenge: &'a str,
+    pub pin: Option<&'a str>,
+    /// Hexadecimal session information
+    pub session: Option<&'a str>,
+}
+
+/// Computes the response to the challenge at the current time
+pub fn ocra(secret: &str, suite: &OcraSuite, input: &OcraInput) -> Result<String, OtpError> {
+    calculate_ocra_response(secret, suite, input, get_current_timestamp())
+}
+
+fn calculate_ocra_response(
+    secret: &str,
+    suite: &OcraSuite,
+    input: &OcraInput,
+    seconds: u64,
+) -> Result<String, OtpError> {
+    let key = BASE32_NOPAD
+        .decode(secret.as_bytes())
+        .map_err(|e| OtpError::SecretEncoding(e.kind, e.position))?;
+
+    let mut message = suite.suite.as_bytes().to_vec();
+    message.push(0);
+    if suite.uses_counter {
+        let counter = input.counter.ok_or(OtpError::MissingCounter)?;
+        message.extend_from_slice(&counter.to_be_bytes());
+    }
+    message.extend(challenge_bytes(suite, input.challenge)?);
+    if let Some(pin_hash) = suite.pin_hash {
+        let pin = input.pin.ok_or(OtpError::MissingPin)?;
+        message.extend(hash(pin_hash, pin.as_bytes()));
+    }
+    if let Some(length) = suite.session_length {
+        let session = input.session.unwrap_or_default();
+        let session = hex::decode(session).map_err(|_| OtpError::InvalidSession)?;
+        if session.len() > length {
+            return Err(OtpError::InvalidSession);
+        }
+        // Left padded with zeros
+        message.extend(vec![0; length - session.len()]);
+        message.extend(session);
+    }
+    if let Some(step) = suite.time_step {
+        message.extend_from_slice(&(seconds / step).to_be_bytes());
+    }
+
+    let hmac = suite.algorithm.hmac(&key, &message);
+    if suite.digits == 0 {
+        return Ok(hex::encode(hmac));
+    }
+    let offset = (hmac.last().ok_or(OtpError::InvalidOffset)? & 0xf) as usize;
+    let code_bytes: [u8; 4] = match hmac.get(offset..offset + 4).map(|b| b.try_into()) {
+        Some(Ok(x)) => x,
+        _ => return Err(OtpError::InvalidDigest),
+    };
+    let code =
+        (u32::from_be_bytes(code_bytes) & 0x7fffffff) as u64 % 10_u64.pow(suite.digits as u32);
+    Ok(format!("{code:0width$}", width = suite.digits as usize))
+}
+
+/// The challenge converted to hexadecimal, right padded with zeros to 128 bytes
+fn challenge_bytes(suite: &OcraSuite, challenge: &str) -> Result<Vec<u8>, OtpError> {
+    if challenge.is_empty() || challenge.len() > suite.challenge_max_length {
+        return Err(OtpError::InvalidChallenge);
+    }
+    let mut hex_challenge = match suite.challenge_format {
+        ChallengeFormat::Numeric if challenge.chars().all(|c| c.is_ascii_digit()) => {
+            decimal_to_hex(challenge)
+        }
+        ChallengeFormat::Hexadecimal if challenge.chars().all(|c| c.is_ascii_hexdigit()) => {
+            challenge.to_owned()
+        }
+        ChallengeFormat::Alphanumeric if challenge.chars().all(|c| c.is_ascii_alphanumeric()) => {
+            hex::encode(challenge)
+        }
+        _ => return Err(OtpError::InvalidChallenge),
+    };
+    hex_challenge.extend(std::iter::repeat_n(
+        '0',
+        CHALLENGE_LENGTH * 2 - hex_challenge.len(),
+    ));
+    hex::decode(hex_challenge).map_err(|_| OtpError::InvalidChallenge)
+}
+
+/// Hexadecimal representation of a decimal number of up to 64 digits, without leading zeros
+fn decimal_to_hex(decimal: &str) -> String {
+    // Big endian base 256 digits
+    let mut bytes: Vec<u8> = vec![];
+    for digit in decimal.bytes().map(|b| (b - b'0') as u32) {
+        let mut carry = digit;
+        for byte in bytes.iter_mut().rev() {
+            let value = *byte as u32 * 10 + carry;
+            *byte = value as u8;
+            carry = value >> 8;
+        }
+        if carry > 0 {
+            bytes.insert(0, carry as u8);
+        }
+    }
+    let hex = hex::encode(bytes);
+    match hex.trim_start_matches('0') {
+        "" => String::from("0"),
+        trimmed => trimmed.to_owned(),
+    }
+}
+
+fn hash(algorithm: OTPAlgorithm, data: &[u8]) -> Vec<u8> {
+    use sha2::Digest;
+    match algorithm {
+        OTPAlgorithm::Sha256 => sha2::Sha256::digest(data).to_vec(),
+        OTPAlgorithm::Sha512 => sha2::Sha512::digest(data).to_vec(),
+        _ => sha1::Sha1::digest(data).to_vec(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use data_encoding::BASE32_NOPAD;
+
+    use super::{calculate_ocra_response, decimal_to_hex, OcraInput, OcraSuite};
+    use crate::otp::otp_algorithm::OTPAlgorithm;
+    use crate::otp::otp_error::OtpError;
+
+    const SEED_20: &[u8] = b"12345678901234567890";
+    const SEED_32: &[u8] = b"12345678901234567890123456789012";
+    const SEED_64: &[u8] = b"1234567890123456789012345678901234567890123456789012345678901234";
+
+    fn response(
+        seed: &[u8],
+        suite: &str,
+        counter: Option<u64>,
+        challenge: &str,
+        seconds: u64,
+    ) -> String {
+        let input = OcraInput {
+            counter,
+            challenge,
+            pin: Some("1234"),
+            session: None,
+        };
+        calculate_ocra_response(
+            &BASE32_NOPAD.encode(seed),
+            &suite.parse().unwrap(),
+            &input,
+            seconds,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_ocra_suite() {
+        let suite: OcraSuite = "OCRA-1:HOTP-SHA256-8:C-QN08-PSHA1".parse().unwrap();
+        assert_eq!(OTPAlgorithm::Sha256, suite.algorithm);
+        assert_eq!(8, suite.digits);
+        assert!(suite.uses_counter);
+        assert_eq!(Some(OTPAlgorithm::Sha1), suite.pin_hash);
+
+        assert_eq!(
+            Err(OtpError::InvalidSuite),
+            "OCRA-1:HOTP-SHA1-6".parse::<OcraSuite>()
+        );
+        assert_eq!(
+            Err(OtpError::InvalidSuite),
+            "OCRA-1:HOTP-MD5-6:QN08".parse::<OcraSuite>()
+        );
+        assert_eq!(
+            Err(OtpError::InvalidSuite),
+            "OCRA-1:HOTP-SHA1-6:C".parse::<OcraSuite>()
+        );
+        // Non ASCII inputs and too long time steps are invalid, without panicking
+        for suite in [
+            "OCRA-1:HOTP-SHA1-6:QN08-T1é",
+            "OCRA-1:HOTP-SHA1-6:Qé8",
+            "OCRA-1:HOTP-SHA1-6:QNé",
+            "OCRA-1:HOTP-SHA1-6:é",
+            "OCRA-1:HOTP-SHA1-6:QN08-T18446744073709551615H",
+        ] {
+            assert_eq!(Err(OtpError::InvalidSuite), suite.parse::<OcraSuite>());
+        }
+        let suite: OcraSuite = "OCRA-1:HOTP-SHA1-6:QN08-T1M".parse().unwrap();
+        assert_eq!(Some(60), suite.time_step);
+    }
+
+    #[test]
+    fn test_ocra_rfc_vectors() {
+        // RFC 6287 appendix C
+        let suite = "OCRA-1:HOTP-SHA1-6:QN08";
+        assert_eq!("237653", response(SEED_20, suite, None, "00000000", 0));
+        assert_eq!("243178", response(SEED_20, suite, None, "11111111", 0));
+        assert_eq!("653583", response(SEED_20, suite, None, "22222222", 0));
+
+        let suite = "OCRA-1:HOTP-SHA256-8:C-QN08-PSHA1";
+        assert_eq!("65347737", response(SEED_32, suite, Some(0), "12345678", 0));
+        assert_eq!("86775851", response(SEED_32, suite, Some(1), "12345678", 0));
+
+        let suite = "OCRA-1:HOTP-SHA256-8:QN08-PSHA1";
+        assert_eq!("83238735", response(SEED_32, suite, None, "00000000", 0));
+
+        let suite = "OCRA-1:HOTP-SHA512-8:C-QN08";
+        assert_eq!("07016083", response(SEED_64, suite, Some(0), "00000000", 0));
+
+        // The timestamp is 0x132d0b6 minutes
+        let suite = "OCRA-1:HOTP-SHA512-8:QN08-T1M";
+        let seconds = 0x132d0b6 * 60;
+        assert_eq!(
+            "95209754",
+            response(SEED_64, suite, None, "00000000", seconds)
+        );
+    }
+
+    #[test]
+    fn test_invalid_challenge() {
+        let input = OcraInput {
+            counter: None,
+            challenge: "1234567A",
+            pin: None,
+            session: None,
+        };
+        let suite = "OCRA-1:HOTP-SHA1-6:QN08".parse().unwrap();
+        let secret = BASE32_NOPAD.encode(SEED_20);
+        assert_eq!(
+            Err(OtpError::InvalidChallenge),
+            calculate_ocra_response(&secret, &suite, &input, 0)
+        );
+    }
+
+    #[test]
+    fn test_decimal_to_hex() {
+        assert_eq!("0", decimal_to_hex("00000000"));
+        assert_eq!("bc614e", decimal_to_hex("12345678"));
+        assert_eq!("10000000000000000", decimal_to_hex("18446744073709551616"));
+    }
+}
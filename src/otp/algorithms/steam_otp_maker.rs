@@ -7,8 +7,13 @@ use super::totp_maker::totp;
 
 const STEAM_ALPHABET: &str = "23456789BCDFGHJKMNPQRTVWXY";
 
-pub fn steam(secret: &str, algorithm: OTPAlgorithm, digits: usize) -> Result<String, OtpError> {
-    match totp(secret, algorithm) {
+pub fn steam(
+    secret: &str,
+    algorithm: OTPAlgorithm,
+    digits: usize,
+    time: u64,
+) -> Result<String, OtpError> {
+    match totp(secret, algorithm, 30, 0, time) {
         Ok(v) => Ok(to_steam_string(v as usize, digits)),
         Err(e) => Err(e),
     }
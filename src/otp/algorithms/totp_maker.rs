@@ -1,26 +1,32 @@
-use std::time::SystemTime;
-
 use crate::otp::otp_algorithm::OTPAlgorithm;
 use crate::otp::otp_error::OtpError;
 
 use super::hotp_maker::hotp;
 
-pub fn totp(secret: &str, algorithm: OTPAlgorithm) -> Result<u32, OtpError> {
-    let time = SystemTime::now()
-        .duration_since(SystemTime::UNIX_EPOCH)
-        .unwrap()
-        .as_secs();
-    generate_totp(secret, algorithm, time, 30, 0)
+/// Generates the code valid at the given Unix time
+pub fn totp(
+    secret: &str,
+    algorithm: OTPAlgorithm,
+    period: u64,
+    t0: i64,
+    time: u64,
+) -> Result<u32, OtpError> {
+    generate_totp(secret, algorithm, time, period.max(1), t0)
 }
 
+/// The periods are counted from t0, times before it use the first one
 fn generate_totp(
     secret: &str,
     algorithm: OTPAlgorithm,
     time: u64,
     time_step: u64,
-    skew: i64,
+    t0: i64,
 ) -> Result<u32, OtpError> {
-    hotp(secret, algorithm, ((time as i64 + skew) as u64) / time_step)
+    let elapsed = match u64::try_from(t0) {
+        Ok(t0) => time.saturating_sub(t0),
+        Err(_) => time.saturating_add(t0.unsigned_abs()),
+    };
+    hotp(secret, algorithm, elapsed / time_step)
 }
 
 #[cfg(test)]
@@ -38,6 +44,27 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_totp_period() {
+        let code = |time| generate_totp("BASE32SECRET3232", OTPAlgorithm::Sha1, time, 60, 0);
+
+        assert_eq!(code(0), code(59));
+        assert_ne!(code(59), code(60));
+    }
+
+    #[test]
+    fn test_totp_t0() {
+        let code = |time, t0| generate_totp("BASE32SECRET3232", OTPAlgorithm::Sha1, time, 30, t0);
+
+        assert_eq!(code(30, 0), code(45, 15));
+        assert_eq!(code(0, 0), code(44, 15));
+        assert_ne!(code(44, 15), code(45, 15));
+        assert_eq!(code(60, 0), code(0, -60));
+        // Extreme values do not overflow
+        assert_eq!(code(0, 0), code(0, i64::MAX));
+        assert!(code(u64::MAX, i64::MIN).is_ok());
+    }
+
     fn format_code(value: u32, digits: u32) -> String {
         // Get the formatted code
         let s = (value % 10_u32.pow(digits)).to_string();
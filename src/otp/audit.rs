@@ -0,0 +1,273 @@
+//! Health check of the codes, finding the secrets easy to guess, public or shared by mistake between accounts
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use data_encoding::BASE32_NOPAD;
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha2::Sha256;
+use zeroize::Zeroizing;
+
+use super::otp_element::OTPElement;
+use super::otp_type::OTPType;
+
+/// Most services use 80 bits, RFC 4226 asks for at least 128
+pub const MIN_SECRET_BITS: usize = 80;
+/// Fewer digits are guessed in a few attempts
+pub const MIN_DIGITS: u64 = 6;
+/// Secret of the examples in the documentation of many libraries and services
+const EXAMPLE_SECRETS: [&str; 1] = ["JBSWY3DPEHPK3PXP"];
+/// Secret of the test vectors of RFC 4226 and RFC 6238, repeated up to the length of the hash
+const RFC_TEST_SECRET: &[u8] = b"1234567890";
+
+#[derive(Serialize, Clone, PartialEq, Eq, Debug)]
+#[serde(tag = "problem", rename_all = "snake_case")]
+pub enum Finding {
+    /// The secret cannot be decoded, so no code can be generated
+    InvalidSecret,
+    ShortSecret {
+        bits: usize,
+    },
+    FewDigits {
+        digits: u64,
+    },
+    /// Secret of the examples or of the test vectors, known to everyone
+    TestSecret,
+    /// Secret repeating a few characters, likely mistyped or made up
+    LowEntropy,
+    /// Same secret of other codes, by index starting from 1
+    DuplicateSecret {
+        indexes: Vec<usize>,
+    },
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Finding::InvalidSecret => write!(f, "The secret is not valid"),
+            Finding::ShortSecret { bits } => write!(
+                f,
+                "The secret is only {bits} bits long, at least {MIN_SECRET_BITS} are recommended"
+            ),
+            Finding::FewDigits { digits } => write!(
+                f,
+                "Codes of {digits} digits are easy to guess, at least {MIN_DIGITS} are recommended"
+            ),
+            Finding::TestSecret => write!(f, "The secret is a well known test secret"),
+            Finding::LowEntropy => write!(f, "The secret repeats a few characters, check it"),
+            Finding::DuplicateSecret { indexes } => {
+                let indexes: Vec<String> = indexes.iter().map(usize::to_string).collect();
+                write!(f, "Same secret of the codes {}", indexes.join(", "))
+            }
+        }
+    }
+}
+
+fn normalized_secret(element: &OTPElement) -> Zeroizing<String> {
+    Zeroizing::new(element.secret.trim_end_matches('=').to_uppercase())
+}
+
+/// Random key of the digests of one comparison, so they cannot be matched against the digests of known secrets
+fn digest_key() -> [u8; 32] {
+    let mut key = [0u8; 32];
+    // Without randomness the digests are plain SHA-256 ones, still not the secrets themselves
+    let _ = getrandom::getrandom(&mut key);
+    key
+}
+
+/// Keyed digest of the secret, so the maps comparing the secrets do not keep copies of them. None for the
+/// empty or invalid secrets, which are not shared but mistyped
+fn secret_digest(element: &OTPElement, key: &[u8; 32]) -> Option<[u8; 32]> {
+    let secret = normalized_secret(element);
+    let decoded = match element.type_ {
+        OTPType::Motp => hex::decode(secret.as_str()).ok(),
+        _ => BASE32_NOPAD.decode(secret.as_bytes()).ok(),
+    };
+    if secret.is_empty() || Zeroizing::new(decoded).is_none() {
+        return None;
+    }
+    // Any key length is accepted by HMAC
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).ok()?;
+    mac.update(secret.as_bytes());
+    Some(mac.finalize().into_bytes().into())
+}
+
+fn is_test_secret(secret: &str, bytes: &[u8]) -> bool {
+    let repeats_rfc_secret = bytes.len() >= RFC_TEST_SECRET.len()
+        && bytes
+            .iter()
+            .zip(RFC_TEST_SECRET.iter().cycle())
+            .all(|(a, b)| a == b);
+    // Like AAAAAAAAAAAAAAAA, typed by hand to try the app
+    let single_byte = bytes.len() > 1 && bytes.iter().all(|b| *b == bytes[0]);
+    EXAMPLE_SECRETS.contains(&secret) || repeats_rfc_secret || single_byte
+}
+
+/// Random secrets use most of the base32 characters, the mistyped or made up ones repeat a few
+fn is_low_entropy(secret: &str) -> bool {
+    let distinct = secret.chars().collect::<HashSet<char>>().len();
+    distinct * 2 < secret.len().min(32)
+}
+
+/// Problems of the code alone, without comparing it with the other ones
+pub fn audit_element(element: &OTPElement) -> Vec<Finding> {
+    if !element.valid_secret() {
+        return vec![Finding::InvalidSecret];
+    }
+    let mut findings = vec![];
+    // MOTP secrets are hex and short by design
+    if element.type_ != OTPType::Motp {
+        let secret = normalized_secret(element);
+        let bytes = Zeroizing::new(BASE32_NOPAD.decode(secret.as_bytes()).unwrap_or_default());
+        if is_test_secret(&secret, &bytes) {
+            findings.push(Finding::TestSecret);
+        } else if bytes.len() * 8 < MIN_SECRET_BITS {
+            findings.push(Finding::ShortSecret {
+                bits: bytes.len() * 8,
+            });
+        } else if is_low_entropy(&secret) {
+            findings.push(Finding::LowEntropy);
+        }
+    }
+    if matches!(element.type_, OTPType::Totp | OTPType::Hotp) && element.digits < MIN_DIGITS {
+        findings.push(Finding::FewDigits {
+            digits: element.digits,
+        });
+    }
+    findings
+}
+
+/// Indexes of the codes sharing their secret, in groups of at least two ordered by their first code
+pub fn duplicate_groups(elements: &[OTPElement]) -> Vec<Vec<usize>> {
+    let key = digest_key();
+    let mut groups: Vec<Vec<usize>> = vec![];
+    let mut by_secret: HashMap<[u8; 32], usize> = HashMap::new();
+    for (i, element) in elements.iter().enumerate() {
+        let Some(digest) = secret_digest(element, &key) else {
+            continue;
+        };
+        match by_secret.get(&digest) {
+            Some(&group) => groups[group].push(i),
+            None => {
+                by_secret.insert(digest, groups.len());
+                groups.push(vec![i]);
+            }
+        }
+    }
+    groups.retain(|group| group.len() > 1);
+    groups
+}
+
+/// Groups of duplicates which generate the same codes too, so they can be merged into one
+pub fn mergeable_groups(elements: &[OTPElement]) -> Vec<Vec<usize>> {
+    let mut mergeable = vec![];
+    for mut group in duplicate_groups(elements) {
+        while let Some(first) = group.first().copied() {
+            let (same, others): (Vec<usize>, Vec<usize>) = group
+                .into_iter()
+                .partition(|i| elements[*i].same_otp_config(&elements[first]));
+            if same.len() > 1 {
+                mergeable.push(same);
+            }
+            group = others;
+        }
+    }
+    mergeable
+}
+
+/// Problems of every code, in the same order
+pub fn audit_elements(elements: &[OTPElement]) -> Vec<Vec<Finding>> {
+    let key = digest_key();
+    let digests: Vec<Option<[u8; 32]>> = elements
+        .iter()
+        .map(|element| secret_digest(element, &key))
+        .collect();
+    let mut by_secret: HashMap<[u8; 32], Vec<usize>> = HashMap::new();
+    for (i, digest) in digests.iter().enumerate() {
+        if let Some(digest) = digest {
+            by_secret.entry(*digest).or_default().push(i);
+        }
+    }
+    elements
+        .iter()
+        .zip(&digests)
+        .enumerate()
+        .map(|(i, (element, digest))| {
+            let mut findings = audit_element(element);
+            let others: Vec<usize> = digest
+                .and_then(|digest| by_secret.get(&digest))
+                .into_iter()
+                .flatten()
+                .filter(|other| **other != i)
+                .map(|other| other + 1)
+                .collect();
+            if !others.is_empty() {
+                findings.push(Finding::DuplicateSecret { indexes: others });
+            }
+            findings
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{audit_elements, duplicate_groups, mergeable_groups, Finding};
+    use crate::otp::from_otp_uri::FromOtpUri;
+    use crate::otp::otp_element::OTPElement;
+
+    #[test]
+    fn test_audit_elements() {
+        let elements: Vec<OTPElement> = [
+            "otpauth://totp/GitHub:alice?secret=NBSWY3DPO5XXE3DEEBQWE3DFFZ2GK43U",
+            "otpauth://totp/Bank:alice?secret=NBSWY3DPO5XXE3DEEBQWE3DFFZ2GK43U&digits=4",
+            "otpauth://totp/Example:alice?secret=JBSWY3DPEHPK3PXP",
+            "otpauth://hotp/Rfc:alice?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ&counter=0",
+            "otpauth://totp/Short:alice?secret=NBSWY3DP",
+            "otpauth://totp/Typo:alice?secret=NBSWY3DP1",
+            "otpauth://totp/Made up:alice?secret=ABCDABCDABCDABCD",
+        ]
+        .iter()
+        .map(|uri| OTPElement::from_otp_uri(uri).unwrap())
+        .collect();
+
+        assert_eq!(
+            vec![
+                vec![Finding::DuplicateSecret { indexes: vec![2] }],
+                vec![
+                    Finding::FewDigits { digits: 4 },
+                    Finding::DuplicateSecret { indexes: vec![1] }
+                ],
+                vec![Finding::TestSecret],
+                vec![Finding::TestSecret],
+                vec![Finding::ShortSecret { bits: 40 }],
+                vec![Finding::InvalidSecret],
+                vec![Finding::LowEntropy],
+            ],
+            audit_elements(&elements)
+        );
+        assert_eq!(
+            r#"{"problem":"duplicate_secret","indexes":[2]}"#,
+            serde_json::to_string(&audit_elements(&elements)[0][0]).unwrap()
+        );
+        // The padding and the case of the secret do not matter
+        let mut elements = elements;
+        elements[5].secret = String::from("nbswy3dp==");
+        assert_eq!(
+            vec![vec![0, 1], vec![4, 5]],
+            duplicate_groups(&elements[..6])
+        );
+        // Empty or invalid secrets are mistakes, not secrets shared between accounts
+        elements[4].secret = String::new();
+        elements[5].secret = String::new();
+        assert_eq!(vec![vec![0, 1]], duplicate_groups(&elements[..6]));
+        // The second code has fewer digits, so merging it would change the codes
+        assert!(mergeable_groups(&elements[..6]).is_empty());
+        elements[1].digits = 6;
+        assert_eq!(vec![vec![0, 1]], mergeable_groups(&elements[..6]));
+        assert_eq!(
+            vec![Finding::ShortSecret { bits: 0 }],
+            audit_elements(&elements[..6])[5]
+        );
+    }
+}
@@ -11,17 +11,35 @@ impl FromOtpUri for OTPElement {
     fn from_otp_uri(otp_uri: &str) -> color_eyre::Result<Self> {
         let parsed_uri = Url::parse(otp_uri).map_err(ErrReport::from)?;
 
-        let otp_type = parsed_uri
-            .host_str()
-            .map(|r| r.to_uppercase())
-            .unwrap_or_else(|| "TOTP".to_string());
+        // Some apps mark Steam codes as TOTP using the encoder parameter
+        let steam_encoder = parsed_uri
+            .query_pairs()
+            .any(|(k, v)| k == "encoder" && v.eq_ignore_ascii_case("steam"));
+
+        let otp_type = if steam_encoder {
+            OTPType::Steam
+        } else {
+            parsed_uri
+                .host_str()
+                .map(OTPType::from)
+                .unwrap_or(OTPType::Totp)
+        };
+        let default_digits = if otp_type == OTPType::Steam { 5 } else { 6 };
+        let default_period = if otp_type == OTPType::Motp { 10 } else { 30 };
 
         let (issuer, label) = get_issuer_and_label(&parsed_uri)?;
 
+        // MOTP secrets are hex strings used as they are to generate codes, so keep them lowercase
         let secret = parsed_uri
             .query_pairs()
             .find(|(k, _v)| k == "secret")
-            .map(|(_k, v)| v.to_uppercase())
+            .map(|(_k, v)| {
+                if otp_type == OTPType::Motp {
+                    v.to_lowercase()
+                } else {
+                    v.to_uppercase()
+                }
+            })
             .ok_or(ErrReport::msg("Secret not found in OTP Uri"))?;
 
         let algorithm = parsed_uri
@@ -33,29 +51,54 @@ impl FromOtpUri for OTPElement {
         let digits = parsed_uri
             .query_pairs()
             .find(|(k, _v)| k == "digits")
-            .map_or(6, |(_k, v)| v.parse::<u64>().unwrap_or(6));
+            .map_or(default_digits, |(_k, v)| {
+                v.parse::<u64>().unwrap_or(default_digits)
+            });
 
         let period = parsed_uri
             .query_pairs()
             .find(|(k, _v)| k == "period")
-            .map_or(30, |(_k, v)| v.parse::<u64>().unwrap_or(30));
+            .map_or(default_period, |(_k, v)| {
+                v.parse::<u64>().unwrap_or(default_period)
+            });
 
         let counter = parsed_uri
             .query_pairs()
             .find(|(k, _v)| k == "counter")
             .and_then(|(_k, v)| v.parse::<u64>().ok());
 
-        Ok(OTPElement {
-            secret,
-            issuer,
-            label,
-            digits,
-            type_: OTPType::from(otp_type.as_str()),
-            algorithm: OTPAlgorithm::from(algorithm.as_str()),
-            period,
-            counter,
-            pin: None,
-        })
+        // Some apps name it epoch
+        let t0 = parsed_uri
+            .query_pairs()
+            .find(|(k, _v)| k == "t0" || k == "epoch")
+            .filter(|_| otp_type == OTPType::Totp)
+            .and_then(|(_k, v)| v.parse::<i64>().ok())
+            .unwrap_or(0);
+
+        let pin = parsed_uri
+            .query_pairs()
+            .find(|(k, _v)| k == "pin")
+            .map(|(_k, v)| v.to_string());
+
+        let ocra_suite = parsed_uri
+            .query_pairs()
+            .find(|(k, _v)| k == "suite")
+            .filter(|_| otp_type == OTPType::Ocra)
+            .map(|(_k, v)| v.to_string());
+
+        let mut element = OTPElement::default();
+        element.secret = secret;
+        element.issuer = issuer;
+        element.label = label;
+        element.digits = digits;
+        element.type_ = otp_type;
+        element.algorithm = OTPAlgorithm::from(algorithm.as_str());
+        element.period = period;
+        element.counter = counter;
+        element.pin = pin;
+        element.t0 = t0;
+        element.ocra_suite = ocra_suite;
+        Ok(element)
     }
 }
 
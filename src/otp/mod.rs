@@ -1,4 +1,5 @@
 pub mod algorithms;
+pub mod audit;
 pub mod from_otp_uri;
 pub mod migrations;
 pub mod otp_algorithm;
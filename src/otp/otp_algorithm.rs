@@ -26,8 +26,14 @@ impl fmt::Display for OTPAlgorithm {
 }
 
 impl From<&str> for OTPAlgorithm {
+    /// Accepts the names used by other apps too, like "SHA-256" or "HmacSHA512", unknown ones are SHA1
     fn from(s: &str) -> Self {
-        match s.to_uppercase().as_str() {
+        let name: String = s
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric())
+            .collect::<String>()
+            .to_uppercase();
+        match name.strip_prefix("HMAC").unwrap_or(&name) {
             "SHA256" => Self::Sha256,
             "SHA512" => Self::Sha512,
             "MD5" => Self::Md5,
@@ -41,3 +47,18 @@ impl Zeroize for OTPAlgorithm {
         *self = OTPAlgorithm::Sha1;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::OTPAlgorithm;
+
+    #[test]
+    fn test_algorithm_names() {
+        assert_eq!(OTPAlgorithm::Sha1, OTPAlgorithm::from("sha1"));
+        assert_eq!(OTPAlgorithm::Sha256, OTPAlgorithm::from("SHA-256"));
+        assert_eq!(OTPAlgorithm::Sha512, OTPAlgorithm::from("HmacSHA512"));
+        assert_eq!(OTPAlgorithm::Sha1, OTPAlgorithm::from("HMAC-SHA-1"));
+        assert_eq!(OTPAlgorithm::Md5, OTPAlgorithm::from("md5"));
+        assert_eq!(OTPAlgorithm::Sha1, OTPAlgorithm::from("unknown"));
+    }
+}
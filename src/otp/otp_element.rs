@@ -1,18 +1,34 @@
-use color_eyre::eyre::ErrReport;
-use std::{fs::File, io::Write, vec};
+use clap::ValueEnum;
+use std::vec;
 
-use crate::crypto::cryptography::{argon_derive_key, encrypt_string_with_key, gen_salt};
+use color_eyre::eyre::eyre;
+
+use crate::crypto::age::encrypt_to_recipients;
+use crate::crypto::cryptography::{
+    decrypt_string, derive_database_key, encrypt_string_with_key, gen_salt, DatabaseKey, KdfParams,
+};
+use crate::crypto::encrypted_database::EncryptedDatabase;
+use crate::lock::{fingerprint, DatabaseLock};
 use crate::otp::otp_error::OtpError;
 use crate::path::get_db_path;
+use crate::reading::read_with_key;
+use crate::remote::RemoteConfig;
+use crate::sync::SyncState;
+use crate::utils::{get_current_timestamp, hostname, url_host, wipe_json, write_atomically};
 use data_encoding::BASE32_NOPAD;
 use qrcode::render::unicode;
 use qrcode::QrCode;
 use serde::{Deserialize, Serialize};
-use zeroize::{Zeroize, ZeroizeOnDrop};
+use serde_json::Value;
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
 
 use super::{
     algorithms::{
-        hotp_maker::hotp, motp_maker::motp, steam_otp_maker::steam, totp_maker::totp,
+        hotp_maker::hotp,
+        motp_maker::motp,
+        ocra_maker::{ocra, OcraInput, OcraSuite},
+        steam_otp_maker::steam,
+        totp_maker::totp,
         yandex_otp_maker::yandex,
     },
     migrations::migrate,
@@ -20,14 +36,84 @@ use super::{
     otp_type::OTPType,
 };
 
+/// Version of the database format written by this release
 pub const CURRENT_DATABASE_VERSION: u16 = 2;
+/// Yandex secrets are made of 16 bytes, optionally followed by other data
+const YANDEX_SECRET_LENGTH: usize = 16;
+/// Periods or counter values shown at most on each side of the current code
+pub const MAX_NEARBY_WINDOW: u64 = 100;
+
+/// What to do with an added element having the same secret and issuer of an existing one
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug, ValueEnum)]
+pub enum DuplicatePolicy {
+    /// Keep the existing element, discarding the new one
+    #[default]
+    Skip,
+    /// Replace the existing element with the new one
+    Overwrite,
+    /// Add the new element anyway
+    KeepBoth,
+}
 
+/// What happened to an element merged into the database
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MergeOutcome {
+    Added,
+    Skipped,
+    /// Replaced the element at the given index
+    Overwritten(usize),
+}
+
+/// Decrypted content of the cotp database, which keeps track of unsaved changes
 #[derive(Serialize, Deserialize, PartialEq, Hash)]
 pub struct OTPDatabase {
     pub(crate) version: u16,
     pub(crate) elements: Vec<OTPElement>,
+    /// Deleted elements, which can be restored until they are purged
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) trash: Vec<TrashedElement>,
+    /// Changes made to the database, only ever appended
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) history: Vec<HistoryEntry>,
+    /// Folder the database is synchronized with, see the sync module
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) sync: Option<SyncState>,
+    /// Server the database is pushed to and pulled from, see the remote module
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) remote: Option<RemoteConfig>,
     #[serde(skip)]
     pub(crate) needs_modification: bool,
+    /// Only usage statistics changed, so the database can be saved without bothering the user
+    #[serde(skip)]
+    pub(crate) usage_modified: bool,
+    /// Stored in the encrypted database envelope, since it is needed before decryption
+    #[serde(skip)]
+    pub(crate) yubikey_slot: Option<u8>,
+    /// Argon2 parameters, stored in the encrypted database envelope too
+    #[serde(skip)]
+    pub(crate) kdf_params: KdfParams,
+    /// Hash of the keyfile combined with the password, if any
+    #[serde(skip)]
+    pub(crate) keyfile_hash: Option<[u8; 32]>,
+    /// The key derivation needs a keyfile, even if its hash is unknown when unlocked with age
+    #[serde(skip)]
+    pub(crate) keyfile_required: bool,
+    /// Recipients able to unlock the database without the password. They are encrypted with the rest of the
+    /// database, so whoever can write the file cannot add their own
+    #[serde(default)]
+    pub(crate) age_recipients: Vec<String>,
+    /// Database key encrypted to the recipients, stored in the encrypted database envelope
+    #[serde(skip)]
+    pub(crate) age_key: Option<String>,
+    /// Fingerprint of the encrypted file this database was read from, None if it did not exist yet
+    #[serde(skip)]
+    pub(crate) disk_fingerprint: Option<[u8; 32]>,
+    /// Fingerprint of the content read from the file, ignoring the usage statistics
+    #[serde(skip)]
+    pub(crate) base_fingerprint: Option<[u8; 32]>,
+    /// Elements encrypted in memory while the dashboard is locked, the plain ones are wiped
+    #[serde(skip)]
+    pub(crate) locked: Option<String>,
 }
 
 impl From<Vec<OTPElement>> for OTPDatabase {
@@ -36,7 +122,21 @@ impl From<Vec<OTPElement>> for OTPDatabase {
         OTPDatabase {
             version: 1,
             elements: val,
+            trash: vec![],
+            history: vec![],
+            sync: None,
+            remote: None,
             needs_modification: true,
+            usage_modified: false,
+            yubikey_slot: None,
+            kdf_params: KdfParams::default(),
+            keyfile_hash: None,
+            keyfile_required: false,
+            age_recipients: vec![],
+            age_key: None,
+            disk_fingerprint: None,
+            base_fingerprint: None,
+            locked: None,
         }
     }
 }
@@ -46,63 +146,483 @@ impl Default for OTPDatabase {
         Self {
             version: CURRENT_DATABASE_VERSION,
             elements: vec![],
+            trash: vec![],
+            history: vec![],
+            sync: None,
+            remote: None,
             needs_modification: false,
+            usage_modified: false,
+            yubikey_slot: None,
+            kdf_params: KdfParams::default(),
+            keyfile_hash: None,
+            keyfile_required: false,
+            age_recipients: vec![],
+            age_key: None,
+            disk_fingerprint: None,
+            base_fingerprint: None,
+            locked: None,
         }
     }
 }
 
 impl OTPDatabase {
+    /// Returns true if the user changed the database since the last save
     pub fn is_modified(&self) -> bool {
         self.needs_modification
     }
 
+    pub fn is_usage_modified(&self) -> bool {
+        self.usage_modified
+    }
+
+    /// Encrypts the database with an already derived key and writes it to the database path.
+    /// The usage statistics saved by another cotp instance since this database was read are merged,
+    /// other changes are not overwritten and [`SaveConflict`] is returned.
     pub fn save(&mut self, key: &Vec<u8>, salt: &[u8]) -> color_eyre::Result<()> {
+        self.save_checking(key, salt, true)
+    }
+
+    /// Saves the database even if another cotp instance changed it, discarding those changes
+    pub fn save_overwriting(&mut self, key: &Vec<u8>, salt: &[u8]) -> color_eyre::Result<()> {
+        self.save_checking(key, salt, false)
+    }
+
+    fn save_checking(
+        &mut self,
+        key: &Vec<u8>,
+        salt: &[u8],
+        check_changes: bool,
+    ) -> color_eyre::Result<()> {
+        // The elements were wiped, so saving would empty the database
+        if self.is_locked() {
+            if !self.needs_modification {
+                return Ok(());
+            }
+            return Err(eyre!(
+                "The database was locked, your changes have not been saved"
+            ));
+        }
+        let _lock = DatabaseLock::exclusive()?;
+        let on_disk = std::fs::read(get_db_path()).ok();
+        if check_changes && on_disk.as_deref().map(fingerprint) != self.disk_fingerprint {
+            // Usage statistics can be lost, but user changes cannot
+            if !self.needs_modification {
+                return Ok(());
+            }
+            self.merge_usage_from(on_disk.as_deref().unwrap_or_default(), key)?;
+        }
         self.needs_modification = false;
+        self.usage_modified = false;
         migrate(self)?;
-        match self.overwrite_database_key(key, salt) {
-            Ok(()) => Ok(()),
-            Err(e) => Err(ErrReport::from(e)),
-        }
+        self.overwrite_database_key(key, salt)?;
+        self.base_fingerprint = Some(self.content_fingerprint()?);
+        Ok(())
     }
 
-    fn overwrite_database_key(&self, key: &Vec<u8>, salt: &[u8]) -> Result<(), std::io::Error> {
-        let json: &str = &serde_json::to_string(&self)?;
-        let encrypted = encrypt_string_with_key(json.to_string(), key, salt).unwrap();
-        let mut file = File::create(get_db_path())?;
-        match serde_json::to_string(&encrypted) {
-            Ok(content) => {
-                file.write_all(content.as_bytes())?;
-                file.sync_all()?;
-                Ok(())
+    /// Takes the usage statistics of the database saved by another instance, failing if it changed anything else
+    fn merge_usage_from(&mut self, encrypted: &[u8], key: &[u8]) -> color_eyre::Result<()> {
+        let encrypted = std::str::from_utf8(encrypted).map_err(|_| SaveConflict)?;
+        let mut saved = read_with_key(encrypted, key.to_vec()).map_err(|_| SaveConflict)?;
+        if self.base_fingerprint.is_none()
+            || Some(saved.content_fingerprint()?) != self.base_fingerprint
+        {
+            return Err(SaveConflict.into());
+        }
+        for element in self.elements.iter_mut() {
+            if let Some(other) = saved
+                .elements
+                .iter()
+                .find(|other| element.same_except_usage(other))
+            {
+                element.usage_count = element.usage_count.max(other.usage_count);
+                element.last_used = element.last_used.max(other.last_used);
             }
-            Err(e) => Err(std::io::Error::from(e)),
         }
+        Ok(())
+    }
+
+    /// Fingerprint of the serialized database with the usage statistics left out
+    pub(crate) fn content_fingerprint(&mut self) -> color_eyre::Result<[u8; 32]> {
+        let usage: Vec<(u64, u64)> = self
+            .elements
+            .iter_mut()
+            .map(|e| {
+                (
+                    std::mem::take(&mut e.usage_count),
+                    std::mem::take(&mut e.last_used),
+                )
+            })
+            .collect();
+        let json = serde_json::to_string(&self).map(Zeroizing::new);
+        for (element, (usage_count, last_used)) in self.elements.iter_mut().zip(usage) {
+            element.usage_count = usage_count;
+            element.last_used = last_used;
+        }
+        Ok(fingerprint(json?.as_bytes()))
+    }
+
+    fn overwrite_database_key(&mut self, key: &Vec<u8>, salt: &[u8]) -> color_eyre::Result<()> {
+        // The key is encrypted again only when it or the recipients change
+        if !self.age_recipients.is_empty() && self.age_key.is_none() {
+            self.age_key = Some(encrypt_to_recipients(key, &self.age_recipients)?);
+        }
+        let content = serde_json::to_string(&self.encrypt(key, salt)?)?;
+        write_atomically(&get_db_path(), content.as_bytes())?;
+        self.disk_fingerprint = Some(fingerprint(content.as_bytes()));
+        Ok(())
+    }
+
+    fn encrypt(&self, key: &Vec<u8>, salt: &[u8]) -> color_eyre::Result<EncryptedDatabase> {
+        // The plain text is wiped by the encryption
+        let json = serde_json::to_string(&self)?;
+        Ok(encrypt_string_with_key(json, key, salt)?
+            .with_yubikey_slot(self.yubikey_slot)
+            .with_kdf_params(self.kdf_params)
+            .with_keyfile(self.keyfile_required)
+            .with_age_key(self.age_key.to_owned()))
+    }
+
+    /// Encrypts the elements in memory with the database key and wipes the plain ones,
+    /// so that the password is needed to use them again
+    pub fn lock(&mut self, key: &Vec<u8>, salt: &[u8]) -> color_eyre::Result<()> {
+        self.locked = Some(serde_json::to_string(&self.encrypt(key, salt)?)?);
+        self.elements.clear();
+        self.trash.clear();
+        self.history.clear();
+        self.sync = None;
+        self.remote = None;
+        Ok(())
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.locked.is_some()
+    }
+
+    /// Decrypts the elements locked in memory, returning the database key derived from the password
+    pub fn unlock(&mut self, password: &str) -> color_eyre::Result<DatabaseKey> {
+        let locked = self
+            .locked
+            .as_deref()
+            .ok_or(eyre!("The database is not locked"))?;
+        let (mut contents, key, _salt) = decrypt_string(locked, password)?;
+        let unlocked = serde_json::from_str::<OTPDatabase>(&contents);
+        contents.zeroize();
+        let unlocked = unlocked?;
+        self.elements = unlocked.elements;
+        self.trash = unlocked.trash;
+        self.history = unlocked.history;
+        self.sync = unlocked.sync;
+        self.remote = unlocked.remote;
+        self.locked = None;
+        Ok(key)
+    }
+
+    /// True if the password derives the key the database is encrypted with
+    pub fn is_password_correct(
+        &self,
+        password: &str,
+        key: &[u8],
+        salt: &[u8],
+    ) -> color_eyre::Result<bool> {
+        if self.keyfile_required && self.keyfile_hash.is_none() {
+            return Err(eyre!(
+                "The keyfile is needed to check the password, use --keyfile"
+            ));
+        }
+        let derived = derive_database_key(
+            password.as_bytes(),
+            salt,
+            self.yubikey_slot,
+            self.keyfile_hash.as_ref().map(|h| h.as_slice()),
+            &self.kdf_params,
+        )?;
+        Ok(derived.as_slice() == key)
     }
 
-    pub fn save_with_pw(&mut self, password: &str) -> color_eyre::Result<(Vec<u8>, [u8; 16])> {
+    /// Derives a new key from the password and saves the database, returning the key and its salt
+    pub fn save_with_pw(&mut self, password: &str) -> color_eyre::Result<(DatabaseKey, [u8; 16])> {
+        if self.keyfile_required && self.keyfile_hash.is_none() {
+            return Err(eyre!(
+                "The keyfile is needed to change the password, use --keyfile"
+            ));
+        }
+        // Encrypted to the recipients again with the new key
+        self.age_key = None;
         let salt = gen_salt()?;
-        let key = argon_derive_key(password.as_bytes(), &salt)?;
+        let key = derive_database_key(
+            password.as_bytes(),
+            &salt,
+            self.yubikey_slot,
+            self.keyfile_hash.as_ref().map(|h| h.as_slice()),
+            &self.kdf_params,
+        )?;
         self.save(&key, &salt)?;
         Ok((key, salt))
     }
 
+    /// Appends the elements, recording their creation time if unknown
     pub fn add_all(&mut self, mut elements: Vec<OTPElement>) {
         self.mark_modified();
+        let now = get_current_timestamp();
+        elements
+            .iter_mut()
+            .filter(|e| e.created_at == 0)
+            .for_each(|e| e.created_at = now);
         self.elements.append(&mut elements)
     }
 
     pub fn add_element(&mut self, element: OTPElement) {
+        self.add_all(vec![element])
+    }
+
+    /// Returns the index of the element with the same secret and issuer, if any
+    pub fn find_duplicate(&self, element: &OTPElement) -> Option<usize> {
+        self.elements.iter().position(|e| e.is_duplicate(element))
+    }
+
+    /// Adds the elements, handling the duplicates following the policy.
+    /// Returns the outcome of each element, in the same order
+    pub fn merge(
+        &mut self,
+        elements: Vec<OTPElement>,
+        policy: DuplicatePolicy,
+    ) -> Vec<MergeOutcome> {
+        elements
+            .into_iter()
+            .map(|element| match (self.find_duplicate(&element), policy) {
+                (Some(_), DuplicatePolicy::Skip) => MergeOutcome::Skipped,
+                (Some(index), DuplicatePolicy::Overwrite) => {
+                    self.replace_element(index, element);
+                    MergeOutcome::Overwritten(index)
+                }
+                (_, _) => {
+                    self.add_element(element);
+                    MergeOutcome::Added
+                }
+            })
+            .collect()
+    }
+
+    /// Merges each group of duplicates into its kept element, which gets the tags, websites, recovery codes
+    /// and notes of the others, then moves the others to the trash. The ones generating different codes are kept
+    pub fn merge_duplicates(&mut self, merges: &[(usize, Vec<usize>)]) {
+        let mut merged = vec![];
+        for (kept, others) in merges {
+            for &other in others.iter().filter(|other| *other != kept) {
+                if let Some(other_element) = self.elements.get(other).cloned() {
+                    if let Some(element) = self
+                        .elements
+                        .get_mut(*kept)
+                        .filter(|element| element.same_otp_config(&other_element))
+                    {
+                        element.absorb(&other_element);
+                        merged.push(other);
+                    }
+                }
+            }
+        }
+        // From the last one, so the indexes of the others do not change
+        merged.sort_unstable();
+        merged.dedup();
+        for index in merged.into_iter().rev() {
+            self.delete_element(index);
+        }
+    }
+
+    /// Swaps two elements, used to manually sort the database
+    pub fn swap_elements(&mut self, a: usize, b: usize) {
         self.mark_modified();
-        self.elements.push(element)
+        self.elements.swap(a, b);
+    }
+
+    /// Increments the usage counter of the element at the given index, recording when it was used
+    pub fn record_usage(&mut self, index: usize) {
+        if let Some(element) = self.elements.get_mut(index) {
+            element.usage_count = element.usage_count.saturating_add(1);
+            element.last_used = get_current_timestamp();
+            self.usage_modified = true;
+        }
     }
 
     pub fn mark_modified(&mut self) {
         self.needs_modification = true;
     }
 
+    /// Appends the change to the history, like "Added GitHub - alice"
+    pub fn record_change(&mut self, message: impl Into<String>) {
+        self.mark_modified();
+        self.history.push(HistoryEntry {
+            timestamp: get_current_timestamp(),
+            host: hostname(),
+            message: message.into(),
+        });
+    }
+
+    /// Changes made to the database, from the oldest one
+    pub fn history_ref(&self) -> &[HistoryEntry] {
+        &self.history
+    }
+
+    /// Adds the changes recorded by a copy of the database, like the one of another machine,
+    /// keeping them in chronological order
+    pub fn merge_history(&mut self, other: &[HistoryEntry]) {
+        let missing: Vec<HistoryEntry> = other
+            .iter()
+            .filter(|entry| !self.history.contains(entry))
+            .cloned()
+            .collect();
+        if missing.is_empty() {
+            return;
+        }
+        self.mark_modified();
+        self.history.extend(missing);
+        // Stable, so the changes made in the same second keep their order
+        self.history.sort_by_key(|entry| entry.timestamp);
+    }
+
+    pub fn yubikey_slot(&self) -> Option<u8> {
+        self.yubikey_slot
+    }
+
+    /// Sets the YubiKey slot used to derive the key on the next password change
+    pub fn set_yubikey_slot(&mut self, yubikey_slot: Option<u8>) {
+        self.yubikey_slot = yubikey_slot;
+    }
+
+    pub fn kdf_params(&self) -> KdfParams {
+        self.kdf_params
+    }
+
+    pub fn keyfile_hash(&self) -> Option<[u8; 32]> {
+        self.keyfile_hash
+    }
+
+    /// Sets the keyfile hash used to derive the key on the next password change
+    pub fn set_keyfile_hash(&mut self, keyfile_hash: Option<[u8; 32]>) {
+        self.keyfile_required = keyfile_hash.is_some();
+        self.keyfile_hash = keyfile_hash;
+    }
+
+    pub fn age_recipients(&self) -> &[String] {
+        &self.age_recipients
+    }
+
+    /// Lets the recipient unlock the database, returning false if it already could
+    pub fn add_age_recipient(&mut self, recipient: &str) -> bool {
+        if self.age_recipients.iter().any(|r| r == recipient) {
+            return false;
+        }
+        self.age_recipients.push(recipient.to_owned());
+        self.age_key = None;
+        self.mark_modified();
+        true
+    }
+
+    /// Stops the recipient from unlocking the database, returning false if it could not
+    pub fn remove_age_recipient(&mut self, recipient: &str) -> bool {
+        let count = self.age_recipients.len();
+        self.age_recipients.retain(|r| r != recipient);
+        if count == self.age_recipients.len() {
+            return false;
+        }
+        self.age_key = None;
+        self.mark_modified();
+        true
+    }
+
+    /// True if the database should be encrypted again, because its key derivation is weaker than
+    /// the target one or its format is older than the current one
+    pub fn needs_key_upgrade(&self, target: &KdfParams) -> bool {
+        self.kdf_params.is_weaker_than(target) || self.version < CURRENT_DATABASE_VERSION
+    }
+
+    /// Sets the Argon2 parameters used to derive the key on the next password change
+    pub fn set_kdf_params(&mut self, kdf_params: KdfParams) {
+        self.kdf_params = kdf_params;
+    }
+
+    /// Forgets every pending change, so the database is not saved
+    pub fn discard_modifications(&mut self) {
+        self.needs_modification = false;
+        self.usage_modified = false;
+    }
+
+    /// Puts back an element at the given position, used to undo a deletion, taking it out of the trash
+    pub fn insert_element(&mut self, index: usize, element: OTPElement) {
+        self.mark_modified();
+        if let Some(trashed) = self.trash.iter().rposition(|t| t.element == element) {
+            self.trash.remove(trashed);
+        }
+        self.elements
+            .insert(index.min(self.elements.len()), element);
+    }
+
+    /// Replaces the element at the given index, if present
+    pub fn replace_element(&mut self, index: usize, element: OTPElement) {
+        if let Some(current) = self.elements.get_mut(index) {
+            *current = element;
+            self.mark_modified();
+        }
+    }
+
+    /// Moves the element to the trash, from which it can be restored
     pub fn delete_element(&mut self, index: usize) {
         self.mark_modified();
-        self.elements.remove(index);
+        let element = self.elements.remove(index);
+        self.trash.push(TrashedElement {
+            element,
+            deleted_at: get_current_timestamp(),
+        });
+    }
+
+    /// Deletes the element without moving it to the trash, used to undo an addition
+    pub fn remove_element(&mut self, index: usize) -> OTPElement {
+        self.mark_modified();
+        self.elements.remove(index)
+    }
+
+    /// Deleted elements, from the oldest deletion
+    pub fn trash_ref(&self) -> &[TrashedElement] {
+        &self.trash
+    }
+
+    /// Moves the trashed element at the given index back to the end of the elements
+    pub fn restore_trashed(&mut self, index: usize) -> color_eyre::Result<()> {
+        if index >= self.trash.len() {
+            return Err(eyre!("No deleted code at index {}", index + 1));
+        }
+        let trashed = self.trash.remove(index);
+        self.mark_modified();
+        self.elements.push(trashed.element);
+        Ok(())
+    }
+
+    /// Permanently deletes the trashed element at the given index, or all of them.
+    /// Returns how many elements were purged
+    pub fn purge_trash(&mut self, index: Option<usize>) -> color_eyre::Result<usize> {
+        let purged = match index {
+            Some(i) if i >= self.trash.len() => {
+                return Err(eyre!("No deleted code at index {}", i + 1))
+            }
+            Some(i) => {
+                self.trash.remove(i);
+                1
+            }
+            None => self.trash.drain(..).count(),
+        };
+        if purged > 0 {
+            self.mark_modified();
+        }
+        Ok(purged)
+    }
+
+    /// Permanently deletes the elements trashed more than the given days ago, 0 keeps them until purged
+    pub(crate) fn purge_expired_trash(&mut self, retention_days: u64) {
+        if retention_days == 0 {
+            return;
+        }
+        let oldest = get_current_timestamp().saturating_sub(retention_days.saturating_mul(86400));
+        self.trash.retain(|t| t.deleted_at >= oldest);
     }
 
     pub fn elements_ref(&self) -> &[OTPElement] {
@@ -117,15 +637,134 @@ impl OTPDatabase {
         self.elements.get_mut(i)
     }
 
-    pub fn sort(&mut self) {
-        self.elements.sort_unstable_by(|c1, c2| {
-            c1.issuer
-                .to_ascii_lowercase()
-                .cmp(&c2.issuer.to_ascii_lowercase())
-        })
+    /// Returns every distinct tag used in the database, sorted alphabetically
+    pub fn tags(&self) -> Vec<String> {
+        let mut tags: Vec<String> = self
+            .elements
+            .iter()
+            .flat_map(|e| e.tags.iter().cloned())
+            .collect();
+        tags.sort_by_key(|t| t.to_lowercase());
+        tags.dedup_by(|a, b| same_tag(a, b));
+        tags
+    }
+
+    /// Returns the elements with their indexes, sorted by issuer and label ignoring the case
+    pub fn sorted_by_issuer(&self) -> Vec<(usize, &OTPElement)> {
+        let mut elements: Vec<(usize, &OTPElement)> = self.elements.iter().enumerate().collect();
+        elements.sort_by_cached_key(|(_, e)| (e.issuer.to_lowercase(), e.label.to_lowercase()));
+        elements
+    }
+
+    /// Returns the indexes of the elements matching the query, case insensitively.
+    /// Elements whose issuer, label or "issuer:label" equals the query take precedence
+    /// over the ones merely containing it.
+    pub fn find_matching(&self, query: &str) -> Vec<usize> {
+        let query = query.to_lowercase();
+        let exact: Vec<usize> = self
+            .elements
+            .iter()
+            .enumerate()
+            .filter(|(_, e)| {
+                let issuer = e.issuer.to_lowercase();
+                let label = e.label.to_lowercase();
+                issuer == query || label == query || format!("{issuer}:{label}") == query
+            })
+            .map(|(i, _)| i)
+            .collect();
+        if !exact.is_empty() {
+            return exact;
+        }
+        self.elements
+            .iter()
+            .enumerate()
+            .filter(|(_, e)| {
+                e.issuer.to_lowercase().contains(&query) || e.label.to_lowercase().contains(&query)
+            })
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// Returns the indexes of the elements used on a website, best matches first: the ones with its
+    /// exact domain, then the ones with a parent domain, like github.com for gist.github.com,
+    /// then the ones whose issuer matches it, like "GitHub" for "https://github.com/login".
+    /// A bare domain is accepted too.
+    pub fn find_by_url(&self, url: &str) -> Vec<usize> {
+        let Some(host) = url_host(url) else {
+            return vec![];
+        };
+        let mut matches: Vec<(usize, u8)> = self
+            .elements
+            .iter()
+            .enumerate()
+            .filter_map(|(i, e)| e.url_match_score(&host).map(|score| (i, score)))
+            .collect();
+        // Stable, so the elements with the same score keep the database order
+        matches.sort_by_key(|(_, score)| std::cmp::Reverse(*score));
+        matches.into_iter().map(|(i, _)| i).collect()
+    }
+}
+
+fn domain_matches_host(domain: &str, host: &str) -> bool {
+    host.strip_suffix(domain)
+        .is_some_and(|prefix| prefix.is_empty() || prefix.ends_with('.'))
+}
+
+/// An issuer matches if it is the host or one of its parent domains, like "github.com", or if it equals
+/// the name of the registered domain ignoring case, spaces and punctuation, like "GitHub" for gist.github.com.
+/// The other labels are not compared, so github.evil.example does not get the codes of GitHub.
+fn issuer_matches_host(issuer: &str, host: &str) -> bool {
+    let issuer = issuer.trim().to_lowercase();
+    if issuer.is_empty() {
+        return false;
+    }
+    if issuer.contains('.') && domain_matches_host(&issuer, host) {
+        return true;
+    }
+    let issuer: String = issuer.chars().filter(|c| c.is_alphanumeric()).collect();
+    registered_name(host) == Some(issuer.as_str())
+}
+
+/// Second level suffixes under country domains, like co.uk, which are not the name of a site
+const SECOND_LEVEL_SUFFIXES: [&str; 7] = ["ac", "co", "com", "edu", "gov", "net", "org"];
+
+/// The label registered under the public suffix, like github for gist.github.com or github.co.uk
+fn registered_name(host: &str) -> Option<&str> {
+    let mut labels = host.rsplit('.');
+    let top_level = labels.next()?;
+    let name = labels.next()?;
+    if top_level.len() == 2 && SECOND_LEVEL_SUFFIXES.contains(&name) {
+        return labels.next();
     }
+    Some(name)
+}
+
+/// A code before or after the current one, returned by OTPElement::get_nearby_codes
+#[derive(Debug, PartialEq)]
+pub struct NearbyCode {
+    /// Periods, or counter values, from the current code
+    pub offset: i64,
+    pub code: String,
+    /// Unix times from which the code is valid, until the end one excluded, None for HOTP codes
+    pub validity: Option<(u64, u64)>,
+    pub counter: Option<u64>,
 }
 
+/// The database has been changed by another cotp instance since it was read
+#[derive(Debug)]
+pub struct SaveConflict;
+
+impl std::fmt::Display for SaveConflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(
+            "The database has been changed by another cotp instance, your changes have not been saved",
+        )
+    }
+}
+
+impl std::error::Error for SaveConflict {}
+
+/// A single OTP code with all the parameters needed to generate it
 #[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug, Hash, Zeroize, ZeroizeOnDrop)]
 pub struct OTPElement {
     pub secret: String,
@@ -138,9 +777,156 @@ pub struct OTPElement {
     pub period: u64,
     pub counter: Option<u64>,
     pub pin: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Unix timestamp of when the element was added into the database
+    #[serde(default)]
+    pub created_at: u64,
+    /// How many times the code has been copied
+    #[serde(default)]
+    pub usage_count: u64,
+    /// Unix timestamp of the last time the code was copied, 0 if never
+    #[serde(default)]
+    pub last_used: u64,
+    /// Pinned elements are shown before the others in the dashboard
+    #[serde(default)]
+    pub pinned: bool,
+    /// Unix time from which the TOTP periods are counted (the T0 of RFC 6238), negative values move them back
+    #[serde(default)]
+    pub t0: i64,
+    /// RFC 6287 suite of OCRA codes, like OCRA-1:HOTP-SHA1-6:QN08
+    #[serde(default)]
+    pub ocra_suite: Option<String>,
+    /// Websites where the code is used, like github.com, matched by the browser integrations
+    #[serde(default)]
+    pub domains: Vec<String>,
+    /// Free text about the account, like where its recovery codes are kept
+    #[serde(default)]
+    pub note: String,
+    /// Single use codes given by the service to log in without the OTP codes
+    #[serde(default)]
+    pub recovery_codes: Vec<RecoveryCode>,
+    /// Logo of the service, imported from other apps
+    #[serde(default)]
+    pub icon: Option<Icon>,
+    /// Random identifier given when the database is first synchronized, to recognize the element in the sync folder
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub id: String,
+    /// Characters per group when the code is shown, like 3 for 123 456, 0 to not group it.
+    /// None follows the configuration
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub digit_group: Option<u64>,
+}
+
+/// Image or short text identifying the service, like the logos of Aegis and the labels of 2FAS
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq, Eq, Debug, Hash, Zeroize)]
+pub struct Icon {
+    /// Base64 encoded image
+    #[serde(default)]
+    pub image: Option<String>,
+    /// Type of the image, like image/png or image/svg+xml
+    #[serde(default)]
+    pub mime: Option<String>,
+    /// A few letters shown in place of the image
+    #[serde(default)]
+    pub text: Option<String>,
+    /// Background color of the text, as a color name or a hex value like #ff8800
+    #[serde(default)]
+    pub color: Option<String>,
+}
+
+/// Change made to the database, recorded with when and where it was made
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug, Hash)]
+pub struct HistoryEntry {
+    /// Unix timestamp of the change
+    pub timestamp: u64,
+    /// Name of the machine the change was made on
+    #[serde(default)]
+    pub host: String,
+    pub message: String,
+}
+
+/// Deleted element, kept in the encrypted database until it is restored or purged
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug, Hash)]
+pub struct TrashedElement {
+    pub element: OTPElement,
+    /// Unix timestamp of the deletion
+    pub deleted_at: u64,
+}
+
+/// Recovery code of an account, kept after being used to know which ones are left
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug, Hash, Zeroize, ZeroizeOnDrop)]
+pub struct RecoveryCode {
+    pub code: String,
+    /// Unix timestamp of when the code was used, 0 if it is still valid
+    #[serde(default)]
+    pub used_at: u64,
+}
+
+impl RecoveryCode {
+    pub fn new(code: &str) -> Self {
+        RecoveryCode {
+            code: code.trim().to_owned(),
+            used_at: 0,
+        }
+    }
+
+    pub fn is_used(&self) -> bool {
+        self.used_at != 0
+    }
+}
+
+/// A TOTP code of 6 digits every 30 seconds with SHA-1, like the codes of most services. OTPElement is wiped on
+/// drop, so the struct update syntax cannot move the other fields out of it: the fields are assigned instead
+impl Default for OTPElement {
+    fn default() -> Self {
+        OTPElement {
+            secret: String::new(),
+            issuer: String::new(),
+            label: String::new(),
+            digits: 6,
+            type_: OTPType::Totp,
+            algorithm: OTPAlgorithm::Sha1,
+            period: 30,
+            counter: None,
+            pin: None,
+            tags: vec![],
+            created_at: 0,
+            usage_count: 0,
+            last_used: 0,
+            pinned: false,
+            t0: 0,
+            ocra_suite: None,
+            domains: vec![],
+            note: String::new(),
+            recovery_codes: vec![],
+            icon: None,
+            id: String::new(),
+            digit_group: None,
+        }
+    }
 }
 
 impl OTPElement {
+    /// How well the element matches the host of a website, None if it does not.
+    /// The domains set by the user are the only ones allowed, the issuer is a guess for the elements without them.
+    fn url_match_score(&self, host: &str) -> Option<u8> {
+        if self.domains.iter().any(|d| d.eq_ignore_ascii_case(host)) {
+            Some(3)
+        } else if self
+            .domains
+            .iter()
+            .any(|d| domain_matches_host(&d.to_lowercase(), host))
+        {
+            Some(2)
+        } else if self.domains.is_empty() && issuer_matches_host(&self.issuer, host) {
+            Some(1)
+        } else {
+            None
+        }
+    }
+
+    /// Builds the otpauth:// URI, understood by most authenticator apps
     pub fn get_otpauth_uri(&self) -> String {
         let otp_type = self.type_.to_string().to_lowercase();
         let secret = &urlencoding::encode(self.secret.as_str());
@@ -149,11 +935,32 @@ impl OTPElement {
         let digits = self.digits;
         let period = self.period;
         let mut uri: String = format!("otpauth://{otp_type}/{label}?secret={secret}&algorithm={algorithm}&digits={digits}&period={period}&lock=false");
+        // Some apps read the issuer only from its parameter
+        if !self.issuer.is_empty() {
+            uri.push_str("&issuer=");
+            uri.push_str(&urlencoding::encode(&self.issuer));
+        }
 
         if self.type_ == OTPType::Hotp {
             uri.push_str("&counter=");
             uri.push_str(self.counter.unwrap_or(0).to_string().as_str());
         }
+        if self.type_ == OTPType::Totp && self.t0 != 0 {
+            uri.push_str("&t0=");
+            uri.push_str(self.t0.to_string().as_str());
+        }
+        if let Some(suite) = self
+            .ocra_suite
+            .as_ref()
+            .filter(|_| self.type_ == OTPType::Ocra)
+        {
+            uri.push_str("&suite=");
+            uri.push_str(&urlencoding::encode(suite));
+        }
+        if let Some(pin) = self.pin.as_ref().filter(|_| self.requires_pin()) {
+            uri.push_str("&pin=");
+            uri.push_str(&urlencoding::encode(pin));
+        }
         uri
     }
 
@@ -166,10 +973,16 @@ impl OTPElement {
             .build()
     }
 
+    /// Generates the current code
     pub fn get_otp_code(&self) -> Result<String, OtpError> {
+        self.get_otp_code_at(get_current_timestamp())
+    }
+
+    /// Generates the code valid at the given Unix time, HOTP codes use their counter
+    pub fn get_otp_code_at(&self, time: u64) -> Result<String, OtpError> {
         match self.type_ {
             OTPType::Totp => {
-                let code = totp(&self.secret, self.algorithm)?;
+                let code = totp(&self.secret, self.algorithm, self.period, self.t0, time)?;
 
                 Ok(self.format_code(code))
             }
@@ -181,7 +994,7 @@ impl OTPElement {
                 }
                 None => Err(OtpError::MissingCounter),
             },
-            OTPType::Steam => steam(&self.secret, self.algorithm, self.digits as usize),
+            OTPType::Steam => steam(&self.secret, self.algorithm, self.digits as usize, time),
             OTPType::Yandex => match &self.pin {
                 Some(pin) => yandex(
                     &self.secret,
@@ -189,6 +1002,7 @@ impl OTPElement {
                     self.period,
                     self.digits as usize,
                     self.algorithm,
+                    time,
                 ),
                 None => Err(OtpError::MissingPin),
             },
@@ -198,9 +1012,133 @@ impl OTPElement {
                     pin.as_str(),
                     self.period,
                     self.digits as usize,
+                    time,
                 ),
                 None => Err(OtpError::MissingPin),
             },
+            // Responses are only computed for a challenge
+            OTPType::Ocra => Err(OtpError::MissingChallenge),
+        }
+    }
+
+    /// Codes from `window` periods, or counter values, before the current one to `window` after it
+    pub fn get_nearby_codes(&self, window: u64) -> Result<Vec<NearbyCode>, OtpError> {
+        self.get_nearby_codes_at(window, get_current_timestamp())
+    }
+
+    /// Codes around the one valid at the given Unix time, at most [`MAX_NEARBY_WINDOW`] on each side
+    pub fn get_nearby_codes_at(&self, window: u64, time: u64) -> Result<Vec<NearbyCode>, OtpError> {
+        let window = window.min(MAX_NEARBY_WINDOW) as i64;
+        let mut codes = vec![];
+        match self.type_ {
+            OTPType::Ocra => return Err(OtpError::MissingChallenge),
+            OTPType::Hotp => {
+                let current = self.counter.ok_or(OtpError::MissingCounter)?;
+                for offset in -window..=window {
+                    let Some(counter) = current.checked_add_signed(offset) else {
+                        continue;
+                    };
+                    let code = hotp(&self.secret, self.algorithm, counter)?;
+                    codes.push(NearbyCode {
+                        offset,
+                        code: self.format_code(code),
+                        validity: None,
+                        counter: Some(counter),
+                    });
+                }
+            }
+            _ => {
+                let period = self.period.max(1) as i64;
+                let t0 = self.time_offset() as i128;
+                let now = time as i128;
+                let current_start = t0 + (now - t0).div_euclid(period as i128) * period as i128;
+                let Ok(current_start) = i64::try_from(current_start) else {
+                    return Ok(codes);
+                };
+                for offset in -window..=window {
+                    let start = current_start.saturating_add(offset.saturating_mul(period));
+                    if start < 0 {
+                        continue;
+                    }
+                    codes.push(NearbyCode {
+                        offset,
+                        code: self.get_otp_code_at(start as u64)?,
+                        validity: Some((start as u64, start.saturating_add(period) as u64)),
+                        counter: None,
+                    });
+                }
+            }
+        }
+        Ok(codes)
+    }
+
+    /// Computes the OCRA response to the challenge, using the stored counter and PIN if the suite needs them
+    pub fn get_ocra_response(
+        &self,
+        challenge: &str,
+        session: Option<&str>,
+    ) -> Result<String, OtpError> {
+        let suite = self.parsed_ocra_suite()?;
+        let input = OcraInput {
+            counter: self.counter,
+            challenge,
+            pin: self.pin.as_deref(),
+            session,
+        };
+        ocra(&self.secret, &suite, &input)
+    }
+
+    pub fn parsed_ocra_suite(&self) -> Result<OcraSuite, OtpError> {
+        self.ocra_suite
+            .as_deref()
+            .ok_or(OtpError::InvalidSuite)?
+            .parse()
+    }
+
+    /// Searches the window following the stored counter for two consecutive HOTP codes, like the last ones
+    /// accepted by the server, returning the counter of the code after them
+    pub fn find_hotp_resync_counter(
+        &self,
+        first_code: &str,
+        second_code: &str,
+        window: u64,
+    ) -> Result<Option<u64>, OtpError> {
+        let start = self.counter.unwrap_or(0);
+        let code_at =
+            |counter| hotp(&self.secret, self.algorithm, counter).map(|c| self.format_code(c));
+        let mut current = code_at(start)?;
+        for counter in start..start.saturating_add(window) {
+            let next = code_at(counter + 1)?;
+            if current == first_code && next == second_code {
+                return Ok(Some(counter + 2));
+            }
+            current = next;
+        }
+        Ok(None)
+    }
+
+    /// Seconds before the code changes, None for counter based codes
+    pub fn remaining_seconds(&self) -> Option<u64> {
+        self.remaining_seconds_at(get_current_timestamp())
+    }
+
+    /// Seconds before the code valid at the given Unix time changes
+    pub fn remaining_seconds_at(&self, time: u64) -> Option<u64> {
+        match self.type_ {
+            OTPType::Hotp | OTPType::Ocra => None,
+            _ => {
+                let period = self.period.max(1) as i128;
+                let elapsed = (time as i128 - self.time_offset() as i128).rem_euclid(period);
+                Some((period - elapsed) as u64)
+            }
+        }
+    }
+
+    /// The T0 used to generate the code, only TOTP codes can move their periods
+    pub fn time_offset(&self) -> i64 {
+        match self.type_ {
+            OTPType::Totp => self.t0,
+            _ => 0,
         }
     }
 
@@ -210,81 +1148,804 @@ impl OTPElement {
         "0".repeat(self.digits as usize - s.chars().count()) + s.as_str()
     }
 
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| same_tag(t, tag))
+    }
+
+    /// Issuer and label, like GitHub - alice, to name the element in messages
+    pub fn name(&self) -> String {
+        match (self.issuer.is_empty(), self.label.is_empty()) {
+            (false, false) => format!("{} - {}", self.issuer, self.label),
+            (false, true) => self.issuer.to_owned(),
+            _ => self.label.to_owned(),
+        }
+    }
+
+    /// Tells if the field has the value ignoring case, like issuer=Google for cotp edit --filter.
+    /// Tags and domains match if the element has the value among them
+    pub fn field_matches(&self, field: &str, value: &str) -> color_eyre::Result<bool> {
+        let value = value.trim();
+        let equals = |s: &str| s.eq_ignore_ascii_case(value);
+        Ok(match field {
+            "issuer" => equals(&self.issuer),
+            "label" => equals(&self.label),
+            "type" => equals(&self.type_.to_string()),
+            "algorithm" => equals(&self.algorithm.to_string()),
+            "digits" => equals(&self.digits.to_string()),
+            "period" => equals(&self.period.to_string()),
+            "tag" => self.tags.iter().any(|t| equals(t)),
+            "domain" => self.domains.iter().any(|d| equals(d)),
+            _ => {
+                return Err(eyre!(
+                    "Cannot filter by {field}, use issuer, label, type, algorithm, digits, period, tag or domain"
+                ))
+            }
+        })
+    }
+
+    /// Changes the field to the value, like digits=6 for cotp edit --set. Tags and domains are comma separated
+    pub fn set_field(&mut self, field: &str, value: &str) -> color_eyre::Result<()> {
+        let list = || {
+            value
+                .split(',')
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+                .map(String::from)
+                .collect()
+        };
+        match field {
+            "issuer" => self.issuer = value.to_owned(),
+            "label" => self.label = value.to_owned(),
+            "algorithm" => {
+                self.algorithm = OTPAlgorithm::from_str(value.trim(), true)
+                    .map_err(|_| eyre!("Unknown algorithm {value}"))?
+            }
+            "digits" => {
+                let digits = parse_field(field, value)?;
+                if !(1..=9).contains(&digits) {
+                    return Err(eyre!("Digits must be a number between 1 and 9"));
+                }
+                self.digits = digits
+            }
+            "period" => {
+                let period = parse_field(field, value)?;
+                if period < 1 {
+                    return Err(eyre!("Period must be a positive number"));
+                }
+                self.period = period
+            }
+            "t0" => self.t0 = parse_field(field, value)?,
+            "counter" => self.counter = Some(parse_field(field, value)?),
+            "tags" => self.tags = list(),
+            "domains" => self.domains = list(),
+            "note" => self.note = value.to_owned(),
+            // Empty to follow the configuration again
+            "digit_group" if value.trim().is_empty() => self.digit_group = None,
+            "digit_group" => self.digit_group = Some(parse_field(field, value)?),
+            _ => {
+                return Err(eyre!(
+                    "Cannot set {field}, use issuer, label, algorithm, digits, period, t0, counter, tags, domains, note or digit_group"
+                ))
+            }
+        }
+        Ok(())
+    }
+
+    /// Change recorded in the history when the element is replaced by the edited one
+    pub fn edit_description(&self, edited: &OTPElement) -> String {
+        let mut description = format!(
+            "Edited {}: {}",
+            self.name(),
+            self.changed_fields(edited).join(", ")
+        );
+        if edited.name() != self.name() {
+            description.push_str(&format!(", now {}", edited.name()));
+        }
+        description
+    }
+
+    /// Names of the fields whose value differs in the other element, without their values
+    pub fn changed_fields(&self, other: &OTPElement) -> Vec<String> {
+        let (Ok(mut before), Ok(mut after)) =
+            (serde_json::to_value(self), serde_json::to_value(other))
+        else {
+            return vec![];
+        };
+        let changed = match (&before, &after) {
+            (Value::Object(before), Value::Object(after)) => after
+                .iter()
+                .filter(|(field, value)| before.get(*field) != Some(value))
+                .map(|(field, _)| field.to_owned())
+                .collect(),
+            _ => vec![],
+        };
+        // The serialized secrets are wiped
+        wipe_json(&mut before);
+        wipe_json(&mut after);
+        changed
+    }
+
+    /// Tells if the two elements are the same code, even if they have been used a different number of times
+    fn same_except_usage(&self, other: &OTPElement) -> bool {
+        let mut other = other.clone();
+        other.usage_count = self.usage_count;
+        other.last_used = self.last_used;
+        *self == other
+    }
+
+    /// Adds what the duplicate has more, keeping the fields of this element
+    fn absorb(&mut self, other: &OTPElement) {
+        for tag in &other.tags {
+            if !self.has_tag(tag) {
+                self.tags.push(tag.to_owned());
+            }
+        }
+        for domain in &other.domains {
+            if !self.domains.contains(domain) {
+                self.domains.push(domain.to_owned());
+            }
+        }
+        for code in &other.recovery_codes {
+            if !self.recovery_codes.iter().any(|c| c.code == code.code) {
+                self.recovery_codes.push(code.clone());
+            }
+        }
+        if !other.note.is_empty() && !self.note.contains(&other.note) {
+            if !self.note.is_empty() {
+                self.note.push('\n');
+            }
+            self.note.push_str(&other.note);
+        }
+        if self.issuer.is_empty() {
+            self.issuer = other.issuer.to_owned();
+        }
+        if self.icon.is_none() {
+            self.icon = other.icon.clone();
+        }
+        self.usage_count = self.usage_count.saturating_add(other.usage_count);
+        self.last_used = self.last_used.max(other.last_used);
+        self.pinned |= other.pinned;
+    }
+
+    /// The code split in groups of characters, as many as the element or the configuration tell, to read it
+    /// more easily. The code is copied without the spaces
+    pub fn group_code(&self, code: &str, default_group: u64) -> String {
+        group_digits(code, self.digit_group.unwrap_or(default_group))
+    }
+
+    /// Yandex and MOTP codes are generated using a pin along with the secret
+    pub fn requires_pin(&self) -> bool {
+        matches!(self.type_, OTPType::Yandex | OTPType::Motp)
+    }
+
+    /// Two elements are duplicates if they have the same secret and issuer, ignoring case and padding
+    pub fn is_duplicate(&self, other: &OTPElement) -> bool {
+        let normalize = |s: &str| s.trim_end_matches('=').to_uppercase();
+        normalize(&self.secret) == normalize(&other.secret)
+            && self.issuer.eq_ignore_ascii_case(&other.issuer)
+    }
+
+    /// Same codes generated from the same secret, like the copies of a code made by several imports
+    pub fn same_otp_config(&self, other: &OTPElement) -> bool {
+        self.type_ == other.type_
+            && self.algorithm == other.algorithm
+            && self.digits == other.digits
+            && self.period == other.period
+            && self.counter == other.counter
+            && self.pin == other.pin
+            && self.t0 == other.t0
+            && self.ocra_suite == other.ocra_suite
+    }
+
     pub fn valid_secret(&self) -> bool {
         match self.type_ {
             OTPType::Motp => hex::decode(&self.secret).is_ok(),
+            OTPType::Yandex => BASE32_NOPAD
+                .decode(self.secret.as_bytes())
+                .is_ok_and(|s| s.len() >= YANDEX_SECRET_LENGTH),
             _ => BASE32_NOPAD.decode(self.secret.as_bytes()).is_ok(),
         }
     }
 }
 
+fn parse_field<T: std::str::FromStr>(field: &str, value: &str) -> color_eyre::Result<T> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| eyre!("{value} is not a valid {field}"))
+}
+
+/// Whether two tags are the same, ignoring the case
+pub fn same_tag(a: &str, b: &str) -> bool {
+    a == b || a.to_lowercase() == b.to_lowercase()
+}
+
+/// Splits the code in groups of the given size separated by spaces, like 123 456, 0 to leave it as it is
+pub fn group_digits(code: &str, size: u64) -> String {
+    if size == 0 {
+        return code.to_owned();
+    }
+    let chars: Vec<char> = code.chars().collect();
+    chars
+        .chunks(size as usize)
+        .map(|group| group.iter().collect::<String>())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
 fn get_label(issuer: &str, label: &str) -> String {
     let encoded_label = urlencoding::encode(label);
+    if issuer.is_empty() {
+        return encoded_label.into_owned();
+    }
     let encoded_issuer = urlencoding::encode(issuer);
     format!("{encoded_issuer}:{encoded_label}")
 }
 
+/// TOTP element with a valid secret, shared by the tests
+#[cfg(test)]
+pub(crate) fn test_element(issuer: &str, label: &str) -> OTPElement {
+    let mut element = OTPElement::default();
+    element.secret = String::from("JBSWY3DPEHPK3PXP");
+    element.issuer = String::from(issuer);
+    element.label = String::from(label);
+    element
+}
+
 #[cfg(test)]
 mod test {
-    use crate::otp::otp_element::OTPAlgorithm::Sha1;
-    use crate::otp::otp_element::OTPElement;
-    use crate::otp::otp_element::OTPType::Totp;
+    use crate::otp::otp_element::OTPType::{Motp, Steam, Totp, Yandex};
+    use crate::otp::otp_element::{
+        same_tag, test_element, DuplicatePolicy, HistoryEntry, MergeOutcome, OTPAlgorithm,
+        OTPDatabase, OTPElement, SaveConflict, MAX_NEARBY_WINDOW,
+    };
 
+    use crate::crypto::cryptography::{derive_database_key, gen_salt, KdfParams};
     use crate::otp::from_otp_uri::FromOtpUri;
 
     #[test]
     fn test_serialization_otp_uri_full_element() {
-        let otp_element = OTPElement {
-            secret: String::from("xr5gh44x7bprcqgrdtulafeevt5rxqlbh5wvked22re43dh2d4mapv5g"),
-            issuer: String::from("IssuerText"),
-            label: String::from("LabelText"),
-            digits: 6,
-            type_: Totp,
-            algorithm: Sha1,
-            period: 30,
-            counter: None,
-            pin: None,
-        };
-        assert_eq!("otpauth://totp/IssuerText:LabelText?secret=xr5gh44x7bprcqgrdtulafeevt5rxqlbh5wvked22re43dh2d4mapv5g&algorithm=SHA1&digits=6&period=30&lock=false",otp_element.get_otpauth_uri().as_str());
+        let mut otp_element = OTPElement::default();
+        otp_element.secret =
+            String::from("xr5gh44x7bprcqgrdtulafeevt5rxqlbh5wvked22re43dh2d4mapv5g");
+        otp_element.issuer = String::from("IssuerText");
+        otp_element.label = String::from("LabelText");
+        assert_eq!("otpauth://totp/IssuerText:LabelText?secret=xr5gh44x7bprcqgrdtulafeevt5rxqlbh5wvked22re43dh2d4mapv5g&algorithm=SHA1&digits=6&period=30&lock=false&issuer=IssuerText",otp_element.get_otpauth_uri().as_str());
     }
 
     #[test]
     fn test_serialization_otp_uri_no_issuer() {
-        let otp_element = OTPElement {
-            secret: String::from("xr5gh44x7bprcqgrdtulafeevt5rxqlbh5wvked22re43dh2d4mapv5g"),
-            issuer: String::from(""),
-            label: String::from("LabelText"),
-            digits: 6,
-            type_: Totp,
-            algorithm: Sha1,
-            period: 30,
-            counter: None,
-            pin: None,
-        };
-        assert_eq!("otpauth://totp/:LabelText?secret=xr5gh44x7bprcqgrdtulafeevt5rxqlbh5wvked22re43dh2d4mapv5g&algorithm=SHA1&digits=6&period=30&lock=false",otp_element.get_otpauth_uri().as_str());
+        let mut otp_element = OTPElement::default();
+        otp_element.secret =
+            String::from("xr5gh44x7bprcqgrdtulafeevt5rxqlbh5wvked22re43dh2d4mapv5g");
+        otp_element.issuer = String::from("");
+        otp_element.label = String::from("LabelText");
+        assert_eq!("otpauth://totp/LabelText?secret=xr5gh44x7bprcqgrdtulafeevt5rxqlbh5wvked22re43dh2d4mapv5g&algorithm=SHA1&digits=6&period=30&lock=false",otp_element.get_otpauth_uri().as_str());
     }
 
     #[test]
     fn test_deserialization_otp_uri() {
-        let expected = OTPElement {
-            secret: "xr5gh44x7bprcqgrdtulafeevt5rxqlbh5wvked22re43dh2d4mapv5g".to_uppercase(),
-            issuer: String::from("IssuerText"),
-            label: String::from("LabelText"),
-            digits: 6,
-            type_: Totp,
-            algorithm: Sha1,
-            period: 30,
-            counter: None,
-            pin: None,
-        };
+        let mut expected = OTPElement::default();
+        expected.secret = "xr5gh44x7bprcqgrdtulafeevt5rxqlbh5wvked22re43dh2d4mapv5g".to_uppercase();
+        expected.issuer = String::from("IssuerText");
+        expected.label = String::from("LabelText");
         let otp_uri = "otpauth://totp/IssuerText:LabelText?secret=xr5gh44x7bprcqgrdtulafeevt5rxqlbh5wvked22re43dh2d4mapv5g&algorithm=SHA1&digits=6&period=30&lock=false";
 
         assert_eq!(expected, OTPElement::from_otp_uri(otp_uri).unwrap())
     }
 
+    #[test]
+    fn test_otp_uri_round_trip() {
+        let hotp = OTPElement::from_otp_uri(
+            "otpauth://hotp/My%20Bank:bob?secret=JBSWY3DPEHPK3PXP&algorithm=SHA256&digits=8&counter=42",
+        )
+        .unwrap();
+        let no_issuer =
+            OTPElement::from_otp_uri("otpauth://totp/alice?secret=JBSWY3DPEHPK3PXP&period=60")
+                .unwrap();
+        let t0 = OTPElement::from_otp_uri("otpauth://totp/alice?secret=JBSWY3DPEHPK3PXP&epoch=-15")
+            .unwrap();
+        assert_eq!(-15, t0.t0);
+        assert!(t0.get_otpauth_uri().contains("&t0=-15"));
+        for element in [hotp, no_issuer, t0] {
+            let imported = OTPElement::from_otp_uri(&element.get_otpauth_uri()).unwrap();
+            assert_eq!(element, imported);
+        }
+    }
+
+    #[test]
+    fn test_deserialization_steam_otp_uri() {
+        let steam_type = "otpauth://steam/Steam:user?secret=JBSWY3DPEHPK3PXP";
+        let steam_encoder =
+            "otpauth://totp/Steam:user?secret=JBSWY3DPEHPK3PXP&issuer=Steam&encoder=steam";
+
+        for otp_uri in [steam_type, steam_encoder] {
+            let element = OTPElement::from_otp_uri(otp_uri).unwrap();
+            assert_eq!(Steam, element.type_);
+            assert_eq!(5, element.digits);
+            assert_eq!(5, element.get_otp_code().unwrap().chars().count());
+        }
+    }
+
+    #[test]
+    fn test_yandex_otp_uri_round_trip() {
+        let otp_uri = "otpauth://yaotp/Yandex:user?secret=6SB2IKNM6OBZPAVBVTOHDKS4FAAAAAAADFUTQMBTRY&algorithm=SHA256&digits=8&pin=5239";
+        let element = OTPElement::from_otp_uri(otp_uri).unwrap();
+
+        assert_eq!(Yandex, element.type_);
+        assert_eq!(Some("5239".to_string()), element.pin);
+        assert!(element.valid_secret());
+
+        let exported = OTPElement::from_otp_uri(&element.get_otpauth_uri()).unwrap();
+        assert_eq!(element, exported);
+    }
+
+    #[test]
+    fn test_motp_otp_uri_round_trip() {
+        let otp_uri = "otpauth://motp/MOTP:user?secret=E3152AFEE62599C8&pin=1234";
+        let element = OTPElement::from_otp_uri(otp_uri).unwrap();
+
+        assert_eq!(Motp, element.type_);
+        assert_eq!("e3152afee62599c8", element.secret);
+        assert_eq!(10, element.period);
+        assert!(element.valid_secret());
+
+        let exported = OTPElement::from_otp_uri(&element.get_otpauth_uri()).unwrap();
+        assert_eq!(element, exported);
+    }
+
+    #[test]
+    fn test_database_tags() {
+        let element = |tags: Vec<&str>| {
+            let mut element = test_element("IssuerText", "LabelText");
+            element.tags = tags.into_iter().map(String::from).collect();
+            element
+        };
+        let database: OTPDatabase = vec![
+            element(vec!["work", "banking"]),
+            element(vec![]),
+            element(vec!["Work"]),
+        ]
+        .into();
+
+        assert_eq!(vec!["banking", "work"], database.tags());
+        assert!(database.elements_ref()[0].has_tag("WORK"));
+        assert!(same_tag("Édition", "édition"));
+        assert!(!database.elements_ref()[1].has_tag("work"));
+    }
+
+    #[test]
+    fn test_database_sorted_by_issuer() {
+        let database: OTPDatabase = vec![
+            test_element("gitlab", "bob"),
+            test_element("GitHub", "carol"),
+            test_element("github", "alice"),
+        ]
+        .into();
+
+        let order: Vec<usize> = database
+            .sorted_by_issuer()
+            .into_iter()
+            .map(|(index, _)| index)
+            .collect();
+        assert_eq!(vec![2, 1, 0], order);
+    }
+
+    #[test]
+    fn test_database_find_matching() {
+        let database: OTPDatabase = vec![
+            test_element("GitHub", "alice"),
+            test_element("GitHub Enterprise", "alice"),
+            test_element("Google", "bob"),
+        ]
+        .into();
+
+        assert_eq!(vec![0], database.find_matching("github"));
+        assert_eq!(vec![1], database.find_matching("GitHub Enterprise:Alice"));
+        assert_eq!(vec![0, 1], database.find_matching("alice"));
+        assert_eq!(vec![2], database.find_matching("goo"));
+        assert!(database.find_matching("gitlab").is_empty());
+    }
+
+    #[test]
+    fn test_edit_fields() {
+        let mut element = OTPElement::from_otp_uri(
+            "otpauth://totp/Google:alice?secret=JBSWY3DPEHPK3PXP&digits=8",
+        )
+        .unwrap();
+        element.tags = vec![String::from("work")];
+        assert!(element.field_matches("issuer", "google").unwrap());
+        assert!(element.field_matches("digits", "8").unwrap());
+        assert!(element.field_matches("tag", "Work").unwrap());
+        assert!(!element.field_matches("label", "bob").unwrap());
+        assert!(element.field_matches("secret", "JBSWY3DPEHPK3PXP").is_err());
+
+        element.set_field("digits", "6").unwrap();
+        element.set_field("algorithm", "sha256").unwrap();
+        element.set_field("tags", "personal, ,mail").unwrap();
+        assert_eq!(6, element.digits);
+        assert_eq!(OTPAlgorithm::Sha256, element.algorithm);
+        assert_eq!(vec!["personal", "mail"], element.tags);
+        assert!(element.set_field("digits", "six").is_err());
+        assert!(element.set_field("digits", "0").is_err());
+        assert!(element.set_field("digits", "10").is_err());
+        assert!(element.set_field("period", "0").is_err());
+        element.set_field("digits", "9").unwrap();
+        element.set_field("period", "1").unwrap();
+        assert_eq!(9, element.digits);
+        assert_eq!(1, element.period);
+        assert!(element.set_field("algorithm", "SHA3").is_err());
+        assert!(element.set_field("secret", "JBSWY3DPEHPK3PXP").is_err());
+    }
+
+    #[test]
+    fn test_group_code() {
+        let mut element =
+            OTPElement::from_otp_uri("otpauth://totp/alice?secret=JBSWY3DPEHPK3PXP").unwrap();
+        assert_eq!("123456", element.group_code("123456", 0));
+        assert_eq!("123 456", element.group_code("123456", 3));
+        assert_eq!("1234 5678", element.group_code("12345678", 4));
+        assert_eq!("123 45", element.group_code("12345", 3));
+
+        element.set_field("digit_group", "2").unwrap();
+        assert_eq!("12 34 56", element.group_code("123456", 3));
+        element.set_field("digit_group", "0").unwrap();
+        assert_eq!("123456", element.group_code("123456", 3));
+        element.set_field("digit_group", "").unwrap();
+        assert_eq!(None, element.digit_group);
+        assert!(element.set_field("digit_group", "-1").is_err());
+    }
+
+    #[test]
+    fn test_database_history() {
+        let element =
+            OTPElement::from_otp_uri("otpauth://totp/GitHub:alice?secret=JBSWY3DPEHPK3PXP")
+                .unwrap();
+        let mut edited = element.clone();
+        edited.label = String::from("bob");
+        edited.secret = String::from("GEZDGNBVGY3TQOJQ");
+        assert_eq!("GitHub - alice", element.name());
+        assert_eq!(vec!["label", "secret"], element.changed_fields(&edited));
+        assert_eq!(
+            "Edited GitHub - alice: label, secret, now GitHub - bob",
+            element.edit_description(&edited)
+        );
+
+        let mut database = OTPDatabase::default();
+        database.record_change("Added GitHub - alice");
+        assert!(database.is_modified());
+        let other = vec![
+            HistoryEntry {
+                timestamp: 1,
+                host: String::from("laptop"),
+                message: String::from("Created the database"),
+            },
+            database.history[0].clone(),
+        ];
+
+        database.merge_history(&other);
+        let messages: Vec<&str> = database
+            .history_ref()
+            .iter()
+            .map(|entry| entry.message.as_str())
+            .collect();
+        assert_eq!(
+            vec!["Created the database", "Added GitHub - alice"],
+            messages
+        );
+    }
+
+    #[test]
+    fn test_database_trash() {
+        let element = |uri: &str| OTPElement::from_otp_uri(uri).unwrap();
+        let mut database: OTPDatabase = vec![
+            element("otpauth://totp/GitHub:alice?secret=JBSWY3DPEHPK3PXP"),
+            element("otpauth://totp/Google:bob?secret=JBSWY3DPEHPK3PXP"),
+            element("otpauth://totp/GitLab:carol?secret=JBSWY3DPEHPK3PXP"),
+        ]
+        .into();
+
+        database.delete_element(0);
+        database.delete_element(0);
+        assert_eq!(1, database.elements_ref().len());
+        assert_eq!("Google", database.trash_ref()[1].element.issuer);
+
+        database.restore_trashed(0).unwrap();
+        assert_eq!("GitHub", database.elements_ref()[1].issuer);
+        assert!(database.restore_trashed(1).is_err());
+
+        database.trash[0].deleted_at -= 31 * 86400;
+        database.purge_expired_trash(0);
+        assert_eq!(1, database.trash_ref().len());
+        database.purge_expired_trash(u64::MAX);
+        assert_eq!(1, database.trash_ref().len());
+        database.purge_expired_trash(30);
+        assert!(database.trash_ref().is_empty());
+
+        database.delete_element(0);
+        assert!(database.purge_trash(Some(1)).is_err());
+        assert_eq!(1, database.purge_trash(None).unwrap());
+        assert_eq!(
+            vec!["GitHub"],
+            database
+                .elements
+                .iter()
+                .map(|e| e.issuer.as_str())
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_database_find_by_url() {
+        let element = |issuer: &str, domains: Vec<&str>| {
+            let mut element = test_element(issuer, "alice");
+            element.domains = domains.into_iter().map(String::from).collect();
+            element
+        };
+        let database: OTPDatabase = vec![
+            element("GitHub", vec![]),
+            element("Google", vec![]),
+            element("gitlab.com", vec![]),
+            element("Proton Mail", vec!["proton.me"]),
+            element("Work", vec!["GitHub.com", "gitlab.example.com"]),
+            element("Gist", vec!["gist.github.com"]),
+            element("GitLab", vec!["gitlab.example.com"]),
+        ]
+        .into();
+
+        assert_eq!(vec![4, 0], database.find_by_url("https://github.com/login"));
+        assert_eq!(vec![5, 4, 0], database.find_by_url("gist.github.com"));
+        assert_eq!(
+            vec![1],
+            database.find_by_url("https://accounts.Google.com/")
+        );
+        // The domains of GitLab do not include gitlab.com, so its issuer is not compared
+        assert_eq!(vec![2], database.find_by_url("gitlab.com"));
+        assert_eq!(vec![4, 6], database.find_by_url("gitlab.example.com"));
+        assert_eq!(vec![2], database.find_by_url("https://sso.gitlab.com"));
+        assert!(database.find_by_url("https://notgitlab.com").is_empty());
+        assert!(database
+            .find_by_url("https://github.evil.example")
+            .is_empty());
+        assert!(database
+            .find_by_url("https://google.com.evil.example")
+            .is_empty());
+        assert_eq!(vec![0], database.find_by_url("https://github.co.uk"));
+        assert_eq!(vec![3], database.find_by_url("https://mail.proton.me"));
+        assert!(database.find_by_url("https://example.com").is_empty());
+        assert!(database.find_by_url("").is_empty());
+    }
+
+    #[test]
+    fn test_database_merge() {
+        let element = |secret: &str, issuer: &str, label: &str| {
+            let mut element = test_element(issuer, label);
+            element.secret = String::from(secret);
+            element
+        };
+        let imported = || {
+            vec![
+                element("JBSWY3DPEHPK3PXP====", "github", "renamed"),
+                element("AAAAAAAA", "GitHub", "other"),
+            ]
+        };
+
+        let mut database: OTPDatabase = vec![element("JBSWY3DPEHPK3PXP", "GitHub", "alice")].into();
+        assert_eq!(
+            vec![MergeOutcome::Skipped, MergeOutcome::Added],
+            database.merge(imported(), DuplicatePolicy::Skip)
+        );
+        assert_eq!("alice", database.elements[0].label);
+
+        let mut database: OTPDatabase = vec![element("JBSWY3DPEHPK3PXP", "GitHub", "alice")].into();
+        database.merge(imported(), DuplicatePolicy::Overwrite);
+        assert_eq!(2, database.elements.len());
+        assert_eq!("renamed", database.elements[0].label);
+
+        let mut database: OTPDatabase = vec![element("JBSWY3DPEHPK3PXP", "GitHub", "alice")].into();
+        database.merge(imported(), DuplicatePolicy::KeepBoth);
+        assert_eq!(3, database.elements.len());
+    }
+
+    #[test]
+    fn test_merge_duplicates() {
+        let element = |issuer: &str, tags: &[&str], note: &str| {
+            let mut element =
+                OTPElement::from_otp_uri(&format!("otpauth://totp/{issuer}:alice?secret=AAAA"))
+                    .unwrap();
+            element.tags = tags.iter().map(|t| t.to_string()).collect();
+            element.note = note.to_string();
+            element.usage_count = 2;
+            element
+        };
+        let mut database: OTPDatabase = vec![
+            element("GitHub", &["work"], "Main account"),
+            element("Bank", &[], ""),
+            element("github", &["work", "dev"], "Backup codes in the safe"),
+            element("GitHub", &["personal"], "Main account"),
+            element("GitHub", &["eight digits"], ""),
+        ]
+        .into();
+        database.elements[4].digits = 8;
+        database.merge_duplicates(&[(0, vec![2, 3, 4])]);
+
+        // Codes of different digits are not the same code
+        assert_eq!(3, database.elements.len());
+        assert_eq!(8, database.elements[2].digits);
+        let merged = &database.elements[0];
+        assert_eq!(vec!["work", "dev", "personal"], merged.tags);
+        assert_eq!("Main account\nBackup codes in the safe", merged.note);
+        assert_eq!(6, merged.usage_count);
+        assert_eq!("Bank", database.elements[1].issuer);
+        assert_eq!(2, database.trash_ref().len());
+    }
+
+    #[test]
+    fn test_database_age_recipients() {
+        let mut database = OTPDatabase {
+            age_key: Some(String::from("armored key")),
+            ..Default::default()
+        };
+
+        assert!(database.add_age_recipient("age1alice"));
+        assert!(!database.add_age_recipient("age1alice"));
+        // The key must be encrypted again to the new recipients
+        assert_eq!(None, database.age_key);
+        assert!(database.is_modified());
+
+        database.age_key = Some(String::from("armored key"));
+        assert!(!database.remove_age_recipient("age1bob"));
+        assert!(database.age_key.is_some());
+        assert!(database.remove_age_recipient("age1alice"));
+        assert!(database.age_recipients().is_empty());
+        assert_eq!(None, database.age_key);
+    }
+
+    #[test]
+    fn test_nearby_codes() {
+        let mut element = OTPElement::from_otp_uri(
+            "otpauth://hotp/Example:alice?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ&counter=1",
+        )
+        .unwrap();
+        let codes = element.get_nearby_codes(1).unwrap();
+        let values: Vec<&str> = codes.iter().map(|c| c.code.as_str()).collect();
+        assert_eq!(vec!["755224", "287082", "359152"], values);
+        assert_eq!(Some(0), codes[0].counter);
+
+        // Negative counters are skipped
+        element.counter = Some(0);
+        assert_eq!(2, element.get_nearby_codes(1).unwrap().len());
+
+        element.type_ = Totp;
+        element.period = 60;
+        let now = 1_111_111_111;
+        let codes = element.get_nearby_codes_at(2, now).unwrap();
+        assert_eq!(5, codes.len());
+        assert_eq!(Some((1_111_111_080, 1_111_111_140)), codes[2].validity);
+        assert_eq!(element.get_otp_code_at(now).unwrap(), codes[2].code);
+        assert_eq!(Some((1_111_111_020, 1_111_111_080)), codes[1].validity);
+        assert_eq!(
+            element.get_otp_code_at(1_111_111_140).unwrap(),
+            codes[3].code
+        );
+
+        // Huge windows are limited
+        let codes = element.get_nearby_codes_at(u64::MAX, now).unwrap();
+        assert_eq!(2 * MAX_NEARBY_WINDOW as usize + 1, codes.len());
+    }
+
+    #[test]
+    fn test_hotp_resync() {
+        // RFC 4226 test vectors
+        let mut element = OTPElement::from_otp_uri(
+            "otpauth://hotp/Example:alice?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ&counter=0",
+        )
+        .unwrap();
+        assert_eq!(
+            Ok(Some(9)),
+            element.find_hotp_resync_counter("162583", "399871", 10)
+        );
+        assert_eq!(
+            Ok(None),
+            element.find_hotp_resync_counter("162583", "399871", 5)
+        );
+        // The codes must be consecutive
+        assert_eq!(
+            Ok(None),
+            element.find_hotp_resync_counter("162583", "520489", 10)
+        );
+        element.counter = Some(6);
+        assert_eq!(
+            Ok(None),
+            element.find_hotp_resync_counter("287082", "359152", 10)
+        );
+    }
+
+    #[test]
+    fn test_database_lock() {
+        let params = KdfParams {
+            memory: 8,
+            iterations: 1,
+            parallelism: 1,
+        };
+        let salt = gen_salt().unwrap();
+        let key = derive_database_key(b"password", &salt, None, None, &params).unwrap();
+        let element =
+            OTPElement::from_otp_uri("otpauth://totp/GitHub:alice?secret=JBSWY3DPEHPK3PXP")
+                .unwrap();
+        let mut database: OTPDatabase = vec![element.clone()].into();
+        database.set_kdf_params(params);
+        assert!(database
+            .is_password_correct("password", &key, &salt)
+            .unwrap());
+        assert!(!database
+            .is_password_correct("wrong password", &key, &salt)
+            .unwrap());
+
+        database.lock(&key, &salt).unwrap();
+        assert!(database.is_locked());
+        assert!(database.elements_ref().is_empty());
+        // The wiped elements must not overwrite the database file
+        assert!(database.save(&key, &salt).is_err());
+
+        assert!(database.unlock("wrong password").is_err());
+        assert_eq!(key, database.unlock("password").unwrap());
+        assert!(!database.is_locked());
+        assert_eq!(vec![element], database.elements);
+        assert!(database.is_modified());
+    }
+
+    #[test]
+    fn test_merge_usage_from() {
+        let params = KdfParams {
+            memory: 8,
+            iterations: 1,
+            parallelism: 1,
+        };
+        let salt = gen_salt().unwrap();
+        let key = derive_database_key(b"password", &salt, None, None, &params).unwrap();
+        let elements = || {
+            ["GitHub", "Bank"]
+                .map(|issuer| {
+                    OTPElement::from_otp_uri(&format!(
+                        "otpauth://totp/{issuer}:alice?secret=JBSWY3DPEHPK3PXP"
+                    ))
+                    .unwrap()
+                })
+                .to_vec()
+        };
+        let encrypted = |database: &OTPDatabase| {
+            serde_json::to_string(&database.encrypt(&key, &salt).unwrap()).unwrap()
+        };
+
+        let mut database: OTPDatabase = elements().into();
+        database.base_fingerprint = Some(database.content_fingerprint().unwrap());
+        database.elements[1].label = String::from("bob");
+        database.elements[1].usage_count = 1;
+
+        // Another instance only copied a code
+        let mut saved: OTPDatabase = elements().into();
+        saved.elements[0].usage_count = 3;
+        saved.elements[0].last_used = 100;
+        database
+            .merge_usage_from(encrypted(&saved).as_bytes(), &key)
+            .unwrap();
+        assert_eq!(3, database.elements[0].usage_count);
+        assert_eq!(100, database.elements[0].last_used);
+        assert_eq!(1, database.elements[1].usage_count);
+        assert_eq!("bob", database.elements[1].label);
+
+        // Another instance added a code
+        saved.elements.push(elements().remove(0));
+        let error = database
+            .merge_usage_from(encrypted(&saved).as_bytes(), &key)
+            .unwrap_err();
+        assert!(error.downcast_ref::<SaveConflict>().is_some());
+    }
+
     #[test]
     fn test_deserialization_with_issuer_parameter() {
         let otp_uri = "otpauth://totp/2Ponies%40Github%20No.1?secret=JBSWY3DPEHPK3PXP&algorithm=SHA1&digits=6&period=30&lock=false&issuer=test";
-        assert_eq!(true, OTPElement::from_otp_uri(otp_uri).is_ok())
+        assert!(OTPElement::from_otp_uri(otp_uri).is_ok())
     }
 }
@@ -9,6 +9,11 @@ pub enum OtpError {
     MissingCounter,                    // Missing counter for HOTP codes
     InvalidOffset,                     // Invalid offset
     InvalidDigest,                     // Invalid digest
+    InvalidDigits,                     // Too many digits for the generated code
+    InvalidSuite,                      // Missing or malformed OCRA suite
+    MissingChallenge,                  // OCRA codes are computed from a challenge
+    InvalidChallenge,                  // Challenge not matching the OCRA suite
+    InvalidSession,                    // Session information not matching the OCRA suite
 }
 
 impl Display for OtpError {
@@ -21,7 +26,14 @@ impl Display for OtpError {
             OtpError::MissingCounter => f.write_str("Missing counter value"),
             OtpError::InvalidDigest => f.write_str("Invalid digest"),
             OtpError::InvalidOffset => f.write_str("Invalid offset"),
+            OtpError::InvalidDigits => f.write_str("Invalid digits"),
             OtpError::ShortSecret => f.write_str("Secret length less than 16 bytes"),
+            OtpError::InvalidSuite => f.write_str("Invalid OCRA suite"),
+            OtpError::MissingChallenge => f.write_str("Challenge needed, use cotp ocra"),
+            OtpError::InvalidChallenge => f.write_str("Invalid challenge for the OCRA suite"),
+            OtpError::InvalidSession => {
+                f.write_str("Invalid session information for the OCRA suite")
+            }
         }
     }
 }
@@ -22,6 +22,9 @@ pub enum OTPType {
     #[serde(alias = "motp")]
     #[serde(alias = "MOTP")]
     Motp,
+    #[serde(alias = "ocra")]
+    #[serde(alias = "OCRA")]
+    Ocra,
 }
 
 impl fmt::Display for OTPType {
@@ -32,6 +35,7 @@ impl fmt::Display for OTPType {
             OTPType::Steam => "STEAM",
             OTPType::Yandex => "YANDEX",
             OTPType::Motp => "MOTP",
+            OTPType::Ocra => "OCRA",
         };
         write!(f, "{to_string}")
     }
@@ -42,8 +46,9 @@ impl From<&str> for OTPType {
         match s.to_uppercase().as_str() {
             "HOTP" => Self::Hotp,
             "STEAM" => Self::Steam,
-            "YANDEX" => Self::Yandex,
+            "YANDEX" | "YAOTP" => Self::Yandex,
             "MOTP" => Self::Motp,
+            "OCRA" => Self::Ocra,
             _ => Self::Totp,
         }
     }
@@ -0,0 +1,477 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use cotp::otp::audit::{Finding, MIN_DIGITS, MIN_SECRET_BITS};
+use cotp::otp::otp_element::{HistoryEntry, OTPElement};
+use cotp::otp::otp_type::OTPType;
+use cotp::utils::{format_timestamp, get_current_timestamp};
+use std::io::{self, Write};
+
+use crate::i18n::{tr, tr_args};
+
+/// Format used to print codes on the standard output
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug, ValueEnum)]
+pub enum OutputFormat {
+    /// Human readable output
+    #[default]
+    Text,
+    /// Structured output for scripts and other tools
+    Json,
+}
+
+/// Current state of a code, printed by the list and extract commands
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug)]
+pub struct CodeOutput {
+    /// Index of the code, starting from 1 as shown in the dashboard
+    pub index: usize,
+    pub issuer: String,
+    pub label: String,
+    #[serde(rename = "type")]
+    pub type_: OTPType,
+    pub code: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Seconds before the code changes, not available for counter based codes
+    pub remaining_seconds: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub counter: Option<u64>,
+}
+
+impl CodeOutput {
+    pub fn new(index: usize, element: &OTPElement) -> Self {
+        Self::at(index, element, get_current_timestamp())
+    }
+
+    /// The state of the code at the given Unix time
+    pub fn at(index: usize, element: &OTPElement, time: u64) -> Self {
+        let (code, error) = match element.get_otp_code_at(time) {
+            Ok(code) => (Some(code), None),
+            Err(e) => (None, Some(e.to_string())),
+        };
+        CodeOutput {
+            index: index + 1,
+            issuer: element.issuer.to_owned(),
+            label: element.label.to_owned(),
+            type_: element.type_,
+            code,
+            error,
+            remaining_seconds: element.remaining_seconds_at(time),
+            counter: element.counter,
+        }
+    }
+
+    /// The code as a short sentence, without the padding and the columns a screen reader would read aloud
+    pub fn plain_line(&self) -> String {
+        let name = match (self.issuer.is_empty(), self.label.is_empty()) {
+            (false, false) => format!("{} {}", self.issuer, self.label),
+            (true, false) => self.label.to_owned(),
+            _ => self.issuer.to_owned(),
+        };
+        let validity = match (self.remaining_seconds, self.counter) {
+            (Some(1), _) => format!(", {}", tr("plain-second-left")),
+            (Some(seconds), _) => format!(
+                ", {}",
+                tr_args("plain-seconds-left", &[("seconds", &seconds.to_string())])
+            ),
+            (None, Some(counter)) => format!(
+                ", {}",
+                tr_args("plain-counter", &[("counter", &counter.to_string())])
+            ),
+            (None, None) => String::new(),
+        };
+        let code = match &self.code {
+            Some(code) => code.to_owned(),
+            None => tr_args("plain-error", &[("message", self.text_code())]),
+        };
+        format!("{}. {name}: {code}{validity}", self.index)
+    }
+
+    fn text_code(&self) -> &str {
+        self.code
+            .as_deref()
+            .or(self.error.as_deref())
+            .unwrap_or_default()
+    }
+}
+
+/// Prints a single code, only the code itself in text format
+pub fn print_code(output: &CodeOutput, format: OutputFormat) -> color_eyre::Result<()> {
+    match format {
+        OutputFormat::Text => println!("{}", output.text_code()),
+        OutputFormat::Json => println!("{}", serde_json::to_string(output)?),
+    }
+    Ok(())
+}
+
+/// Prints a list of codes, as an aligned table in text format
+pub fn print_codes(outputs: &[CodeOutput], format: OutputFormat) -> color_eyre::Result<()> {
+    match format {
+        OutputFormat::Text => print!("{}", to_text_table(outputs)),
+        OutputFormat::Json => println!("{}", serde_json::to_string(outputs)?),
+    }
+    Ok(())
+}
+
+/// Writes a line for each code, without aligning them
+pub fn print_plain(out: &mut impl Write, outputs: &[CodeOutput]) -> io::Result<()> {
+    for output in outputs {
+        writeln!(out, "{}", output.plain_line())?;
+    }
+    out.flush()
+}
+
+/// How much a code has been used, printed by the stats command
+#[derive(Serialize, PartialEq, Eq, Debug)]
+pub struct StatsOutput {
+    /// Index of the code, starting from 1 as shown in the dashboard
+    pub index: usize,
+    pub issuer: String,
+    pub label: String,
+    /// How many times the code has been copied
+    pub usage_count: u64,
+    /// Unix timestamp of the last copy
+    pub last_used: Option<u64>,
+}
+
+impl StatsOutput {
+    pub fn new(index: usize, element: &OTPElement) -> Self {
+        StatsOutput {
+            index: index + 1,
+            issuer: element.issuer.to_owned(),
+            label: element.label.to_owned(),
+            usage_count: element.usage_count,
+            last_used: (element.last_used > 0).then_some(element.last_used),
+        }
+    }
+}
+
+/// Prints the usage statistics, as an aligned table in text format
+pub fn print_stats(outputs: &[StatsOutput], format: OutputFormat) -> color_eyre::Result<()> {
+    match format {
+        OutputFormat::Text => {
+            let header = ["Id", "Issuer", "Label", "Copies", "Last used"].map(String::from);
+            let rows = outputs
+                .iter()
+                .map(|o| {
+                    [
+                        o.index.to_string(),
+                        o.issuer.to_owned(),
+                        o.label.to_owned(),
+                        o.usage_count.to_string(),
+                        o.last_used
+                            .map(format_timestamp)
+                            .unwrap_or_else(|| String::from("never")),
+                    ]
+                })
+                .collect();
+            print!("{}", format_table(header, rows))
+        }
+        OutputFormat::Json => println!("{}", serde_json::to_string(outputs)?),
+    }
+    Ok(())
+}
+
+/// Problems of a code, printed by the audit command
+#[derive(Serialize, PartialEq, Eq, Debug)]
+pub struct AuditOutput {
+    /// Index of the code, starting from 1 as shown in the dashboard
+    pub index: usize,
+    pub issuer: String,
+    pub label: String,
+    pub findings: Vec<Finding>,
+}
+
+impl AuditOutput {
+    pub fn new(index: usize, element: &OTPElement, findings: Vec<Finding>) -> Self {
+        AuditOutput {
+            index: index + 1,
+            issuer: element.issuer.to_owned(),
+            label: element.label.to_owned(),
+            findings,
+        }
+    }
+}
+
+/// Prints the problems found, one per line of an aligned table in text format
+pub fn print_audit(outputs: &[AuditOutput], format: OutputFormat) -> color_eyre::Result<()> {
+    match format {
+        OutputFormat::Text => {
+            if outputs.is_empty() {
+                println!("{}", tr("audit-clean"));
+                return Ok(());
+            }
+            let header = [
+                "column-id",
+                "column-issuer",
+                "column-label",
+                "column-problem",
+            ]
+            .map(|id| tr(id).to_owned());
+            let rows = outputs
+                .iter()
+                .flat_map(|o| {
+                    o.findings.iter().map(|finding| {
+                        [
+                            o.index.to_string(),
+                            o.issuer.to_owned(),
+                            o.label.to_owned(),
+                            finding_message(finding),
+                        ]
+                    })
+                })
+                .collect();
+            print!("{}", format_table(header, rows))
+        }
+        OutputFormat::Json => println!("{}", serde_json::to_string(outputs)?),
+    }
+    Ok(())
+}
+
+/// The translated description of the problem, the JSON output names it by its tag instead
+fn finding_message(finding: &Finding) -> String {
+    match finding {
+        Finding::InvalidSecret => tr("audit-invalid-secret").to_owned(),
+        Finding::ShortSecret { bits } => tr_args(
+            "audit-short-secret",
+            &[
+                ("bits", &bits.to_string()),
+                ("min", &MIN_SECRET_BITS.to_string()),
+            ],
+        ),
+        Finding::FewDigits { digits } => tr_args(
+            "audit-few-digits",
+            &[
+                ("digits", &digits.to_string()),
+                ("min", &MIN_DIGITS.to_string()),
+            ],
+        ),
+        Finding::TestSecret => tr("audit-test-secret").to_owned(),
+        Finding::LowEntropy => tr("audit-low-entropy").to_owned(),
+        Finding::DuplicateSecret { indexes } => {
+            let indexes: Vec<String> = indexes.iter().map(usize::to_string).collect();
+            tr_args(
+                "audit-duplicate-secret",
+                &[("indexes", &indexes.join(", "))],
+            )
+        }
+    }
+}
+
+/// Prints the changes of the database, as an aligned table in text format
+pub fn print_history(entries: &[&HistoryEntry], format: OutputFormat) -> color_eyre::Result<()> {
+    match format {
+        OutputFormat::Text => {
+            if entries.is_empty() {
+                println!("{}", tr("history-empty"));
+                return Ok(());
+            }
+            let header =
+                ["column-date", "column-host", "column-change"].map(|id| tr(id).to_owned());
+            let rows = entries
+                .iter()
+                .map(|entry| {
+                    [
+                        format_timestamp(entry.timestamp),
+                        entry.host.to_owned(),
+                        entry.message.to_owned(),
+                    ]
+                })
+                .collect();
+            print!("{}", format_table(header, rows))
+        }
+        OutputFormat::Json => println!("{}", serde_json::to_string(entries)?),
+    }
+    Ok(())
+}
+
+/// Current code printed by the status command, the JSON format is the one of the waybar custom modules
+#[derive(Serialize, PartialEq, Eq, Debug)]
+pub struct StatusOutput {
+    /// Code and seconds before it changes
+    pub text: String,
+    /// Issuer and label of the code
+    pub tooltip: String,
+    /// Share of the period still remaining, not available for counter based codes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub percentage: Option<u64>,
+}
+
+impl StatusOutput {
+    pub fn new(element: &OTPElement) -> Self {
+        let code = CodeOutput::new(0, element);
+        let text = match code.remaining_seconds {
+            Some(seconds) => format!("{} {seconds}s", code.text_code()),
+            None => code.text_code().to_owned(),
+        };
+        StatusOutput {
+            text,
+            tooltip: format!("{}:{}", element.issuer, element.label),
+            percentage: code
+                .remaining_seconds
+                .map(|seconds| seconds * 100 / element.period.max(1)),
+        }
+    }
+}
+
+/// Prints the status of a code on a single line
+pub fn print_status(
+    out: &mut impl Write,
+    output: &StatusOutput,
+    format: OutputFormat,
+) -> io::Result<()> {
+    match format {
+        OutputFormat::Text => writeln!(out, "{}", output.text)?,
+        OutputFormat::Json => writeln!(out, "{}", serde_json::to_string(output)?)?,
+    }
+    out.flush()
+}
+
+fn to_text_table(outputs: &[CodeOutput]) -> String {
+    let header = ["Id", "Issuer", "Label", "Code", "Remaining"].map(String::from);
+    let rows: Vec<[String; 5]> = outputs
+        .iter()
+        .map(|o| {
+            [
+                o.index.to_string(),
+                o.issuer.to_owned(),
+                o.label.to_owned(),
+                o.text_code().to_owned(),
+                match (o.remaining_seconds, o.counter) {
+                    (Some(seconds), _) => format!("{seconds}s"),
+                    (None, Some(counter)) => format!("counter {counter}"),
+                    (None, None) => String::new(),
+                },
+            ]
+        })
+        .collect();
+    format_table(header, rows)
+}
+
+/// Aligns the columns, padding each value to the widest one
+fn format_table<const N: usize>(header: [String; N], rows: Vec<[String; N]>) -> String {
+    let widths: Vec<usize> = (0..N)
+        .map(|column| {
+            std::iter::once(&header)
+                .chain(rows.iter())
+                .map(|row| row[column].chars().count())
+                .max()
+                .unwrap_or_default()
+        })
+        .collect();
+    std::iter::once(&header)
+        .chain(rows.iter())
+        .map(|row| {
+            let line = row
+                .iter()
+                .zip(widths.iter())
+                .map(|(value, width)| format!("{value:width$}"))
+                .collect::<Vec<String>>()
+                .join("  ");
+            format!("{}\n", line.trim_end())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use cotp::otp::{otp_element::OTPElement, otp_type::OTPType};
+
+    use super::{
+        print_plain, print_status, to_text_table, CodeOutput, OutputFormat, StatsOutput,
+        StatusOutput,
+    };
+
+    fn hotp_element() -> OTPElement {
+        let mut element = OTPElement::default();
+        element.secret = String::from("JBSWY3DPEHPK3PXP");
+        element.issuer = String::from("Example");
+        element.label = String::from("alice");
+        element.type_ = OTPType::Hotp;
+        element.algorithm = cotp::otp::otp_algorithm::OTPAlgorithm::Sha1;
+        element.counter = Some(0);
+        element
+    }
+
+    #[test]
+    fn test_json_output() {
+        let output = CodeOutput::new(0, &hotp_element());
+
+        assert_eq!(
+            r#"{"index":1,"issuer":"Example","label":"alice","type":"HOTP","code":"282760","remaining_seconds":null,"counter":0}"#,
+            serde_json::to_string(&output).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_text_output() {
+        let output = CodeOutput::new(0, &hotp_element());
+
+        assert_eq!(
+            "Id  Issuer   Label  Code    Remaining\n1   Example  alice  282760  counter 0\n",
+            to_text_table(&[output])
+        );
+    }
+
+    #[test]
+    fn test_plain_output() {
+        let mut element = hotp_element();
+        assert_eq!(
+            "1. Example alice: 282760, counter 0",
+            CodeOutput::new(0, &element).plain_line()
+        );
+
+        element.issuer = String::new();
+        element.type_ = OTPType::Totp;
+        element.counter = None;
+        assert_eq!(
+            "3. alice: 996554, 1 second left",
+            CodeOutput::at(2, &element, 59).plain_line()
+        );
+        assert_eq!(
+            "3. alice: 358462, 30 seconds left",
+            CodeOutput::at(2, &element, 1111111110).plain_line()
+        );
+
+        let mut out = Vec::new();
+        print_plain(&mut out, &[CodeOutput::at(0, &element, 59)]).unwrap();
+        assert_eq!(b"1. alice: 996554, 1 second left\n", out.as_slice());
+    }
+
+    #[test]
+    fn test_status_output() {
+        assert_eq!(
+            r#"{"text":"282760","tooltip":"Example:alice"}"#,
+            serde_json::to_string(&StatusOutput::new(&hotp_element())).unwrap()
+        );
+
+        let mut element = hotp_element();
+        element.type_ = OTPType::Totp;
+        element.counter = None;
+        let status = StatusOutput::new(&element);
+        let seconds = element.remaining_seconds().unwrap();
+        assert!(status.text.ends_with(&format!(" {seconds}s")));
+        assert_eq!(Some(seconds * 100 / 30), status.percentage);
+
+        let mut out = Vec::new();
+        let status = StatusOutput::new(&hotp_element());
+        print_status(&mut out, &status, OutputFormat::Text).unwrap();
+        print_status(&mut out, &status, OutputFormat::Json).unwrap();
+        assert_eq!(
+            "282760\n{\"text\":\"282760\",\"tooltip\":\"Example:alice\"}\n",
+            String::from_utf8(out).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_stats_output() {
+        let mut element = hotp_element();
+        assert_eq!(None, StatsOutput::new(0, &element).last_used);
+
+        element.usage_count = 3;
+        element.last_used = 1700000000;
+        assert_eq!(
+            r#"{"index":2,"issuer":"Example","label":"alice","usage_count":3,"last_used":1700000000}"#,
+            serde_json::to_string(&StatsOutput::new(1, &element)).unwrap()
+        );
+    }
+}
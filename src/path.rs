@@ -1,18 +1,122 @@
+use color_eyre::eyre::eyre;
 use dirs::{data_dir, home_dir};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::OnceLock;
 use std::{env, fs};
 
 const CURRENT_DB_PATH: &str = "./db.cotp";
 const XDG_PATH: &str = "cotp/db.cotp";
 const HOME_PATH: &str = ".cotp/db.cotp";
+const PROFILES_DIR: &str = "profiles";
+const PROFILE_EXTENSION: &str = "cotp";
+const DB_FILE_NAME: &str = "db.cotp";
+/// Name of the profile using the database outside the profiles directory
+pub const DEFAULT_PROFILE: &str = "default";
 
 static ONCE_COMPUTED_PATH: OnceLock<PathBuf> = OnceLock::new();
+static SELECTED_PROFILE: OnceLock<String> = OnceLock::new();
+static CUSTOM_DB_PATH: OnceLock<PathBuf> = OnceLock::new();
 
 pub fn get_db_path() -> PathBuf {
+    if let Some(path) = CUSTOM_DB_PATH.get() {
+        return path.to_owned();
+    }
     env::var("COTP_DB_PATH")
-        .map(PathBuf::from)
-        .unwrap_or_else(|_| get_default_db_path())
+        .map(|path| resolve_db_path(Path::new(&path)))
+        .unwrap_or_else(|_| match get_profile() {
+            Some(profile) => get_profile_path(profile),
+            None => get_default_db_path(),
+        })
+}
+
+/// Uses the database at the given path for the rest of the execution, like one on a USB stick or in a synced folder
+pub fn set_db_path(path: &Path) -> color_eyre::Result<()> {
+    CUSTOM_DB_PATH
+        .set(resolve_db_path(path))
+        .map_err(|_| eyre!("A database path has already been selected"))
+}
+
+/// Whether the database path has been chosen by the user instead of the profile
+pub fn has_custom_db_path() -> bool {
+    CUSTOM_DB_PATH.get().is_some() || env::var_os("COTP_DB_PATH").is_some()
+}
+
+/// Directories get the default database file name, relative paths are made absolute
+/// so that the keyring entry and the backups do not depend on the working directory
+fn resolve_db_path(path: &Path) -> PathBuf {
+    let path = if path.is_dir() {
+        path.join(DB_FILE_NAME)
+    } else {
+        path.to_path_buf()
+    };
+    std::path::absolute(&path).unwrap_or(path)
+}
+
+/// Selects the profile whose database is used for the rest of the execution
+pub fn set_profile(name: &str) -> color_eyre::Result<()> {
+    validate_profile_name(name)?;
+    if name == DEFAULT_PROFILE {
+        return Ok(());
+    }
+    SELECTED_PROFILE
+        .set(name.to_owned())
+        .map_err(|_| eyre!("A profile has already been selected"))
+}
+
+/// The selected profile, None when using the default database
+pub fn get_profile() -> Option<&'static str> {
+    SELECTED_PROFILE.get().map(String::as_str)
+}
+
+/// Profile names become file names, so only letters, digits, - and _ are allowed
+pub fn validate_profile_name(name: &str) -> color_eyre::Result<()> {
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(eyre!(
+            "Invalid profile name {name}, use only letters, digits, - and _"
+        ));
+    }
+    Ok(())
+}
+
+/// Profile databases are stored in the profiles directory, next to the default one
+pub fn get_profile_path(name: &str) -> PathBuf {
+    if name == DEFAULT_PROFILE {
+        return get_default_db_path();
+    }
+    get_profiles_dir().join(format!("{name}.{PROFILE_EXTENSION}"))
+}
+
+fn get_profiles_dir() -> PathBuf {
+    get_default_db_path()
+        .parent()
+        .map(|dir| dir.join(PROFILES_DIR))
+        .unwrap_or(PathBuf::from(PROFILES_DIR))
+}
+
+/// Names of the existing profiles, sorted, including the default one if its database exists
+pub fn list_profiles() -> std::io::Result<Vec<String>> {
+    let mut profiles = Vec::new();
+    if get_default_db_path().exists() {
+        profiles.push(DEFAULT_PROFILE.to_owned());
+    }
+    let profiles_dir = get_profiles_dir();
+    if !profiles_dir.exists() {
+        return Ok(profiles);
+    }
+    let mut names = fs::read_dir(profiles_dir)?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && path.extension().is_some_and(|e| e == PROFILE_EXTENSION))
+        .filter_map(|path| Some(path.file_stem()?.to_string_lossy().into_owned()))
+        .filter(|name| validate_profile_name(name).is_ok())
+        .collect::<Vec<String>>();
+    names.sort();
+    profiles.append(&mut names);
+    Ok(profiles)
 }
 
 // Pushing an absolute path to a PathBuf replaces the entire PathBuf: https://doc.rust-lang.org/std/path/struct.PathBuf.html#method.push
@@ -29,9 +133,8 @@ fn get_default_db_path() -> PathBuf {
     ONCE_COMPUTED_PATH
         .get_or_init(|| {
             data_dir()
-                .map(PathBuf::from)
                 .map(|p| p.join(XDG_PATH))
-                .map(|xdg| {
+                .inspect(|xdg| {
                     if !xdg.exists() {
                         if let Some(home) = &home_path {
                             if home.exists() {
@@ -42,10 +145,48 @@ fn get_default_db_path() -> PathBuf {
                             }
                         }
                     }
-                    xdg
                 })
                 .or(home_path)
                 .unwrap_or(portable_path)
         })
         .to_owned()
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{
+        get_default_db_path, get_profile_path, resolve_db_path, validate_profile_name,
+        DEFAULT_PROFILE,
+    };
+
+    #[test]
+    fn test_profile_names() {
+        assert!(validate_profile_name("work").is_ok());
+        assert!(validate_profile_name("personal_2-fa").is_ok());
+        assert!(validate_profile_name("").is_err());
+        assert!(validate_profile_name("../work").is_err());
+        assert!(validate_profile_name("work.cotp").is_err());
+    }
+
+    #[test]
+    fn test_profile_path() {
+        assert_eq!(get_default_db_path(), get_profile_path(DEFAULT_PROFILE));
+        assert!(get_profile_path("work").ends_with("profiles/work.cotp"));
+    }
+
+    #[test]
+    fn test_resolve_db_path() {
+        let dir = std::env::temp_dir();
+        assert_eq!(dir.join("db.cotp"), resolve_db_path(&dir));
+        assert_eq!(
+            dir.join("vault.cotp"),
+            resolve_db_path(&dir.join("vault.cotp"))
+        );
+
+        let relative = resolve_db_path(Path::new("vault.cotp"));
+        assert!(relative.is_absolute());
+        assert!(relative.ends_with("vault.cotp"));
+    }
+}
@@ -0,0 +1,96 @@
+//! Chooses a code with a dmenu-like program, which reads the choices from its standard input
+//! and prints the selected one.
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use clap::ValueEnum;
+use color_eyre::eyre::eyre;
+use cotp::otp::otp_element::OTPElement;
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug, ValueEnum)]
+pub enum Menu {
+    #[default]
+    Rofi,
+    Dmenu,
+    Wofi,
+    Fuzzel,
+}
+
+impl Menu {
+    fn command(self) -> Command {
+        let (program, args): (&str, &[&str]) = match self {
+            Menu::Rofi => ("rofi", &["-dmenu", "-i", "-p", "cotp"]),
+            Menu::Dmenu => ("dmenu", &["-i", "-p", "cotp"]),
+            Menu::Wofi => ("wofi", &["--dmenu", "--insensitive", "--prompt", "cotp"]),
+            Menu::Fuzzel => ("fuzzel", &["--dmenu", "--prompt", "cotp> "]),
+        };
+        let mut command = Command::new(program);
+        command.args(args);
+        command
+    }
+}
+
+/// Line shown in the menu, starting with the index to tell apart the codes with the same issuer and label
+fn menu_line(index: usize, issuer: &str, label: &str) -> String {
+    if label.is_empty() {
+        format!("{} {issuer}", index + 1)
+    } else {
+        format!("{} {issuer}:{label}", index + 1)
+    }
+}
+
+/// Index of the element of a line printed by the menu
+fn parse_selection(selection: &str) -> Option<usize> {
+    selection
+        .trim()
+        .split_once(' ')
+        .and_then(|(index, _)| index.parse::<usize>().ok())
+        .and_then(|index| index.checked_sub(1))
+}
+
+/// Shows the elements in the menu, returning the index of the chosen one or None if the menu was dismissed
+pub fn pick(menu: Menu, elements: &[OTPElement]) -> color_eyre::Result<Option<usize>> {
+    let mut command = menu.command();
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|e| {
+            eyre!(
+                "Cannot start {}: {e}",
+                command.get_program().to_string_lossy()
+            )
+        })?;
+    // Taken so the pipe is closed once the lines are written
+    if let Some(mut stdin) = child.stdin.take() {
+        let lines = elements
+            .iter()
+            .enumerate()
+            .map(|(i, e)| menu_line(i, &e.issuer, &e.label))
+            .collect::<Vec<String>>()
+            .join("\n");
+        stdin.write_all(lines.as_bytes())?;
+    }
+    let output = child.wait_with_output()?;
+    // The menus exit with an error status when dismissed
+    if !output.status.success() {
+        return Ok(None);
+    }
+    Ok(parse_selection(&String::from_utf8_lossy(&output.stdout)).filter(|i| *i < elements.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{menu_line, parse_selection};
+
+    #[test]
+    fn test_selection_round_trip() {
+        let line = menu_line(4, "GitHub", "alice smith");
+        assert_eq!("5 GitHub:alice smith", line);
+        assert_eq!(Some(4), parse_selection(&format!("{line}\n")));
+        assert_eq!("1 Example", menu_line(0, "Example", ""));
+        assert_eq!(None, parse_selection(""));
+        assert_eq!(None, parse_selection("0 GitHub"));
+    }
+}
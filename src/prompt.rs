@@ -0,0 +1,205 @@
+//! Password prompt drawn on the terminal, showing an asterisk for every typed character.
+//!
+//! Backspace deletes the last character, Ctrl-U or Esc clear the input and pasted text is appended.
+//! New passwords are rated while they are typed, and a warning is shown if Caps Lock seems to be on.
+
+use std::io::{self, IsTerminal, Write};
+
+use crossterm::cursor::MoveToColumn;
+use crossterm::event::{
+    self, DisableBracketedPaste, EnableBracketedPaste, Event, KeyCode, KeyEventKind, KeyModifiers,
+};
+use crossterm::terminal::{self, Clear, ClearType};
+use crossterm::{execute, queue};
+use zeroize::{Zeroize, Zeroizing};
+
+/// Typed letters needed before warning that they are all uppercase
+const CAPS_LOCK_HINT_LETTERS: usize = 3;
+const STRENGTH_METER_WIDTH: usize = 10;
+
+/// How hard the password is to guess, estimated from its length and the kinds of characters it uses
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum PasswordStrength {
+    VeryWeak,
+    Weak,
+    Fair,
+    Strong,
+    VeryStrong,
+}
+
+impl PasswordStrength {
+    pub fn of(password: &str) -> PasswordStrength {
+        let bits = estimated_entropy(password);
+        match bits {
+            b if b < 28.0 => PasswordStrength::VeryWeak,
+            b if b < 36.0 => PasswordStrength::Weak,
+            b if b < 60.0 => PasswordStrength::Fair,
+            b if b < 128.0 => PasswordStrength::Strong,
+            _ => PasswordStrength::VeryStrong,
+        }
+    }
+
+    fn description(&self) -> &'static str {
+        match self {
+            PasswordStrength::VeryWeak => "very weak",
+            PasswordStrength::Weak => "weak",
+            PasswordStrength::Fair => "fair",
+            PasswordStrength::Strong => "strong",
+            PasswordStrength::VeryStrong => "very strong",
+        }
+    }
+
+    fn meter(&self) -> String {
+        let filled = (*self as usize + 1) * STRENGTH_METER_WIDTH / 5;
+        format!(
+            "[{}{}] {}",
+            "#".repeat(filled),
+            "-".repeat(STRENGTH_METER_WIDTH - filled),
+            self.description()
+        )
+    }
+}
+
+/// Bits of entropy of a random password of the same length, using the same kinds of characters.
+/// Repeated characters are counted once, so that "aaaaaaaa" is not rated as a random password
+fn estimated_entropy(password: &str) -> f64 {
+    let mut pool = 0;
+    if password.chars().any(|c| c.is_ascii_lowercase()) {
+        pool += 26;
+    }
+    if password.chars().any(|c| c.is_ascii_uppercase()) {
+        pool += 26;
+    }
+    if password.chars().any(|c| c.is_ascii_digit()) {
+        pool += 10;
+    }
+    if password
+        .chars()
+        .any(|c| c.is_ascii_punctuation() || c == ' ')
+    {
+        pool += 33;
+    }
+    if !password.is_ascii() {
+        pool += 100;
+    }
+    let mut chars: Vec<char> = password.chars().collect();
+    chars.sort_unstable();
+    chars.dedup();
+    let length = chars.len() + (password.chars().count() - chars.len()) / 2;
+    chars.zeroize();
+    length as f64 * (pool.max(1) as f64).log2()
+}
+
+/// True if every typed letter is uppercase, a hint that Caps Lock is on since terminals do not report it
+fn looks_like_caps_lock(password: &str) -> bool {
+    let mut letters = password.chars().filter(|c| c.is_alphabetic());
+    password.chars().filter(|c| c.is_alphabetic()).count() >= CAPS_LOCK_HINT_LETTERS
+        && letters.all(|c| c.is_uppercase())
+}
+
+/// Reads a password on the terminal, showing its strength if it is a new one.
+/// Fails if there is no terminal, like when the standard input is redirected,
+/// and with [`io::ErrorKind::Interrupted`] if the user presses Ctrl-C.
+pub fn read_password(message: &str, show_strength: bool) -> io::Result<String> {
+    if !io::stdin().is_terminal() {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "The standard input is not a terminal",
+        ));
+    }
+    terminal::enable_raw_mode()?;
+    let mut stderr = io::stderr();
+    let _ = execute!(stderr, EnableBracketedPaste);
+    let result = read_keys(message, show_strength, &mut stderr);
+    let _ = execute!(stderr, DisableBracketedPaste);
+    terminal::disable_raw_mode()?;
+    eprintln!();
+    match result {
+        Ok(Some(password)) => Ok(password),
+        Ok(None) => Err(io::Error::new(
+            io::ErrorKind::Interrupted,
+            "The password prompt was interrupted",
+        )),
+        Err(e) => Err(e),
+    }
+}
+
+/// Returns None if the user pressed Ctrl-C
+fn read_keys(
+    message: &str,
+    show_strength: bool,
+    stderr: &mut io::Stderr,
+) -> io::Result<Option<String>> {
+    let mut password = Zeroizing::new(String::new());
+    loop {
+        draw(message, &password, show_strength, stderr)?;
+        match event::read()? {
+            Event::Key(key) if key.kind != KeyEventKind::Release => {
+                let control = key.modifiers.contains(KeyModifiers::CONTROL);
+                match key.code {
+                    KeyCode::Enter => return Ok(Some(password.to_string())),
+                    KeyCode::Char('c') if control => return Ok(None),
+                    KeyCode::Char('u') if control => password.zeroize(),
+                    KeyCode::Esc => password.zeroize(),
+                    KeyCode::Backspace => {
+                        password.pop();
+                    }
+                    KeyCode::Char(c) if !control => password.push(c),
+                    _ => {}
+                }
+            }
+            Event::Paste(mut text) => {
+                password.push_str(text.lines().next().unwrap_or_default());
+                text.zeroize();
+            }
+            _ => {}
+        }
+    }
+}
+
+fn draw(
+    message: &str,
+    password: &str,
+    show_strength: bool,
+    stderr: &mut io::Stderr,
+) -> io::Result<()> {
+    let mut line = format!("{message}{}", "*".repeat(password.chars().count()));
+    if show_strength && !password.is_empty() {
+        line.push_str("  ");
+        line.push_str(&PasswordStrength::of(password).meter());
+    }
+    if looks_like_caps_lock(password) {
+        line.push_str("  (Caps Lock is on)");
+    }
+    queue!(stderr, MoveToColumn(0), Clear(ClearType::CurrentLine))?;
+    write!(stderr, "{line}")?;
+    stderr.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{looks_like_caps_lock, PasswordStrength};
+
+    #[test]
+    fn test_password_strength() {
+        assert_eq!(PasswordStrength::VeryWeak, PasswordStrength::of("aaaaaaaa"));
+        assert_eq!(PasswordStrength::VeryWeak, PasswordStrength::of("12345678"));
+        assert_eq!(PasswordStrength::Weak, PasswordStrength::of("password"));
+        assert_eq!(
+            PasswordStrength::Strong,
+            PasswordStrength::of("Tr0ub4dor&3")
+        );
+        assert_eq!(
+            PasswordStrength::VeryStrong,
+            PasswordStrength::of("correct horse battery staple Tr0ub4dor&3")
+        );
+    }
+
+    #[test]
+    fn test_caps_lock_hint() {
+        assert!(looks_like_caps_lock("PASSWORD1"));
+        assert!(!looks_like_caps_lock("Password1"));
+        assert!(!looks_like_caps_lock("PA"));
+        assert!(!looks_like_caps_lock("12345678"));
+    }
+}
@@ -1,24 +1,71 @@
 use crate::crypto;
+use crate::crypto::age::decrypt_with_identity;
+use crate::crypto::cryptography::{
+    required_yubikey_slot, requires_keyfile, stored_age_key, stored_kdf_params, DatabaseKey,
+};
+use crate::crypto::keyfile::keyfile_hash;
+use crate::lock::{fingerprint, DatabaseLock};
 use crate::otp::otp_element::{OTPDatabase, OTPElement};
 use crate::path::get_db_path;
 use crate::utils;
 use color_eyre::eyre::{eyre, ErrReport};
+use std::env;
 use std::fs::read_to_string;
 use std::io::{self, BufRead};
+use std::path::Path;
 use zeroize::Zeroize;
 
-pub type ReadResult = (OTPDatabase, Vec<u8>, Vec<u8>);
+const PASSWORD_ENV_VAR: &str = "COTP_PASSWORD";
+
+pub type ReadResult = (OTPDatabase, DatabaseKey, Vec<u8>);
 
 pub fn get_elements_from_input() -> color_eyre::Result<ReadResult> {
-    let pw = utils::password("Password: ", 8);
-    get_elements_with_password(pw)
+    get_elements_with_password(password_from_input()?)
 }
 
 pub fn get_elements_from_stdin() -> color_eyre::Result<ReadResult> {
-    if let Some(password) = io::stdin().lock().lines().next() {
-        return get_elements_with_password(password?);
+    get_elements_with_password(password_from_stdin()?)
+}
+
+/// Prompts for the database password
+pub fn password_from_input() -> io::Result<String> {
+    // The password can be empty if the YubiKey response is enough to derive the key
+    let minimum_length = match read_encrypted_text().map(|t| required_yubikey_slot(&t)) {
+        Ok(Some(_)) => 0,
+        _ => 8,
+    };
+    utils::password("Password: ", minimum_length)
+}
+
+/// Reads the database password from the first line of the standard input
+pub fn password_from_stdin() -> color_eyre::Result<String> {
+    match io::stdin().lock().lines().next() {
+        Some(password) => Ok(password?),
+        None => Err(eyre!("Failure during stdin reading")),
     }
-    Err(eyre!("Failure during stdin reading"))
+}
+
+/// Reads the database password from the first line of the file, like a secret mounted by the scheduler.
+/// On Unix a file descriptor can be given with /dev/fd/N
+pub fn password_from_file(path: &Path) -> color_eyre::Result<String> {
+    let mut content = read_to_string(path)
+        .map_err(|e| eyre!("Cannot read the password file {}: {e}", path.display()))?;
+    let password = content.lines().next().unwrap_or_default().to_owned();
+    content.zeroize();
+    Ok(password)
+}
+
+/// Reads the database password from the COTP_PASSWORD environment variable, which is then removed
+/// so that the programs started by cotp do not inherit it
+pub fn password_from_env() -> Option<String> {
+    let password = env::var(PASSWORD_ENV_VAR).ok()?;
+    env::remove_var(PASSWORD_ENV_VAR);
+    // Removed, the variable is read only once, so the warning is printed once too
+    eprintln!(
+        "Warning: the password was read from {PASSWORD_ENV_VAR}, which other processes of the same user may see, \
+        prefer --password-file"
+    );
+    Some(password)
 }
 
 fn get_elements_with_password(mut password: String) -> color_eyre::Result<ReadResult> {
@@ -27,7 +74,13 @@ fn get_elements_with_password(mut password: String) -> color_eyre::Result<ReadRe
     Ok((elements, key, salt))
 }
 
-pub fn read_decrypted_text(password: &str) -> color_eyre::Result<(String, Vec<u8>, Vec<u8>)> {
+pub fn read_decrypted_text(password: &str) -> color_eyre::Result<(String, DatabaseKey, Vec<u8>)> {
+    let encrypted_contents = read_encrypted_text()?;
+    crypto::cryptography::decrypt_string(&encrypted_contents, password)
+}
+
+fn read_encrypted_text() -> color_eyre::Result<String> {
+    let _lock = DatabaseLock::shared()?;
     let encrypted_contents = read_to_string(get_db_path()).map_err(ErrReport::from)?;
     if encrypted_contents.is_empty() {
         return match delete_db() {
@@ -40,23 +93,107 @@ pub fn read_decrypted_text(password: &str) -> color_eyre::Result<(String, Vec<u8
         };
     }
     //rust close files at the end of the function
-    crypto::cryptography::decrypt_string(&encrypted_contents, password)
+    Ok(encrypted_contents)
 }
 
 pub fn read_from_file(password: &str) -> color_eyre::Result<ReadResult> {
-    match read_decrypted_text(password) {
-        Ok((mut contents, key, salt)) => {
-            let mut database: OTPDatabase = serde_json::from_str(&contents)
-                .or_else(|_| serde_json::from_str::<Vec<OTPElement>>(&contents).map(|r| r.into()))
-                .map_err(ErrReport::from)?;
-            contents.zeroize();
-            database.sort();
-            Ok((database, key, salt))
-        }
-        Err(e) => Err(e),
+    let encrypted_contents = read_encrypted_text()?;
+    let disk_fingerprint = fingerprint(encrypted_contents.as_bytes());
+    let (mut database, key, salt) = decrypt_database(encrypted_contents, password)?;
+    database.disk_fingerprint = Some(disk_fingerprint);
+    Ok((database, key, salt))
+}
+
+/// Reads another cotp database, like the one of a different device
+pub fn read_from_path(path: &Path, password: &str) -> color_eyre::Result<ReadResult> {
+    let encrypted_contents = read_to_string(path).map_err(ErrReport::from)?;
+    if encrypted_contents.trim().is_empty() {
+        return Err(eyre!("The database file {} is empty", path.display()));
     }
+    decrypt_database(encrypted_contents, password)
+}
+
+/// Unlocks the database with an age identity, decrypting the database key encrypted to its recipients
+pub fn read_from_file_with_age_identity(identity: &Path) -> color_eyre::Result<ReadResult> {
+    let encrypted_contents = read_encrypted_text()?;
+    let disk_fingerprint = fingerprint(encrypted_contents.as_bytes());
+    let age_key = stored_age_key(&encrypted_contents).ok_or(eyre!(
+        "This database cannot be unlocked with age, add a recipient with cotp age add"
+    ))?;
+    let key = decrypt_with_identity(&age_key, identity)?;
+    let (contents, key, salt) =
+        crypto::cryptography::decrypt_string_with_key(&encrypted_contents, key)
+            .map_err(|_| eyre!("The age identity unlocked an invalid database key"))?;
+    let mut database = database_from_contents(contents, &encrypted_contents)?;
+    database.disk_fingerprint = Some(disk_fingerprint);
+    Ok((database, key, salt))
+}
+
+/// Decrypts the database text with an already derived key, like the one of the database in use
+pub fn read_with_key(encrypted_contents: &str, key: Vec<u8>) -> color_eyre::Result<OTPDatabase> {
+    let (contents, _, _) = crypto::cryptography::decrypt_string_with_key(encrypted_contents, key)?;
+    database_from_contents(contents, encrypted_contents)
+}
+
+fn decrypt_database(encrypted_contents: String, password: &str) -> color_eyre::Result<ReadResult> {
+    let (contents, key, salt) =
+        crypto::cryptography::decrypt_string(&encrypted_contents, password)?;
+    Ok((
+        database_from_contents(contents, &encrypted_contents)?,
+        key,
+        salt,
+    ))
+}
+
+/// Deserializes the decrypted database, restoring the settings stored in its encrypted envelope
+fn database_from_contents(
+    mut contents: String,
+    encrypted_contents: &str,
+) -> color_eyre::Result<OTPDatabase> {
+    let result = serde_json::from_str(&contents)
+        .or_else(|_| serde_json::from_str::<Vec<OTPElement>>(&contents).map(|r| r.into()))
+        .map_err(ErrReport::from);
+    contents.zeroize();
+    let mut database: OTPDatabase = result?;
+    database.set_yubikey_slot(required_yubikey_slot(encrypted_contents));
+    database.set_kdf_params(stored_kdf_params(encrypted_contents));
+    if requires_keyfile(encrypted_contents) {
+        database.keyfile_required = true;
+        database.keyfile_hash = keyfile_hash();
+    }
+    database.age_key = stored_age_key(encrypted_contents);
+    database.base_fingerprint = Some(database.content_fingerprint()?);
+    Ok(database)
 }
 
 fn delete_db() -> io::Result<()> {
     std::fs::remove_file(get_db_path())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{database_from_contents, password_from_file};
+
+    #[test]
+    fn test_password_from_file() {
+        let path = std::env::temp_dir().join(format!("cotp-password-test-{}", std::process::id()));
+        std::fs::write(&path, "pa$$w0rd\r\nignored\n").unwrap();
+        assert_eq!("pa$$w0rd", password_from_file(&path).unwrap());
+        std::fs::remove_file(&path).unwrap();
+        assert!(password_from_file(&path).is_err());
+    }
+
+    #[test]
+    fn test_recipients_only_read_from_the_encrypted_database() {
+        // The envelope is not authenticated, so the recipients written there are ignored
+        let envelope =
+            r#"{"version":2,"nonce":"","salt":"","cipher":"","age_recipients":["age1attacker"]}"#;
+        let database = database_from_contents(String::from("[]"), envelope).unwrap();
+        assert!(database.age_recipients().is_empty());
+
+        let contents =
+            String::from(r#"{"version":2,"elements":[],"age_recipients":["age1alice"]}"#);
+        let database = database_from_contents(contents, envelope).unwrap();
+        assert_eq!(vec!["age1alice"], database.age_recipients());
+    }
+}
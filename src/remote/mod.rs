@@ -0,0 +1,229 @@
+//! Storage of the encrypted database on a remote server, so phones and desktops can share one vault.
+//!
+//! The requests are made with curl, like the encryption with gpg, so cotp does not need its own HTTP and TLS stack.
+//! The server identifies every version of the database with an ETag: a push only replaces the version
+//! last pushed or pulled by this machine, so the changes pushed by another one are never lost.
+
+pub mod s3;
+pub mod webdav;
+
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use color_eyre::eyre::{eyre, ErrReport};
+use serde::{Deserialize, Serialize};
+
+use crate::otp::otp_element::OTPDatabase;
+use crate::path::get_db_path;
+use crate::utils::write_atomically;
+use s3::S3Config;
+use webdav::WebDavConfig;
+
+const STATE_EXTENSION: &str = ".remote";
+/// Precedes the status written by curl after the error messages
+const STATUS_MARKER: &str = "cotp-status:";
+
+/// Server storing the database, with its credentials, kept in the encrypted database
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Hash, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RemoteConfig {
+    WebDav(WebDavConfig),
+    S3(S3Config),
+}
+
+/// Version of the remote database the upload is allowed to replace
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Precondition<'a> {
+    /// The one with this ETag
+    Version(&'a str),
+    /// None, the upload creates the remote database
+    Missing,
+    /// Any, overwriting the changes pushed by the other machines
+    Any,
+}
+
+/// Remote database and its ETag
+pub struct Download {
+    pub content: Vec<u8>,
+    pub etag: Option<String>,
+}
+
+impl RemoteConfig {
+    pub fn url(&self) -> String {
+        match self {
+            RemoteConfig::WebDav(config) => config.url.clone(),
+            RemoteConfig::S3(config) => config.url(),
+        }
+    }
+
+    /// Downloads the database, None if it is still the version with the given ETag
+    pub fn download(&self, etag: Option<&str>) -> color_eyre::Result<Option<Download>> {
+        match self {
+            RemoteConfig::WebDav(config) => config.download(etag),
+            RemoteConfig::S3(config) => config.download(etag),
+        }
+    }
+
+    /// Uploads the database file, returning the ETag of the new remote version
+    pub fn upload(
+        &self,
+        path: &Path,
+        precondition: Precondition,
+    ) -> color_eyre::Result<Option<String>> {
+        match self {
+            RemoteConfig::WebDav(config) => config.upload(path, precondition),
+            RemoteConfig::S3(config) => config.upload(path, precondition),
+        }
+    }
+
+    /// Timestamps in milliseconds of the versions kept by the previous pushes, newest first
+    pub fn versions(&self) -> color_eyre::Result<Vec<u64>> {
+        match self {
+            RemoteConfig::S3(config) => config.versions(),
+            RemoteConfig::WebDav(_) => Err(eyre!("Only the S3 servers keep the pushed versions")),
+        }
+    }
+
+    pub fn download_version(&self, version: u64) -> color_eyre::Result<Vec<u8>> {
+        match self {
+            RemoteConfig::S3(config) => config.download_version(version),
+            RemoteConfig::WebDav(_) => Err(eyre!("Only the S3 servers keep the pushed versions")),
+        }
+    }
+}
+
+impl OTPDatabase {
+    pub fn remote_config(&self) -> Option<&RemoteConfig> {
+        self.remote.as_ref()
+    }
+
+    pub fn set_remote_config(&mut self, config: Option<RemoteConfig>) {
+        self.mark_modified();
+        self.remote = config;
+    }
+}
+
+/// Next to the database and not inside it, since a pull replaces the database
+fn state_path() -> PathBuf {
+    let mut path = get_db_path().into_os_string();
+    path.push(STATE_EXTENSION);
+    PathBuf::from(path)
+}
+
+/// What this machine last pushed or pulled
+#[derive(Serialize, Deserialize)]
+struct RemoteState {
+    url: String,
+    etag: Option<String>,
+}
+
+/// ETag of the version last pushed to or pulled from the url
+pub fn last_etag(url: &str) -> Option<String> {
+    let content = fs::read_to_string(state_path()).ok()?;
+    serde_json::from_str::<RemoteState>(&content)
+        .ok()
+        .filter(|state| state.url == url)?
+        .etag
+}
+
+pub fn set_last_etag(url: &str, etag: Option<String>) -> color_eyre::Result<()> {
+    let state = RemoteState {
+        url: url.to_owned(),
+        etag,
+    };
+    write_atomically(&state_path(), serde_json::to_string(&state)?.as_bytes())
+        .map_err(|e| eyre!("Cannot write {}: {e}", state_path().display()))
+}
+
+/// Answer of the server
+pub(crate) struct Response {
+    pub status: u16,
+    pub etag: Option<String>,
+    pub body: Vec<u8>,
+}
+
+/// Quotes an option of the curl configuration file
+pub(crate) fn curl_option(name: &str, value: &str) -> String {
+    let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+    format!("{name} = \"{value}\"\n")
+}
+
+/// Runs curl, giving it the options with the url and the credentials on the standard input so they do not show in the process list
+pub(crate) fn curl(secret_options: &str, args: &[&str]) -> color_eyre::Result<Response> {
+    let mut child = Command::new("curl")
+        .args(["--silent", "--show-error", "--location", "--config", "-"])
+        .arg("--write-out")
+        .arg(format!(
+            "%{{stderr}}\n{STATUS_MARKER}%{{http_code}} %header{{etag}}\n"
+        ))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| eyre!("Cannot run curl, make sure it is installed: {e}"))?;
+    // The options are read before anything else, they cannot fill the pipe while curl is writing its output
+    let mut stdin = child.stdin.take().ok_or(eyre!("Cannot write to curl"))?;
+    stdin.write_all(secret_options.as_bytes())?;
+    drop(stdin);
+    let output = child.wait_with_output()?;
+    let (errors, status, etag) = parse_status(&String::from_utf8_lossy(&output.stderr));
+    if !output.status.success() || status == 0 {
+        return Err(eyre!("The request failed: {errors}"));
+    }
+    Ok(Response {
+        status,
+        etag,
+        body: output.stdout,
+    })
+}
+
+/// Splits the standard error of curl into its error messages, the HTTP status and the ETag
+fn parse_status(stderr: &str) -> (String, u16, Option<String>) {
+    let (errors, status) = stderr.rsplit_once(STATUS_MARKER).unwrap_or((stderr, ""));
+    let (code, etag) = status.trim_end().split_once(' ').unwrap_or((status, ""));
+    let etag = Some(etag.trim())
+        .filter(|e| !e.is_empty())
+        .map(String::from);
+    (
+        errors.trim().to_owned(),
+        code.trim().parse().unwrap_or(0),
+        etag,
+    )
+}
+
+/// Error for the statuses with the same meaning on every server
+pub(crate) fn status_error(status: u16, url: &str) -> ErrReport {
+    match status {
+        401 | 403 => eyre!("Access denied to {url}, check the username and the password"),
+        404 => eyre!("Nothing found at {url}, push the database first"),
+        412 => eyre!(
+            "The database at {url} was changed by another machine since the last pull, pull it first or push with --force"
+        ),
+        _ => eyre!("The server answered {status} for {url}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{curl_option, parse_status};
+
+    #[test]
+    fn test_curl_output() {
+        assert_eq!(
+            (String::new(), 200, Some(String::from("\"5f2a\""))),
+            parse_status("\ncotp-status:200 \"5f2a\"\n")
+        );
+        assert_eq!(
+            (String::from("curl: (6) Could not resolve host"), 0, None),
+            parse_status("curl: (6) Could not resolve host\ncotp-status:000 \n")
+        );
+        assert_eq!(
+            r#"user = "alice:pass\"word\\"
+"#,
+            curl_option("user", r#"alice:pass"word\"#)
+        );
+    }
+}
@@ -0,0 +1,247 @@
+//! S3 compatible object storage like AWS, MinIO or Backblaze B2, with the requests signed by curl.
+//!
+//! Every push also stores a copy of the database under a key ending with its timestamp in milliseconds, so the
+//! previous versions can be restored even if the versioning of the bucket is disabled. The database is already
+//! encrypted, the server side encryption configured on the bucket is applied or not without any difference.
+//!
+//! The pushes are conditional like the WebDAV ones, but only the servers supporting the conditional writes,
+//! like AWS since 2024 or MinIO, check the If-Match header: the others ignore it and always replace the database.
+
+use std::path::Path;
+
+use color_eyre::eyre::eyre;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use super::{curl, curl_option, status_error, Download, Precondition, Response};
+use crate::clock;
+
+const DEFAULT_REGION: &str = "us-east-1";
+const VERSIONS_SUFFIX: &str = ".versions/";
+/// The payload is not part of the signature, curl would have to read the uploaded file twice
+const UNSIGNED_PAYLOAD: &str = "x-amz-content-sha256: UNSIGNED-PAYLOAD";
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct S3Config {
+    /// Address of the server, like https://s3.eu-west-1.amazonaws.com or https://s3.us-west-004.backblazeb2.com
+    pub endpoint: String,
+    pub region: String,
+    pub bucket: String,
+    /// Key of the database object, like cotp/db.cotp
+    pub key: String,
+    pub access_key: String,
+    secret_key: String,
+}
+
+/// Answer of ListObjectsV2
+#[derive(Deserialize, Default, PartialEq, Debug)]
+#[serde(rename_all = "PascalCase")]
+struct ListBucketResult {
+    #[serde(default)]
+    contents: Vec<ListedObject>,
+    next_continuation_token: Option<String>,
+}
+
+#[derive(Deserialize, PartialEq, Debug)]
+#[serde(rename_all = "PascalCase")]
+struct ListedObject {
+    key: String,
+}
+
+impl S3Config {
+    pub fn new(
+        endpoint: &str,
+        region: Option<&str>,
+        bucket: &str,
+        key: &str,
+        access_key: &str,
+        secret_key: &str,
+    ) -> color_eyre::Result<Self> {
+        let parsed = Url::parse(endpoint).map_err(|e| eyre!("Invalid endpoint {endpoint}: {e}"))?;
+        if !matches!(parsed.scheme(), "http" | "https") {
+            return Err(eyre!("The endpoint must start with https:// or http://"));
+        }
+        if bucket.is_empty() || bucket.contains('/') {
+            return Err(eyre!("Invalid bucket name {bucket}"));
+        }
+        let key = key.trim_start_matches('/');
+        if key.is_empty() || key.ends_with('/') {
+            return Err(eyre!("The key must be the one of the database object"));
+        }
+        Ok(S3Config {
+            endpoint: endpoint.trim_end_matches('/').to_owned(),
+            region: region.unwrap_or(DEFAULT_REGION).to_owned(),
+            bucket: bucket.to_owned(),
+            key: key.to_owned(),
+            access_key: access_key.to_owned(),
+            secret_key: secret_key.to_owned(),
+        })
+    }
+
+    /// Path style address of the object, supported by every S3 compatible server
+    fn object_url(&self, key: &str) -> String {
+        let key = key
+            .split('/')
+            .map(|segment| urlencoding::encode(segment).into_owned())
+            .collect::<Vec<_>>()
+            .join("/");
+        format!("{}/{}/{key}", self.endpoint, self.bucket)
+    }
+
+    pub fn url(&self) -> String {
+        self.object_url(&self.key)
+    }
+
+    fn versions_prefix(&self) -> String {
+        format!("{}{VERSIONS_SUFFIX}", self.key)
+    }
+
+    fn request(&self, url: &str, args: &[&str]) -> color_eyre::Result<Response> {
+        let options = curl_option("url", url)
+            + &curl_option("aws-sigv4", &format!("aws:amz:{}:s3", self.region))
+            + &curl_option("user", &format!("{}:{}", self.access_key, self.secret_key));
+        let mut args = args.to_vec();
+        args.extend(["--header", UNSIGNED_PAYLOAD]);
+        curl(&options, &args)
+    }
+
+    pub fn download(&self, etag: Option<&str>) -> color_eyre::Result<Option<Download>> {
+        let if_none_match = etag.map(|etag| format!("If-None-Match: {etag}"));
+        let mut args = vec![];
+        if let Some(header) = &if_none_match {
+            args.extend(["--header", header]);
+        }
+        let response = self.request(&self.url(), &args)?;
+        match response.status {
+            200 => Ok(Some(Download {
+                content: response.body,
+                etag: response.etag,
+            })),
+            304 => Ok(None),
+            status => Err(status_error(status, &self.url())),
+        }
+    }
+
+    /// Uploads the database, then its copy kept as a version
+    pub fn upload(
+        &self,
+        path: &Path,
+        precondition: Precondition,
+    ) -> color_eyre::Result<Option<String>> {
+        let header = match precondition {
+            Precondition::Version(etag) => Some(format!("If-Match: {etag}")),
+            Precondition::Missing => Some(String::from("If-None-Match: *")),
+            Precondition::Any => None,
+        };
+        let path = path.to_string_lossy();
+        let mut args = vec!["--upload-file", &path];
+        if let Some(header) = &header {
+            args.extend(["--header", header]);
+        }
+        let response = self.request(&self.url(), &args)?;
+        if response.status != 200 {
+            return Err(status_error(response.status, &self.url()));
+        }
+        // Two pushes of the same second would replace the same version
+        let version_key = format!("{}{}", self.versions_prefix(), clock::now_ms());
+        let version_url = self.object_url(&version_key);
+        let version = self.request(&version_url, &["--upload-file", &path])?;
+        if version.status != 200 {
+            return Err(status_error(version.status, &version_url));
+        }
+        Ok(response.etag)
+    }
+
+    /// Timestamps in milliseconds of the pushed versions, newest first
+    pub fn versions(&self) -> color_eyre::Result<Vec<u64>> {
+        let prefix = self.versions_prefix();
+        let mut versions = vec![];
+        let mut token: Option<String> = None;
+        loop {
+            let mut url = format!(
+                "{}/{}?list-type=2&prefix={}",
+                self.endpoint,
+                self.bucket,
+                urlencoding::encode(&prefix)
+            );
+            if let Some(token) = &token {
+                url += &format!("&continuation-token={}", urlencoding::encode(token));
+            }
+            let response = self.request(&url, &[])?;
+            if response.status != 200 {
+                return Err(status_error(response.status, &url));
+            }
+            let list = parse_list(&String::from_utf8_lossy(&response.body))?;
+            versions.extend(
+                list.contents
+                    .iter()
+                    .filter_map(|object| object.key.strip_prefix(&prefix)?.parse::<u64>().ok()),
+            );
+            token = list.next_continuation_token;
+            if token.is_none() {
+                break;
+            }
+        }
+        versions.sort_unstable_by(|a, b| b.cmp(a));
+        Ok(versions)
+    }
+
+    pub fn download_version(&self, version: u64) -> color_eyre::Result<Vec<u8>> {
+        let url = self.object_url(&format!("{}{version}", self.versions_prefix()));
+        let response = self.request(&url, &[])?;
+        match response.status {
+            200 => Ok(response.body),
+            404 => Err(eyre!("No version {version}, see cotp remote versions")),
+            status => Err(status_error(status, &url)),
+        }
+    }
+}
+
+fn parse_list(xml: &str) -> color_eyre::Result<ListBucketResult> {
+    quick_xml::de::from_str(xml).map_err(|e| eyre!("Invalid answer of the server: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_list, S3Config};
+
+    #[test]
+    fn test_s3_urls() {
+        let config = S3Config::new(
+            "https://s3.eu-west-1.amazonaws.com/",
+            Some("eu-west-1"),
+            "vault",
+            "/cotp/my db.cotp",
+            "AKIA",
+            "secret",
+        )
+        .unwrap();
+        assert_eq!(
+            "https://s3.eu-west-1.amazonaws.com/vault/cotp/my%20db.cotp",
+            config.url()
+        );
+        assert_eq!("cotp/my db.cotp.versions/", config.versions_prefix());
+        assert!(S3Config::new("https://minio:9000", None, "vault", "cotp/", "", "").is_err());
+    }
+
+    #[test]
+    fn test_parse_list() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<ListBucketResult xmlns="http://s3.amazonaws.com/doc/2006-03-01/">
+  <Name>vault</Name>
+  <Prefix>db.cotp.versions/</Prefix>
+  <KeyCount>2</KeyCount>
+  <IsTruncated>true</IsTruncated>
+  <Contents><Key>db.cotp.versions/1729000000</Key><Size>1024</Size></Contents>
+  <Contents><Key>db.cotp.versions/1729000100</Key><Size>1056</Size></Contents>
+  <NextContinuationToken>1ueGcxLPRx1Tr</NextContinuationToken>
+</ListBucketResult>"#;
+        let list = parse_list(xml).unwrap();
+        assert_eq!(2, list.contents.len());
+        assert_eq!("db.cotp.versions/1729000100", list.contents[1].key);
+        assert_eq!(
+            Some(String::from("1ueGcxLPRx1Tr")),
+            list.next_continuation_token
+        );
+    }
+}
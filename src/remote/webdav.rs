@@ -0,0 +1,108 @@
+//! WebDAV servers like Nextcloud, ownCloud or Apache with mod_dav, storing the database as a single file
+
+use std::path::Path;
+
+use color_eyre::eyre::eyre;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use super::{curl, curl_option, status_error, Download, Precondition, Response};
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct WebDavConfig {
+    /// Address of the database file, like https://cloud.example.com/remote.php/dav/files/alice/cotp/db.cotp
+    pub url: String,
+    pub username: String,
+    /// An app password, for the servers supporting them
+    password: String,
+}
+
+impl WebDavConfig {
+    pub fn new(url: &str, username: &str, password: &str) -> color_eyre::Result<Self> {
+        let parsed = Url::parse(url).map_err(|e| eyre!("Invalid url {url}: {e}"))?;
+        if !matches!(parsed.scheme(), "http" | "https") {
+            return Err(eyre!("The url must start with https:// or http://"));
+        }
+        if parsed.path().ends_with('/') {
+            return Err(eyre!(
+                "The url must be the one of the database file, not of its folder"
+            ));
+        }
+        Ok(WebDavConfig {
+            url: url.to_owned(),
+            username: username.to_owned(),
+            password: password.to_owned(),
+        })
+    }
+
+    fn request(&self, args: &[&str]) -> color_eyre::Result<Response> {
+        let mut options = curl_option("url", &self.url);
+        if !self.username.is_empty() {
+            options += &curl_option("user", &format!("{}:{}", self.username, self.password));
+        }
+        curl(&options, args)
+    }
+
+    pub fn download(&self, etag: Option<&str>) -> color_eyre::Result<Option<Download>> {
+        let if_none_match = etag.map(|etag| format!("If-None-Match: {etag}"));
+        let mut args = vec![];
+        if let Some(header) = &if_none_match {
+            args.extend(["--header", header]);
+        }
+        let response = self.request(&args)?;
+        match response.status {
+            200 => Ok(Some(Download {
+                content: response.body,
+                etag: response.etag,
+            })),
+            304 => Ok(None),
+            status => Err(status_error(status, &self.url)),
+        }
+    }
+
+    pub fn upload(
+        &self,
+        path: &Path,
+        precondition: Precondition,
+    ) -> color_eyre::Result<Option<String>> {
+        let header = match precondition {
+            Precondition::Version(etag) => Some(format!("If-Match: {etag}")),
+            Precondition::Missing => Some(String::from("If-None-Match: *")),
+            Precondition::Any => None,
+        };
+        let path = path.to_string_lossy();
+        let mut args = vec!["--upload-file", &path];
+        if let Some(header) = &header {
+            args.extend(["--header", header]);
+        }
+        let response = self.request(&args)?;
+        match response.status {
+            200 | 201 | 204 if response.etag.is_some() => Ok(response.etag),
+            // Some servers only give the ETag when asked for the file
+            200 | 201 | 204 => Ok(self.request(&["--head"])?.etag),
+            409 => Err(eyre!(
+                "The folder of {} does not exist, create it first",
+                self.url
+            )),
+            status => Err(status_error(status, &self.url)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::WebDavConfig;
+
+    #[test]
+    fn test_webdav_url() {
+        assert!(WebDavConfig::new(
+            "https://cloud.example.com/remote.php/dav/files/alice/db.cotp",
+            "alice",
+            "app password"
+        )
+        .is_ok());
+        assert!(WebDavConfig::new("https://cloud.example.com/files/", "alice", "").is_err());
+        assert!(WebDavConfig::new("ftp://example.com/db.cotp", "", "").is_err());
+        assert!(WebDavConfig::new("db.cotp", "", "").is_err());
+    }
+}
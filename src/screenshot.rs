@@ -0,0 +1,69 @@
+use color_eyre::eyre::eyre;
+use image::DynamicImage;
+
+/// Screenshot utilities which print a PNG image of the whole screen on the standard output
+#[cfg(target_os = "linux")]
+const WAYLAND_TOOLS: &[(&str, &[&str])] = &[("grim", &["-"])];
+#[cfg(target_os = "linux")]
+const X11_TOOLS: &[(&str, &[&str])] = &[("maim", &[]), ("import", &["-window", "root", "png:-"])];
+
+/// Grabs the content of the whole screen
+pub fn capture_screen() -> color_eyre::Result<DynamicImage> {
+    let png = capture_png()?;
+    image::load_from_memory(&png).map_err(|e| eyre!("Cannot read the screenshot: {e}"))
+}
+
+#[cfg(target_os = "linux")]
+fn capture_png() -> color_eyre::Result<Vec<u8>> {
+    let tools: Vec<&(&str, &[&str])> = if env_var_set("WAYLAND_DISPLAY") {
+        WAYLAND_TOOLS.iter().chain(X11_TOOLS).collect()
+    } else {
+        X11_TOOLS.iter().collect()
+    };
+    tools
+        .into_iter()
+        .find_map(|(program, args)| run_capture(program, args))
+        .ok_or(eyre!(
+            "Cannot capture the screen, please install grim (Wayland), maim or imagemagick (X11)"
+        ))
+}
+
+#[cfg(target_os = "macos")]
+fn capture_png() -> color_eyre::Result<Vec<u8>> {
+    let path = std::env::temp_dir().join(format!("cotp-{}.png", std::process::id()));
+    let captured = std::process::Command::new("screencapture")
+        .args(["-x", "-t", "png"])
+        .arg(&path)
+        .status()
+        .is_ok_and(|s| s.success());
+    let png = std::fs::read(&path);
+    let _ = std::fs::remove_file(&path);
+    match png {
+        Ok(png) if captured => Ok(png),
+        _ => Err(eyre!("Cannot capture the screen using screencapture")),
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn capture_png() -> color_eyre::Result<Vec<u8>> {
+    Err(eyre!(
+        "Screen capture is not supported on this platform, save a screenshot and use cotp import --qr-image"
+    ))
+}
+
+#[cfg(target_os = "linux")]
+fn run_capture(program: &str, args: &[&str]) -> Option<Vec<u8>> {
+    std::process::Command::new(program)
+        .args(args)
+        .output()
+        .ok()
+        .filter(|output| output.status.success() && !output.stdout.is_empty())
+        .map(|output| output.stdout)
+}
+
+#[cfg(target_os = "linux")]
+fn env_var_set(env_var: &str) -> bool {
+    std::env::var(env_var)
+        .map(|v| !v.trim().is_empty())
+        .unwrap_or(false)
+}
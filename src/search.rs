@@ -0,0 +1,159 @@
+//! Matching of the codes by regular expression, glob and type, shared by cotp list and the dashboard search.
+//!
+//! The regular expressions are matched ignoring case by the regex crate, whose time is proportional to the
+//! text and the expression, whatever is typed in the search bar. The compiled size and the nesting of the
+//! expressions are limited so a pasted one cannot exhaust the memory or the stack.
+
+use color_eyre::eyre::eyre;
+use regex::RegexBuilder;
+
+use crate::otp::otp_element::OTPElement;
+use crate::otp::otp_type::OTPType;
+
+/// Bytes taken at most by a compiled expression
+const SIZE_LIMIT: usize = 1 << 20;
+/// Groups and repetitions opened at most one inside another
+const NEST_LIMIT: u32 = 100;
+
+/// Regular expression found anywhere in the text, ignoring case
+#[derive(Clone, Debug)]
+pub struct Regex {
+    regex: regex::Regex,
+}
+
+impl Regex {
+    pub fn new(pattern: &str) -> color_eyre::Result<Self> {
+        let regex = RegexBuilder::new(pattern)
+            .case_insensitive(true)
+            .size_limit(SIZE_LIMIT)
+            .nest_limit(NEST_LIMIT)
+            .build()
+            .map_err(|e| eyre!("Invalid regular expression {pattern}: {e}"))?;
+        Ok(Regex { regex })
+    }
+
+    /// Matches the whole text against a glob, where * is any text and ? any character
+    pub fn glob(pattern: &str) -> color_eyre::Result<Self> {
+        let translated: String = pattern
+            .split('*')
+            .map(|part| {
+                part.split('?')
+                    .map(regex::escape)
+                    .collect::<Vec<_>>()
+                    .join(".")
+            })
+            .collect::<Vec<_>>()
+            .join(".*");
+        Regex::new(&format!("^(?s:{translated})$"))
+    }
+
+    /// Query typed in the search bar, matched literally while it is not a valid regular expression yet
+    pub fn lenient(query: &str) -> Self {
+        Regex::new(query)
+            .or_else(|_| Regex::new(&regex::escape(query)))
+            .unwrap_or_else(|_| Regex::new("").unwrap())
+    }
+
+    pub fn is_match(&self, text: &str) -> bool {
+        self.regex.is_match(text)
+    }
+
+    /// Like is_match, only finding the matches at the start of the text
+    pub fn is_prefix_match(&self, text: &str) -> bool {
+        // The leftmost match starts at the beginning whenever one does
+        self.regex.find(text).is_some_and(|m| m.start() == 0)
+    }
+}
+
+/// Filters of cotp list, a code must match all of them
+#[derive(Clone, Default, Debug)]
+pub struct CodeFilter {
+    /// Found in the issuer or in the label
+    pub search: Option<Regex>,
+    pub issuer: Option<Regex>,
+    pub label: Option<Regex>,
+    pub type_: Option<OTPType>,
+}
+
+impl CodeFilter {
+    pub fn matches(&self, element: &OTPElement) -> bool {
+        self.search
+            .as_ref()
+            .is_none_or(|r| r.is_match(&element.issuer) || r.is_match(&element.label))
+            && self
+                .issuer
+                .as_ref()
+                .is_none_or(|r| r.is_match(&element.issuer))
+            && self
+                .label
+                .as_ref()
+                .is_none_or(|r| r.is_match(&element.label))
+            && self.type_.is_none_or(|t| t == element.type_)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CodeFilter, Regex};
+    use crate::otp::from_otp_uri::FromOtpUri;
+    use crate::otp::otp_element::OTPElement;
+    use crate::otp::otp_type::OTPType;
+
+    #[test]
+    fn test_regex() {
+        let matches = |pattern: &str, text: &str| Regex::new(pattern).unwrap().is_match(text);
+        assert!(matches("hub", "GitHub"));
+        assert!(matches("^git", "GitHub"));
+        assert!(!matches("^hub", "GitHub"));
+        assert!(matches("^(google|github)$", "GitHub"));
+        assert!(!matches("^(google|gitlab)$", "GitHub"));
+        assert!(matches(r"alice\d{2,}@", "alice42@example.com"));
+        assert!(!matches(r"alice\d{2,}@", "alice4@example.com"));
+        assert!(matches("^[a-c]+x?$", "CAB"));
+        assert!(matches("[^a-z]", "abc1"));
+        assert!(!matches("[^a-z]", "abc"));
+        assert!(matches(r"a\.b", "a.b"));
+        assert!(!matches(r"a\.b", "axb"));
+        assert!(matches("(a*)*b", "aaab"));
+        assert!(!matches("(a|b)*c", "ababab"));
+        assert!(matches("", "anything"));
+
+        assert!(Regex::new("(github").is_err());
+        assert!(Regex::new("github)").is_err());
+        assert!(Regex::new("*hub").is_err());
+        assert!(Regex::new("[z-a]").is_err());
+        assert!(Regex::lenient("(git").is_match("(github"));
+        assert!(Regex::new("git").unwrap().is_prefix_match("GitHub"));
+        assert!(!Regex::new("hub").unwrap().is_prefix_match("GitHub"));
+    }
+
+    #[test]
+    fn test_glob() {
+        assert!(Regex::glob("git*").unwrap().is_match("GitHub"));
+        assert!(Regex::glob("*@example.???")
+            .unwrap()
+            .is_match("alice@example.com"));
+        assert!(!Regex::glob("git").unwrap().is_match("GitHub"));
+        assert!(!Regex::glob("a.c").unwrap().is_match("abc"));
+    }
+
+    #[test]
+    fn test_code_filter() {
+        let element =
+            OTPElement::from_otp_uri("otpauth://totp/GitHub:alice?secret=JBSWY3DPEHPK3PXP")
+                .unwrap();
+        assert!(CodeFilter::default().matches(&element));
+        let filter = CodeFilter {
+            search: Some(Regex::new("^ali").unwrap()),
+            issuer: Some(Regex::glob("git*").unwrap()),
+            label: None,
+            type_: Some(OTPType::Totp),
+        };
+        assert!(filter.matches(&element));
+        let filter = CodeFilter {
+            type_: Some(OTPType::Hotp),
+            ..filter
+        };
+        assert!(!filter.matches(&element));
+    }
+}
@@ -0,0 +1,22 @@
+//! Termination signals of the services running until the user stops them.
+
+use nix::sys::signal::{SigSet, Signal};
+
+/// SIGINT and SIGTERM, received only by wait once blocked
+pub struct TerminationSignals(SigSet);
+
+impl TerminationSignals {
+    /// Blocks the signals in the current thread and in the ones started afterwards,
+    /// so it must be called before starting them
+    pub fn block() -> nix::Result<Self> {
+        let mut signals = SigSet::empty();
+        signals.add(Signal::SIGINT);
+        signals.add(Signal::SIGTERM);
+        signals.thread_block()?;
+        Ok(TerminationSignals(signals))
+    }
+
+    pub fn wait(&self) -> nix::Result<()> {
+        self.0.wait().map(|_| ())
+    }
+}
@@ -0,0 +1,528 @@
+//! Synchronization of the database through a folder shared with git, Syncthing or a cloud drive.
+//!
+//! Every code is encrypted into its own file named after its id, so the changes made to different codes
+//! on different machines touch different files and never conflict. The changes made to the same code
+//! are merged field by field against its state at the last synchronization, which the database keeps.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+
+use color_eyre::eyre::eyre;
+use data_encoding::BASE64;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::crypto::cryptography::{
+    argon_derive_key, decrypt_string, decrypt_string_with_key, encrypt_string_with_key, gen_salt,
+    stored_kdf_params, DatabaseKey, KdfParams,
+};
+use crate::otp::otp_element::{OTPDatabase, OTPElement};
+use crate::utils::{wipe_json, write_atomically};
+
+/// Encrypted known text telling if the password of the folder is right, with the salt and the key derivation parameters
+const FOLDER_FILE: &str = "cotp-sync.json";
+const FOLDER_CHECK: &str = "cotp sync folder";
+const ENTRIES_DIR: &str = "entries";
+const ENTRY_EXTENSION: &str = "cotp";
+const ID_LENGTH: usize = 16;
+
+/// Folder and key of the synchronization, stored in the encrypted database
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct SyncState {
+    pub folder: PathBuf,
+    /// Base64 encoded key, derived from the password of the folder
+    key: Zeroizing<String>,
+    salt: String,
+    kdf: KdfParams,
+    /// Codes as they were after the last synchronization, by id
+    #[serde(default)]
+    base: BTreeMap<String, OTPElement>,
+}
+
+/// What a synchronization changed
+#[derive(Default, PartialEq, Eq, Debug)]
+pub struct SyncReport {
+    /// Codes added, changed and deleted in the database by the other machines
+    pub added: usize,
+    pub updated: usize,
+    pub deleted: usize,
+    /// Codes written to or deleted from the folder
+    pub sent: usize,
+    /// Codes changed on both sides, keeping the local value of the fields changed on both
+    pub conflicts: Vec<String>,
+    /// Conflicting copies of the codes made by the tool syncing the folder, merged and deleted
+    pub merged_copies: usize,
+}
+
+// Zeroizing does not implement Hash
+impl Hash for SyncState {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.folder.hash(state);
+        self.key.as_str().hash(state);
+        self.salt.hash(state);
+        self.kdf.hash(state);
+        self.base.hash(state);
+    }
+}
+
+/// Code read from the folder, or a copy of it made when two machines changed it at the same time
+struct Entry {
+    id: String,
+    path: PathBuf,
+    element: OTPElement,
+    conflict_copy: bool,
+}
+
+/// Id of the code whose file has this name, telling if the file is a conflicting copy of it. Syncthing names the
+/// copies like id.sync-conflict-20240101-120000-DEVICE.cotp, Dropbox and Nextcloud like id (conflicted copy ...).cotp
+fn entry_id(file_name: &str) -> Option<(&str, bool)> {
+    let name = file_name.strip_suffix(&format!(".{ENTRY_EXTENSION}"))?;
+    if let Some((id, _)) = name.split_once(".sync-conflict-") {
+        return Some((id, true));
+    }
+    match name.split_once(" (") {
+        Some((id, rest)) if rest.contains("conflicted copy") => Some((id, true)),
+        _ => Some((name, false)),
+    }
+}
+
+pub fn is_sync_folder(folder: &Path) -> bool {
+    folder.join(FOLDER_FILE).exists()
+}
+
+/// Prepares the folder to receive the codes, encrypting them with a key derived from the password
+pub fn create_folder(
+    folder: &Path,
+    password: &str,
+    kdf: KdfParams,
+) -> color_eyre::Result<SyncState> {
+    if is_sync_folder(folder) {
+        return Err(eyre!("{} is already a sync folder", folder.display()));
+    }
+    fs::create_dir_all(folder.join(ENTRIES_DIR))?;
+    let salt = gen_salt()?;
+    let mut key = argon_derive_key(password.as_bytes(), &salt, &kdf)?;
+    let check = encrypt_string_with_key(FOLDER_CHECK.to_owned(), &key, &salt)?.with_kdf_params(kdf);
+    write_atomically(
+        &folder.join(FOLDER_FILE),
+        serde_json::to_string(&check)?.as_bytes(),
+    )?;
+    let state = SyncState {
+        folder: folder.to_owned(),
+        key: Zeroizing::new(BASE64.encode(&key)),
+        salt: BASE64.encode(&salt),
+        kdf,
+        base: BTreeMap::new(),
+    };
+    key.zeroize();
+    Ok(state)
+}
+
+/// Derives the key of an existing folder, checking the password
+pub fn open_folder(folder: &Path, password: &str) -> color_eyre::Result<SyncState> {
+    let check = fs::read_to_string(folder.join(FOLDER_FILE))
+        .map_err(|e| eyre!("{} is not a sync folder: {e}", folder.display()))?;
+    let (text, key, salt) = decrypt_string(&check, password)?;
+    if text != FOLDER_CHECK {
+        return Err(eyre!("Invalid sync folder"));
+    }
+    Ok(SyncState {
+        folder: folder.to_owned(),
+        key: Zeroizing::new(BASE64.encode(&key)),
+        salt: BASE64.encode(&salt),
+        kdf: stored_kdf_params(&check),
+        base: BTreeMap::new(),
+    })
+}
+
+impl SyncState {
+    fn entries_dir(&self) -> PathBuf {
+        self.folder.join(ENTRIES_DIR)
+    }
+
+    fn entry_path(&self, id: &str) -> PathBuf {
+        self.entries_dir().join(format!("{id}.{ENTRY_EXTENSION}"))
+    }
+
+    fn key(&self) -> color_eyre::Result<DatabaseKey> {
+        BASE64
+            .decode(self.key.as_bytes())
+            .map(Zeroizing::new)
+            .map_err(|e| eyre!("Invalid sync key: {e}"))
+    }
+
+    /// Decrypts the codes in the folder and their conflicting copies
+    fn read_entries(&self) -> color_eyre::Result<Vec<Entry>> {
+        let mut entries = vec![];
+        for file in fs::read_dir(self.entries_dir())? {
+            let path = file?.path();
+            let Some((id, conflict_copy)) = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(entry_id)
+            else {
+                continue;
+            };
+            let content = fs::read_to_string(&path)?;
+            let (mut json, _, _) = decrypt_string_with_key(&content, self.key()?.to_vec())
+                .map_err(|e| eyre!("Cannot decrypt {}: {e}", path.display()))?;
+            let element = serde_json::from_str::<OTPElement>(&json);
+            json.zeroize();
+            entries.push(Entry {
+                id: id.to_owned(),
+                element: element?,
+                path,
+                conflict_copy,
+            });
+        }
+        Ok(entries)
+    }
+
+    fn write_entry(&self, element: &OTPElement) -> color_eyre::Result<()> {
+        let salt = BASE64
+            .decode(self.salt.as_bytes())
+            .map_err(|e| eyre!("Invalid sync salt: {e}"))?;
+        let key = self.key()?;
+        let encrypted = encrypt_string_with_key(serde_json::to_string(element)?, &key, &salt)?
+            .with_kdf_params(self.kdf);
+        write_atomically(
+            &self.entry_path(&element.id),
+            serde_json::to_string(&encrypted)?.as_bytes(),
+        )?;
+        Ok(())
+    }
+
+    fn remove_entry(&self, id: &str) -> color_eyre::Result<()> {
+        match fs::remove_file(self.entry_path(id)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Copy of the element stored in the folder, without the usage statistics which change at every copy
+fn shared(element: &OTPElement) -> OTPElement {
+    let mut element = element.clone();
+    element.usage_count = 0;
+    element.last_used = 0;
+    element
+}
+
+fn new_id() -> color_eyre::Result<String> {
+    let mut bytes = [0u8; ID_LENGTH];
+    getrandom::getrandom(&mut bytes)?;
+    Ok(hex::encode(bytes))
+}
+
+/// Three way merge of a code, returning the merged one, None if deleted, and whether both sides changed it
+pub fn merge_element(
+    base: Option<&OTPElement>,
+    local: Option<&OTPElement>,
+    remote: Option<&OTPElement>,
+) -> color_eyre::Result<(Option<OTPElement>, bool)> {
+    if local == remote || base == remote {
+        return Ok((local.cloned(), false));
+    }
+    if base == local {
+        return Ok((remote.cloned(), false));
+    }
+    match (base, local, remote) {
+        (Some(base), Some(local), Some(remote)) => {
+            merge_fields(base, local, remote).map(|(e, c)| (Some(e), c))
+        }
+        // Added on both sides, like the codes of a machine joining the synchronization: the folder wins
+        (None, Some(_), Some(remote)) => Ok((Some(remote.clone()), false)),
+        // Changed on one side and deleted on the other, the change wins
+        (_, local, remote) => Ok((local.or(remote).cloned(), true)),
+    }
+}
+
+/// Takes the fields changed on the remote side only, keeping the local value of the ones changed on both
+fn merge_fields(
+    base: &OTPElement,
+    local: &OTPElement,
+    remote: &OTPElement,
+) -> color_eyre::Result<(OTPElement, bool)> {
+    let object = |element: &OTPElement| match serde_json::to_value(element) {
+        Ok(Value::Object(map)) => Ok(map),
+        Ok(_) => Err(eyre!("Invalid code")),
+        Err(e) => Err(e.into()),
+    };
+    let base = object(base)?;
+    let local = object(local)?;
+    let remote = object(remote)?;
+    let mut merged: Map<String, Value> = local.clone();
+    let mut conflict = false;
+    for (field, value) in &remote {
+        if local.get(field) == Some(value) {
+            continue;
+        }
+        if local.get(field) == base.get(field) {
+            merged.insert(field.to_owned(), value.clone());
+        } else if base.get(field) != Some(value) {
+            conflict = true;
+        }
+    }
+    let merged = serde_json::from_value(Value::Object(merged));
+    for mut map in [base, local, remote] {
+        map.values_mut().for_each(wipe_json);
+    }
+    Ok((merged?, conflict))
+}
+
+impl OTPDatabase {
+    pub fn sync_state(&self) -> Option<&SyncState> {
+        self.sync.as_ref()
+    }
+
+    /// Synchronizes with another folder from now on, or stops synchronizing
+    pub fn set_sync_state(&mut self, state: Option<SyncState>) {
+        self.mark_modified();
+        self.sync = state;
+    }
+
+    /// Merges the changes made in the folder by the other machines with the local ones, then writes the result into the folder
+    pub fn synchronize(&mut self) -> color_eyre::Result<SyncReport> {
+        let mut state = self
+            .sync
+            .clone()
+            .ok_or(eyre!("The database is not synchronized with any folder"))?;
+        let entries = state.read_entries()?;
+        let (copies, entries): (Vec<Entry>, Vec<Entry>) =
+            entries.into_iter().partition(|entry| entry.conflict_copy);
+        // As written in the folder, to know which files to write
+        let folder: BTreeMap<String, OTPElement> = entries
+            .into_iter()
+            .map(|entry| (entry.id, entry.element))
+            .collect();
+        let mut report = SyncReport::default();
+        // The changes of the copies are merged like the ones of another machine, the folder winning the conflicts
+        let mut remote = folder.clone();
+        for copy in &copies {
+            let (merged, conflict) = merge_element(
+                state.base.get(&copy.id),
+                remote.get(&copy.id),
+                Some(&copy.element),
+            )?;
+            if conflict {
+                report.conflicts.push(copy.element.name());
+            }
+            match merged {
+                Some(merged) => remote.insert(copy.id.to_owned(), merged),
+                None => remote.remove(&copy.id),
+            };
+        }
+        self.assign_sync_ids(&remote)?;
+
+        let ids: BTreeSet<String> = state
+            .base
+            .keys()
+            .chain(remote.keys())
+            .cloned()
+            .chain(self.elements.iter().map(|e| e.id.to_owned()))
+            .collect();
+        let mut base = BTreeMap::new();
+        for id in ids {
+            let position = self.elements.iter().position(|e| e.id == id);
+            let local = position.map(|i| shared(&self.elements[i]));
+            let (merged, conflict) =
+                merge_element(state.base.get(&id), local.as_ref(), remote.get(&id))?;
+            if conflict {
+                if let Some(element) = merged.as_ref().or(local.as_ref()) {
+                    report.conflicts.push(element.name());
+                }
+            }
+            match (position, &merged) {
+                (Some(i), Some(merged)) if local.as_ref() != Some(merged) => {
+                    let current = &self.elements[i];
+                    let mut updated = merged.clone();
+                    updated.usage_count = current.usage_count;
+                    updated.last_used = current.last_used;
+                    self.replace_element(i, updated);
+                    report.updated += 1;
+                }
+                (Some(i), None) => {
+                    self.delete_element(i);
+                    report.deleted += 1;
+                }
+                (None, Some(merged)) => {
+                    self.mark_modified();
+                    self.elements.push(merged.clone());
+                    report.added += 1;
+                }
+                _ => {}
+            }
+            if merged.as_ref() != folder.get(&id) {
+                match &merged {
+                    Some(element) => state.write_entry(element)?,
+                    None => state.remove_entry(&id)?,
+                }
+                report.sent += 1;
+            }
+            if let Some(merged) = merged {
+                base.insert(id, merged);
+            }
+        }
+        // Merged into the codes written above
+        for copy in copies {
+            fs::remove_file(&copy.path)?;
+            report.merged_copies += 1;
+        }
+        if base != state.base {
+            state.base = base;
+            self.set_sync_state(Some(state));
+        }
+        Ok(report)
+    }
+
+    /// Gives an id to the elements without one, or with the one of another element. The codes already
+    /// in the folder get its id, so they are not duplicated when another machine joins the synchronization
+    fn assign_sync_ids(&mut self, remote: &BTreeMap<String, OTPElement>) -> color_eyre::Result<()> {
+        let mut used = BTreeSet::new();
+        for i in 0..self.elements.len() {
+            let id = &self.elements[i].id;
+            if !id.is_empty() && !used.contains(id) {
+                used.insert(id.to_owned());
+                continue;
+            }
+            let known = remote
+                .iter()
+                .find(|(id, e)| !used.contains(*id) && e.is_duplicate(&self.elements[i]))
+                .map(|(id, _)| id.to_owned());
+            let id = match known {
+                Some(id) => id,
+                None => new_id()?,
+            };
+            used.insert(id.clone());
+            self.elements[i].id = id;
+            self.mark_modified();
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::crypto::cryptography::KdfParams;
+    use crate::otp::from_otp_uri::FromOtpUri;
+    use crate::otp::otp_element::{OTPDatabase, OTPElement};
+
+    use super::{create_folder, entry_id, merge_element, open_folder, shared};
+
+    fn element(uri: &str) -> OTPElement {
+        OTPElement::from_otp_uri(uri).unwrap()
+    }
+
+    #[test]
+    fn test_merge_element() {
+        let base = element("otpauth://totp/GitHub:alice?secret=JBSWY3DPEHPK3PXP");
+        let mut local = base.clone();
+        local.note = String::from("Work account");
+        let mut remote = base.clone();
+        remote.label = String::from("bob");
+
+        let (merged, conflict) = merge_element(Some(&base), Some(&local), Some(&remote)).unwrap();
+        let merged = merged.unwrap();
+        assert!(!conflict);
+        assert_eq!("bob", merged.label);
+        assert_eq!("Work account", merged.note);
+
+        // Both changed the label, the local one is kept
+        local.label = String::from("carol");
+        let (merged, conflict) = merge_element(Some(&base), Some(&local), Some(&remote)).unwrap();
+        assert!(conflict);
+        assert_eq!("carol", merged.unwrap().label);
+
+        // Deleted on the remote side only
+        assert_eq!(
+            (None, false),
+            merge_element(Some(&base), Some(&base), None).unwrap()
+        );
+        // Deleted on the remote side, changed locally
+        let (merged, conflict) = merge_element(Some(&base), Some(&local), None).unwrap();
+        assert!(conflict);
+        assert_eq!(Some(local), merged);
+    }
+
+    #[test]
+    fn test_entry_id() {
+        assert_eq!(Some(("abc", false)), entry_id("abc.cotp"));
+        assert_eq!(
+            Some(("abc", true)),
+            entry_id("abc.sync-conflict-20240101-120000-ABCDEFG.cotp")
+        );
+        assert_eq!(
+            Some(("abc", true)),
+            entry_id("abc (alice's conflicted copy 2024-01-01).cotp")
+        );
+        assert_eq!(None, entry_id("abc.cotp.tmp"));
+    }
+
+    #[test]
+    fn test_synchronize() {
+        let folder = std::env::temp_dir().join(format!("cotp-sync-test-{}", std::process::id()));
+        let kdf = KdfParams {
+            memory: 8,
+            iterations: 1,
+            parallelism: 1,
+        };
+        let mut first: OTPDatabase = vec![
+            element("otpauth://totp/GitHub:alice?secret=JBSWY3DPEHPK3PXP"),
+            element("otpauth://totp/Google:bob?secret=GEZDGNBVGY3TQOJQ"),
+        ]
+        .into();
+        first.set_sync_state(Some(create_folder(&folder, "password", kdf).unwrap()));
+        assert_eq!(2, first.synchronize().unwrap().sent);
+        assert!(open_folder(&folder, "wrong password").is_err());
+
+        // The other machine already has one of the codes
+        let mut second: OTPDatabase = vec![element(
+            "otpauth://totp/GitHub:alice?secret=JBSWY3DPEHPK3PXP",
+        )]
+        .into();
+        second.set_sync_state(Some(open_folder(&folder, "password").unwrap()));
+        let report = second.synchronize().unwrap();
+        assert_eq!((1, 0), (report.added, report.sent));
+        assert_eq!(first.elements_ref()[0].id, second.elements_ref()[0].id);
+
+        second.delete_element(1);
+        second.mut_element(0).unwrap().note = String::from("Work account");
+        assert_eq!(2, second.synchronize().unwrap().sent);
+
+        first.record_usage(0);
+        let report = first.synchronize().unwrap();
+        assert_eq!(
+            (0, 1, 1, 0),
+            (report.added, report.updated, report.deleted, report.sent)
+        );
+        assert_eq!("Work account", first.elements_ref()[0].note);
+        assert_eq!(1, first.elements_ref()[0].usage_count);
+        assert_eq!(1, first.trash_ref().len());
+
+        // Syncthing kept both versions of a code changed at the same time
+        let state = first.sync_state().unwrap().clone();
+        let mut copy = shared(&first.elements_ref()[0]);
+        copy.label = String::from("bob");
+        state.write_entry(&copy).unwrap();
+        let copy_path = state.entries_dir().join(format!(
+            "{}.sync-conflict-20240101-120000-ABCDEFG.cotp",
+            copy.id
+        ));
+        std::fs::rename(state.entry_path(&copy.id), &copy_path).unwrap();
+        state
+            .write_entry(&shared(&first.elements_ref()[0]))
+            .unwrap();
+        let report = first.synchronize().unwrap();
+        assert_eq!(
+            (1, 1, 1),
+            (report.merged_copies, report.updated, report.sent)
+        );
+        assert_eq!("bob", first.elements_ref()[0].label);
+        assert!(!copy_path.exists());
+
+        std::fs::remove_dir_all(folder).unwrap();
+    }
+}
@@ -0,0 +1,67 @@
+use std::sync::mpsc;
+
+use color_eyre::eyre::eyre;
+use robius_authentication::{
+    AndroidText, BiometricStrength, Context, PolicyBuilder, Text, WindowsText,
+};
+
+use crate::os_keyring;
+
+/// polkit action defined in packaging/com.github.replydev.cotp.policy
+const POLKIT_ACTION_ID: &str = "com.github.replydev.cotp.unlock";
+
+/// Provides the database password without typing it
+pub trait UnlockBackend {
+    /// Returns the database password, or an error if this backend cannot provide it
+    fn password(&self) -> color_eyre::Result<String>;
+}
+
+/// Password stored in the OS keyring
+pub struct KeyringUnlock;
+
+impl UnlockBackend for KeyringUnlock {
+    fn password(&self) -> color_eyre::Result<String> {
+        os_keyring::get_password().ok_or(eyre!(
+            "No password stored in the keyring, run cotp keyring set first"
+        ))
+    }
+}
+
+/// Shows the platform authentication prompt, waiting for the user to complete it.
+///
+/// This only confirms that someone is in front of the machine: it protects no secret,
+/// the password stays readable from the keyring by whoever can run cotp --use-keyring.
+pub fn confirm_presence() -> color_eyre::Result<()> {
+    let policy = PolicyBuilder::new()
+        .biometrics(Some(BiometricStrength::Strong))
+        .password(true)
+        .companion(true)
+        .action_ids([POLKIT_ACTION_ID])
+        .build()
+        .ok_or(eyre!(
+            "Native authentication is not supported on this platform"
+        ))?;
+    let text = Text {
+        android: AndroidText {
+            title: "Confirm cotp",
+            subtitle: None,
+            description: None,
+        },
+        apple: "confirm opening the cotp database",
+        windows: WindowsText::new_truncated(
+            "Confirm cotp",
+            "Authenticate to confirm opening the cotp database",
+        ),
+    };
+    let (sender, receiver) = mpsc::channel();
+    Context::new(())
+        .authenticate(text, &policy, move |result| {
+            let _ = sender.send(result);
+        })
+        .map_err(|e| eyre!("Cannot show the authentication prompt: {e:?}"))?;
+    match receiver.recv() {
+        Ok(Ok(())) => Ok(()),
+        Ok(Err(e)) => Err(eyre!("Authentication failed: {e:?}")),
+        Err(_) => Err(eyre!("Authentication prompt closed unexpectedly")),
+    }
+}
@@ -1,48 +1,270 @@
+use crate::clock;
 use crate::path::get_db_path;
-use std::time::{SystemTime, UNIX_EPOCH};
+use crate::prompt::{self, PasswordStrength};
+use serde_json::Value;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use zeroize::Zeroize;
 
-pub fn init_app() -> Result<bool, ()> {
+/// Creates the database directory, returning true if the database does not exist yet
+pub fn init_app() -> io::Result<bool> {
     let db_path = get_db_path();
-    let db_dir = db_path.parent().unwrap();
-    if !db_dir.exists() {
-        if let Err(_e) = std::fs::create_dir_all(db_dir) {
-            return Err(());
-        }
-        return Ok(true);
+    if let Some(db_dir) = db_path.parent() {
+        std::fs::create_dir_all(db_dir)?;
     }
     Ok(!db_path.exists())
 }
 
+/// Replaces the file content without ever leaving it truncated, even after a crash or a power loss:
+/// the content is synced into a temporary file of the same directory, which is then renamed over the old one
+pub fn write_atomically(path: &Path, content: &[u8]) -> io::Result<()> {
+    let temp_path = temp_path(path);
+    let result = write_synced(&temp_path, content, path).and_then(|_| fs::rename(&temp_path, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    result?;
+    // The rename itself is durable only after syncing the directory
+    #[cfg(unix)]
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        File::open(dir)?.sync_all()?;
+    }
+    Ok(())
+}
+
+/// Writes a file only the user can read, like the exported codes
+#[cfg(unix)]
+pub fn write_private(path: &Path, content: &[u8]) -> io::Result<()> {
+    use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+
+    let mut file = fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(path)?;
+    // The mode applies only to new files, an overwritten file may be readable by everyone
+    file.set_permissions(fs::Permissions::from_mode(0o600))?;
+    file.write_all(content)
+}
+
+#[cfg(not(unix))]
+pub fn write_private(path: &Path, content: &[u8]) -> io::Result<()> {
+    fs::write(path, content)
+}
+
+fn write_synced(temp_path: &Path, content: &[u8], original: &Path) -> io::Result<()> {
+    let mut file = File::create(temp_path)?;
+    // Keep the permissions chosen for the original file
+    if let Ok(metadata) = fs::metadata(original) {
+        file.set_permissions(metadata.permissions())?;
+    }
+    file.write_all(content)?;
+    file.sync_all()
+}
+
+fn temp_path(path: &Path) -> PathBuf {
+    let mut temp_path = path.as_os_str().to_owned();
+    temp_path.push(".tmp");
+    PathBuf::from(temp_path)
+}
+
+/// Wipes the strings of a JSON value, which may contain secrets
+pub(crate) fn wipe_json(value: &mut Value) {
+    match value {
+        Value::String(s) => s.zeroize(),
+        Value::Array(values) => values.iter_mut().for_each(wipe_json),
+        Value::Object(map) => map.values_mut().for_each(wipe_json),
+        _ => {}
+    }
+}
+
+/// Seconds elapsed since the unix epoch, corrected with the clock offset
+pub fn get_current_timestamp() -> u64 {
+    clock::now_ms() / 1000
+}
+
+/// Name of this machine, to know where a change was made, empty if unknown
+pub fn hostname() -> String {
+    #[cfg(unix)]
+    let name = nix::unistd::gethostname()
+        .ok()
+        .and_then(|name| name.into_string().ok());
+    #[cfg(not(unix))]
+    let name = std::env::var("COMPUTERNAME").ok();
+    name.unwrap_or_default()
+}
+
+/// Formats a unix timestamp as an UTC date and time, like 2024-10-15 13:20:00 UTC
+pub fn format_timestamp(timestamp: u64) -> String {
+    let (days, seconds) = (timestamp / 86400, timestamp % 86400);
+    // Civil date from days since the epoch: https://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719468;
+    let era = z / 146097;
+    let day_of_era = z % 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02} UTC",
+        seconds / 3600,
+        seconds % 3600 / 60,
+        seconds % 60
+    )
+}
+
+/// Lowercase host of an URL, or of a domain without the scheme, like github.com for https://GitHub.com/login
+pub fn url_host(url: &str) -> Option<String> {
+    let parsed = match url::Url::parse(url.trim()) {
+        Ok(parsed) => parsed,
+        Err(_) => url::Url::parse(&format!("https://{}", url.trim())).ok()?,
+    };
+    parsed
+        .host_str()
+        .map(|h| h.trim_end_matches('.').to_lowercase())
+        .filter(|h| !h.is_empty())
+}
+
 pub fn millis_before_next_step() -> u64 {
-    let now = SystemTime::now();
-    let since_the_epoch = now.duration_since(UNIX_EPOCH).unwrap();
-    let in_ms = since_the_epoch.as_secs() * 1000 + since_the_epoch.subsec_nanos() as u64 / 1000000;
-    in_ms % 30000
+    clock::now_ms() % 30000
 }
 
 pub fn percentage() -> u16 {
-    (millis_before_next_step() * 100 / 30000) as u16
+    period_percentage(30, 0)
 }
 
-pub fn password(message: &str, minimum_length: usize) -> String {
+/// Percentage of the current period already elapsed, with the periods counted from the t0 Unix time
+pub fn period_percentage(period: u64, t0: i64) -> u16 {
+    let period_ms = period.max(1) as i128 * 1000;
+    let elapsed = clock::now_ms() as i128 - t0 as i128 * 1000;
+    (elapsed.rem_euclid(period_ms) * 100 / period_ms) as u16
+}
+
+/// Asks a password, failing with [`io::ErrorKind::Interrupted`] if the user presses Ctrl-C
+pub fn password(message: &str, minimum_length: usize) -> io::Result<String> {
+    read_password(message, minimum_length, false)
+}
+
+fn read_password(message: &str, minimum_length: usize, show_strength: bool) -> io::Result<String> {
     loop {
-        let password = rpassword::prompt_password(message).unwrap();
+        let password = match prompt::read_password(message, show_strength) {
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => return Err(e),
+            // The plain prompt still works without a terminal supporting the raw mode
+            Err(_) => rpassword::prompt_password(message)?,
+            Ok(password) => password,
+        };
         if password.chars().count() < minimum_length {
             println!("Please insert a password with at least {minimum_length} digits.");
             continue;
         }
-        return password;
+        return Ok(password);
     }
 }
 
-pub fn verified_password(message: &str, minimum_length: usize) -> String {
+/// Asks a new password twice, confirming the weak ones
+pub fn verified_password(message: &str, minimum_length: usize) -> io::Result<String> {
     loop {
-        let password = password(message, minimum_length);
-        let verify_password = rpassword::prompt_password("Retype the same password: ").unwrap();
-        if password != verify_password {
+        let mut password = read_password(message, minimum_length, true)?;
+        if PasswordStrength::of(&password) <= PasswordStrength::Weak
+            && !confirm(
+                "This password is easy to guess, use it anyway? [y/N] ",
+                false,
+            )
+        {
+            password.zeroize();
+            continue;
+        }
+        let mut verify_password = match read_password("Retype the same password: ", 0, false) {
+            Ok(verify_password) => verify_password,
+            Err(e) => {
+                password.zeroize();
+                return Err(e);
+            }
+        };
+        let matching = password == verify_password;
+        verify_password.zeroize();
+        if !matching {
+            password.zeroize();
             println!("Passwords do not match");
             continue;
         }
-        return password;
+        return Ok(password);
+    }
+}
+
+/// Asks a yes or no question, returning the default answer if the user just presses enter
+pub fn confirm(message: &str, default: bool) -> bool {
+    loop {
+        print!("{message}");
+        let _ = io::stdout().flush();
+        let mut answer = String::new();
+        if io::stdin().read_line(&mut answer).is_err() {
+            return false;
+        }
+        match answer.trim().to_lowercase().as_str() {
+            "" => return default,
+            "y" | "yes" => return true,
+            "n" | "no" => return false,
+            _ => println!("Please answer y or n"),
+        }
+    }
+}
+
+/// Asks a question, returning the trimmed answer, empty if the user just presses enter
+pub fn ask(message: &str) -> String {
+    print!("{message}");
+    let _ = io::stdout().flush();
+    let mut answer = String::new();
+    let _ = io::stdin().read_line(&mut answer);
+    answer.trim().to_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_timestamp, write_atomically};
+
+    #[test]
+    fn test_format_timestamp() {
+        assert_eq!("1970-01-01 00:00:00 UTC", format_timestamp(0));
+        assert_eq!("2000-02-29 12:34:56 UTC", format_timestamp(951827696));
+        assert_eq!("2024-12-31 23:59:59 UTC", format_timestamp(1735689599));
+    }
+
+    #[test]
+    fn test_write_atomically() {
+        let dir = std::env::temp_dir().join(format!("cotp-write-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("db.cotp");
+
+        write_atomically(&path, b"first").unwrap();
+        write_atomically(&path, b"second").unwrap();
+
+        assert_eq!(b"second".to_vec(), std::fs::read(&path).unwrap());
+        assert_eq!(1, std::fs::read_dir(&dir).unwrap().count());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("cotp-private-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("exported.cotp");
+        std::fs::write(&path, b"readable by everyone").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+
+        super::write_private(&path, b"secret").unwrap();
+
+        assert_eq!(b"secret".to_vec(), std::fs::read(&path).unwrap());
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(0o600, mode & 0o777);
+        std::fs::remove_dir_all(dir).unwrap();
     }
 }
@@ -0,0 +1,188 @@
+//! Guided creation of the database: its location, the key derivation parameters, the ways to unlock it
+//! and the codes imported from another authenticator app.
+
+use std::path::Path;
+
+use clap::{Args, Command, FromArgMatches};
+use color_eyre::eyre::eyre;
+use cotp::config::{set_config_value, Config};
+use cotp::crypto::keyfile::keyfile_hash;
+use cotp::otp::otp_element::{DuplicatePolicy, OTPDatabase};
+use cotp::path::{get_db_path, get_profile, has_custom_db_path, set_db_path};
+use cotp::reading::ReadResult;
+use cotp::utils;
+use zeroize::Zeroize;
+
+use crate::args::{BackupType, ImportArgs};
+use crate::argument_functions::{
+    backup_source, calibrated_kdf_params, check_secrets, enroll_keyfile, merge_elements,
+    read_backup,
+};
+use crate::os_keyring;
+
+/// Runs the wizard for cotp init, refusing to replace an existing database
+pub fn init(use_keyring: bool) -> color_eyre::Result<()> {
+    if !utils::init_app()? {
+        return Err(eyre!(
+            "A database already exists at {}, use --database-path or --profile to create another one",
+            get_db_path().display()
+        ));
+    }
+    run(use_keyring).map(|_| ())
+}
+
+/// Creates the database asking the user how, then saves it
+pub fn run(use_keyring: bool) -> color_eyre::Result<ReadResult> {
+    println!("No database found, let's create it");
+    let custom_location = choose_location()?;
+
+    let mut database = OTPDatabase::default();
+    let kdf_params = if utils::confirm(
+        "Measure the speed of this machine to choose the key derivation parameters, instead of the configured ones? [y/N] ",
+        false,
+    ) {
+        calibrated_kdf_params()?
+    } else {
+        Config::load()?.kdf
+    };
+    database.set_kdf_params(kdf_params);
+    database.record_change("Created the database");
+
+    let mut pw = utils::verified_password("Choose a password: ", 8)?;
+    // The keyfile given on the command line is enrolled without asking
+    let keyfile = keyfile_hash().or_else(choose_keyfile);
+    database.set_keyfile_hash(keyfile);
+    let store_in_keyring = use_keyring
+        || utils::confirm(
+            "Store the password in the OS keyring, to unlock the database with --use-keyring? [y/N] ",
+            false,
+        );
+    let mut database = import_codes(database);
+
+    let save_result = database.save_with_pw(&pw);
+    if store_in_keyring && save_result.is_ok() {
+        if let Err(e) = os_keyring::set_password(&pw) {
+            eprintln!("{e}");
+        }
+    }
+    pw.zeroize();
+    let (key, salt) = save_result?;
+    if custom_location {
+        // Saved as the default database, so it is opened without --database-path
+        let path = get_db_path().display().to_string();
+        match set_config_value("database.path", &toml_edit::Value::from(path).to_string()) {
+            Ok(config_path) => println!(
+                "Database created, its path has been saved into {}",
+                config_path.display()
+            ),
+            Err(e) => println!(
+                "Database created, open it with --database-path {0} or by setting COTP_DB_PATH={0}. {e}",
+                get_db_path().display()
+            ),
+        }
+    }
+    Ok((database, key, salt.to_vec()))
+}
+
+/// Asks where to create the database, returning true if it is not the default location
+fn choose_location() -> color_eyre::Result<bool> {
+    // Already chosen on the command line or by the profile
+    if has_custom_db_path() || get_profile().is_some() {
+        println!("Creating the database at {}", get_db_path().display());
+        return Ok(false);
+    }
+    let answer = utils::ask(&format!(
+        "Database location, a file or a directory [{}]: ",
+        get_db_path().display()
+    ));
+    if answer.is_empty() {
+        return Ok(false);
+    }
+    set_db_path(Path::new(&answer))?;
+    if !utils::init_app()? {
+        return Err(eyre!(
+            "A database already exists at {}",
+            get_db_path().display()
+        ));
+    }
+    Ok(true)
+}
+
+/// Hash of the keyfile required together with the password, if the user wants one
+fn choose_keyfile() -> Option<[u8; 32]> {
+    if !utils::confirm(
+        "Require a keyfile too, so the password alone cannot unlock the database? [y/N] ",
+        false,
+    ) {
+        return None;
+    }
+    loop {
+        let path = utils::ask("Keyfile path, created if missing: ");
+        if path.is_empty() {
+            return None;
+        }
+        match enroll_keyfile(Path::new(&path)) {
+            Ok(hash) => return Some(hash),
+            Err(e) => eprintln!("{e}"),
+        }
+    }
+}
+
+/// Imports the backups of other apps until the user is done
+fn import_codes(mut database: OTPDatabase) -> OTPDatabase {
+    let mut question = "Import the codes from another app? [y/N] ";
+    while utils::confirm(question, false) {
+        println!("Formats: {}", import_formats().join(", "));
+        let format = utils::ask("Format: ");
+        let path = utils::ask("Backup path: ");
+        let args = import_args(&format, &path);
+        let source = args.as_ref().map(backup_source).unwrap_or_default();
+        match args.and_then(read_backup) {
+            Ok(elements) => {
+                let elements = check_secrets(elements, false);
+                database =
+                    merge_elements(database, elements, DuplicatePolicy::Skip, false, &source);
+                question = "Import the codes from another app too? [y/N] ";
+            }
+            Err(e) => eprintln!("Cannot import the codes: {e}"),
+        }
+    }
+    database
+}
+
+/// Names of the import command flags choosing the backup format
+fn import_formats() -> Vec<String> {
+    BackupType::augment_args(Command::new("import"))
+        .get_arguments()
+        .filter_map(|arg| arg.get_long())
+        .map(String::from)
+        .collect()
+}
+
+/// Same arguments as cotp import --<format> --path <path>
+fn import_args(format: &str, path: &str) -> color_eyre::Result<ImportArgs> {
+    let matches = ImportArgs::augment_args(Command::new("import")).try_get_matches_from([
+        "import",
+        &format!("--{format}"),
+        "--path",
+        path,
+    ])?;
+    Ok(ImportArgs::from_arg_matches(&matches)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{import_args, import_formats};
+
+    #[test]
+    fn test_import_args() {
+        let formats = import_formats();
+        assert!(formats.iter().any(|f| f == "aegis"));
+        assert!(formats.iter().all(|f| f != "path"));
+
+        let args = import_args("aegis", "backup.json").unwrap();
+        assert!(args.backup_type.aegis);
+        assert_eq!("backup.json", args.path.to_str().unwrap());
+        assert!(import_args("unknown", "backup.json").is_err());
+    }
+}